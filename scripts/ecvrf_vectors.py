@@ -0,0 +1,95 @@
+#!/usr/bin/env python3
+# Reference ECVRF-SECP256K1-SHA256-TAI, written from RFC 9381 section 5 (TAI
+# encode_to_curve, RFC 6979 nonce of 5.4.2.1) with suite string 0xFE and no dependency
+# on the canister code. Prints the vectors pinned in cryptography/vrf.rs:
+#   python3 scripts/ecvrf_vectors.py
+import hashlib, hmac
+p = 2**256 - 2**32 - 977
+n = 0xFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141
+G = (0x79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798,
+     0x483ADA7726A3C4655DA4FBFC0E1108A8FD17B448A68554199C47D08FFB10D4B8)
+def add(P, Q):
+    if P is None: return Q
+    if Q is None: return P
+    if P[0] == Q[0] and (P[1] + Q[1]) % p == 0: return None
+    if P == Q: l = 3 * P[0] * P[0] * pow(2 * P[1], -1, p) % p
+    else: l = (Q[1] - P[1]) * pow(Q[0] - P[0], -1, p) % p
+    x = (l * l - P[0] - Q[0]) % p
+    return (x, (l * (P[0] - x) - P[1]) % p)
+def mul(k, P):
+    R = None
+    while k:
+        if k & 1: R = add(R, P)
+        P = add(P, P); k >>= 1
+    return R
+def neg(P): return (P[0], (-P[1]) % p)
+def enc(P): return bytes([2 + (P[1] & 1)]) + P[0].to_bytes(32, 'big')
+def dec(b):
+    if len(b) != 33 or b[0] not in (2, 3): return None
+    x = int.from_bytes(b[1:], 'big')
+    if x >= p: return None
+    y2 = (pow(x, 3, p) + 7) % p
+    y = pow(y2, (p + 1) // 4, p)
+    if y * y % p != y2: return None
+    if (y & 1) != (b[0] & 1): y = p - y
+    return (x, y)
+H = lambda b: hashlib.sha256(b).digest()
+S = b'\xfe'
+def encode_to_curve(pk, alpha):
+    for ctr in range(256):
+        P = dec(b'\x02' + H(S + b'\x01' + pk + alpha + bytes([ctr]) + b'\x00'))
+        if P: return P
+def rfc6979(x, h1, q=n, hf=hashlib.sha256):
+    qlen = q.bit_length(); rlen = (qlen + 7) // 8
+    def bits2int(b):
+        v = int.from_bytes(b, 'big'); bl = len(b) * 8
+        return v >> (bl - qlen) if bl > qlen else v
+    def int2oct(v): return v.to_bytes(rlen, 'big')
+    def bits2oct(b): return int2oct(bits2int(b) % q)
+    V = b'\x01' * 32; K = b'\x00' * 32
+    K = hmac.new(K, V + b'\x00' + int2oct(x) + bits2oct(h1), hf).digest(); V = hmac.new(K, V, hf).digest()
+    K = hmac.new(K, V + b'\x01' + int2oct(x) + bits2oct(h1), hf).digest(); V = hmac.new(K, V, hf).digest()
+    while True:
+        T = b''
+        while len(T) < rlen:
+            V = hmac.new(K, V, hf).digest(); T += V
+        k = bits2int(T)
+        if 1 <= k < q: return k
+        K = hmac.new(K, V + b'\x00', hf).digest(); V = hmac.new(K, V, hf).digest()
+def challenge(*pts):
+    return H(S + b'\x02' + b''.join(enc(P) for P in pts) + b'\x00')[:16]
+def prove(x, alpha):
+    Y = mul(x, G); h = encode_to_curve(enc(Y), alpha); hs = enc(h)
+    gamma = mul(x, h)
+    k = rfc6979(x, H(hs))
+    c = challenge(Y, h, gamma, mul(k, G), mul(k, h))
+    s = (k + int.from_bytes(c, 'big') * x) % n
+    return enc(gamma) + c + s.to_bytes(32, 'big')
+def proof_to_hash(pi):
+    return H(S + b'\x03' + pi[:33] + b'\x00')
+def verify(Y, alpha, pi):
+    gamma = dec(pi[:33]); c = pi[33:49]; s = int.from_bytes(pi[49:], 'big')
+    if gamma is None or s >= n: return None
+    h = encode_to_curve(enc(Y), alpha); ci = int.from_bytes(c, 'big')
+    U = add(mul(s, G), neg(mul(ci, Y))); V = add(mul(s, h), neg(mul(ci, gamma)))
+    return proof_to_hash(pi) if challenge(Y, h, gamma, U, V) == c else None
+
+if __name__ == '__main__':
+    # RFC 6979 A.2.5, P-256 / SHA-256 / "sample": checks the nonce derivation
+    q256 = 0xFFFFFFFF00000000FFFFFFFFFFFFFFFFBCE6FAADA7179E84F3B9CAC2FC632551
+    x256 = 0xC9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721
+    k = rfc6979(x256, H(b'sample'), q256)
+    assert k == 0xA6E3C57DD01ABE90086538398355DD4C3B17AA873382B0F24D6129493D8AAD60, hex(k)
+    assert rfc6979(x256, H(b'test'), q256) == 0xD16B6AE827F17175E040871A1C7EC3500192C4C92677336EC2537ACAEE0008E0
+    for x, alpha in [(1, b''), (0xC9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721, b'sample'),
+                     (0xC9AFA9D845BA75166B5C215767B1D6934E50C3DB36E89B127B8A622B120F6721, b'test'),
+                     (n - 1, bytes(range(32)))]:
+        pi = prove(x, alpha)
+        Y = mul(x, G)
+        assert verify(Y, alpha, pi) == proof_to_hash(pi)
+        print('x    ', x.to_bytes(32, 'big').hex())
+        print('alpha', alpha.hex())
+        print('H    ', enc(encode_to_curve(enc(Y), alpha)).hex())
+        print('pi   ', pi.hex())
+        print('beta ', proof_to_hash(pi).hex())
+        print()
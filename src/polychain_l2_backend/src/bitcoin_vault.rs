@@ -1,53 +1,126 @@
+use crate::errors::VaultError;
+use crate::types::{PendingWithdrawal, SupportedChain, WithdrawalSource};
 use candid::{CandidType, Deserialize};
-use std::collections::HashMap;
+use std::collections::HashSet;
 
+/// Réserves BTC mises en commun: le solde de chaque compte vit dans le ledger L2,
+/// le vault ne garde que ce qui est détenu sur Bitcoin pour l'ensemble des comptes
 #[derive(CandidType, Deserialize, Clone, Debug)]
 pub struct BitcoinVault {
-    pub native_reserves: HashMap<String, u64>,
-    pub wrapped_balances: HashMap<String, u64>,
+    pub native_reserve: u64,
+    pub wrapped_reserve: u64,
+    /// Adresses ayant déposé dans chaque réserve, pour les statistiques
+    pub native_depositors: HashSet<String>,
+    pub wrapped_depositors: HashSet<String>,
     pub deposit_threshold: u64,
     pub total_deposits: u64,
     pub transaction_count: u64,
+    pub pending_withdrawals: Vec<PendingWithdrawal>,
 }
 
 impl BitcoinVault {
     pub fn new() -> Self {
         Self {
-            native_reserves: HashMap::new(),
-            wrapped_balances: HashMap::new(),
+            native_reserve: 0,
+            wrapped_reserve: 0,
+            native_depositors: HashSet::new(),
+            wrapped_depositors: HashSet::new(),
             deposit_threshold: 100_000,
             total_deposits: 0,
             transaction_count: 0,
+            pending_withdrawals: Vec::new(),
         }
     }
 
-    pub fn deposit_bitcoin(&mut self, address: String, amount: u64) -> String {
-        self.total_deposits += amount;
+    /// Rien n'est modifié si le total des réserves déborderait
+    pub fn deposit_bitcoin(&mut self, address: String, amount: u64) -> Result<String, VaultError> {
+        // Chaque réserve est bornée par le total
+        self.total_deposits = self
+            .total_deposits
+            .checked_add(amount)
+            .ok_or(VaultError::Overflow(SupportedChain::Bitcoin))?;
         self.transaction_count += 1;
 
         if amount >= self.deposit_threshold {
             // Gros montant = Bitcoin natif (sécurité max)
-            *self.native_reserves.entry(address.clone()).or_insert(0) += amount;
-            format!("Deposited {amount} satoshi as NATIVE Bitcoin for {address}",)
+            self.native_reserve += amount;
+            self.native_depositors.insert(address.clone());
+            Ok(format!(
+                "Deposited {amount} satoshi as NATIVE Bitcoin for {address}",
+            ))
         } else {
             // Micro-montant = Wrapped optimisé (performance max)
-            *self.wrapped_balances.entry(address.clone()).or_insert(0) += amount;
-            format!("Deposited {amount} satoshi as WRAPPED Bitcoin for {address}",)
+            self.wrapped_reserve += amount;
+            self.wrapped_depositors.insert(address.clone());
+            Ok(format!(
+                "Deposited {amount} satoshi as WRAPPED Bitcoin for {address}",
+            ))
         }
     }
 
-    pub fn get_balance(&self, address: &str) -> (u64, u64) {
-        let native = self.native_reserves.get(address).copied().unwrap_or(0);
-        let wrapped = self.wrapped_balances.get(address).copied().unwrap_or(0);
-        (native, wrapped)
+    /// Réserves (natif, wrapped) détenues pour tous les comptes
+    pub fn reserves(&self) -> (u64, u64) {
+        (self.native_reserve, self.wrapped_reserve)
     }
 
-    pub fn withdraw_bitcoin(&mut self, address: String, amount: u64, _crypto_algo: &str) -> String {
+    /// Prélever un retrait déjà couvert par le solde L2 de `address` sur les réserves
+    pub fn withdraw_bitcoin(
+        &mut self,
+        address: String,
+        amount: u64,
+        crypto_algo: &str,
+    ) -> Result<PendingWithdrawal, VaultError> {
+        if amount == 0 {
+            return Err(VaultError::ZeroAmount);
+        }
+
+        // Même règle qu'au dépôt: gros montant depuis le natif, micro-montant depuis le wrapped,
+        // l'autre réserve paie ce que la première ne couvre pas
+        let (native, wrapped) = self.reserves();
+        let source = if amount >= self.deposit_threshold {
+            WithdrawalSource::Native
+        } else {
+            WithdrawalSource::Wrapped
+        };
+        let (from_native, from_wrapped) = source
+            .split(amount.into(), native.into(), wrapped.into())
+            .ok_or(VaultError::InsufficientReserves {
+                asset: SupportedChain::Bitcoin,
+                native: native.into(),
+                wrapped: wrapped.into(),
+                requested: amount.into(),
+            })?;
+        // Chaque portion est bornée par `amount`, un u64
+        self.native_reserve -= from_native as u64;
+        self.wrapped_reserve -= from_wrapped as u64;
+        self.total_deposits = self.total_deposits.saturating_sub(amount);
+
         // Generate cryptographically secure transaction ID using Blake3
         let tx_data = format!("{}:{}:{}", address, amount, self.transaction_count);
         let tx_id = blake3::hash(tx_data.as_bytes());
         self.transaction_count += 1;
 
-        hex::encode(tx_id.as_bytes())
+        let withdrawal = PendingWithdrawal {
+            tx_id: hex::encode(tx_id.as_bytes()),
+            address,
+            asset: SupportedChain::Bitcoin,
+            amount: amount.into(),
+            source,
+            crypto_algo: crypto_algo.to_string(),
+            native_amount: Some(from_native),
+            wrapped_amount: Some(from_wrapped),
+        };
+        self.pending_withdrawals.push(withdrawal.clone());
+        Ok(withdrawal)
+    }
+
+    /// Retirer un retrait payé sur Bitcoin de la liste d'attente
+    pub fn complete_withdrawal(&mut self, tx_id: &str) -> Result<PendingWithdrawal, VaultError> {
+        let index = self
+            .pending_withdrawals
+            .iter()
+            .position(|withdrawal| withdrawal.tx_id == tx_id)
+            .ok_or_else(|| VaultError::UnknownWithdrawal(tx_id.to_string()))?;
+        Ok(self.pending_withdrawals.remove(index))
     }
 }
@@ -1,21 +1,46 @@
 use crate::{
-    cryptography::bridge::CryptographyBridge,
-    errors::CryptographyError,
-    types::{PolyBlock, PolyTransaction},
+    clock,
+    commitment::{Commitment, CommitmentBook, CommitmentSubmission},
+    cryptography::bridge::{CryptographyBridge, VrfBridge},
+    cryptography::verify_signature,
+    errors::{ChainError, ConsensusError, CryptographyError, SequencerError},
+    fairness::OrderingFairness,
+    fees::FeeMarket,
+    mempool::{Mempool, MempoolConfig},
+    pacemaker::{Pacemaker, PacemakerConfig},
+    types::{
+        ConsensusStep, OrderingProof, PolyBlock, PolyTransaction, ProposerVrf, QuorumCertificate,
+        QuorumThreshold, TimeoutCertificate, Vote,
+    },
+    validators::EpochSnapshot,
 };
-use std::collections::HashMap;
+use candid::{CandidType, Deserialize};
+use std::collections::{HashMap, HashSet};
 
 /// Transaction Sequencer for fair and deterministic ordering
 #[derive(Debug)]
 pub struct TransactionSequencer<C: CryptographyBridge> {
-    pending_transactions: Vec<PolyTransaction>,
+    mempool: Mempool,
+    /// Commit-reveal commitments waiting for their transaction
+    commitments: CommitmentBook,
     sequence_counter: u64,
     ordering_strategy: OrderingStrategy,
+    /// Next nonce each sender must use, counting transactions already admitted
+    next_nonces: HashMap<String, u64>,
+    fee_market: FeeMarket,
+    /// Ids of transactions replaced, evicted or expired since the last `take_dropped`
+    dropped: Vec<String>,
+    /// Secret seed of the key signing blocks and evaluating the ordering VRF, drawn once
+    /// and persisted with the sequencer
+    proposer_seed: Option<[u8; 32]>,
+    /// Key pair derived from `proposer_seed`
+    proposer_key: Option<(C::PublicKey, C::SecretKey)>,
+    /// Hex public key blocks and ordering proofs are verified against
+    public_key: Option<String>,
     cryptography: C,
-    private_keys: HashMap<String, C::SecretKey>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
 pub enum OrderingStrategy {
     FirstComeFirstServed,
     PriorityFee,
@@ -23,54 +48,482 @@ pub enum OrderingStrategy {
     VrfRandom,
 }
 
-impl<C: CryptographyBridge> TransactionSequencer<C> {
-    pub fn new(cryptography: C, private_keys: HashMap<String, C::SecretKey>) -> Self {
+/// Sequencer state persisted across upgrades. The key seed only ever leaves the
+/// canister heap for its stable memory, no endpoint returns a snapshot.
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct SequencerSnapshot {
+    pub mempool: Mempool,
+    pub commitments: CommitmentBook,
+    pub sequence_counter: u64,
+    pub ordering_strategy: OrderingStrategy,
+    pub next_nonces: HashMap<String, u64>,
+    pub fee_market: FeeMarket,
+    /// Secret seed of the sequencer key, `None` until one is drawn
+    pub proposer_seed: Option<Vec<u8>>,
+    /// Published sequencer key
+    pub public_key: Option<String>,
+}
+
+impl<C: VrfBridge> TransactionSequencer<C> {
+    pub fn new(cryptography: C) -> Self {
         Self {
-            pending_transactions: Vec::new(),
+            mempool: Mempool::default(),
+            commitments: CommitmentBook::default(),
             sequence_counter: 0,
             ordering_strategy: OrderingStrategy::FairOrdering,
+            next_nonces: HashMap::new(),
+            fee_market: FeeMarket::default(),
+            dropped: Vec::new(),
+            proposer_seed: None,
+            proposer_key: None,
+            public_key: None,
             cryptography,
-            private_keys,
         }
     }
 
-    pub fn add_transaction(&mut self, transaction: PolyTransaction) -> Result<String, String> {
-        if !transaction.is_valid() {
-            return Err("Invalid transaction".to_string());
+    pub fn from_snapshot(cryptography: C, snapshot: SequencerSnapshot) -> Self {
+        let proposer_seed = snapshot
+            .proposer_seed
+            .and_then(|seed| <[u8; 32]>::try_from(seed).ok());
+        let proposer_key = proposer_seed.and_then(|seed| cryptography.key_from_seed(&seed).ok());
+        Self {
+            mempool: snapshot.mempool,
+            commitments: snapshot.commitments,
+            sequence_counter: snapshot.sequence_counter,
+            ordering_strategy: snapshot.ordering_strategy,
+            next_nonces: snapshot.next_nonces,
+            fee_market: snapshot.fee_market,
+            dropped: Vec::new(),
+            proposer_seed,
+            proposer_key,
+            public_key: snapshot.public_key,
+            cryptography,
+        }
+    }
+
+    pub fn snapshot(&self) -> SequencerSnapshot {
+        SequencerSnapshot {
+            mempool: self.mempool.clone(),
+            commitments: self.commitments.clone(),
+            sequence_counter: self.sequence_counter,
+            ordering_strategy: self.ordering_strategy.clone(),
+            next_nonces: self.next_nonces.clone(),
+            fee_market: self.fee_market.clone(),
+            proposer_seed: self.proposer_seed.map(Vec::from),
+            public_key: self.public_key.clone(),
         }
+    }
+
+    /// Derive the sequencer key from secret randomness and publish its public half
+    pub fn set_proposer_seed(&mut self, seed: [u8; 32]) -> Result<(), SequencerError> {
+        let (public_key, secret_key) = self
+            .cryptography
+            .key_from_seed(&seed)
+            .map_err(|e| SequencerError::BlockSigning(e.to_string()))?;
+        self.public_key = Some(hex::encode(
+            self.cryptography.public_key_to_bytes(&public_key),
+        ));
+        self.proposer_seed = Some(seed);
+        self.proposer_key = Some((public_key, secret_key));
+        Ok(())
+    }
+
+    /// Publish a key without its secret, e.g. the key of a run being replayed: blocks
+    /// verify against it but this sequencer cannot sign or evaluate the VRF
+    pub fn set_public_key(&mut self, public_key: String) {
+        self.public_key = Some(public_key);
+    }
+
+    /// Hex public key blocks and ordering proofs are verified against, `None` until
+    /// the key is provisioned
+    pub fn public_key(&self) -> Option<&str> {
+        self.public_key.as_deref()
+    }
+
+    /// Admit a transaction submitted in the clear, it takes the next ordering slot
+    pub fn add_transaction(
+        &mut self,
+        transaction: PolyTransaction,
+    ) -> Result<String, SequencerError> {
+        let tx_id = self.admit(transaction, self.sequence_counter)?;
+        self.sequence_counter += 1;
+        Ok(tx_id)
+    }
 
-        self.pending_transactions.push(transaction);
+    /// Reserve the next ordering slot for a transaction that is only revealed later, by
+    /// the sender that signed the commitment
+    pub fn submit_commitment(
+        &mut self,
+        submission: &CommitmentSubmission,
+    ) -> Result<u64, SequencerError> {
+        submission.verify()?;
+        let now = i64::try_from(clock::now()).unwrap_or(i64::MAX);
+        self.expire_transactions(now);
+        let slot = self.sequence_counter;
+        let config = self.mempool.config();
+        let (capacity, per_sender) = (config.max_size, config.max_per_sender);
+        self.commitments.commit(
+            &submission.commitment,
+            &submission.sender,
+            slot,
+            now,
+            capacity,
+            per_sender,
+        )?;
         self.sequence_counter += 1;
-        Ok(format!("seq_tx_{}", self.sequence_counter))
+        Ok(slot)
+    }
+
+    /// Admit the transaction behind a commitment, in the slot reserved when it was committed
+    pub fn reveal_transaction(
+        &mut self,
+        transaction: PolyTransaction,
+        salt: &[u8],
+    ) -> Result<String, SequencerError> {
+        let window = self.mempool.config().reveal_window_seconds;
+        let commitment = self
+            .commitments
+            .find_open(&transaction, salt, window)?
+            .clone();
+        let tx_id = self.admit(transaction, commitment.slot)?;
+        self.commitments.remove(&commitment.hash);
+        Ok(tx_id)
+    }
+
+    pub fn commitment(&self, hash: &str) -> Option<&Commitment> {
+        self.commitments.get(&hash.to_lowercase())
     }
 
-    pub fn sequence_batch(&mut self, batch_size: usize) -> Vec<PolyTransaction> {
-        let take_count = batch_size.min(self.pending_transactions.len());
+    fn admit(&mut self, transaction: PolyTransaction, slot: u64) -> Result<String, SequencerError> {
+        if !transaction.is_valid() {
+            return Err(SequencerError::InvalidTransaction);
+        }
+        self.expire_transactions(transaction.time_stamp);
+
+        // Whether the key may spend for the sender is the ledger's call, see `AccountLedger`
+        Self::verify_transaction_signature(&transaction)?;
+
+        // Un plafond sous le prix de base courant ne serait jamais exécutable
+        let base_fee = self.fee_market.base_fee(transaction.asset);
+        if transaction.max_fee < base_fee {
+            return Err(SequencerError::FeeTooLow {
+                sender: transaction.sender,
+                max_fee: transaction.max_fee,
+                base_fee,
+            });
+        }
+
+        // Replay protection: each sender's nonces must be used once, in order,
+        // unless the transaction outbids the pending one with the same nonce
+        let expected = self.next_nonce(&transaction.sender);
+        let replacing = self
+            .mempool
+            .contains(&transaction.sender, transaction.nonce);
+        match transaction.nonce.cmp(&expected) {
+            _ if replacing => {}
+            std::cmp::Ordering::Less => {
+                return Err(SequencerError::NonceAlreadyUsed {
+                    sender: transaction.sender,
+                    expected,
+                    nonce: transaction.nonce,
+                })
+            }
+            std::cmp::Ordering::Greater => {
+                return Err(SequencerError::NonceOutOfOrder {
+                    sender: transaction.sender,
+                    expected,
+                    nonce: transaction.nonce,
+                })
+            }
+            std::cmp::Ordering::Equal => {}
+        }
+
+        let sender = transaction.sender.clone();
+        let tx_id = transaction.tx_id();
+        let insertion = self.mempool.insert(transaction, slot, &self.fee_market)?;
+        match insertion.replaced {
+            Some(replaced) => self.dropped.push(replaced.tx_id()),
+            None => {
+                self.next_nonces.insert(sender.clone(), expected + 1);
+            }
+        }
+        if let Some(evicted) = insertion.evicted {
+            self.release(vec![evicted]);
+        }
+        Ok(tx_id)
+    }
+
+    /// Drop the pending transactions whose TTL ran out at `now`, returns how many went.
+    /// Commitments left unrevealed past their window go too.
+    pub fn expire_transactions(&mut self, now: i64) -> usize {
+        let window = self.mempool.config().reveal_window_seconds;
+        self.commitments.expire(now, window);
+        let expired = self.mempool.expire(now);
+        let count = expired.len();
+        self.release(expired);
+        count
+    }
+
+    /// Forget transactions removed from the mempool without being sequenced:
+    /// their senders get the lowest of these nonces back
+    fn release(&mut self, transactions: Vec<PolyTransaction>) {
+        for tx in transactions {
+            let next_nonce = self.next_nonces.entry(tx.sender.clone()).or_insert(0);
+            *next_nonce = (*next_nonce).min(tx.nonce);
+            self.dropped.push(tx.tx_id());
+        }
+    }
+
+    /// Ids of the transactions that left the mempool without being sequenced
+    pub fn take_dropped(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.dropped)
+    }
+
+    /// Order the queue with the current strategy and take the next batch to build on
+    /// `previous_hash`. Under VrfRandom the batch carries the proof of its permutation.
+    pub fn sequence_batch(
+        &mut self,
+        previous_hash: &str,
+        batch_size: usize,
+    ) -> Result<SequencedBatch, SequencerError> {
+        self.order_and_take(previous_hash, batch_size, VrfSource::Evaluate)
+    }
+
+    /// Sequence a batch again as the original run did, ordering a VrfRandom batch by the
+    /// VRF evaluation that run logged instead of evaluating with this sequencer's key
+    pub fn replay_batch(
+        &mut self,
+        previous_hash: &str,
+        batch_size: usize,
+        ordering_proof: Option<&OrderingProof>,
+    ) -> Result<SequencedBatch, SequencerError> {
+        self.order_and_take(
+            previous_hash,
+            batch_size,
+            VrfSource::Recorded(ordering_proof),
+        )
+    }
+
+    fn order_and_take(
+        &mut self,
+        previous_hash: &str,
+        batch_size: usize,
+        vrf: VrfSource,
+    ) -> Result<SequencedBatch, SequencerError> {
+        let mut ordering_proof = None;
 
         match self.ordering_strategy {
             OrderingStrategy::FirstComeFirstServed => {
                 // Simple FIFO
-                self.pending_transactions.drain(0..take_count).collect()
             }
-            OrderingStrategy::FairOrdering => {
-                // Anti-MEV fair ordering using cryptographically secure hash
-                self.pending_transactions.sort_by_key(|tx| {
-                    let combined_data = format!("{}:{}", tx.sender, tx.time_stamp);
-                    let hash = blake3::hash(combined_data.as_bytes());
-                    // Convert first 8 bytes to u64 for sorting
-                    u64::from_le_bytes(hash.as_bytes()[0..8].try_into().unwrap_or([0; 8]))
+            OrderingStrategy::PriorityFee => {
+                // Highest tip over the base fee first, arrival order among equal tips
+                let fee_market = &self.fee_market;
+                self.mempool.reorder(|txs| {
+                    txs.sort_by_key(|tx| std::cmp::Reverse(fee_market.priority_score(tx)))
                 });
-                self.pending_transactions.drain(0..take_count).collect()
             }
-            _ => {
-                // Default to FIFO for other strategies
-                self.pending_transactions.drain(0..take_count).collect()
+            OrderingStrategy::FairOrdering => {
+                // Anti-MEV: slots are handed out by the sequencer, a committed
+                // transaction got its slot before its contents were revealed
+                self.mempool.sort_by_slot();
+            }
+            OrderingStrategy::VrfRandom => {
+                // Nobody, the sequencer included, picks the order: it follows from the VRF output
+                let proof = match vrf {
+                    VrfSource::Evaluate => self.evaluate_ordering_vrf(previous_hash)?,
+                    VrfSource::Recorded(proof) => self.recorded_vrf(proof, previous_hash)?,
+                };
+                let seed = crate::crypto::decode_hash(&proof.output)
+                    .ok_or_else(|| SequencerError::Randomness("malformed VRF output".into()))?;
+                self.mempool
+                    .reorder(|txs| txs.sort_by_cached_key(|tx| vrf_ordering_key(&seed, tx)));
+                ordering_proof = Some(proof);
             }
         }
+
+        // Whatever the strategy, a sender's transactions leave in nonce order
+        self.mempool.reorder(Self::enforce_nonce_order);
+        let fairness = OrderingFairness::measure(&self.mempool.arrival_ranks(), batch_size);
+        Ok(SequencedBatch {
+            transactions: self.mempool.take_front(batch_size),
+            strategy: self.ordering_strategy.clone(),
+            fairness,
+            ordering_proof,
+        })
+    }
+
+    /// Key pair the sequencer signs and evaluates the VRF with
+    fn proposer_key(&self) -> Result<&(C::PublicKey, C::SecretKey), SequencerError> {
+        self.proposer_key
+            .as_ref()
+            .ok_or(SequencerError::KeyNotProvisioned)
+    }
+
+    /// Hex public key blocks are signed with
+    pub fn proposer_public_key(&self) -> Result<String, SequencerError> {
+        self.proposer_key()?;
+        self.public_key
+            .clone()
+            .ok_or(SequencerError::KeyNotProvisioned)
+    }
+
+    /// Name the sequencer as the block's proposer and sign the resulting hash
+    pub fn sign_block(&self, block: PolyBlock) -> Result<PolyBlock, SequencerError> {
+        let signing = |e: CryptographyError| SequencerError::BlockSigning(e.to_string());
+        let public_key = self.proposer_public_key()?;
+        let mut block = block.with_proposer(C::ALGORITHM, public_key);
+        let (_, secret_key) = self.proposer_key()?;
+
+        let signature = self
+            .cryptography
+            .sign(secret_key, &block.signing_message())
+            .map_err(signing)?;
+        let signature = hex::encode(self.cryptography.signature_to_bytes(&signature));
+        if let Some(proposer) = block.proposer.as_mut() {
+            proposer.signature = signature;
+        }
+        Ok(block)
+    }
+
+    fn evaluate_ordering_vrf(&self, previous_hash: &str) -> Result<OrderingProof, SequencerError> {
+        let randomness = |e: CryptographyError| SequencerError::Randomness(e.to_string());
+        let public_key = self.proposer_public_key()?;
+        let (_, secret_key) = self.proposer_key()?;
+
+        let proof = self
+            .cryptography
+            .vrf_prove(secret_key, &OrderingProof::message(previous_hash))
+            .map_err(randomness)?;
+        let output = self
+            .cryptography
+            .vrf_proof_to_hash(&proof)
+            .map_err(randomness)?;
+        Ok(OrderingProof {
+            algorithm: C::ALGORITHM,
+            public_key,
+            input: previous_hash.to_string(),
+            output: hex::encode(output),
+            proof: hex::encode(proof),
+        })
+    }
+
+    /// A logged VRF evaluation still has to be over `previous_hash` and verify under
+    /// the published key
+    fn recorded_vrf(
+        &self,
+        proof: Option<&OrderingProof>,
+        previous_hash: &str,
+    ) -> Result<OrderingProof, SequencerError> {
+        let proof = proof.ok_or_else(|| {
+            SequencerError::Randomness("no VRF evaluation was logged for this batch".into())
+        })?;
+        let public_key = self.public_key().ok_or(SequencerError::KeyNotProvisioned)?;
+        if proof.input != previous_hash || !proof.verify(public_key) {
+            return Err(SequencerError::Randomness(format!(
+                "logged VRF proof does not verify over {previous_hash}"
+            )));
+        }
+        Ok(proof.clone())
+    }
+
+    /// Check the signature over the canonical payload with the scheme the sender named
+    fn verify_transaction_signature(transaction: &PolyTransaction) -> Result<(), SequencerError> {
+        let (Some(algorithm), Some(public_key), Some(signature)) = (
+            transaction.algorithm,
+            transaction.public_key.as_ref(),
+            transaction.signature.as_ref(),
+        ) else {
+            return Err(SequencerError::MissingSignature(transaction.sender.clone()));
+        };
+
+        let invalid = |reason: String| SequencerError::InvalidSignature {
+            sender: transaction.sender.clone(),
+            reason,
+        };
+        let public_key_bytes =
+            hex::decode(public_key).map_err(|e| invalid(format!("public key is not hex: {e}")))?;
+        let signature_bytes =
+            hex::decode(signature).map_err(|e| invalid(format!("signature is not hex: {e}")))?;
+
+        let is_valid = verify_signature(
+            algorithm,
+            &public_key_bytes,
+            &transaction.signing_payload(),
+            &signature_bytes,
+        )
+        .map_err(|e| invalid(e.to_string()))?;
+        if !is_valid {
+            return Err(invalid(format!("{algorithm:?} verification failed")));
+        }
+        Ok(())
+    }
+
+    /// Sort each sender's transactions by nonce within the slots the strategy gave them,
+    /// so any prefix of the queue holds every sender's lowest nonces
+    fn enforce_nonce_order(transactions: &mut [PolyTransaction]) {
+        let mut slots_by_sender: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (slot, tx) in transactions.iter().enumerate() {
+            slots_by_sender.entry(&tx.sender).or_default().push(slot);
+        }
+
+        let reorders: Vec<(Vec<usize>, Vec<PolyTransaction>)> = slots_by_sender
+            .into_values()
+            .filter(|slots| slots.len() > 1)
+            .map(|slots| {
+                let mut sender_txs: Vec<PolyTransaction> = slots
+                    .iter()
+                    .map(|&slot| transactions[slot].clone())
+                    .collect();
+                sender_txs.sort_by_key(|tx| tx.nonce);
+                (slots, sender_txs)
+            })
+            .collect();
+
+        for (slots, sender_txs) in reorders {
+            for (slot, tx) in slots.into_iter().zip(sender_txs) {
+                transactions[slot] = tx;
+            }
+        }
+    }
+
+    /// Nonce the sender's next transaction must carry
+    pub fn next_nonce(&self, sender: &str) -> u64 {
+        self.next_nonces.get(sender).copied().unwrap_or(0)
+    }
+
+    pub fn fee_market(&self) -> &FeeMarket {
+        &self.fee_market
+    }
+
+    /// Feed a sealed block's size back into the base fees
+    pub fn record_block(&mut self, block_size: u64) {
+        self.fee_market.record_block(block_size);
+    }
+
+    pub fn mempool(&self) -> &Mempool {
+        &self.mempool
+    }
+
+    pub fn set_mempool_config(&mut self, config: MempoolConfig) {
+        self.mempool.set_config(config);
+    }
+
+    pub fn pending_transactions(&self) -> &[PolyTransaction] {
+        self.mempool.transactions()
     }
 
     pub fn pending_count(&self) -> usize {
-        self.pending_transactions.len()
+        self.mempool.len()
+    }
+
+    /// Nothing waiting: no pending transaction and no commitment left to expire
+    pub fn is_idle(&self) -> bool {
+        self.mempool.len() == 0 && self.commitments.is_empty()
+    }
+
+    pub fn ordering_strategy(&self) -> &OrderingStrategy {
+        &self.ordering_strategy
     }
 
     pub fn set_ordering_strategy(&mut self, strategy: OrderingStrategy) {
@@ -78,103 +531,363 @@ impl<C: CryptographyBridge> TransactionSequencer<C> {
     }
 }
 
-/// PoS Consensus with VRF (ton code)
-// Removed std::time::Instant import for IC compatibility
+/// Where a VrfRandom batch gets its VRF output
+enum VrfSource<'a> {
+    /// Evaluated with the sequencer key
+    Evaluate,
+    /// Logged by the run being replayed
+    Recorded(Option<&'a OrderingProof>),
+}
+
+/// Batch taken off the sequencer queue, in execution order
+#[derive(Debug, Clone)]
+pub struct SequencedBatch {
+    pub transactions: Vec<PolyTransaction>,
+    /// Strategy the batch was ordered with
+    pub strategy: OrderingStrategy,
+    pub ordering_proof: Option<OrderingProof>,
+    /// Order of the batch against arrival order
+    pub fairness: OrderingFairness,
+}
+
+/// VrfRandom sort key: senders are shuffled by the seed, each sender's transactions stay
+/// contiguous and in nonce order. Any subset of a batch is still sorted by this key,
+/// so a block missing its ledger-rejected transactions remains verifiable.
+pub fn vrf_ordering_key(seed: &[u8; 32], tx: &PolyTransaction) -> ([u8; 32], u64) {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(seed);
+    hasher.update(tx.sender.as_bytes());
+    (hasher.finalize().into(), tx.nonce)
+}
+
+/// Check a VrfRandom block: proof under the published sequencer key over its parent
+/// hash, and transactions in key order
+pub fn verify_ordering(block: &PolyBlock, sequencer_key: Option<&str>) -> Result<(), String> {
+    let Some(strategy) = &block.ordering else {
+        return Ok(());
+    };
+    let proof = match (strategy, &block.ordering_proof) {
+        (OrderingStrategy::VrfRandom, Some(proof)) => proof,
+        (OrderingStrategy::VrfRandom, None) => {
+            return Err("VrfRandom block carries no VRF proof".to_string())
+        }
+        (_, None) => return Ok(()),
+        (_, Some(_)) => return Err(format!("{strategy:?} block carries a VRF proof")),
+    };
+    if proof.input != block.previous_hash {
+        return Err("VRF input is not the previous block hash".to_string());
+    }
+    let sequencer_key = sequencer_key.ok_or("no sequencer key is published")?;
+    if !proof.verify(sequencer_key) {
+        return Err("VRF proof does not verify under the sequencer key".to_string());
+    }
+    let seed = crate::crypto::decode_hash(&proof.output)
+        .ok_or_else(|| "malformed VRF output".to_string())?;
+    let in_order = block
+        .transactions
+        .windows(2)
+        .all(|pair| vrf_ordering_key(&seed, &pair[0]) <= vrf_ordering_key(&seed, &pair[1]));
+    if !in_order {
+        return Err("transactions are not in VRF order".to_string());
+    }
+    Ok(())
+}
+
+/// `previous_hash` of the genesis block
+pub const GENESIS_PREVIOUS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Check a block against its own header, its parent (`None` for the genesis block) and
+/// the published sequencer key. From `signed_from` on, every block must be signed under
+/// that key; blocks below it were sealed before the key was published.
+pub fn verify_block(
+    height: u64,
+    block: &PolyBlock,
+    parent: Option<&PolyBlock>,
+    sequencer_key: Option<&str>,
+    signed_from: u64,
+) -> Result<(), ChainError> {
+    if block.tx_root != hex::encode(block.calculate_tx_root()) {
+        return Err(ChainError::TxRootMismatch { height });
+    }
+
+    let computed = block.calculate_hash();
+    if block.hash != computed {
+        return Err(ChainError::HashMismatch {
+            height,
+            stored: block.hash.clone(),
+            computed,
+        });
+    }
+
+    let expected = parent.map_or(GENESIS_PREVIOUS_HASH, |parent| parent.hash.as_str());
+    if block.previous_hash != expected {
+        return Err(ChainError::BrokenLink {
+            height,
+            previous_hash: block.previous_hash.clone(),
+            expected: expected.to_string(),
+        });
+    }
+
+    verify_ordering(block, sequencer_key)
+        .map_err(|reason| ChainError::InvalidOrdering { height, reason })?;
+
+    if height >= signed_from {
+        if block.proposer.is_none() {
+            return Err(ChainError::MissingProposer { height });
+        }
+        let sequencer_key = sequencer_key.ok_or_else(|| ChainError::InvalidSignature {
+            height,
+            reason: "no sequencer key is published".to_string(),
+        })?;
+        block
+            .verify_signature(sequencer_key)
+            .map_err(|reason| ChainError::InvalidSignature { height, reason })?;
+    } else if let Some(proposer) = &block.proposer {
+        // Sealed before the key was published: only its own signature can be checked
+        block
+            .verify_signature(&proposer.public_key)
+            .map_err(|reason| ChainError::InvalidSignature { height, reason })?;
+    }
+
+    if let Some(parent) = parent {
+        if block.timestamp < parent.timestamp {
+            return Err(ChainError::TimestampRegression {
+                height,
+                timestamp: block.timestamp,
+                parent_timestamp: parent.timestamp,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Walk `blocks`, the chain from height `start` on, and stop at the first invalid block.
+/// `parent` is the block at `start - 1`, `None` when `blocks` starts at genesis.
+pub fn verify_chain(
+    start: u64,
+    parent: Option<&PolyBlock>,
+    blocks: &[PolyBlock],
+    sequencer_key: Option<&str>,
+    signed_from: u64,
+) -> Result<(), ChainError> {
+    let mut parent = parent;
+    for (height, block) in (start..).zip(blocks) {
+        verify_block(height, block, parent, sequencer_key, signed_from)?;
+        parent = Some(block);
+    }
+    Ok(())
+}
+
+// PoS Consensus with VRF (ton code)
+// Time comes from the runtime clock (`clock::now`), never from the IC directly
+
+type ProposerResult = Result<Vec<ProposerTicket>, ConsensusError>;
+
+/// Stake and decoded key of each validator, by address
+type Members<K> = HashMap<String, (u128, K)>;
 
-type ProposerResult<C> = Result<Vec<(String, Vec<u8>, C)>, CryptographyError>;
+/// Committee seat drawn through the VRF, ranked by `priority`
+#[derive(Debug, Clone)]
+pub struct ProposerTicket {
+    /// VRF output scaled by the validator's share of the stake
+    pub priority: f64,
+    pub vrf: ProposerVrf,
+}
 
 #[derive(Debug)]
 pub struct AlgoConsensus<C: CryptographyBridge>
 where
     C::SignedMessage: std::fmt::Debug + Clone + Sync,
 {
+    /// Stake share a validator needs to enter the proposer committee
     participation_threshold: f64,
     max_proposers: usize,
-    validators: usize,
-    round_timeout: u64,
+    /// Share of the stake certificates need, fixed for the instance
+    quorum: QuorumThreshold,
+    /// Round timeouts of the heights this instance runs
+    pacemaker: PacemakerConfig,
     cryptography: C,
     private_keys: HashMap<String, C::SecretKey>,
+    /// Validators whose node is down: they keep their VRF rank but neither propose nor vote
+    offline: HashSet<String>,
 }
 
-impl<C: CryptographyBridge> AlgoConsensus<C>
+impl<C: VrfBridge> AlgoConsensus<C>
 where
     C::SignedMessage: std::fmt::Debug + Clone + Sync,
 {
     pub fn new(
         participation_threshold: f64,
         max_proposers: usize,
-        validators: usize,
-        round_timeout: u64,
+        quorum: QuorumThreshold,
+        pacemaker: PacemakerConfig,
         cryptography: C,
         private_keys: HashMap<String, C::SecretKey>,
-    ) -> Result<Self, CryptographyError> {
+    ) -> Result<Self, ConsensusError> {
+        // En dessous de 2/3, deux quorums peuvent ne partager aucun votant honnête
+        let (numerator, denominator) =
+            (u128::from(quorum.numerator), u128::from(quorum.denominator));
+        if numerator >= denominator || numerator * 3 < denominator * 2 {
+            return Err(ConsensusError::InvalidQuorum {
+                numerator: quorum.numerator,
+                denominator: quorum.denominator,
+            });
+        }
         let (_pk, sk) = cryptography.key_generator()?;
 
         // Test basique de signature au lieu de VRF
         if cryptography.sign(&sk, b"test").is_err() {
-            return Err(CryptographyError::SigningError);
+            return Err(CryptographyError::SigningError.into());
         }
         Ok(Self {
             participation_threshold,
             max_proposers,
-            validators,
-            round_timeout,
+            quorum,
+            pacemaker,
             cryptography,
             private_keys,
+            offline: HashSet::new(),
         })
     }
 
+    /// Strictly more than the instance's quorum share of the total stake voted
+    pub fn has_quorum(&self, voted_stake: u128, total_stake: u128) -> bool {
+        self.quorum.is_reached(voted_stake, total_stake)
+    }
+
     pub fn create_instance(
         cryptography: C,
         private_keys: HashMap<String, C::SecretKey>,
-    ) -> Result<Self, CryptographyError> {
-        Self::new(0.1, 5, 3, 5000, cryptography, private_keys)
+    ) -> Result<Self, ConsensusError> {
+        Self::new(
+            0.1,
+            5,
+            QuorumThreshold::TWO_THIRDS,
+            PacemakerConfig::default(),
+            cryptography,
+            private_keys,
+        )
+    }
+
+    /// Total stake of the epoch and the members of its active set signing with this scheme.
+    /// Members with another scheme cannot vote here but their stake still counts.
+    fn members(
+        &self,
+        set: &EpochSnapshot,
+    ) -> Result<(u128, Members<C::PublicKey>), ConsensusError> {
+        if set.total_stake == 0 {
+            return Err(ConsensusError::NoStake);
+        }
+        let members = set
+            .validators
+            .iter()
+            .filter(|validator| validator.algorithm == C::ALGORITHM && validator.stake > 0)
+            .filter_map(|validator| {
+                let bytes = hex::decode(&validator.public_key).ok()?;
+                let public_key = self.cryptography.public_key_from_bytes(&bytes).ok()?;
+                Some((validator.address.clone(), (validator.stake, public_key)))
+            })
+            .collect();
+        Ok((set.total_stake, members))
     }
 
-    fn create_seed(&self, prev_hash: &[u8; 32], time_stamp: i64) -> [u8; 32] {
+    /// Take a validator's node down or bring it back up
+    pub fn set_online(&mut self, validator: &str, online: bool) {
+        if online {
+            self.offline.remove(validator);
+        } else {
+            self.offline.insert(validator.to_string());
+        }
+    }
+
+    /// VRF input of `height` building on `prev_hash`, the same in every round so the
+    /// committee keeps its ranking through view changes
+    pub fn create_seed(&self, prev_hash: &[u8; 32], height: u64) -> [u8; 32] {
         use blake3::Hasher;
         let mut hasher = Hasher::new();
         hasher.update(prev_hash);
-        hasher.update(&time_stamp.to_le_bytes());
+        hasher.update(&height.to_le_bytes());
         hasher.finalize().into()
     }
 
-    fn select_proposers(
+    /// Evaluate the VRF of `validator` on `seed`, `None` if its secret key does not
+    /// match the public key it is registered with
+    fn evaluate_vrf(
         &self,
-        balances: &HashMap<String, f64>,
+        validator: &str,
+        secret_key: &C::SecretKey,
+        public_key: &C::PublicKey,
         seed: &[u8; 32],
-        _public_keys: &HashMap<String, C::PublicKey>,
-    ) -> ProposerResult<C::SignedMessage> {
-        let total_stake: f64 = balances.values().sum();
-        if total_stake <= 0.0 {
-            return Err(CryptographyError::SigningError);
-        }
+    ) -> Result<Option<ProposerVrf>, ConsensusError> {
+        let proof = self.cryptography.vrf_prove(secret_key, seed)?;
+        let Some(output) = self.cryptography.vrf_verify(public_key, seed, &proof)? else {
+            return Ok(None);
+        };
+        Ok(Some(ProposerVrf {
+            validator: validator.to_string(),
+            algorithm: C::ALGORITHM,
+            public_key: hex::encode(self.cryptography.public_key_to_bytes(public_key)),
+            seed: hex::encode(seed),
+            proof: hex::encode(proof),
+            output: hex::encode(output),
+        }))
+    }
 
-        let mut candidates: Vec<(String, f64, Vec<u8>, C::SignedMessage)> = Vec::new();
+    /// Check a proposer's VRF `output` and `proof` against its public key and the round seed
+    pub fn vrf_verify(
+        &self,
+        public_key: &C::PublicKey,
+        seed: &[u8; 32],
+        output: &[u8],
+        proof: &[u8],
+    ) -> Result<bool, CryptographyError> {
+        let proven = self.cryptography.vrf_verify(public_key, seed, proof)?;
+        Ok(proven.is_some_and(|proven| proven.as_slice() == output))
+    }
 
-        for (address, stake) in balances.iter().filter(|(_, balance)| **balance > 0.0) {
-            let weight = *stake / total_stake;
-            if weight < self.participation_threshold {
+    /// Share of the stake `stake` weighs, `None` below the participation threshold
+    fn eligible_weight(&self, stake: u128, total_stake: u128) -> Option<f64> {
+        let weight = stake as f64 / total_stake as f64;
+        (weight >= self.participation_threshold).then_some(weight)
+    }
+
+    /// VRF tickets of every eligible member this instance holds a key for, in rank order
+    fn select_proposers(&self, set: &EpochSnapshot, seed: &[u8; 32]) -> ProposerResult {
+        let (total_stake, members) = self.members(set)?;
+
+        let mut candidates: Vec<ProposerTicket> = Vec::new();
+
+        for (address, (stake, public_key)) in &members {
+            let Some(weight) = self.eligible_weight(*stake, total_stake) else {
                 continue;
-            }
+            };
 
-            if let Some(private_key) = self.private_keys.get(address) {
-                if let Ok((vrf_output, proof)) = self.vrf_generate_internal(private_key, seed) {
-                    let priority = self.vrf_output(&vrf_output) * weight;
-                    candidates.push((address.clone(), priority, vrf_output.to_vec(), proof));
-                }
+            let Some(private_key) = self.private_keys.get(address) else {
+                continue;
+            };
+            if let Some(vrf) = self.evaluate_vrf(address, private_key, public_key, seed)? {
+                let output = hex::decode(&vrf.output).unwrap_or_default();
+                candidates.push(ProposerTicket {
+                    priority: self.vrf_output(&output) * weight,
+                    vrf,
+                });
             }
         }
 
-        candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        let selected = candidates
-            .into_iter()
-            .take(self.max_proposers)
-            .map(|(addr, _, output, proof)| (addr, output, proof))
-            .collect();
+        Self::rank(&mut candidates);
+        Ok(candidates)
+    }
 
-        Ok(selected)
+    /// Highest priority first, ties broken by address
+    fn rank(tickets: &mut [ProposerTicket]) {
+        tickets.sort_by(|a, b| {
+            b.priority
+                .partial_cmp(&a.priority)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.vrf.validator.cmp(&b.vrf.validator))
+        });
     }
 
     fn vrf_output(&self, output: &[u8]) -> f64 {
@@ -186,55 +899,948 @@ where
         u64::from_le_bytes(first_bytes.try_into().unwrap()) as f64 / u64::MAX as f64
     }
 
-    pub fn run_consensus_round(
+    fn is_online(&self, validator: &str) -> bool {
+        self.private_keys.contains_key(validator) && !self.offline.contains(validator)
+    }
+
+    /// Hex signature of `validator` over `message`
+    fn sign(&self, validator: &str, message: &[u8]) -> Result<String, ConsensusError> {
+        let secret_key = self
+            .private_keys
+            .get(validator)
+            .ok_or_else(|| ConsensusError::UnknownValidator(validator.to_string()))?;
+        let signature = self.cryptography.sign(secret_key, message)?;
+        Ok(hex::encode(
+            self.cryptography.signature_to_bytes(&signature),
+        ))
+    }
+
+    /// Why the hex `signature` is not `public_key`'s signature over `message`, if it is not
+    fn check_signature(
         &self,
-        prev_hash: &[u8; 32],
-        transactions: Vec<PolyTransaction>,
-        balances: &HashMap<String, f64>,
-        public_keys: &HashMap<String, C::PublicKey>,
-    ) -> Result<PolyBlock, CryptographyError> {
-        let start_time = ic_cdk::api::time();
+        public_key: &C::PublicKey,
+        message: &[u8],
+        signature: &str,
+    ) -> Result<(), String> {
+        let signature = hex::decode(signature).map_err(|e| format!("signature is not hex: {e}"))?;
+        let signature = self
+            .cryptography
+            .signature_from_bytes(&signature)
+            .map_err(|e| e.to_string())?;
+        let is_valid = self
+            .cryptography
+            .verify(public_key, message, &signature)
+            .map_err(|e| e.to_string())?;
+        if !is_valid {
+            return Err(format!("{:?} verification failed", C::ALGORITHM));
+        }
+        Ok(())
+    }
+
+    /// Every online member of the active set signs `message` with its own key, not only
+    /// the proposer committee: the quorum is counted over the whole stake
+    fn collect_votes(
+        &self,
+        message: &[u8],
+        set: &EpochSnapshot,
+    ) -> Result<Vec<Vote>, ConsensusError> {
+        let (_, members) = self.members(set)?;
+        let mut voters: Vec<&String> = members
+            .keys()
+            .filter(|validator| self.is_online(validator))
+            .collect();
+        voters.sort();
+        voters
+            .into_iter()
+            .map(|validator| {
+                Ok(Vote {
+                    validator: validator.clone(),
+                    stake: 0,
+                    signature: self.sign(validator, message)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Check the votes over `message` and add up their stake
+    ///
+    /// Every vote must come from a staked validator with a known key, at most once, with a
+    /// valid signature: a bad vote fails the whole certificate rather than being skipped.
+    fn count_votes(
+        &self,
+        message: &[u8],
+        votes: Vec<Vote>,
+        members: &Members<C::PublicKey>,
+    ) -> Result<(Vec<Vote>, u128), ConsensusError> {
+        let mut voters = HashSet::new();
+        let mut voted_stake: u128 = 0;
+        let mut counted = Vec::with_capacity(votes.len());
+
+        for mut vote in votes {
+            let Some((stake, public_key)) = members.get(&vote.validator) else {
+                return Err(ConsensusError::UnknownValidator(vote.validator));
+            };
+            if !voters.insert(vote.validator.clone()) {
+                return Err(ConsensusError::DuplicateVote(vote.validator));
+            }
+            self.check_signature(public_key, message, &vote.signature)
+                .map_err(|reason| ConsensusError::InvalidVote {
+                    validator: vote.validator.clone(),
+                    reason,
+                })?;
+
+            vote.stake = *stake;
+            voted_stake = voted_stake.saturating_add(*stake);
+            counted.push(vote);
+        }
+        Ok((counted, voted_stake))
+    }
+
+    fn check_quorum(&self, voted_stake: u128, total_stake: u128) -> Result<(), ConsensusError> {
+        if !self.has_quorum(voted_stake, total_stake) {
+            return Err(ConsensusError::QuorumNotReached {
+                voted: voted_stake,
+                total: total_stake,
+                numerator: self.quorum.numerator,
+                denominator: self.quorum.denominator,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check the proposal signature, the view change and the votes of a draft `certificate`,
+    /// filling in the stake totals once the votes reach the quorum
+    pub fn certify(
+        &self,
+        certificate: QuorumCertificate,
+        set: &EpochSnapshot,
+    ) -> Result<QuorumCertificate, ConsensusError> {
+        let (total_stake, members) = self.members(set)?;
+        let (height, round) = (certificate.height, certificate.round);
+
+        let proposer = &certificate.proposer.validator;
+        let (_, proposer_key) = members
+            .get(proposer)
+            .ok_or_else(|| ConsensusError::UnknownValidator(proposer.clone()))?;
+        let proposal = ConsensusStep::Proposal.message(height, round, &certificate.block_hash);
+        self.check_signature(proposer_key, &proposal, &certificate.proposal_signature)
+            .map_err(|reason| ConsensusError::InvalidProposal {
+                validator: proposer.clone(),
+                reason,
+            })?;
+
+        // Hors du tour 0, le tour précédent doit avoir expiré avec un quorum
+        let timeout_certificate = match (round, certificate.timeout_certificate) {
+            (0, _) => None,
+            (_, Some(timeout)) if timeout.height == height && timeout.round + 1 == round => {
+                Some(self.certify_timeout(timeout, set)?)
+            }
+            _ => return Err(ConsensusError::MissingTimeout { height, round }),
+        };
+
+        let message = ConsensusStep::Vote.message(height, round, &certificate.block_hash);
+        let (votes, voted_stake) = self.count_votes(&message, certificate.votes, &members)?;
+        self.check_quorum(voted_stake, total_stake)?;
 
-        // Convert hash for compatibility
-        let prev_hash_string = hex::encode(prev_hash);
-        let block = PolyBlock::new(transactions, prev_hash_string);
-        let seed = self.create_seed(prev_hash, block.timestamp);
+        Ok(QuorumCertificate {
+            algorithm: C::ALGORITHM,
+            timeout_certificate,
+            votes,
+            voted_stake,
+            total_stake,
+            threshold: self.quorum,
+            ..certificate
+        })
+    }
+
+    /// Count the timeout votes of a draft `certificate`, filling in the stake totals once
+    /// they reach the quorum
+    pub fn certify_timeout(
+        &self,
+        certificate: TimeoutCertificate,
+        set: &EpochSnapshot,
+    ) -> Result<TimeoutCertificate, ConsensusError> {
+        let (total_stake, members) = self.members(set)?;
+        let message = TimeoutCertificate::message(certificate.height, certificate.round);
+        let (votes, voted_stake) = self.count_votes(&message, certificate.votes, &members)?;
+        self.check_quorum(voted_stake, total_stake)?;
+
+        Ok(TimeoutCertificate {
+            algorithm: C::ALGORITHM,
+            votes,
+            voted_stake,
+            total_stake,
+            threshold: self.quorum,
+            ..certificate
+        })
+    }
 
-        let proposers = self.select_proposers(balances, &seed, public_keys)?;
-        if proposers.is_empty() {
-            return Err(CryptographyError::SigningError);
+    /// Check the certificate stored with `block` against the validator set that voted it
+    pub fn verify_certificate(
+        &self,
+        block: &PolyBlock,
+        set: &EpochSnapshot,
+    ) -> Result<(), ConsensusError> {
+        let certificate = block
+            .certificate
+            .as_ref()
+            .ok_or_else(|| ConsensusError::MissingCertificate(block.hash.clone()))?;
+        if certificate.block_hash != block.hash {
+            return Err(ConsensusError::CertificateMismatch {
+                expected: block.hash.clone(),
+                certified: certificate.block_hash.clone(),
+            });
         }
+        self.verify_proposer(block, certificate, set)?;
+        self.certify(certificate.clone(), set)?;
+        Ok(())
+    }
+
+    /// Check the certificate's VRF tickets against the registered keys and the seed of the
+    /// block's height, rank them again and require the proposer to lead the round. Every
+    /// eligible member of the epoch must have drawn, so the proposer cannot pick the subset
+    /// it leads.
+    fn verify_proposer(
+        &self,
+        block: &PolyBlock,
+        certificate: &QuorumCertificate,
+        set: &EpochSnapshot,
+    ) -> Result<(), ConsensusError> {
+        let proposer = &certificate.proposer;
+        let invalid = |validator: &str, reason: &str| ConsensusError::InvalidVrf {
+            validator: validator.to_string(),
+            reason: reason.to_string(),
+        };
+        let (total_stake, members) = self.members(set)?;
+        let prev_hash: [u8; 32] = hex::decode(&block.previous_hash)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                invalid(
+                    &proposer.validator,
+                    "previous hash is not a 32-byte hex hash",
+                )
+            })?;
+        let seed = self.create_seed(&prev_hash, certificate.height);
 
-        // Simplified consensus check
-        let is_approved = proposers.len() >= self.validators.min(2);
-        if !is_approved {
-            return Err(CryptographyError::SigningError);
+        let candidates = certificate
+            .candidates
+            .as_ref()
+            .ok_or_else(|| invalid(&proposer.validator, "certificate carries no VRF tickets"))?;
+        let mut drawn = HashSet::new();
+        let mut tickets = Vec::with_capacity(candidates.len());
+        for vrf in candidates {
+            if !drawn.insert(vrf.validator.as_str()) {
+                return Err(invalid(&vrf.validator, "drew more than one ticket"));
+            }
+            tickets.push(self.check_ticket(vrf, &members, total_stake, &seed)?);
+        }
+        // Le classement porte sur tous les membres éligibles de l'époque, votants ou non:
+        // un ticket omis aurait pu devancer le proposeur
+        for (address, (stake, _)) in &members {
+            let eligible = self.eligible_weight(*stake, total_stake).is_some();
+            if eligible && !drawn.contains(address.as_str()) {
+                return Err(invalid(address, "eligible member drew no ticket"));
+            }
         }
 
-        // Check timeout using IC time (nanoseconds)
-        let elapsed_ns = ic_cdk::api::time() - start_time;
-        let elapsed_ms = elapsed_ns / 1_000_000; // Convert to milliseconds
-        if elapsed_ms > self.round_timeout {
-            return Err(CryptographyError::SigningError);
+        Self::rank(&mut tickets);
+        tickets.truncate(self.max_proposers);
+        if tickets.is_empty() {
+            return Err(ConsensusError::EmptyCommittee);
+        }
+        let leader = &tickets[(certificate.round % tickets.len() as u64) as usize].vrf;
+        if leader != proposer {
+            return Err(ConsensusError::WrongProposer {
+                validator: proposer.validator.clone(),
+                round: certificate.round,
+                leader: leader.validator.clone(),
+            });
         }
+        Ok(())
+    }
 
+    /// Check a VRF ticket against the member's registered key and `seed`, and price it
+    fn check_ticket(
+        &self,
+        vrf: &ProposerVrf,
+        members: &Members<C::PublicKey>,
+        total_stake: u128,
+        seed: &[u8; 32],
+    ) -> Result<ProposerTicket, ConsensusError> {
+        let invalid = |reason: &str| ConsensusError::InvalidVrf {
+            validator: vrf.validator.clone(),
+            reason: reason.to_string(),
+        };
+        let (stake, public_key) = members
+            .get(&vrf.validator)
+            .ok_or_else(|| ConsensusError::UnknownValidator(vrf.validator.clone()))?;
+        let weight = self
+            .eligible_weight(*stake, total_stake)
+            .ok_or_else(|| invalid("stake is below the participation threshold"))?;
+        if vrf.public_key != hex::encode(self.cryptography.public_key_to_bytes(public_key)) {
+            return Err(invalid("public key is not the registered key"));
+        }
+        if vrf.seed != hex::encode(seed) {
+            return Err(invalid("seed is not the seed of the block's height"));
+        }
+        let (Ok(output), Ok(proof)) = (hex::decode(&vrf.output), hex::decode(&vrf.proof)) else {
+            return Err(invalid("output or proof is not hex"));
+        };
+        if !self.vrf_verify(public_key, seed, &output, &proof)? {
+            return Err(invalid("proof does not verify"));
+        }
+        Ok(ProposerTicket {
+            priority: self.vrf_output(&output) * weight,
+            vrf: vrf.clone(),
+        })
+    }
+
+    /// Rank the committee of `height` and enter its round 0
+    pub fn start_height(
+        &self,
+        prev_hash: [u8; 32],
+        height: u64,
+        transactions: Vec<PolyTransaction>,
+        state_root: [u8; 32],
+        set: &EpochSnapshot,
+    ) -> Result<ConsensusHeight, ConsensusError> {
+        let seed = self.create_seed(&prev_hash, height);
+        let candidates = self.select_proposers(set, &seed)?;
+        let committee_size = candidates.len().min(self.max_proposers);
+        if committee_size == 0 {
+            return Err(ConsensusError::EmptyCommittee);
+        }
+        Ok(ConsensusHeight {
+            prev_hash,
+            transactions,
+            state_root,
+            candidates,
+            committee_size,
+            pacemaker: Pacemaker::new(self.pacemaker.clone(), height, clock::now()),
+        })
+    }
+
+    /// Let the leader of the current round propose a block and the active set vote on it
+    ///
+    /// An offline leader or too little online stake leaves the round without a block; it
+    /// then runs until its timeout and `on_timeout` hands it to the next proposer.
+    pub fn propose(
+        &self,
+        run: &ConsensusHeight,
+        set: &EpochSnapshot,
+    ) -> Result<PolyBlock, ConsensusError> {
+        let (height, round) = (run.pacemaker.height(), run.pacemaker.round());
+        let leader = &run.leader().vrf;
+        if !self.is_online(&leader.validator) {
+            return Err(ConsensusError::LeaderOffline {
+                validator: leader.validator.clone(),
+                round,
+            });
+        }
+
+        let timestamp = i64::try_from(clock::now()).unwrap_or(i64::MAX);
+        let mut block = PolyBlock::new_at(
+            run.transactions.clone(),
+            hex::encode(run.prev_hash),
+            run.state_root,
+            timestamp,
+        );
+
+        // Le proposeur et les validateurs actifs signent le hash, hors certificat
+        let proposal = ConsensusStep::Proposal.message(height, round, &block.hash);
+        let proposal_signature = self.sign(&leader.validator, &proposal)?;
+        let vote = ConsensusStep::Vote.message(height, round, &block.hash);
+        let votes = self.collect_votes(&vote, set)?;
+        let certificate = self.certify(
+            QuorumCertificate {
+                algorithm: C::ALGORITHM,
+                height,
+                round,
+                block_hash: block.hash.clone(),
+                proposer: leader.clone(),
+                candidates: Some(
+                    run.candidates
+                        .iter()
+                        .map(|ticket| ticket.vrf.clone())
+                        .collect(),
+                ),
+                proposal_signature,
+                timeout_certificate: run.pacemaker.high_timeout().cloned(),
+                votes,
+                voted_stake: 0,
+                total_stake: 0,
+                threshold: self.quorum,
+            },
+            set,
+        )?;
+
+        block.certificate = Some(certificate);
         Ok(block)
     }
-}
 
-// Extension VRF pour le consensus
-impl<C: CryptographyBridge> AlgoConsensus<C>
-where
-    C::SignedMessage: std::fmt::Debug + Clone + Sync,
-{
-    // Méthode VRF intégrée qui utilise les méthodes crypto existantes
-    fn vrf_generate_internal(
+    /// Round timer: once the current round is past its deadline, the online active set signs
+    /// its timeout and the certificate moves the height to the next round and proposer.
+    /// `None` while the round still has time.
+    pub fn on_timeout(
         &self,
-        secret_key: &C::SecretKey,
-        seed: &[u8],
-    ) -> Result<(Vec<u8>, C::SignedMessage), CryptographyError> {
-        // Fallback: utilise signature normale comme VRF
-        let signature = self.cryptography.sign(secret_key, seed)?;
-        Ok((seed.to_vec(), signature))
+        run: &mut ConsensusHeight,
+        set: &EpochSnapshot,
+    ) -> Result<Option<TimeoutCertificate>, ConsensusError> {
+        let now = clock::now();
+        if !run.pacemaker.is_expired(now) {
+            return Ok(None);
+        }
+        let (height, round) = (run.pacemaker.height(), run.pacemaker.round());
+        let message = TimeoutCertificate::message(height, round);
+        let votes = self.collect_votes(&message, set)?;
+        let certificate = self.certify_timeout(
+            TimeoutCertificate {
+                algorithm: C::ALGORITHM,
+                height,
+                round,
+                votes,
+                voted_stake: 0,
+                total_stake: 0,
+                threshold: self.quorum,
+            },
+            set,
+        )?;
+        run.pacemaker.advance_round(certificate.clone(), now);
+        Ok(Some(certificate))
+    }
+}
+
+/// Consensus on one height: the committee in VRF order and the pacemaker moving through
+/// its rounds. Round `r` is led by the `r`-th member of the committee, wrapping around.
+#[derive(Debug)]
+pub struct ConsensusHeight {
+    prev_hash: [u8; 32],
+    transactions: Vec<PolyTransaction>,
+    state_root: [u8; 32],
+    /// Tickets of every eligible member in rank order, the first `committee_size` are the committee
+    candidates: Vec<ProposerTicket>,
+    committee_size: usize,
+    pacemaker: Pacemaker,
+}
+
+impl ConsensusHeight {
+    pub fn pacemaker(&self) -> &Pacemaker {
+        &self.pacemaker
+    }
+
+    pub fn committee(&self) -> &[ProposerTicket] {
+        &self.candidates[..self.committee_size]
+    }
+
+    /// Proposer of the current round
+    pub fn leader(&self) -> &ProposerTicket {
+        let committee = self.committee();
+        let index = self.pacemaker.round() % committee.len() as u64;
+        &committee[index as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, ManualClock};
+    use crate::cryptography::ecdsa::Ecdsa;
+    use crate::evidence::EquivocationEvidence;
+    use crate::state;
+    use crate::types::CryptoAlgorithm;
+    use crate::validators::ActiveValidator;
+    use std::rc::Rc;
+
+    fn build_chain(length: usize) -> Vec<PolyBlock> {
+        let mut blocks: Vec<PolyBlock> = Vec::new();
+        for _ in 0..length {
+            let previous_hash = blocks
+                .last()
+                .map_or(GENESIS_PREVIOUS_HASH.to_string(), |block| {
+                    block.hash.clone()
+                });
+            blocks.push(PolyBlock::new(
+                Vec::new(),
+                previous_hash,
+                state::empty_state_root(),
+            ));
+        }
+        blocks
+    }
+
+    #[test]
+    fn verify_chain_accepts_linked_blocks() {
+        assert_eq!(verify_chain(0, None, &[], None, 0), Ok(()));
+        assert_eq!(verify_chain(0, None, &build_chain(4), None, 4), Ok(()));
+    }
+
+    #[test]
+    fn verify_chain_reports_first_invalid_block() {
+        // Body altéré sans recalculer l'en-tête
+        let mut blocks = build_chain(4);
+        blocks[2].transactions.push(PolyTransaction::new(
+            "mallory".to_string(),
+            "bob".to_string(),
+            1,
+            crate::types::SupportedChain::Bitcoin,
+            0,
+        ));
+        assert_eq!(
+            verify_chain(0, None, &blocks, None, 4),
+            Err(ChainError::TxRootMismatch { height: 2 })
+        );
+
+        // En-tête altéré sans recalculer le hash
+        let mut blocks = build_chain(4);
+        blocks[1].nonce = 7;
+        assert!(matches!(
+            verify_chain(0, None, &blocks, None, 4),
+            Err(ChainError::HashMismatch { height: 1, .. })
+        ));
+
+        // Bloc re-haché proprement mais détaché de son parent
+        let mut blocks = build_chain(4);
+        blocks[3].previous_hash = "forged".to_string();
+        blocks[3].hash = blocks[3].calculate_hash();
+        assert!(matches!(
+            verify_chain(0, None, &blocks, None, 4),
+            Err(ChainError::BrokenLink { height: 3, .. })
+        ));
+        // Un segment se vérifie contre son parent, aux hauteurs de la chaîne
+        assert_eq!(
+            verify_chain(1, Some(&blocks[0]), &blocks[1..3], None, 4),
+            Ok(())
+        );
+        assert!(matches!(
+            verify_chain(2, Some(&blocks[1]), &blocks[2..], None, 4),
+            Err(ChainError::BrokenLink { height: 3, .. })
+        ));
+
+        // Horodatage antérieur au parent
+        let mut blocks = build_chain(2);
+        blocks[1].timestamp = blocks[0].timestamp - 1;
+        blocks[1].hash = blocks[1].calculate_hash();
+        assert!(matches!(
+            verify_chain(0, None, &blocks, None, 4),
+            Err(ChainError::TimestampRegression { height: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn blocks_past_the_migration_height_need_the_sequencer_signature() {
+        let mut sequencer = TransactionSequencer::new(Ecdsa);
+        sequencer.set_proposer_seed([7u8; 32]).unwrap();
+        let key = sequencer.public_key().unwrap().to_string();
+        let mut blocks = build_chain(2);
+        let signed = PolyBlock::new(
+            Vec::new(),
+            blocks[1].hash.clone(),
+            state::empty_state_root(),
+        );
+        blocks.push(sequencer.sign_block(signed).unwrap());
+
+        // Les deux premiers blocs précèdent la clé publiée
+        assert_eq!(verify_chain(0, None, &blocks, Some(&key), 2), Ok(()));
+        assert_eq!(
+            verify_chain(0, None, &blocks, Some(&key), 1),
+            Err(ChainError::MissingProposer { height: 1 })
+        );
+        assert!(matches!(
+            verify_chain(0, None, &blocks, None, 2),
+            Err(ChainError::InvalidSignature { height: 2, .. })
+        ));
+
+        // Un bloc signé par sa propre clé ne passe plus au-delà de la hauteur de migration
+        let mut other = TransactionSequencer::new(Ecdsa);
+        other.set_proposer_seed([8u8; 32]).unwrap();
+        let forged = PolyBlock::new(
+            Vec::new(),
+            blocks[1].hash.clone(),
+            state::empty_state_root(),
+        );
+        blocks[2] = other.sign_block(forged).unwrap();
+        assert!(matches!(
+            verify_chain(0, None, &blocks, Some(&key), 2),
+            Err(ChainError::InvalidSignature { height: 2, .. })
+        ));
+        assert_eq!(verify_chain(0, None, &blocks, Some(&key), 3), Ok(()));
+    }
+
+    type ValidatorSet = (
+        AlgoConsensus<Ecdsa>,
+        EpochSnapshot,
+        HashMap<String, <Ecdsa as CryptographyBridge>::PublicKey>,
+    );
+
+    /// Validators `validator-{i}` with the given stakes, only the first `with_keys` can vote
+    fn validator_set(stakes: &[u128], with_keys: usize) -> ValidatorSet {
+        validator_set_with_quorum(stakes, with_keys, QuorumThreshold::TWO_THIRDS)
+    }
+
+    fn validator_set_with_quorum(
+        stakes: &[u128],
+        with_keys: usize,
+        quorum: QuorumThreshold,
+    ) -> ValidatorSet {
+        let mut private_keys = HashMap::new();
+        let mut public_keys = HashMap::new();
+        let mut set = EpochSnapshot::default();
+        for (i, stake) in stakes.iter().enumerate() {
+            let (public_key, secret_key) = Ecdsa.key_generator().unwrap();
+            let address = format!("validator-{i}");
+            if i < with_keys {
+                private_keys.insert(address.clone(), secret_key);
+            }
+            set.validators.push(ActiveValidator {
+                address: address.clone(),
+                algorithm: CryptoAlgorithm::Ecdsa,
+                public_key: hex::encode(Ecdsa.public_key_to_bytes(&public_key)),
+                stake: *stake,
+            });
+            set.total_stake += stake;
+            public_keys.insert(address, public_key);
+        }
+        let consensus = AlgoConsensus::new(
+            0.1,
+            5,
+            quorum,
+            PacemakerConfig::default(),
+            Ecdsa,
+            private_keys,
+        )
+        .unwrap();
+        (consensus, set, public_keys)
+    }
+
+    /// Round 0 of `height` on `prev_hash`
+    fn run_round(
+        consensus: &AlgoConsensus<Ecdsa>,
+        set: &EpochSnapshot,
+        prev_hash: [u8; 32],
+        height: u64,
+    ) -> Result<PolyBlock, ConsensusError> {
+        let run = consensus.start_height(prev_hash, height, Vec::new(), [0; 32], set)?;
+        consensus.propose(&run, set)
+    }
+
+    #[test]
+    fn consensus_round_certifies_block_with_stake_quorum() {
+        let (consensus, set, _) = validator_set(&[100, 100, 100], 3);
+        let block = run_round(&consensus, &set, [0; 32], 1).unwrap();
+
+        let certificate = block.certificate.clone().unwrap();
+        assert_eq!(certificate.block_hash, block.hash);
+        assert_eq!(certificate.votes.len(), 3);
+        assert_eq!(certificate.voted_stake, 300);
+        // Le certificat ne modifie pas le hash qu'il signe
+        assert_eq!(block.hash, block.calculate_hash());
+        assert!(consensus.verify_certificate(&block, &set).is_ok());
+
+        // Vote rejoué: compté une seule fois
+        let mut duplicated = block.clone();
+        let certificate = duplicated.certificate.as_mut().unwrap();
+        certificate.votes.push(certificate.votes[0].clone());
+        assert!(matches!(
+            consensus.verify_certificate(&duplicated, &set),
+            Err(ConsensusError::DuplicateVote(_))
+        ));
+
+        // Vote signé pour un autre bloc
+        let mut forged = block.clone();
+        let other = run_round(&consensus, &set, [1; 32], 1).unwrap();
+        let votes = &mut forged.certificate.as_mut().unwrap().votes;
+        votes[1] = other
+            .certificate
+            .unwrap()
+            .votes
+            .into_iter()
+            .find(|vote| vote.validator == votes[1].validator)
+            .unwrap();
+        assert!(matches!(
+            consensus.verify_certificate(&forged, &set),
+            Err(ConsensusError::InvalidVote { .. })
+        ));
+
+        // Certificat d'un autre bloc
+        let mut detached = block.clone();
+        detached.certificate.as_mut().unwrap().block_hash = "other".to_string();
+        assert!(matches!(
+            consensus.verify_certificate(&detached, &set),
+            Err(ConsensusError::CertificateMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn consensus_round_without_quorum_fails_distinctly() {
+        // Seul un tiers du stake peut voter
+        let (consensus, set, _) = validator_set(&[10, 10, 10], 1);
+        let result = run_round(&consensus, &set, [0; 32], 1);
+        assert!(matches!(
+            result,
+            Err(ConsensusError::QuorumNotReached {
+                voted: 10,
+                total: 30,
+                ..
+            })
+        ));
+
+        // Exactement deux tiers ne suffit pas, le quorum est strict
+        let (consensus, set, _) = validator_set(&[10, 10, 10], 2);
+        assert!(matches!(
+            run_round(&consensus, &set, [0; 32], 1),
+            Err(ConsensusError::QuorumNotReached {
+                voted: 20,
+                total: 30,
+                ..
+            })
+        ));
+        assert!(consensus.has_quorum(21, 30));
+
+        // Un quorum plus exigeant est fixé à la construction et consigné dans le certificat
+        let three_quarters = QuorumThreshold {
+            numerator: 3,
+            denominator: 4,
+        };
+        let (consensus, set, _) = validator_set_with_quorum(&[10, 10, 10, 10], 3, three_quarters);
+        assert!(matches!(
+            run_round(&consensus, &set, [0; 32], 1),
+            Err(ConsensusError::QuorumNotReached {
+                voted: 30,
+                total: 40,
+                numerator: 3,
+                denominator: 4,
+            })
+        ));
+        let (consensus, set, _) = validator_set_with_quorum(&[10, 10, 10, 10], 4, three_quarters);
+        let certificate = run_round(&consensus, &set, [0; 32], 1)
+            .unwrap()
+            .certificate
+            .unwrap();
+        assert_eq!(certificate.threshold, three_quarters);
+        let (consensus, set, _) = validator_set(&[10, 10, 10, 10], 3);
+        assert!(run_round(&consensus, &set, [0; 32], 1).is_ok());
+
+        // Sous deux tiers, ou à 100%, le quorum est refusé
+        for (numerator, denominator) in [(1, 2), (3, 3), (2, 0)] {
+            assert!(matches!(
+                AlgoConsensus::new(
+                    0.1,
+                    5,
+                    QuorumThreshold {
+                        numerator,
+                        denominator
+                    },
+                    PacemakerConfig::default(),
+                    Ecdsa,
+                    HashMap::new(),
+                ),
+                Err(ConsensusError::InvalidQuorum { .. })
+            ));
+        }
+
+        let (consensus, _, _) = validator_set(&[10], 1);
+        assert!(matches!(
+            run_round(&consensus, &EpochSnapshot::default(), [0; 32], 1),
+            Err(ConsensusError::NoStake)
+        ));
+    }
+
+    #[test]
+    fn whole_active_set_votes_beyond_the_committee() {
+        // Dix validateurs égaux: cinq sièges au comité, mais les dix votent
+        let (consensus, set, _) = validator_set(&[10; 10], 10);
+        let run = consensus
+            .start_height([0; 32], 1, Vec::new(), [0; 32], &set)
+            .unwrap();
+        assert_eq!(run.committee().len(), 5);
+        let block = consensus.propose(&run, &set).unwrap();
+        let certificate = block.certificate.clone().unwrap();
+        assert_eq!(certificate.votes.len(), 10);
+        assert_eq!(certificate.voted_stake, 100);
+        assert!(consensus.verify_certificate(&block, &set).is_ok());
+
+        // Un petit validateur hors du comité vote aussi
+        let (consensus, set, _) = validator_set(&[100, 100, 100, 5], 4);
+        let certificate = run_round(&consensus, &set, [0; 32], 1)
+            .unwrap()
+            .certificate
+            .unwrap();
+        assert!(certificate
+            .votes
+            .iter()
+            .any(|vote| vote.validator == "validator-3"));
+    }
+
+    #[test]
+    fn proposer_vrf_is_publicly_verifiable() {
+        let (consensus, set, public_keys) = validator_set(&[100, 100, 100], 3);
+        let block = run_round(&consensus, &set, [7; 32], 1).unwrap();
+        let proposer = block.certificate.clone().unwrap().proposer;
+
+        // N'importe quel nœud: clé publique, graine du tour, sortie et preuve
+        let seed = consensus.create_seed(&[7; 32], 1);
+        assert_eq!(proposer.seed, hex::encode(seed));
+        let output = hex::decode(&proposer.output).unwrap();
+        let proof = hex::decode(&proposer.proof).unwrap();
+        let public_key = &public_keys[&proposer.validator];
+        assert!(consensus
+            .vrf_verify(public_key, &seed, &output, &proof)
+            .unwrap());
+        assert!(proposer.verify());
+
+        // Sortie imprévisible mais unique: une autre sortie ne passe pas
+        let mut forged_output = output.clone();
+        forged_output[0] ^= 1;
+        assert!(!consensus
+            .vrf_verify(public_key, &seed, &forged_output, &proof)
+            .unwrap());
+        let other_key = public_keys
+            .iter()
+            .find(|(validator, _)| **validator != proposer.validator)
+            .map(|(_, key)| key)
+            .unwrap();
+        assert!(!consensus
+            .vrf_verify(other_key, &seed, &output, &proof)
+            .unwrap());
+
+        // Preuve rejouée à une autre hauteur
+        let mut replayed = block.clone();
+        replayed.certificate.as_mut().unwrap().height += 1;
+        assert!(matches!(
+            consensus.verify_certificate(&replayed, &set),
+            Err(ConsensusError::InvalidVrf { .. })
+        ));
+    }
+
+    #[test]
+    fn only_the_round_leader_may_propose() {
+        let (consensus, set, _) = validator_set(&[100; 7], 7);
+        let block = run_round(&consensus, &set, [9; 32], 3).unwrap();
+        let certificate = block.certificate.clone().unwrap();
+        let candidates = certificate.candidates.clone().unwrap();
+        // Sept tickets, comité des cinq premiers, le premier mène le tour 0
+        assert_eq!(candidates.len(), 7);
+        assert_eq!(certificate.proposer, candidates[0]);
+
+        // Un autre membre du comité, avec un ticket valide, ne mène pas le tour 0
+        let mut usurped = block.clone();
+        usurped.certificate.as_mut().unwrap().proposer = candidates[1].clone();
+        assert!(matches!(
+            consensus.verify_certificate(&usurped, &set),
+            Err(ConsensusError::WrongProposer { round: 0, .. })
+        ));
+
+        // Écarter le ticket du mieux classé ferait monter le suivant: refusé, il a voté
+        let mut pruned = usurped.clone();
+        pruned.certificate.as_mut().unwrap().candidates = Some(candidates[1..].to_vec());
+        assert!(matches!(
+            consensus.verify_certificate(&pruned, &set),
+            Err(ConsensusError::InvalidVrf { .. })
+        ));
+
+        // Même sans voter, le mieux classé garde son rang: son ticket ne peut pas être omis
+        let mut silent = pruned.clone();
+        let leader = candidates[0].validator.clone();
+        silent
+            .certificate
+            .as_mut()
+            .unwrap()
+            .votes
+            .retain(|vote| vote.validator != leader);
+        assert!(matches!(
+            consensus.verify_certificate(&silent, &set),
+            Err(ConsensusError::InvalidVrf { validator, .. }) if validator == leader
+        ));
+
+        // Sans tickets, le rang du proposeur ne peut pas être recalculé
+        let mut bare = block.clone();
+        bare.certificate.as_mut().unwrap().candidates = None;
+        assert!(matches!(
+            consensus.verify_certificate(&bare, &set),
+            Err(ConsensusError::InvalidVrf { .. })
+        ));
+    }
+
+    #[test]
+    fn offline_proposer_is_replaced_after_a_timeout() {
+        let clock = ManualClock::starting_at(1_000_000_000);
+        clock::install(Rc::new(clock.clone()));
+        let (mut consensus, set, _) = validator_set(&[100, 100, 100, 100], 4);
+        let mut run = consensus
+            .start_height([3; 32], 2, Vec::new(), [0; 32], &set)
+            .unwrap();
+        let first_leader = run.leader().vrf.validator.clone();
+        consensus.set_online(&first_leader, false);
+        assert!(matches!(
+            consensus.propose(&run, &set),
+            Err(ConsensusError::LeaderOffline { round: 0, .. })
+        ));
+
+        // Le tour n'a pas encore expiré
+        assert!(consensus.on_timeout(&mut run, &set).unwrap().is_none());
+        clock.advance(5_000_000_000);
+        let timeout = consensus.on_timeout(&mut run, &set).unwrap().unwrap();
+        assert_eq!(
+            (timeout.round, timeout.votes.len(), timeout.voted_stake),
+            (0, 3, 300)
+        );
+
+        // Tour suivant: deuxième du classement VRF, délai doublé
+        assert_eq!(run.pacemaker().round(), 1);
+        assert_eq!(run.leader().vrf.validator, run.committee()[1].vrf.validator);
+        assert_eq!(run.pacemaker().remaining(clock.now()), 10_000_000_000);
+
+        let block = consensus.propose(&run, &set).unwrap();
+        let certificate = block.certificate.clone().unwrap();
+        assert_eq!(certificate.round, 1);
+        assert_ne!(certificate.proposer.validator, first_leader);
+        assert_eq!(certificate.timeout_certificate, Some(timeout));
+        assert!(consensus.verify_certificate(&block, &set).is_ok());
+
+        // Sans certificat de timeout, rien ne justifie le changement de vue
+        let mut unjustified = block.clone();
+        unjustified
+            .certificate
+            .as_mut()
+            .unwrap()
+            .timeout_certificate = None;
+        assert!(matches!(
+            consensus.verify_certificate(&unjustified, &set),
+            Err(ConsensusError::MissingTimeout {
+                height: 2,
+                round: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn conflicting_certificates_reveal_equivocation() {
+        let (consensus, set, public_keys) = validator_set(&[100, 100, 100], 3);
+        let first = run_round(&consensus, &set, [0; 32], 5)
+            .unwrap()
+            .certificate
+            .unwrap();
+        let second = run_round(&consensus, &set, [1; 32], 5)
+            .unwrap()
+            .certificate
+            .unwrap();
+
+        // Chaque votant a signé deux blocs à la même hauteur et au même tour
+        let evidence = EquivocationEvidence::from_certificates(&first, &second);
+        let votes = evidence
+            .iter()
+            .filter(|evidence| evidence.step == ConsensusStep::Vote)
+            .count();
+        assert_eq!(votes, 3);
+        for evidence in &evidence {
+            assert!(evidence
+                .verify(&Ecdsa, &public_keys[&evidence.validator])
+                .is_ok());
+        }
+
+        // Un autre tour n'est pas une équivoque
+        let next_round = QuorumCertificate { round: 1, ..second };
+        assert!(EquivocationEvidence::from_certificates(&first, &next_round).is_empty());
     }
 }
@@ -0,0 +1,111 @@
+//! Time source of the runtime
+//! The IC system time traps outside a canister, so nothing reads it directly: the
+//! sequencer, consensus and the endpoints call `clock::now`, which asks the one clock
+//! installed for the thread. On the IC it is the system time, natively it is the host
+//! clock, and a replay or a test swaps in a `ManualClock` with `clock::install` so it
+//! reproduces the same timestamps and hashes.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+pub trait Clock: std::fmt::Debug {
+    /// Nanoseconds since the UNIX epoch
+    fn now(&self) -> u64;
+}
+
+/// IC system time in a canister, the host clock in a native build
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    #[cfg(target_arch = "wasm32")]
+    fn now(&self) -> u64 {
+        ic_cdk::api::time()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now(&self) -> u64 {
+        use std::time::{SystemTime, UNIX_EPOCH};
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_nanos() as u64)
+    }
+}
+
+/// Clock that only moves when told to, clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Rc<Cell<u64>>,
+}
+
+impl ManualClock {
+    pub fn starting_at(now: u64) -> Self {
+        Self {
+            now: Rc::new(Cell::new(now)),
+        }
+    }
+
+    pub fn set(&self, now: u64) {
+        self.now.set(now);
+    }
+
+    pub fn advance(&self, nanos: u64) {
+        self.now.set(self.now.get().saturating_add(nanos));
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> u64 {
+        self.now.get()
+    }
+}
+
+thread_local! {
+    static CLOCK: RefCell<Rc<dyn Clock>> = RefCell::new(Rc::new(SystemClock));
+}
+
+/// Replace the runtime clock, returns the previous one
+pub fn install(clock: Rc<dyn Clock>) -> Rc<dyn Clock> {
+    CLOCK.with(|current| current.replace(clock))
+}
+
+/// Current time of the runtime clock, in nanoseconds
+pub fn now() -> u64 {
+    CLOCK.with(|clock| clock.borrow().now())
+}
+
+/// Current time of the runtime clock as a signed timestamp, as stored in transactions and blocks
+pub fn timestamp() -> i64 {
+    i64::try_from(now()).unwrap_or(i64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PolyBlock, PolyTransaction, SupportedChain};
+
+    #[test]
+    fn installed_clock_stamps_transactions_and_blocks() {
+        let clock = ManualClock::starting_at(42);
+        install(Rc::new(clock.clone()));
+
+        let tx = PolyTransaction::new(
+            "alice".to_string(),
+            "bob".to_string(),
+            1,
+            SupportedChain::Bitcoin,
+            0,
+        );
+        assert_eq!(tx.time_stamp, 42);
+
+        clock.advance(8);
+        let first = PolyBlock::new(vec![tx.clone()], "genesis".to_string(), [0; 32]);
+        let second = PolyBlock::new(vec![tx], "genesis".to_string(), [0; 32]);
+        assert_eq!(first.timestamp, 50);
+        // Même horloge, mêmes entrées: même hash
+        assert_eq!(first.hash, second.hash);
+
+        clock.set(u64::MAX);
+        assert_eq!(timestamp(), i64::MAX);
+    }
+}
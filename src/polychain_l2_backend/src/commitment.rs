@@ -0,0 +1,245 @@
+//! Commit-reveal submission for FairOrdering
+//! A sender first publishes a hiding commitment to its signed transaction and gets an
+//! ordering slot from the sequencer. The transaction revealed later takes that slot,
+//! so its place in the queue was fixed before anyone could read its contents.
+//! The sender signs the commitment with its account key and only that sender can reveal
+//! it; each sender holds a bounded number of open commitments, so slots cannot be hoarded
+//! anonymously. Commitments that are not revealed within the reveal window expire.
+
+use crate::{
+    cryptography,
+    errors::CommitmentError,
+    types::{CryptoAlgorithm, PolyTransaction},
+};
+use candid::{CandidType, Deserialize};
+use std::collections::HashMap;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct Commitment {
+    /// `PolyTransaction::commitment(salt)` of the transaction to reveal
+    pub hash: String,
+    /// Ordering slot the revealed transaction will take
+    pub slot: u64,
+    pub committed_at: i64,
+    /// Sender that signed the commitment, `None` for commitments restored from before
+    /// they were signed
+    pub sender: Option<String>,
+}
+
+/// Commitment signed by the sender of the transaction it hides
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommitmentSubmission {
+    /// `PolyTransaction::commitment(salt)` of the transaction to reveal
+    pub commitment: String,
+    pub sender: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key, the one bound to the sender's account
+    pub public_key: String,
+    /// Hex signature over `CommitmentSubmission::message()`
+    pub signature: String,
+}
+
+impl CommitmentSubmission {
+    /// Domain tag of the commitment signature
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/commitment/v1";
+
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(self.sender.as_bytes());
+        message.push(0);
+        message.extend_from_slice(self.commitment.to_lowercase().as_bytes());
+        message
+    }
+
+    /// Check the sender's signature, whether the key is the account's is the ledger's call
+    pub fn verify(&self) -> Result<(), CommitmentError> {
+        let invalid = |reason: String| CommitmentError::InvalidSignature {
+            sender: self.sender.clone(),
+            reason,
+        };
+        let public_key = hex::decode(&self.public_key)
+            .map_err(|e| invalid(format!("public key is not hex: {e}")))?;
+        let signature = hex::decode(&self.signature)
+            .map_err(|e| invalid(format!("signature is not hex: {e}")))?;
+        let is_valid = cryptography::verify_signature(
+            self.algorithm,
+            &public_key,
+            &self.message(),
+            &signature,
+        )
+        .map_err(|e| invalid(e.to_string()))?;
+        if !is_valid {
+            return Err(invalid(format!("{:?} verification failed", self.algorithm)));
+        }
+        Ok(())
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct CommitmentBook {
+    commitments: HashMap<String, Commitment>,
+}
+
+impl CommitmentBook {
+    pub fn get(&self, hash: &str) -> Option<&Commitment> {
+        self.commitments.get(hash)
+    }
+
+    /// Record a commitment of `sender`, at most `capacity` can wait for their reveal and
+    /// at most `per_sender` of them from the same sender
+    pub fn commit(
+        &mut self,
+        hash: &str,
+        sender: &str,
+        slot: u64,
+        now: i64,
+        capacity: u64,
+        per_sender: u64,
+    ) -> Result<(), CommitmentError> {
+        let hash = crate::crypto::decode_hash(hash)
+            .map(hex::encode)
+            .ok_or_else(|| CommitmentError::Malformed(hash.to_string()))?;
+        if self.commitments.contains_key(&hash) {
+            return Err(CommitmentError::Duplicate(hash));
+        }
+        if self.commitments.len() as u64 >= capacity {
+            return Err(CommitmentError::Full { capacity });
+        }
+        if self.open_by(sender) >= per_sender {
+            return Err(CommitmentError::SenderLimit {
+                sender: sender.to_string(),
+                limit: per_sender,
+            });
+        }
+        self.commitments.insert(
+            hash.clone(),
+            Commitment {
+                hash,
+                slot,
+                committed_at: now,
+                sender: Some(sender.to_string()),
+            },
+        );
+        Ok(())
+    }
+
+    /// Commitments of `sender` waiting for their reveal
+    pub fn open_by(&self, sender: &str) -> u64 {
+        self.commitments
+            .values()
+            .filter(|commitment| commitment.sender.as_deref() == Some(sender))
+            .count() as u64
+    }
+
+    /// Commitment opened by `transaction` and `salt`, if it is still within its window
+    pub fn find_open(
+        &self,
+        transaction: &PolyTransaction,
+        salt: &[u8],
+        window_seconds: u64,
+    ) -> Result<&Commitment, CommitmentError> {
+        let hash = transaction.commitment(salt);
+        let commitment = self
+            .commitments
+            .get(&hash)
+            .ok_or(CommitmentError::Unknown(hash))?;
+        if commitment
+            .sender
+            .as_ref()
+            .is_some_and(|sender| *sender != transaction.sender)
+        {
+            return Err(CommitmentError::WrongSender {
+                hash: commitment.hash.clone(),
+                sender: transaction.sender.clone(),
+            });
+        }
+        if Self::expired(commitment, transaction.time_stamp, window_seconds) {
+            return Err(CommitmentError::Expired(commitment.hash.clone()));
+        }
+        Ok(commitment)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commitments.is_empty()
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<Commitment> {
+        self.commitments.remove(hash)
+    }
+
+    /// Drop the commitments whose reveal window closed at `now`
+    pub fn expire(&mut self, now: i64, window_seconds: u64) -> Vec<Commitment> {
+        let expired: Vec<String> = self
+            .commitments
+            .values()
+            .filter(|commitment| Self::expired(commitment, now, window_seconds))
+            .map(|commitment| commitment.hash.clone())
+            .collect();
+        expired
+            .iter()
+            .filter_map(|hash| self.commitments.remove(hash))
+            .collect()
+    }
+
+    fn expired(commitment: &Commitment, now: i64, window_seconds: u64) -> bool {
+        let window = i64::try_from(window_seconds)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(NANOS_PER_SECOND);
+        commitment.committed_at.saturating_add(window) <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SupportedChain;
+
+    fn transfer(time_stamp: i64) -> PolyTransaction {
+        let mut tx = PolyTransaction::new(
+            "alice".to_string(),
+            "bob".to_string(),
+            100,
+            SupportedChain::Bitcoin,
+            0,
+        );
+        tx.time_stamp = time_stamp;
+        tx
+    }
+
+    #[test]
+    fn reveal_must_match_salt_and_window() {
+        let mut book = CommitmentBook::default();
+        let hash = transfer(0).commitment(b"salt");
+        book.commit(&hash, "alice", 7, 0, 10, 10).unwrap();
+        assert_eq!(
+            book.commit(&hash.to_uppercase(), "alice", 8, 0, 10, 10),
+            Err(CommitmentError::Duplicate(hash.clone()))
+        );
+        assert!(matches!(
+            book.commit("not a hash", "alice", 8, 0, 10, 10),
+            Err(CommitmentError::Malformed(_))
+        ));
+
+        // Mauvais sel: rien n'est ouvert
+        assert!(matches!(
+            book.find_open(&transfer(NANOS_PER_SECOND), b"other", 60),
+            Err(CommitmentError::Unknown(_))
+        ));
+        // L'horodatage d'arrivée ne fait pas partie de l'engagement
+        assert_eq!(
+            book.find_open(&transfer(NANOS_PER_SECOND), b"salt", 60)
+                .unwrap()
+                .slot,
+            7
+        );
+        assert_eq!(
+            book.find_open(&transfer(60 * NANOS_PER_SECOND), b"salt", 60),
+            Err(CommitmentError::Expired(hash.clone()))
+        );
+
+        assert_eq!(book.expire(60 * NANOS_PER_SECOND, 60).len(), 1);
+        assert!(book.get(&hash).is_none());
+    }
+}
@@ -63,7 +63,8 @@ impl CompressedBatch {
         for tx in transactions {
             tx.sender.hash(&mut hasher);
             tx.recipient.hash(&mut hasher);
-            tx.amount.to_bits().hash(&mut hasher);
+            tx.amount.hash(&mut hasher);
+            tx.asset.hash(&mut hasher);
         }
 
         format!("batch_{:x}", hasher.finish())
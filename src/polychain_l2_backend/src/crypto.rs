@@ -1,48 +1,143 @@
-/// Cryptographically secure hash calculation using Blake3
-/// This is critical for transaction hashes, block hashes, and all security-sensitive operations
-pub fn calculate_hash(data: &str) -> String {
-    let hash = blake3::hash(data.as_bytes());
-    hex::encode(hash.as_bytes())
+use candid::{CandidType, Deserialize};
+
+/// Domain tags keep leaves and inner nodes from ever hashing to the same value
+const MERKLE_LEAF_TAG: u8 = 0x00;
+const MERKLE_NODE_TAG: u8 = 0x01;
+
+/// One step of a Merkle inclusion path, from the leaf up to the root
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct MerkleStep {
+    /// Hex-encoded sibling node
+    pub sibling: String,
+    /// Whether the sibling sits left of the running hash
+    pub sibling_is_left: bool,
+}
+
+fn merkle_leaf(item_hash: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[MERKLE_LEAF_TAG]);
+    hasher.update(item_hash);
+    hasher.finalize().into()
+}
+
+pub(crate) fn merkle_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    let mut hasher = blake3::Hasher::new();
+    hasher.update(&[MERKLE_NODE_TAG]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
 }
 
-/// Calculate hash from raw bytes (more efficient for binary data)
-pub fn calculate_hash_bytes(data: &[u8]) -> String {
-    let hash = blake3::hash(data);
-    hex::encode(hash.as_bytes())
+/// Hash every level of the tree, leaves first. An unpaired last node is
+/// promoted unchanged instead of being duplicated, so no two leaf lists share a root.
+fn merkle_levels(item_hashes: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+    let mut levels = vec![item_hashes.iter().map(merkle_leaf).collect::<Vec<_>>()];
+    while levels.last().is_some_and(|level| level.len() > 1) {
+        let next = levels
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => merkle_node(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+        levels.push(next);
+    }
+    levels
 }
 
-/// Calculate hash and return as bytes (for performance-critical operations)
-pub fn calculate_hash_raw(data: &[u8]) -> [u8; 32] {
-    blake3::hash(data).into()
+/// Merkle root over item hashes, all zeroes for an empty list
+pub fn calculate_merkle_root(item_hashes: &[[u8; 32]]) -> [u8; 32] {
+    merkle_levels(item_hashes)
+        .last()
+        .and_then(|root_level| root_level.first().copied())
+        .unwrap_or([0; 32])
 }
 
-/*
-pub fn calculate_merkle_root(hashes: &[String]) -> String {
-    if hashes.is_empty() {
-        return String::new();
+/// Inclusion path for the item at `index`, `None` if out of range
+pub fn merkle_proof(item_hashes: &[[u8; 32]], index: usize) -> Option<Vec<MerkleStep>> {
+    if index >= item_hashes.len() {
+        return None;
+    }
+
+    let levels = merkle_levels(item_hashes);
+    let mut path = Vec::new();
+    let mut position = index;
+    for level in &levels[..levels.len() - 1] {
+        let sibling = position ^ 1;
+        // A promoted node has no sibling at this level
+        if let Some(node) = level.get(sibling) {
+            path.push(MerkleStep {
+                sibling: hex::encode(node),
+                sibling_is_left: sibling < position,
+            });
+        }
+        position /= 2;
     }
+    Some(path)
+}
 
-    if hashes.len() == 1 {
-        return hashes[0].clone();
+/// Check that `item_hash` is committed by `root` through `path`. Pure, needs no chain state.
+pub fn verify_merkle_proof(item_hash: &[u8; 32], path: &[MerkleStep], root: &[u8; 32]) -> bool {
+    let mut running = merkle_leaf(item_hash);
+    for step in path {
+        let Some(sibling) = decode_hash(&step.sibling) else {
+            return false;
+        };
+        running = if step.sibling_is_left {
+            merkle_node(&sibling, &running)
+        } else {
+            merkle_node(&running, &sibling)
+        };
     }
+    running == *root
+}
 
-    let mut current_level = hashes.to_vec();
+/// Decode a hex-encoded 32-byte hash
+pub fn decode_hash(hex_hash: &str) -> Option<[u8; 32]> {
+    hex::decode(hex_hash).ok()?.try_into().ok()
+}
 
-    while current_level.len() > 1 {
-        let mut next_level = Vec::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        for chunk in current_level.chunks(2) {
-            let combined = if chunk.len() == 2 {
-                format!("{}{}", chunk[0], chunk[1])
-            } else {
-                format!("{}{}", chunk[0], chunk[0])
-            };
-            next_level.push(calculate_hash(&combined));
-        }
+    fn items(count: u8) -> Vec<[u8; 32]> {
+        (0..count).map(|i| blake3::hash(&[i]).into()).collect()
+    }
 
-        current_level = next_level;
+    #[test]
+    fn merkle_proofs_verify_for_every_leaf() {
+        for count in 1..=9 {
+            let hashes = items(count);
+            let root = calculate_merkle_root(&hashes);
+            for (index, hash) in hashes.iter().enumerate() {
+                let path = merkle_proof(&hashes, index).unwrap();
+                assert!(verify_merkle_proof(hash, &path, &root));
+            }
+            assert!(merkle_proof(&hashes, hashes.len()).is_none());
+        }
     }
 
-    current_level[0].clone()
+    #[test]
+    fn merkle_proof_rejects_wrong_leaf_or_root() {
+        let hashes = items(5);
+        let root = calculate_merkle_root(&hashes);
+        let path = merkle_proof(&hashes, 2).unwrap();
+
+        assert!(!verify_merkle_proof(&hashes[3], &path, &root));
+        assert!(!verify_merkle_proof(&hashes[2], &path, &[0; 32]));
+
+        // A duplicated last leaf must not reproduce the same root
+        let mut padded = hashes.clone();
+        padded.push(hashes[4]);
+        assert_ne!(calculate_merkle_root(&padded), root);
+
+        // An inner node cannot pass for a leaf
+        let inner = merkle_node(&merkle_leaf(&hashes[0]), &merkle_leaf(&hashes[1]));
+        let short_path = merkle_proof(&hashes, 0).unwrap()[1..].to_vec();
+        assert!(!verify_merkle_proof(&inner, &short_path, &root));
+    }
 }
-*/
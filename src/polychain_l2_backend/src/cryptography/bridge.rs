@@ -1,10 +1,13 @@
-use crate::errors::CryptographyError;
+use crate::{errors::CryptographyError, types::CryptoAlgorithm};
 
 pub trait CryptographyBridge {
     type PublicKey;
     type SecretKey;
     type SignedMessage;
 
+    /// Scheme tag recorded next to keys and signatures produced by this bridge
+    const ALGORITHM: CryptoAlgorithm;
+
     fn key_generator(&self) -> Result<(Self::PublicKey, Self::SecretKey), CryptographyError>;
     fn sign(
         &self,
@@ -18,4 +21,48 @@ pub trait CryptographyBridge {
         signature: &Self::SignedMessage,
     ) -> Result<bool, CryptographyError>;
     fn public_key_to_bytes(&self, public_key: &Self::PublicKey) -> Vec<u8>;
+    fn public_key_from_bytes(&self, bytes: &[u8]) -> Result<Self::PublicKey, CryptographyError>;
+    fn signature_to_bytes(&self, signature: &Self::SignedMessage) -> Vec<u8>;
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Self::SignedMessage, CryptographyError>;
+
+    /// Verify a signature received as raw bytes, e.g. from a submitted transaction
+    fn verify_bytes(
+        &self,
+        public_key: &[u8],
+        message: &[u8],
+        signature: &[u8],
+    ) -> Result<bool, CryptographyError> {
+        let public_key = self.public_key_from_bytes(public_key)?;
+        let signature = self.signature_from_bytes(signature)?;
+        self.verify(&public_key, message, &signature)
+    }
+}
+
+/// Verifiable random function keyed by the scheme's own key pair
+pub trait VrfBridge: CryptographyBridge {
+    /// Key pair whose secret scalar is `seed`, for keys persisted as their seed
+    fn key_from_seed(
+        &self,
+        seed: &[u8; 32],
+    ) -> Result<(Self::PublicKey, Self::SecretKey), CryptographyError>;
+
+    /// Proof of the VRF evaluation of `input`, unique for a given key and input
+    fn vrf_prove(
+        &self,
+        secret_key: &Self::SecretKey,
+        input: &[u8],
+    ) -> Result<Vec<u8>, CryptographyError>;
+
+    /// Output proven by `proof` for `input` under `public_key`, `None` if the proof is invalid
+    fn vrf_verify(
+        &self,
+        public_key: &Self::PublicKey,
+        input: &[u8],
+        proof: &[u8],
+    ) -> Result<Option<[u8; 32]>, CryptographyError>;
+
+    /// Output of a proof, without checking it
+    fn vrf_proof_to_hash(&self, proof: &[u8]) -> Result<[u8; 32], CryptographyError> {
+        super::vrf::proof_to_hash(proof)
+    }
 }
@@ -6,7 +6,8 @@ use k256::{
     elliptic_curve::rand_core::OsRng,
 };
 
-use super::bridge::CryptographyBridge;
+use super::bridge::{CryptographyBridge, VrfBridge};
+use crate::types::CryptoAlgorithm;
 
 /// ECDSA signature scheme
 /// # Security
@@ -16,7 +17,7 @@ use super::bridge::CryptographyBridge;
 /// # Features
 /// - Key generation using secure OS randomness
 /// - Signing and verification of messages
-/// - VRF generation and verification using blake3 hashing
+/// - ECVRF proofs with the same key pair (see `super::vrf`)
 
 #[derive(Debug, Clone)]
 pub struct Ecdsa;
@@ -26,6 +27,8 @@ impl CryptographyBridge for Ecdsa {
     type SecretKey = SigningKey;
     type SignedMessage = Signature;
 
+    const ALGORITHM: CryptoAlgorithm = CryptoAlgorithm::Ecdsa;
+
     fn key_generator(
         &self,
     ) -> Result<(Self::PublicKey, Self::SecretKey), crate::errors::CryptographyError> {
@@ -54,6 +57,58 @@ impl CryptographyBridge for Ecdsa {
     fn public_key_to_bytes(&self, public_key: &Self::PublicKey) -> Vec<u8> {
         public_key.to_encoded_point(true).to_bytes().to_vec()
     }
+
+    fn public_key_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::PublicKey, crate::errors::CryptographyError> {
+        VerifyingKey::from_sec1_bytes(bytes).map_err(|e| {
+            crate::errors::CryptographyError::InvalidEncoding(format!("ECDSA public key: {e}"))
+        })
+    }
+
+    fn signature_to_bytes(&self, signature: &Self::SignedMessage) -> Vec<u8> {
+        signature.to_bytes().to_vec()
+    }
+
+    fn signature_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::SignedMessage, crate::errors::CryptographyError> {
+        Signature::from_slice(bytes).map_err(|e| {
+            crate::errors::CryptographyError::InvalidEncoding(format!("ECDSA signature: {e}"))
+        })
+    }
+}
+
+/// ECVRF with the secp256k1 key pair
+impl VrfBridge for Ecdsa {
+    fn key_from_seed(
+        &self,
+        seed: &[u8; 32],
+    ) -> Result<(Self::PublicKey, Self::SecretKey), crate::errors::CryptographyError> {
+        // Zéro ou au-delà de l'ordre de la courbe: probabilité ~2^-128
+        let secret_key = SigningKey::from_bytes(seed.into())
+            .map_err(|_| crate::errors::CryptographyError::KeyGeneration)?;
+        Ok((*secret_key.verifying_key(), secret_key))
+    }
+
+    fn vrf_prove(
+        &self,
+        secret_key: &Self::SecretKey,
+        input: &[u8],
+    ) -> Result<Vec<u8>, crate::errors::CryptographyError> {
+        super::vrf::prove(secret_key.as_nonzero_scalar(), input)
+    }
+
+    fn vrf_verify(
+        &self,
+        public_key: &Self::PublicKey,
+        input: &[u8],
+        proof: &[u8],
+    ) -> Result<Option<[u8; 32]>, crate::errors::CryptographyError> {
+        super::vrf::verify(public_key.as_affine(), input, proof)
+    }
 }
 
 #[cfg(test)]
@@ -6,6 +6,7 @@
 use std::fmt;
 
 use super::bridge::CryptographyBridge;
+use crate::types::CryptoAlgorithm;
 use fips205::{
     slh_dsa_sha2_128f::{self, PrivateKey, PublicKey},
     traits::{SerDes, Signer, Verifier},
@@ -45,6 +46,8 @@ impl CryptographyBridge for Falcon512 {
     type SecretKey = PrivateKey;
     type SignedMessage = FALCONSignature;
 
+    const ALGORITHM: CryptoAlgorithm = CryptoAlgorithm::Falcon512;
+
     fn key_generator(
         &self,
     ) -> Result<(Self::PublicKey, Self::SecretKey), crate::errors::CryptographyError> {
@@ -86,6 +89,38 @@ impl CryptographyBridge for Falcon512 {
     fn public_key_to_bytes(&self, public_key: &Self::PublicKey) -> Vec<u8> {
         public_key.0.clone().into_bytes().to_vec()
     }
+
+    fn public_key_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::PublicKey, crate::errors::CryptographyError> {
+        let key_array: [u8; slh_dsa_sha2_128f::PK_LEN] = bytes.try_into().map_err(|_| {
+            crate::errors::CryptographyError::InvalidEncoding(format!(
+                "SLH-DSA-SHA2-128f public key must be {} bytes, got {}",
+                slh_dsa_sha2_128f::PK_LEN,
+                bytes.len()
+            ))
+        })?;
+        PublicKey::try_from_bytes(&key_array)
+            .map(FALCONPublicKey)
+            .map_err(|e| {
+                crate::errors::CryptographyError::InvalidEncoding(format!(
+                    "SLH-DSA-SHA2-128f public key: {e}"
+                ))
+            })
+    }
+
+    fn signature_to_bytes(&self, signature: &Self::SignedMessage) -> Vec<u8> {
+        signature.0.clone()
+    }
+
+    fn signature_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::SignedMessage, crate::errors::CryptographyError> {
+        // Length is checked by verify()
+        Ok(FALCONSignature(bytes.to_vec()))
+    }
 }
 
 #[cfg(test)]
@@ -8,6 +8,7 @@ use std::fmt;
 use crate::errors::CryptographyError;
 
 use super::bridge::CryptographyBridge;
+use crate::types::CryptoAlgorithm;
 use fips204::{
     ml_dsa_44::{self, PrivateKey, PublicKey},
     traits::{SerDes, Signer, Verifier},
@@ -46,6 +47,8 @@ impl CryptographyBridge for Mldsa44 {
     type SecretKey = PrivateKey;
     type SignedMessage = MLDSASignature;
 
+    const ALGORITHM: CryptoAlgorithm = CryptoAlgorithm::Mldsa44;
+
     fn key_generator(&self) -> Result<(Self::PublicKey, Self::SecretKey), CryptographyError> {
         let mut rng = OsRng;
         let (private_key, public_key) = ml_dsa_44::try_keygen_with_rng(&mut rng)
@@ -85,6 +88,28 @@ impl CryptographyBridge for Mldsa44 {
     fn public_key_to_bytes(&self, public_key: &Self::PublicKey) -> Vec<u8> {
         public_key.0.clone().into_bytes().to_vec()
     }
+
+    fn public_key_from_bytes(&self, bytes: &[u8]) -> Result<Self::PublicKey, CryptographyError> {
+        let key_array: [u8; ml_dsa_44::PK_LEN] = bytes.try_into().map_err(|_| {
+            CryptographyError::InvalidEncoding(format!(
+                "ML-DSA-44 public key must be {} bytes, got {}",
+                ml_dsa_44::PK_LEN,
+                bytes.len()
+            ))
+        })?;
+        PublicKey::try_from_bytes(key_array)
+            .map(MLDSAPublicKey)
+            .map_err(|e| CryptographyError::InvalidEncoding(format!("ML-DSA-44 public key: {e}")))
+    }
+
+    fn signature_to_bytes(&self, signature: &Self::SignedMessage) -> Vec<u8> {
+        signature.0.clone()
+    }
+
+    fn signature_from_bytes(&self, bytes: &[u8]) -> Result<Self::SignedMessage, CryptographyError> {
+        // Length is checked by verify()
+        Ok(MLDSASignature(bytes.to_vec()))
+    }
 }
 
 #[cfg(test)]
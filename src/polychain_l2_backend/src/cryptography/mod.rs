@@ -3,3 +3,51 @@ pub mod ecdsa;
 pub mod falcon;
 pub mod mldsa;
 pub mod schnorr;
+pub mod vrf;
+
+use crate::{errors::CryptographyError, types::CryptoAlgorithm};
+use bridge::{CryptographyBridge, VrfBridge};
+
+/// Verify raw signature bytes with the scheme named by `algorithm`
+pub fn verify_signature(
+    algorithm: CryptoAlgorithm,
+    public_key: &[u8],
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool, CryptographyError> {
+    match algorithm {
+        CryptoAlgorithm::Ecdsa => ecdsa::Ecdsa.verify_bytes(public_key, message, signature),
+        CryptoAlgorithm::Schnorr => schnorr::Schnorr.verify_bytes(public_key, message, signature),
+        CryptoAlgorithm::Mldsa44 => mldsa::Mldsa44.verify_bytes(public_key, message, signature),
+        CryptoAlgorithm::Falcon512 => {
+            falcon::Falcon512.verify_bytes(public_key, message, signature)
+        }
+    }
+}
+
+/// Whether keys of `algorithm` can evaluate the proposer VRF
+pub fn supports_vrf(algorithm: CryptoAlgorithm) -> bool {
+    matches!(algorithm, CryptoAlgorithm::Ecdsa | CryptoAlgorithm::Schnorr)
+}
+
+/// Check a VRF proof with the scheme named by `algorithm`, returns the proven output
+pub fn vrf_verify(
+    algorithm: CryptoAlgorithm,
+    public_key: &[u8],
+    input: &[u8],
+    proof: &[u8],
+) -> Result<Option<[u8; 32]>, CryptographyError> {
+    match algorithm {
+        CryptoAlgorithm::Ecdsa => {
+            let public_key = ecdsa::Ecdsa.public_key_from_bytes(public_key)?;
+            ecdsa::Ecdsa.vrf_verify(&public_key, input, proof)
+        }
+        CryptoAlgorithm::Schnorr => {
+            let public_key = schnorr::Schnorr.public_key_from_bytes(public_key)?;
+            schnorr::Schnorr.vrf_verify(&public_key, input, proof)
+        }
+        CryptoAlgorithm::Mldsa44 | CryptoAlgorithm::Falcon512 => {
+            Err(CryptographyError::VrfUnsupported(algorithm))
+        }
+    }
+}
@@ -9,7 +9,8 @@ use k256::{
     },
 };
 
-use super::bridge::CryptographyBridge;
+use super::bridge::{CryptographyBridge, VrfBridge};
+use crate::types::CryptoAlgorithm;
 
 /// Schnorr signature scheme
 /// # Security
@@ -30,6 +31,8 @@ impl CryptographyBridge for Schnorr {
     type SecretKey = SigningKey;
     type SignedMessage = Signature;
 
+    const ALGORITHM: CryptoAlgorithm = CryptoAlgorithm::Schnorr;
+
     fn key_generator(
         &self,
     ) -> Result<(Self::PublicKey, Self::SecretKey), crate::errors::CryptographyError> {
@@ -58,6 +61,64 @@ impl CryptographyBridge for Schnorr {
     fn public_key_to_bytes(&self, public_key: &Self::PublicKey) -> Vec<u8> {
         public_key.to_bytes().to_vec()
     }
+
+    fn public_key_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::PublicKey, crate::errors::CryptographyError> {
+        // from_bytes panics on anything but a 32-byte x-only key
+        if bytes.len() != 32 {
+            return Err(crate::errors::CryptographyError::InvalidEncoding(format!(
+                "Schnorr public key must be 32 bytes, got {}",
+                bytes.len()
+            )));
+        }
+        VerifyingKey::from_bytes(bytes).map_err(|e| {
+            crate::errors::CryptographyError::InvalidEncoding(format!("Schnorr public key: {e}"))
+        })
+    }
+
+    fn signature_to_bytes(&self, signature: &Self::SignedMessage) -> Vec<u8> {
+        signature.to_bytes().to_vec()
+    }
+
+    fn signature_from_bytes(
+        &self,
+        bytes: &[u8],
+    ) -> Result<Self::SignedMessage, crate::errors::CryptographyError> {
+        Signature::try_from(bytes).map_err(|e| {
+            crate::errors::CryptographyError::InvalidEncoding(format!("Schnorr signature: {e}"))
+        })
+    }
+}
+
+/// ECVRF with the secp256k1 key pair
+impl VrfBridge for Schnorr {
+    fn key_from_seed(
+        &self,
+        seed: &[u8; 32],
+    ) -> Result<(Self::PublicKey, Self::SecretKey), crate::errors::CryptographyError> {
+        let secret_key = SigningKey::from_bytes(seed)
+            .map_err(|_| crate::errors::CryptographyError::KeyGeneration)?;
+        Ok((*secret_key.verifying_key(), secret_key))
+    }
+
+    fn vrf_prove(
+        &self,
+        secret_key: &Self::SecretKey,
+        input: &[u8],
+    ) -> Result<Vec<u8>, crate::errors::CryptographyError> {
+        super::vrf::prove(secret_key.as_nonzero_scalar(), input)
+    }
+
+    fn vrf_verify(
+        &self,
+        public_key: &Self::PublicKey,
+        input: &[u8],
+        proof: &[u8],
+    ) -> Result<Option<[u8; 32]>, crate::errors::CryptographyError> {
+        super::vrf::verify(public_key.as_affine(), input, proof)
+    }
 }
 
 #[cfg(test)]
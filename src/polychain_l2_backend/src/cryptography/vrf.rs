@@ -0,0 +1,268 @@
+//! ECVRF-SECP256K1-SHA256-TAI: the RFC 9381 ECVRF over secp256k1 with SHA-256 and
+//! try-and-increment hashing to the curve, suite string `0xFE`
+//!
+//! RFC 9381 only registers P-256 and edwards25519 suites; this one follows its section 5
+//! step for step with the secp256k1 parameters and the suite byte `0xFE` used by other
+//! secp256k1 implementations of the same construction. Exact encoding:
+//!
+//! - points are SEC1 compressed, 33 bytes; scalars are big-endian, 32 bytes
+//! - `Y` is the prover's public key, `alpha` the input bytes
+//! - `H = encode_to_curve(Y, alpha)`: the first `ctr` in `0..=255` for which
+//!   `0x02 || SHA256(0xFE || 0x01 || Y || alpha || ctr || 0x00)` decodes to a point
+//! - `Gamma = x * H`, with the nonce `k` from RFC 6979 (HMAC-SHA256) on the secret key `x`
+//!   and `SHA256(H)`, as in RFC 9381 section 5.4.2.1
+//! - `c = SHA256(0xFE || 0x02 || Y || H || Gamma || k*G || k*H || 0x00)[..16]`
+//! - `s = k + c * x mod n`, proof `pi = Gamma || c || s`, 81 bytes
+//! - output `beta = SHA256(0xFE || 0x03 || Gamma || 0x00)`, the cofactor being 1
+//!
+//! For a given key and input there is exactly one valid output, and nobody without the
+//! secret key can predict it. The test vectors of this module come from
+//! `scripts/ecvrf_vectors.py`, a separate implementation of the same steps.
+
+use k256::{
+    elliptic_curve::{
+        bigint::ArrayEncoding,
+        group::Group,
+        ops::Reduce,
+        sec1::{FromEncodedPoint, ToEncodedPoint},
+        Curve, PrimeField,
+    },
+    sha2::{Digest, Sha256},
+    AffinePoint, EncodedPoint, FieldBytes, NonZeroScalar, ProjectivePoint, Scalar, Secp256k1, U256,
+};
+
+use crate::errors::CryptographyError;
+
+const SUITE: u8 = 0xFE;
+const POINT_LEN: usize = 33;
+const CHALLENGE_LEN: usize = 16;
+const SCALAR_LEN: usize = 32;
+
+/// Length of an encoded proof: Gamma, the truncated challenge and s
+pub const PROOF_LEN: usize = POINT_LEN + CHALLENGE_LEN + SCALAR_LEN;
+
+/// Length of a VRF output
+pub const OUTPUT_LEN: usize = 32;
+
+fn point_to_bytes(point: &ProjectivePoint) -> Vec<u8> {
+    point.to_affine().to_encoded_point(true).as_bytes().to_vec()
+}
+
+fn point_from_bytes(bytes: &[u8]) -> Option<ProjectivePoint> {
+    let encoded = EncodedPoint::from_bytes(bytes).ok()?;
+    let point: Option<AffinePoint> = AffinePoint::from_encoded_point(&encoded).into();
+    point.map(ProjectivePoint::from)
+}
+
+/// Scalar from a big-endian string of at most 32 bytes
+fn scalar_from_bytes(bytes: &[u8]) -> Option<Scalar> {
+    let mut repr = [0u8; SCALAR_LEN];
+    repr[SCALAR_LEN - bytes.len()..].copy_from_slice(bytes);
+    Scalar::from_repr(FieldBytes::clone_from_slice(&repr)).into()
+}
+
+/// Try-and-increment: hash the key and input with a counter until it lands on the curve
+fn encode_to_curve(public_key: &[u8], alpha: &[u8]) -> Result<ProjectivePoint, CryptographyError> {
+    for counter in 0..=u8::MAX {
+        let hash = Sha256::new()
+            .chain_update([SUITE, 0x01])
+            .chain_update(public_key)
+            .chain_update(alpha)
+            .chain_update([counter, 0x00])
+            .finalize();
+        let mut candidate = vec![0x02];
+        candidate.extend_from_slice(&hash);
+        if let Some(point) = point_from_bytes(&candidate) {
+            return Ok(point);
+        }
+    }
+    // Chaque essai réussit avec une probabilité ~1/2: 256 échecs n'arrivent pas en pratique
+    Err(CryptographyError::InvalidEncoding(
+        "VRF input does not hash to the curve".to_string(),
+    ))
+}
+
+fn challenge(points: [&ProjectivePoint; 5]) -> [u8; CHALLENGE_LEN] {
+    let mut hasher = Sha256::new().chain_update([SUITE, 0x02]);
+    for point in points {
+        hasher.update(point_to_bytes(point));
+    }
+    let hash = hasher.chain_update([0x00]).finalize();
+    let mut truncated = [0u8; CHALLENGE_LEN];
+    truncated.copy_from_slice(&hash[..CHALLENGE_LEN]);
+    truncated
+}
+
+/// RFC 6979 nonce over `SHA256(H)`, RFC 9381 section 5.4.2.1
+fn nonce(secret_key: &NonZeroScalar, h: &ProjectivePoint) -> Scalar {
+    let digest = Sha256::digest(point_to_bytes(h));
+    // generate_k attend le condensé déjà réduit modulo n (bits2octets)
+    let reduced = <Scalar as Reduce<U256>>::reduce_bytes(&digest).to_repr();
+    let order = FieldBytes::from(Secp256k1::ORDER.to_be_byte_array());
+    let k = rfc6979::generate_k::<Sha256, _>(&secret_key.to_repr(), &order, &reduced, &[]);
+    scalar_from_bytes(&k).expect("RFC 6979 nonces are below the group order")
+}
+
+/// Prove `alpha` under `secret_key`, the output is `proof_to_hash(proof)`
+pub fn prove(secret_key: &NonZeroScalar, alpha: &[u8]) -> Result<Vec<u8>, CryptographyError> {
+    let public_key = ProjectivePoint::GENERATOR * **secret_key;
+    let h = encode_to_curve(&point_to_bytes(&public_key), alpha)?;
+    let gamma = h * **secret_key;
+    let k = nonce(secret_key, &h);
+    let c = challenge([
+        &public_key,
+        &h,
+        &gamma,
+        &(ProjectivePoint::GENERATOR * k),
+        &(h * k),
+    ]);
+    let c_scalar = scalar_from_bytes(&c).expect("a 128-bit challenge is a valid scalar");
+    let s = k + c_scalar * **secret_key;
+
+    let mut proof = point_to_bytes(&gamma);
+    proof.extend_from_slice(&c);
+    proof.extend_from_slice(&s.to_bytes());
+    Ok(proof)
+}
+
+/// Split a proof into Gamma, c and s
+fn decode_proof(
+    proof: &[u8],
+) -> Result<(ProjectivePoint, [u8; CHALLENGE_LEN], Scalar), CryptographyError> {
+    if proof.len() != PROOF_LEN {
+        return Err(CryptographyError::InvalidEncoding(format!(
+            "VRF proof is {} bytes, expected {PROOF_LEN}",
+            proof.len()
+        )));
+    }
+    let gamma = point_from_bytes(&proof[..POINT_LEN]).ok_or_else(|| {
+        CryptographyError::InvalidEncoding("VRF proof Gamma is not a curve point".to_string())
+    })?;
+    let mut c = [0u8; CHALLENGE_LEN];
+    c.copy_from_slice(&proof[POINT_LEN..POINT_LEN + CHALLENGE_LEN]);
+    let s = scalar_from_bytes(&proof[POINT_LEN + CHALLENGE_LEN..]).ok_or_else(|| {
+        CryptographyError::InvalidEncoding("VRF proof s is not a scalar".to_string())
+    })?;
+    Ok((gamma, c, s))
+}
+
+/// VRF output of a well-formed proof, without checking it
+pub fn proof_to_hash(proof: &[u8]) -> Result<[u8; OUTPUT_LEN], CryptographyError> {
+    let (gamma, _, _) = decode_proof(proof)?;
+    // Cofacteur 1 sur secp256k1
+    Ok(Sha256::new()
+        .chain_update([SUITE, 0x03])
+        .chain_update(point_to_bytes(&gamma))
+        .chain_update([0x00])
+        .finalize()
+        .into())
+}
+
+/// Output of `proof` if it proves `alpha` under `public_key`, `None` if it does not
+pub fn verify(
+    public_key: &AffinePoint,
+    alpha: &[u8],
+    proof: &[u8],
+) -> Result<Option<[u8; OUTPUT_LEN]>, CryptographyError> {
+    let public_key = ProjectivePoint::from(*public_key);
+    if bool::from(public_key.is_identity()) {
+        return Err(CryptographyError::InvalidEncoding(
+            "VRF public key is the identity".to_string(),
+        ));
+    }
+    let (gamma, c, s) = decode_proof(proof)?;
+    let c_scalar = scalar_from_bytes(&c).expect("a 128-bit challenge is a valid scalar");
+
+    let h = encode_to_curve(&point_to_bytes(&public_key), alpha)?;
+    let u = ProjectivePoint::GENERATOR * s - public_key * c_scalar;
+    let v = h * s - gamma * c_scalar;
+    if challenge([&public_key, &h, &gamma, &u, &v]) != c {
+        return Ok(None);
+    }
+    proof_to_hash(proof).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::elliptic_curve::rand_core::OsRng;
+
+    #[test]
+    fn proofs_are_unique_and_bound_to_key_and_input() {
+        let secret_key = NonZeroScalar::random(&mut OsRng);
+        let public_key = (ProjectivePoint::GENERATOR * *secret_key).to_affine();
+
+        let proof = prove(&secret_key, b"seed").unwrap();
+        assert_eq!(proof.len(), PROOF_LEN);
+        // Déterministe: une seule sortie par clé et par entrée
+        assert_eq!(proof, prove(&secret_key, b"seed").unwrap());
+
+        let output = verify(&public_key, b"seed", &proof).unwrap();
+        assert_eq!(output, Some(proof_to_hash(&proof).unwrap()));
+        assert_ne!(
+            output,
+            Some(proof_to_hash(&prove(&secret_key, b"other seed").unwrap()).unwrap())
+        );
+
+        // Autre entrée, autre clé, preuve altérée
+        assert_eq!(verify(&public_key, b"other seed", &proof).unwrap(), None);
+        let other_key =
+            (ProjectivePoint::GENERATOR * *NonZeroScalar::random(&mut OsRng)).to_affine();
+        assert_eq!(verify(&other_key, b"seed", &proof).unwrap(), None);
+        let mut tampered = proof.clone();
+        tampered[PROOF_LEN - 1] ^= 1;
+        assert_eq!(verify(&public_key, b"seed", &tampered).unwrap(), None);
+        assert!(verify(&public_key, b"seed", &proof[1..]).is_err());
+    }
+
+    /// Secret key, input, H, proof and output, hex
+    const VECTORS: [(&str, &str, &str, &str, &str); 4] = [
+        (
+            "0000000000000000000000000000000000000000000000000000000000000001",
+            "",
+            "024192220588c4ef502f5d2ab75552edfbe0256cebb0424efb9c4c58f438c3dcb4",
+            "024192220588c4ef502f5d2ab75552edfbe0256cebb0424efb9c4c58f438c3dcb43740e701a78589f13a3577908db37b1ddb55edaf0706552da59a41b69be3740878407cf6d13675cd94802a33b5e629f7",
+            "6bf7eda22a89f87fb8c8e17fa111727ca02d0a23db29fdcbe7ac84280e8bde24",
+        ),
+        (
+            "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+            "73616d706c65",
+            "0221ceb1ce22cd34d8b73a619164ed64e917ca31fd454075d02e4bdfa9c5ce0b48",
+            "0338ec99b5d0f94ebcc2c704c04af3de8b4289df8798e5fb9f920d7f5d77ac03d7718b9677d1c9348649ac2ec4f7ecbe519b30dd10c4eb5efc21dd5944709f2f3b7e97a25f6f095334593502d05103bc5b",
+            "d466c22e14dc3b7fd169668dd3ee9ac6351429a24aebc5e8af61a0f0de89b65a",
+        ),
+        (
+            "c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721",
+            "74657374",
+            "02594b108580b3735a97736d141720b8f2ddee2bcbef6319d057fef7e9972292ce",
+            "020ead2dc62f604a6ae2003b6c3012cf7ce2988dedf7606110e66edd5bb7f4b17bec303fd0bff5bfdff67ff6e4b6d4775d9efbe999f4d2467b61ab58659b6385c1a6c55fe84d1bb56c70152856a641364f",
+            "20b81616f3a3a4c51986e61f3b8e8e80d84f7fa0e05933bd0317150a5a250c09",
+        ),
+        (
+            "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364140",
+            "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+            "024bf790b7e1bf36d44a0634db171cdc4413723b973e9bc15c37e6d0bed2522c87",
+            "034bf790b7e1bf36d44a0634db171cdc4413723b973e9bc15c37e6d0bed2522c87751bb381be3e0532cd6e8625edbb8a138a46d416309c5dc04d3f0d9bb14e361a4cee7b8f9556dd7db8753d47b3d9d85c",
+            "33ae06c910ee442d6839ee71f9fbd9efcdfaa1d8ce86d749c82e3b3a1cd8e826",
+        ),
+    ];
+
+    #[test]
+    fn matches_the_reference_vectors() {
+        for (secret_key, alpha, h, proof, output) in VECTORS {
+            let secret_key =
+                NonZeroScalar::try_from(hex::decode(secret_key).unwrap().as_slice()).unwrap();
+            let public_key = ProjectivePoint::GENERATOR * *secret_key;
+            let alpha = hex::decode(alpha).unwrap();
+
+            let encoded = encode_to_curve(&point_to_bytes(&public_key), &alpha).unwrap();
+            assert_eq!(hex::encode(point_to_bytes(&encoded)), h);
+            let pi = prove(&secret_key, &alpha).unwrap();
+            assert_eq!(hex::encode(&pi), proof);
+            let beta = verify(&public_key.to_affine(), &alpha, &pi)
+                .unwrap()
+                .unwrap();
+            assert_eq!(hex::encode(beta), output);
+        }
+    }
+}
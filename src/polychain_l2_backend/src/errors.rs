@@ -1,3 +1,5 @@
+use crate::types::{ConsensusStep, CryptoAlgorithm, SupportedChain};
+
 #[derive(thiserror::Error, Debug)]
 pub enum CryptographyError {
     #[error("Failed to generate keys")]
@@ -8,4 +10,286 @@ pub enum CryptographyError {
     KeyGeneration,
     #[error("Signing error")]
     SigningError,
+    #[error("Invalid encoding: {0}")]
+    InvalidEncoding(String),
+    #[error("{0:?} keys do not provide a VRF")]
+    VrfUnsupported(CryptoAlgorithm),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum LedgerError {
+    #[error("Amount must be positive")]
+    ZeroAmount,
+    #[error("Balance overflow for {address} ({asset:?})")]
+    Overflow {
+        address: String,
+        asset: SupportedChain,
+    },
+    #[error("Max fee {max_fee} is below the base fee {base_fee}")]
+    FeeTooLow { max_fee: u128, base_fee: u128 },
+    #[error("Insufficient {asset:?} funds for {address}: balance {balance}, required {required}")]
+    InsufficientFunds {
+        address: String,
+        asset: SupportedChain,
+        balance: u128,
+        required: u128,
+    },
+    #[error("Invalid key registration for {address}: {reason}")]
+    InvalidKey { address: String, reason: String },
+    #[error("{0} has no bound key")]
+    UnboundKey(String),
+    #[error("Public key does not match the key bound to {0}")]
+    KeyMismatch(String),
+    #[error("Withdrawal from {address} is not authorized: {reason}")]
+    UnauthorizedWithdrawal { address: String, reason: String },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum SequencerError {
+    #[error("Invalid transaction")]
+    InvalidTransaction,
+    #[error("Transaction from {0} is not signed")]
+    MissingSignature(String),
+    #[error("Invalid signature from {sender}: {reason}")]
+    InvalidSignature { sender: String, reason: String },
+    #[error("Nonce {nonce} already used by {sender} (next expected nonce is {expected})")]
+    NonceAlreadyUsed {
+        sender: String,
+        expected: u64,
+        nonce: u64,
+    },
+    #[error("Max fee {max_fee} from {sender} is below the base fee {base_fee}")]
+    FeeTooLow {
+        sender: String,
+        max_fee: u128,
+        base_fee: u128,
+    },
+    #[error("Ordering randomness unavailable: {0}")]
+    Randomness(String),
+    #[error("Failed to sign block: {0}")]
+    BlockSigning(String),
+    #[error("Sequencer key is not provisioned")]
+    KeyNotProvisioned,
+    #[error("Nonce {nonce} from {sender} is out of order (next expected nonce is {expected})")]
+    NonceOutOfOrder {
+        sender: String,
+        expected: u64,
+        nonce: u64,
+    },
+    #[error(transparent)]
+    Mempool(#[from] MempoolError),
+    #[error(transparent)]
+    Commitment(#[from] CommitmentError),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum CommitmentError {
+    #[error("Commitment {0} is not a 32-byte hex hash")]
+    Malformed(String),
+    #[error("Commitment {0} was already submitted")]
+    Duplicate(String),
+    #[error("Too many commitments awaiting their reveal ({capacity})")]
+    Full { capacity: u64 },
+    #[error("{sender} already has {limit} commitments awaiting their reveal")]
+    SenderLimit { sender: String, limit: u64 },
+    #[error("Invalid commitment signature from {sender}: {reason}")]
+    InvalidSignature { sender: String, reason: String },
+    #[error("Commitment {hash} was not made by {sender}")]
+    WrongSender { hash: String, sender: String },
+    #[error("No pending commitment {0} matches the revealed transaction")]
+    Unknown(String),
+    #[error("Reveal window of commitment {0} has closed")]
+    Expired(String),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum MempoolError {
+    #[error("Mempool is full ({capacity} transactions) and no pending transaction pays less")]
+    Full { capacity: u64 },
+    #[error("{sender} already has {limit} pending transactions")]
+    SenderLimit { sender: String, limit: u64 },
+    #[error("Replacement for nonce {nonce} from {sender} must raise the fee and max fee by 10%")]
+    ReplacementUnderpriced { sender: String, nonce: u64 },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum VaultError {
+    #[error("Amount must be positive")]
+    ZeroAmount,
+    #[error("{0:?} vault reserve overflow")]
+    Overflow(SupportedChain),
+    #[error("Insufficient {asset:?} vault reserves: native {native}, wrapped {wrapped}, requested {requested}")]
+    InsufficientReserves {
+        asset: SupportedChain,
+        native: u128,
+        wrapped: u128,
+        requested: u128,
+    },
+    #[error("No pending withdrawal {0}")]
+    UnknownWithdrawal(String),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    #[error("Amount must be positive")]
+    ZeroAmount,
+    #[error("{0} is not a registered validator")]
+    UnknownValidator(String),
+    #[error("Invalid key registration for {address}: {reason}")]
+    InvalidRegistration { address: String, reason: String },
+    #[error("{0:?} keys do not provide a VRF and cannot propose blocks")]
+    VrfUnsupported(CryptoAlgorithm),
+    #[error("Public key is already registered by {0}")]
+    KeyInUse(String),
+    #[error("{address} did not authorize this change: {reason}")]
+    Unauthorized { address: String, reason: String },
+    #[error("{address} has {bonded} bonded, cannot unbond {requested}")]
+    InsufficientBond {
+        address: String,
+        bonded: u128,
+        requested: u128,
+    },
+    #[error("Bond overflow for {0}")]
+    Overflow(String),
+    #[error(transparent)]
+    Ledger(#[from] LedgerError),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum EvidenceError {
+    #[error("{0} is not a registered validator")]
+    UnknownValidator(String),
+    #[error("Both signatures cover block {0}, nothing conflicts")]
+    NotConflicting(String),
+    #[error("Invalid validator key: {0}")]
+    InvalidKey(String),
+    #[error("Invalid signature over block {block_hash}: {reason}")]
+    InvalidSignature { block_hash: String, reason: String },
+    #[error("{validator} was already slashed for its {step:?} at height {height}, round {round}")]
+    AlreadySlashed {
+        validator: String,
+        step: ConsensusStep,
+        height: u64,
+        round: u64,
+    },
+    #[error("Evidence for height {height} is past the unbonding period, oldest slashable height is {oldest}")]
+    Expired { height: u64, oldest: u64 },
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ChainError {
+    #[error("Block {height} hash mismatch: stored {stored}, recomputed {computed}")]
+    HashMismatch {
+        height: u64,
+        stored: String,
+        computed: String,
+    },
+    #[error("Block {height} transaction root does not match its transactions")]
+    TxRootMismatch { height: u64 },
+    #[error("Block {height} links to {previous_hash}, expected {expected}")]
+    BrokenLink {
+        height: u64,
+        previous_hash: String,
+        expected: String,
+    },
+    #[error("Block {height} ordering does not follow its VRF proof: {reason}")]
+    InvalidOrdering { height: u64, reason: String },
+    #[error("Block {height} proposer signature is invalid: {reason}")]
+    InvalidSignature { height: u64, reason: String },
+    #[error("Block {height} carries no proposer signature")]
+    MissingProposer { height: u64 },
+    #[error(
+        "Block {height} timestamp {timestamp} is earlier than its parent's {parent_timestamp}"
+    )]
+    TimestampRegression {
+        height: u64,
+        timestamp: i64,
+        parent_timestamp: i64,
+    },
+}
+
+impl ChainError {
+    /// Height of the first invalid block
+    pub fn height(&self) -> u64 {
+        match self {
+            ChainError::HashMismatch { height, .. }
+            | ChainError::TxRootMismatch { height }
+            | ChainError::BrokenLink { height, .. }
+            | ChainError::InvalidOrdering { height, .. }
+            | ChainError::InvalidSignature { height, .. }
+            | ChainError::MissingProposer { height }
+            | ChainError::TimestampRegression { height, .. } => *height,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum ReplayError {
+    #[error("Input {sequence} awaited: replayed calls must complete synchronously")]
+    Suspended { sequence: u64 },
+    #[error("Input {sequence} sealed {replayed:?}, the log recorded {logged:?}")]
+    BlockHashMismatch {
+        sequence: u64,
+        logged: Option<String>,
+        replayed: Option<String>,
+    },
+    #[error("Input {sequence} returned {replayed:?}, the log recorded {logged:?}")]
+    OutcomeMismatch {
+        sequence: u64,
+        logged: Option<String>,
+        replayed: Option<String>,
+    },
+    #[error("Replay panicked: {0}")]
+    Panicked(String),
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq)]
+pub enum StorageError {
+    #[error("Stable memory is full: memory {0} cannot grow")]
+    OutOfMemory(u8),
+    #[error("Stable memory holds neither a state blob nor a known layout")]
+    UnknownLayout,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConsensusError {
+    #[error("Validators hold no stake")]
+    NoStake,
+    #[error("No validator was selected for the committee")]
+    EmptyCommittee,
+    #[error("Vote from {0}, who holds no stake or registered key")]
+    UnknownValidator(String),
+    #[error("{0} voted more than once")]
+    DuplicateVote(String),
+    #[error("Invalid VRF from {validator}: {reason}")]
+    InvalidVrf { validator: String, reason: String },
+    #[error("Invalid proposal signature from {validator}: {reason}")]
+    InvalidProposal { validator: String, reason: String },
+    #[error("Invalid vote from {validator}: {reason}")]
+    InvalidVote { validator: String, reason: String },
+    #[error("Certificate is for block {certified}, not {expected}")]
+    CertificateMismatch { expected: String, certified: String },
+    #[error("Block {0} carries no quorum certificate")]
+    MissingCertificate(String),
+    #[error("Quorum not reached: {voted} of {total} stake voted, more than {numerator}/{denominator} required")]
+    QuorumNotReached {
+        voted: u128,
+        total: u128,
+        numerator: u64,
+        denominator: u64,
+    },
+    #[error("Quorum of {numerator}/{denominator} is not a BFT quorum: it must be at least 2/3 and below 1")]
+    InvalidQuorum { numerator: u64, denominator: u64 },
+    #[error("{validator} proposed round {round}, which {leader} leads")]
+    WrongProposer {
+        validator: String,
+        round: u64,
+        leader: String,
+    },
+    #[error("Proposer {validator} of round {round} is offline")]
+    LeaderOffline { validator: String, round: u64 },
+    #[error("Round {round} at height {height} lacks a timeout certificate for the previous round")]
+    MissingTimeout { height: u64, round: u64 },
+    #[error(transparent)]
+    Cryptography(#[from] CryptographyError),
 }
@@ -0,0 +1,236 @@
+//! Equivocation evidence
+//! A validator signs at most one block hash per height, round and consensus step. Two valid
+//! signatures from the same key over different hashes for the same height, round and step
+//! prove that it double-signed. Anyone holding both signatures, e.g. from two conflicting
+//! certificates, can submit them; the registry then slashes and jails the offender.
+
+use crate::{
+    cryptography::{
+        bridge::CryptographyBridge, ecdsa::Ecdsa, falcon::Falcon512, mldsa::Mldsa44,
+        schnorr::Schnorr,
+    },
+    errors::EvidenceError,
+    types::{ConsensusStep, CryptoAlgorithm, QuorumCertificate},
+};
+use candid::{CandidType, Deserialize};
+
+/// Block hash and the validator's hex signature over it
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct SignedBlockHash {
+    pub block_hash: String,
+    /// Hex signature over `step.message(height, round, block_hash)`
+    pub signature: String,
+}
+
+/// Two signatures of `validator` over different blocks for the same height, round and step
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct EquivocationEvidence {
+    pub validator: String,
+    pub step: ConsensusStep,
+    pub height: u64,
+    pub round: u64,
+    pub first: SignedBlockHash,
+    pub second: SignedBlockHash,
+}
+
+impl EquivocationEvidence {
+    /// Every double-signing revealed by two certificates of the same height and round
+    pub fn from_certificates(a: &QuorumCertificate, b: &QuorumCertificate) -> Vec<Self> {
+        if a.height != b.height || a.round != b.round || a.block_hash == b.block_hash {
+            return Vec::new();
+        }
+        let evidence = |validator: &str, step, first: &str, second: &str| Self {
+            validator: validator.to_string(),
+            step,
+            height: a.height,
+            round: a.round,
+            first: SignedBlockHash {
+                block_hash: a.block_hash.clone(),
+                signature: first.to_string(),
+            },
+            second: SignedBlockHash {
+                block_hash: b.block_hash.clone(),
+                signature: second.to_string(),
+            },
+        };
+
+        let mut found = Vec::new();
+        if a.proposer.validator == b.proposer.validator {
+            found.push(evidence(
+                &a.proposer.validator,
+                ConsensusStep::Proposal,
+                &a.proposal_signature,
+                &b.proposal_signature,
+            ));
+        }
+        for vote in &a.votes {
+            if let Some(other) = b
+                .votes
+                .iter()
+                .find(|other| other.validator == vote.validator)
+            {
+                found.push(evidence(
+                    &vote.validator,
+                    ConsensusStep::Vote,
+                    &vote.signature,
+                    &other.signature,
+                ));
+            }
+        }
+        found
+    }
+
+    /// Check that both signatures are `public_key`'s, over two different blocks
+    pub fn verify<C: CryptographyBridge>(
+        &self,
+        cryptography: &C,
+        public_key: &C::PublicKey,
+    ) -> Result<(), EvidenceError> {
+        if self.first.block_hash == self.second.block_hash {
+            return Err(EvidenceError::NotConflicting(self.first.block_hash.clone()));
+        }
+        for signed in [&self.first, &self.second] {
+            let invalid = |reason: String| EvidenceError::InvalidSignature {
+                block_hash: signed.block_hash.clone(),
+                reason,
+            };
+            let signature = hex::decode(&signed.signature)
+                .map_err(|e| invalid(format!("signature is not hex: {e}")))?;
+            let signature = cryptography
+                .signature_from_bytes(&signature)
+                .map_err(|e| invalid(e.to_string()))?;
+            let message = self
+                .step
+                .message(self.height, self.round, &signed.block_hash);
+            let is_valid = cryptography
+                .verify(public_key, &message, &signature)
+                .map_err(|e| invalid(e.to_string()))?;
+            if !is_valid {
+                return Err(invalid(format!("{:?} verification failed", C::ALGORITHM)));
+            }
+        }
+        Ok(())
+    }
+
+    /// `verify` against a hex key registered with `algorithm`
+    pub fn verify_registered(
+        &self,
+        algorithm: CryptoAlgorithm,
+        public_key: &str,
+    ) -> Result<(), EvidenceError> {
+        let public_key = hex::decode(public_key)
+            .map_err(|e| EvidenceError::InvalidKey(format!("public key is not hex: {e}")))?;
+        match algorithm {
+            CryptoAlgorithm::Ecdsa => self.verify_with(&Ecdsa, &public_key),
+            CryptoAlgorithm::Schnorr => self.verify_with(&Schnorr, &public_key),
+            CryptoAlgorithm::Mldsa44 => self.verify_with(&Mldsa44, &public_key),
+            CryptoAlgorithm::Falcon512 => self.verify_with(&Falcon512, &public_key),
+        }
+    }
+
+    fn verify_with<C: CryptographyBridge>(
+        &self,
+        cryptography: &C,
+        public_key: &[u8],
+    ) -> Result<(), EvidenceError> {
+        let public_key = cryptography
+            .public_key_from_bytes(public_key)
+            .map_err(|e| EvidenceError::InvalidKey(e.to_string()))?;
+        self.verify(cryptography, &public_key)
+    }
+}
+
+/// Penalty applied for an equivocation
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct SlashingEvent {
+    pub validator: String,
+    pub step: ConsensusStep,
+    pub height: u64,
+    pub round: u64,
+    /// The two conflicting hashes the validator signed
+    pub block_hashes: Vec<String>,
+    /// Stake burned, taken from the bond first, then from pending unbondings
+    pub slashed: u128,
+    /// Epoch the evidence was accepted in
+    pub epoch: u64,
+    /// First epoch the validator may rejoin the active set
+    pub jailed_until: u64,
+    /// Time the evidence was accepted (nanoseconds)
+    pub reported_at: u64,
+}
+
+impl SlashingEvent {
+    /// Whether `evidence` reports the same offence
+    pub fn covers(&self, evidence: &EquivocationEvidence) -> bool {
+        self.validator == evidence.validator
+            && self.step == evidence.step
+            && self.height == evidence.height
+            && self.round == evidence.round
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evidence(first: &str, second: &str) -> (EquivocationEvidence, Vec<u8>) {
+        let (public_key, secret_key) = Ecdsa.key_generator().unwrap();
+        let sign = |block_hash: &str| SignedBlockHash {
+            block_hash: block_hash.to_string(),
+            signature: hex::encode(
+                Ecdsa.signature_to_bytes(
+                    &Ecdsa
+                        .sign(&secret_key, &ConsensusStep::Vote.message(4, 1, block_hash))
+                        .unwrap(),
+                ),
+            ),
+        };
+        let evidence = EquivocationEvidence {
+            validator: "alice".to_string(),
+            step: ConsensusStep::Vote,
+            height: 4,
+            round: 1,
+            first: sign(first),
+            second: sign(second),
+        };
+        (evidence, Ecdsa.public_key_to_bytes(&public_key))
+    }
+
+    #[test]
+    fn evidence_needs_two_signed_conflicting_blocks() {
+        let (valid, public_key) = evidence("aa", "bb");
+        let public_key = hex::encode(public_key);
+        assert_eq!(
+            valid.verify_registered(CryptoAlgorithm::Ecdsa, &public_key),
+            Ok(())
+        );
+
+        // Même bloc signé deux fois: pas d'équivoque
+        let (same, same_key) = evidence("aa", "aa");
+        assert_eq!(
+            same.verify_registered(CryptoAlgorithm::Ecdsa, &hex::encode(same_key)),
+            Err(EvidenceError::NotConflicting("aa".to_string()))
+        );
+
+        // Signatures valides mais pour un autre tour ou une autre étape
+        let mut other_round = valid.clone();
+        other_round.round = 2;
+        assert!(matches!(
+            other_round.verify_registered(CryptoAlgorithm::Ecdsa, &public_key),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+        let mut other_step = valid.clone();
+        other_step.step = ConsensusStep::Proposal;
+        assert!(matches!(
+            other_step.verify_registered(CryptoAlgorithm::Ecdsa, &public_key),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+
+        // Clé d'un autre validateur
+        let (_, other_key) = evidence("aa", "bb");
+        assert!(matches!(
+            valid.verify_registered(CryptoAlgorithm::Ecdsa, &hex::encode(other_key)),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+    }
+}
@@ -0,0 +1,189 @@
+//! Measured ordering fairness
+//! A batch is compared with arrival order, given by the ordering slot the sequencer handed
+//! each transaction. Every pair whose later arrival was placed first is an inversion,
+//! including pairs where the earlier arrival was left in the queue for a later batch.
+//! Kendall's tau follows from the inversion count: 1 is arrival order, -1 its reverse.
+
+use crate::chain::OrderingStrategy;
+use candid::{CandidType, Deserialize};
+
+/// Fairness of one batch
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct OrderingFairness {
+    pub transactions: u64,
+    pub inversions: u64,
+    /// Pairs whose first-placed transaction is in the batch
+    pub pairs: u64,
+}
+
+impl OrderingFairness {
+    /// Measure the first `batch_size` entries of a queue, `arrival_ranks` listed in
+    /// produced order. Inversions are counted with a Fenwick tree over the ranks.
+    pub fn measure(arrival_ranks: &[u64], batch_size: usize) -> Self {
+        let count = arrival_ranks.len();
+        let batch_size = batch_size.min(count);
+
+        let mut ranks = arrival_ranks.to_vec();
+        ranks.sort_unstable();
+        ranks.dedup();
+        let mut tree = vec![0u64; ranks.len() + 1];
+
+        let mut inversions = 0;
+        for (position, rank) in arrival_ranks.iter().enumerate().rev() {
+            // Index of the rank among the distinct ranks, 1-based for the tree
+            let index = ranks.partition_point(|other| other < rank) + 1;
+            if position < batch_size {
+                // Transactions placed after this one but arrived before it
+                let mut i = index - 1;
+                while i > 0 {
+                    inversions += tree[i];
+                    i &= i - 1;
+                }
+            }
+            let mut i = index;
+            while i < tree.len() {
+                tree[i] += 1;
+                i += i & i.wrapping_neg();
+            }
+        }
+
+        let pairs = (0..batch_size)
+            .map(|position| (count - 1 - position) as u64)
+            .sum();
+        Self {
+            transactions: batch_size as u64,
+            inversions,
+            pairs,
+        }
+    }
+
+    pub fn kendall_tau(&self) -> f64 {
+        kendall_tau(self.inversions, self.pairs)
+    }
+
+    /// Share of pairs left in arrival order, 1.0 for a perfectly fair batch
+    pub fn score(&self) -> f64 {
+        score(self.inversions, self.pairs)
+    }
+}
+
+fn kendall_tau(inversions: u64, pairs: u64) -> f64 {
+    if pairs == 0 {
+        return 1.0;
+    }
+    1.0 - 2.0 * inversions as f64 / pairs as f64
+}
+
+fn score(inversions: u64, pairs: u64) -> f64 {
+    if pairs == 0 {
+        return 1.0;
+    }
+    1.0 - inversions as f64 / pairs as f64
+}
+
+/// Fairness of every batch sequenced under one strategy, pooled over their pairs
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct StrategyFairness {
+    pub strategy: OrderingStrategy,
+    pub batches: u64,
+    pub transactions: u64,
+    pub inversions: u64,
+    pub pairs: u64,
+    pub kendall_tau: f64,
+    pub fairness_score: f64,
+}
+
+impl StrategyFairness {
+    fn new(strategy: OrderingStrategy) -> Self {
+        Self {
+            strategy,
+            batches: 0,
+            transactions: 0,
+            inversions: 0,
+            pairs: 0,
+            kendall_tau: 1.0,
+            fairness_score: 1.0,
+        }
+    }
+
+    fn record(&mut self, batch: &OrderingFairness) {
+        self.batches += 1;
+        self.transactions += batch.transactions;
+        self.inversions += batch.inversions;
+        self.pairs += batch.pairs;
+        self.kendall_tau = kendall_tau(self.inversions, self.pairs);
+        self.fairness_score = score(self.inversions, self.pairs);
+    }
+}
+
+/// Add a batch to its strategy's totals and return them
+pub fn record(
+    stats: &mut Vec<StrategyFairness>,
+    strategy: &OrderingStrategy,
+    batch: &OrderingFairness,
+) -> StrategyFairness {
+    let index = match stats.iter().position(|entry| entry.strategy == *strategy) {
+        Some(index) => index,
+        None => {
+            stats.push(StrategyFairness::new(strategy.clone()));
+            stats.len() - 1
+        }
+    };
+    stats[index].record(batch);
+    stats[index].clone()
+}
+
+/// Strategy with the best measured fairness, more sequenced transactions breaking ties
+pub fn fairest(stats: &[StrategyFairness]) -> Option<&StrategyFairness> {
+    stats
+        .iter()
+        .filter(|entry| entry.transactions > 0)
+        .max_by(|a, b| {
+            a.fairness_score
+                .total_cmp(&b.fairness_score)
+                .then(a.transactions.cmp(&b.transactions))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inversions_against_arrival_order() {
+        // Ordre d'arrivée: aucune inversion
+        let fair = OrderingFairness::measure(&[0, 1, 2, 3], 4);
+        assert_eq!((fair.inversions, fair.pairs), (0, 6));
+        assert_eq!(fair.kendall_tau(), 1.0);
+
+        // Ordre inverse: toutes les paires
+        let reversed = OrderingFairness::measure(&[3, 2, 1, 0], 4);
+        assert_eq!((reversed.inversions, reversed.pairs), (6, 6));
+        assert_eq!(reversed.kendall_tau(), -1.0);
+
+        // 3 passe avant 0, 1 et 2 restés dans la file
+        let skipped = OrderingFairness::measure(&[3, 0, 1, 2], 1);
+        assert_eq!((skipped.inversions, skipped.pairs), (3, 3));
+        assert_eq!(skipped.score(), 0.0);
+
+        assert_eq!(OrderingFairness::measure(&[], 10).score(), 1.0);
+    }
+
+    #[test]
+    fn strategies_are_pooled_separately() {
+        let mut stats = Vec::new();
+        let fair = OrderingFairness::measure(&[0, 1, 2], 3);
+        let unfair = OrderingFairness::measure(&[2, 1, 0], 3);
+        record(&mut stats, &OrderingStrategy::FirstComeFirstServed, &fair);
+        record(&mut stats, &OrderingStrategy::PriorityFee, &unfair);
+        let priority = record(&mut stats, &OrderingStrategy::PriorityFee, &fair);
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(priority.batches, 2);
+        assert_eq!(priority.fairness_score, 0.5);
+        assert_eq!(
+            fairest(&stats).unwrap().strategy,
+            OrderingStrategy::FirstComeFirstServed
+        );
+    }
+}
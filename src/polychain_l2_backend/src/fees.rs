@@ -0,0 +1,199 @@
+//! EIP-1559 style fee market
+//! Each asset has a base fee, in its own base unit, that follows block fullness:
+//! it rises when blocks are above target and falls back to a floor when they are below.
+//! The base fee is burned, the priority tip goes to `FEE_COLLECTOR`.
+
+use crate::types::{PolyTransaction, SupportedChain};
+use candid::{CandidType, Deserialize};
+use std::{cmp::Ordering, collections::HashMap};
+
+/// L2 account credited with the priority tips
+pub const FEE_COLLECTOR: &str = "polychain_l2_fee_collector";
+
+/// Blocks this many transactions full keep the base fee stable
+pub const DEFAULT_TARGET_BLOCK_SIZE: u64 = 50;
+
+/// Largest change per block is 1/8 of the base fee, as in EIP-1559
+const BASE_FEE_CHANGE_DENOMINATOR: u128 = 8;
+
+/// Scale of the priority score, tips are compared as multiples of their asset's base fee
+const PRIORITY_SCALE: u128 = 1_000_000;
+
+/// Lowest base fee of an asset: 1 sat, 1 gwei, the ICP ledger fee, the Solana signature fee
+pub fn min_base_fee(asset: SupportedChain) -> u128 {
+    match asset {
+        SupportedChain::Bitcoin => 1,
+        SupportedChain::Ethereum => 1_000_000_000,
+        SupportedChain::Icp => 10_000,
+        SupportedChain::Solana => 5_000,
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct FeeMarket {
+    base_fees: HashMap<SupportedChain, u128>,
+    target_block_size: u64,
+}
+
+impl Default for FeeMarket {
+    fn default() -> Self {
+        Self::new(DEFAULT_TARGET_BLOCK_SIZE)
+    }
+}
+
+impl FeeMarket {
+    pub fn new(target_block_size: u64) -> Self {
+        Self {
+            base_fees: HashMap::new(),
+            target_block_size: target_block_size.max(1),
+        }
+    }
+
+    pub fn base_fee(&self, asset: SupportedChain) -> u128 {
+        self.base_fees
+            .get(&asset)
+            .copied()
+            .unwrap_or_else(|| min_base_fee(asset))
+    }
+
+    /// Adjust every base fee after a block of `block_size` transactions
+    pub fn record_block(&mut self, block_size: u64) {
+        let target = u128::from(self.target_block_size);
+        let used = u128::from(block_size);
+
+        for asset in [
+            SupportedChain::Bitcoin,
+            SupportedChain::Ethereum,
+            SupportedChain::Icp,
+            SupportedChain::Solana,
+        ] {
+            let base_fee = self.base_fee(asset);
+            // Move by at least one unit so small fees never get stuck
+            let next = match used.cmp(&target) {
+                Ordering::Greater => {
+                    let delta = base_fee.saturating_mul(used - target)
+                        / target
+                        / BASE_FEE_CHANGE_DENOMINATOR;
+                    base_fee.saturating_add(delta.max(1))
+                }
+                Ordering::Less => {
+                    let delta = base_fee.saturating_mul(target - used)
+                        / target
+                        / BASE_FEE_CHANGE_DENOMINATOR;
+                    base_fee
+                        .saturating_sub(delta.max(1))
+                        .max(min_base_fee(asset))
+                }
+                Ordering::Equal => base_fee,
+            };
+            self.base_fees.insert(asset, next);
+        }
+    }
+
+    /// Whether every base fee sits at its floor, where empty blocks leave it unchanged
+    pub fn is_at_floor(&self) -> bool {
+        self.base_fees
+            .iter()
+            .all(|(asset, base_fee)| *base_fee <= min_base_fee(*asset))
+    }
+
+    /// Ordering key of the PriorityFee strategy, comparable across assets
+    pub fn priority_score(&self, tx: &PolyTransaction) -> u128 {
+        let base_fee = self.base_fee(tx.asset);
+        tx.effective_tip(base_fee).saturating_mul(PRIORITY_SCALE) / base_fee.max(1)
+    }
+}
+
+/// Fee quote for a wallet about to sign a transaction
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct FeeEstimate {
+    pub asset: SupportedChain,
+    pub base_fee: u128,
+    /// Median tip of the pending transactions in this asset, `base_fee / 10` when there are none
+    pub priority_fee: u128,
+    /// Twice the base fee plus the tip, enough to stay includable through several full blocks
+    pub max_fee: u128,
+}
+
+impl FeeEstimate {
+    pub fn new(market: &FeeMarket, asset: SupportedChain, pending: &[PolyTransaction]) -> Self {
+        let base_fee = market.base_fee(asset);
+        let mut tips: Vec<u128> = pending
+            .iter()
+            .filter(|tx| tx.asset == asset)
+            .map(|tx| tx.effective_tip(base_fee))
+            .collect();
+        tips.sort_unstable();
+
+        let priority_fee = tips
+            .get(tips.len() / 2)
+            .copied()
+            .unwrap_or(base_fee / 10)
+            .max(1);
+        Self {
+            asset,
+            base_fee,
+            priority_fee,
+            max_fee: base_fee.saturating_mul(2).saturating_add(priority_fee),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BTC: SupportedChain = SupportedChain::Bitcoin;
+
+    #[test]
+    fn base_fee_follows_block_fullness() {
+        let mut market = FeeMarket::new(10);
+        market.base_fees.insert(BTC, 800);
+
+        // Bloc deux fois plus plein que la cible: +1/8
+        market.record_block(20);
+        assert_eq!(market.base_fee(BTC), 900);
+
+        // Bloc à la cible: inchangé
+        market.record_block(10);
+        assert_eq!(market.base_fee(BTC), 900);
+
+        // Blocs vides: -1/8 jusqu'au plancher
+        market.record_block(0);
+        assert_eq!(market.base_fee(BTC), 788);
+        for _ in 0..100 {
+            market.record_block(0);
+        }
+        assert_eq!(market.base_fee(BTC), min_base_fee(BTC));
+
+        // Même au plancher de 1 sat, un bloc plein fait monter le prix
+        market.record_block(11);
+        assert_eq!(market.base_fee(BTC), 2);
+    }
+
+    #[test]
+    fn estimate_uses_median_pending_tip() {
+        let market = FeeMarket::default();
+        let base_fee = market.base_fee(SupportedChain::Icp);
+        let pending: Vec<PolyTransaction> = [100, 300, 200]
+            .into_iter()
+            .map(|tip| {
+                PolyTransaction::new(
+                    "alice".to_string(),
+                    "bob".to_string(),
+                    1,
+                    SupportedChain::Icp,
+                    0,
+                )
+                .with_fee(tip, base_fee + tip)
+            })
+            .collect();
+
+        let estimate = FeeEstimate::new(&market, SupportedChain::Icp, &pending);
+        assert_eq!(estimate.priority_fee, 200);
+        assert_eq!(estimate.max_fee, 2 * base_fee + 200);
+
+        let idle = FeeEstimate::new(&market, SupportedChain::Solana, &pending);
+        assert_eq!(idle.priority_fee, min_base_fee(SupportedChain::Solana) / 10);
+    }
+}
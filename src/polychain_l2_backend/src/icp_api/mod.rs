@@ -1,7 +1,7 @@
 /// ICP Mainnet API Integration for Real Transaction Data
 /// Fetches real transaction data from ICP Ledger API for demo purposes
 
-use crate::types::PolyTransaction;
+use crate::types::{PolyTransaction, SupportedChain};
 use candid::{CandidType, Deserialize};
 
 #[derive(CandidType, Deserialize, Clone, Debug)]
@@ -65,14 +65,14 @@ impl ICPDataFetcher {
                     (
                         transfer.from.clone(),
                         transfer.to.clone(), 
-                        transfer.amount as f64 / 100_000_000.0, // Convert from e8s to ICP
+                        u128::from(transfer.amount), // e8s, exact base units
                     )
                 },
                 ICPTransactionDetails { transfer: Some(transfer), .. } => {
                     (
                         transfer.from.clone(),
                         transfer.to.clone(),
-                        transfer.amount as f64 / 100_000_000.0, // Convert from e8s to ICP
+                        u128::from(transfer.amount), // e8s, exact base units
                     )
                 },
                 _ => {
@@ -80,7 +80,7 @@ impl ICPDataFetcher {
                     (
                         "unknown_sender".to_string(),
                         "unknown_recipient".to_string(),
-                        0.0,
+                        0,
                     )
                 }
             };
@@ -89,6 +89,7 @@ impl ICPDataFetcher {
                 sender,
                 recipient,
                 amount,
+                asset: SupportedChain::ICP,
                 time_stamp: icp_tx.timestamp / 1_000_000_000, // Convert from nanoseconds to seconds
                 signature: Some(format!("icp_block_{}", icp_tx.block_height)),
             }
@@ -0,0 +1,490 @@
+//! L2 account state
+//! Balances are only moved by sequenced transactions and bridge deposits.
+//! Each account spends with the key the bridge bound to it; a transfer signed by any
+//! other key is refused, whoever submits it first. Bridge withdrawals are signed by the
+//! same key, over the account's withdrawal nonce so a signature pays out once.
+
+use crate::{
+    cryptography,
+    errors::LedgerError,
+    fees::FEE_COLLECTOR,
+    state::{AccountState, AssetBalance},
+    types::{CryptoAlgorithm, PolyTransaction, SupportedChain},
+};
+use candid::{CandidType, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Result of applying a batch of sequenced transactions to the ledger
+#[derive(Debug, Default)]
+pub struct BatchOutcome {
+    pub applied: Vec<PolyTransaction>,
+    pub rejected: Vec<(PolyTransaction, LedgerError)>,
+}
+
+/// Key an account signs its transfers with
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountKey {
+    pub algorithm: CryptoAlgorithm,
+    /// Lowercase hex public key, in the encoding of `algorithm`
+    pub public_key: String,
+}
+
+/// Key binding, signed with the bound key to prove its possession
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountKeyRegistration {
+    pub address: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key, in the encoding of `algorithm`
+    pub public_key: String,
+    /// Hex signature over `AccountKeyRegistration::message()`
+    pub signature: String,
+}
+
+impl AccountKeyRegistration {
+    /// Domain tag of the proof of possession
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/account-key/v1";
+
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(self.address.as_bytes());
+        message.push(0);
+        message.push(self.algorithm as u8);
+        message.extend_from_slice(self.public_key.to_lowercase().as_bytes());
+        message
+    }
+
+    /// Check the proof of possession, returns the key to bind
+    fn verify(&self) -> Result<AccountKey, LedgerError> {
+        let invalid = |reason: String| LedgerError::InvalidKey {
+            address: self.address.clone(),
+            reason,
+        };
+        let public_key = hex::decode(&self.public_key)
+            .map_err(|e| invalid(format!("public key is not hex: {e}")))?;
+        let signature = hex::decode(&self.signature)
+            .map_err(|e| invalid(format!("signature is not hex: {e}")))?;
+        let is_valid = cryptography::verify_signature(
+            self.algorithm,
+            &public_key,
+            &self.message(),
+            &signature,
+        )
+        .map_err(|e| invalid(e.to_string()))?;
+        if !is_valid {
+            return Err(invalid(format!(
+                "{:?} proof of possession failed",
+                self.algorithm
+            )));
+        }
+        Ok(AccountKey {
+            algorithm: self.algorithm,
+            public_key: hex::encode(public_key),
+        })
+    }
+}
+
+/// Bridge withdrawal signed by the key bound to the account
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct WithdrawalAuthorization {
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key, in the encoding of `algorithm`
+    pub public_key: String,
+    /// Withdrawal nonce of the account, see `AccountLedger::next_withdrawal_nonce`
+    pub nonce: u64,
+    /// Hex signature over `WithdrawalAuthorization::message()`
+    pub signature: String,
+}
+
+impl WithdrawalAuthorization {
+    /// Domain tag of the withdrawal signature
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/withdrawal/v1";
+
+    /// Bytes signed to withdraw `amount` base units of `asset` from `address` at `nonce`
+    pub fn message(address: &str, asset: SupportedChain, amount: u128, nonce: u64) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(address.as_bytes());
+        message.push(0);
+        message.push(asset as u8);
+        message.extend_from_slice(&amount.to_le_bytes());
+        message.extend_from_slice(&nonce.to_le_bytes());
+        message
+    }
+}
+
+/// Account ledger holding the spendable L2 balance of every address,
+/// per asset and in base units
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct AccountLedger {
+    balances: HashMap<(String, SupportedChain), u128>,
+    /// Next nonce of every sender with an applied transaction
+    nonces: HashMap<String, u64>,
+    /// Key bound to each account able to send transfers
+    keys: HashMap<String, AccountKey>,
+    /// Next withdrawal nonce of every account that withdrew, absent before withdrawals were signed
+    withdrawal_nonces: Option<HashMap<String, u64>>,
+}
+
+impl AccountLedger {
+    pub fn new() -> Self {
+        Self {
+            balances: HashMap::new(),
+            nonces: HashMap::new(),
+            keys: HashMap::new(),
+            withdrawal_nonces: None,
+        }
+    }
+
+    /// Ledger migrated from a schema without key bindings: every account registers its key again
+    pub fn without_keys(
+        balances: HashMap<(String, SupportedChain), u128>,
+        nonces: HashMap<String, u64>,
+    ) -> Self {
+        Self {
+            balances,
+            nonces,
+            keys: HashMap::new(),
+            withdrawal_nonces: None,
+        }
+    }
+
+    /// Bind the registered key to its account, replacing any earlier key.
+    /// Callers decide who may bind an address; this only checks the key's possession.
+    pub fn bind_key(
+        &mut self,
+        registration: &AccountKeyRegistration,
+    ) -> Result<AccountKey, LedgerError> {
+        let key = registration.verify()?;
+        self.keys.insert(registration.address.clone(), key.clone());
+        Ok(key)
+    }
+
+    pub fn key_of(&self, address: &str) -> Option<&AccountKey> {
+        self.keys.get(address)
+    }
+
+    /// Check that `public_key` is the key bound to `address`
+    pub fn check_key(
+        &self,
+        address: &str,
+        algorithm: CryptoAlgorithm,
+        public_key: &str,
+    ) -> Result<(), LedgerError> {
+        let bound = self
+            .key_of(address)
+            .ok_or_else(|| LedgerError::UnboundKey(address.to_string()))?;
+        if bound.algorithm != algorithm || !bound.public_key.eq_ignore_ascii_case(public_key) {
+            return Err(LedgerError::KeyMismatch(address.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Nonce the next withdrawal from `address` must be signed over
+    pub fn next_withdrawal_nonce(&self, address: &str) -> u64 {
+        self.withdrawal_nonces
+            .as_ref()
+            .and_then(|nonces| nonces.get(address))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Check that the key bound to `address` signed the withdrawal at its next nonce
+    pub fn check_withdrawal(
+        &self,
+        address: &str,
+        asset: SupportedChain,
+        amount: u128,
+        authorization: Option<&WithdrawalAuthorization>,
+    ) -> Result<(), LedgerError> {
+        let unauthorized = |reason: String| LedgerError::UnauthorizedWithdrawal {
+            address: address.to_string(),
+            reason,
+        };
+        let authorization =
+            authorization.ok_or_else(|| unauthorized("withdrawal is not signed".to_string()))?;
+        self.check_key(address, authorization.algorithm, &authorization.public_key)?;
+
+        let expected = self.next_withdrawal_nonce(address);
+        if authorization.nonce != expected {
+            return Err(unauthorized(format!(
+                "nonce {} is not the next withdrawal nonce {expected}",
+                authorization.nonce
+            )));
+        }
+
+        let public_key = hex::decode(&authorization.public_key)
+            .map_err(|e| unauthorized(format!("public key is not hex: {e}")))?;
+        let signature = hex::decode(&authorization.signature)
+            .map_err(|e| unauthorized(format!("signature is not hex: {e}")))?;
+        let message = WithdrawalAuthorization::message(address, asset, amount, expected);
+        let is_valid = cryptography::verify_signature(
+            authorization.algorithm,
+            &public_key,
+            &message,
+            &signature,
+        )
+        .map_err(|e| unauthorized(e.to_string()))?;
+        if !is_valid {
+            return Err(unauthorized(format!(
+                "{:?} signature verification failed",
+                authorization.algorithm
+            )));
+        }
+        Ok(())
+    }
+
+    /// Debit a checked bridge withdrawal and spend the account's withdrawal nonce
+    pub fn withdraw(
+        &mut self,
+        address: &str,
+        asset: SupportedChain,
+        amount: u128,
+    ) -> Result<(), LedgerError> {
+        self.debit(address, asset, amount)?;
+        let nonce = self.next_withdrawal_nonce(address) + 1;
+        self.withdrawal_nonces
+            .get_or_insert_with(HashMap::new)
+            .insert(address.to_string(), nonce);
+        Ok(())
+    }
+
+    /// Every account with a balance or an applied transaction, as committed in the state root
+    pub fn accounts(&self) -> BTreeMap<String, AccountState> {
+        let mut accounts: BTreeMap<String, AccountState> = BTreeMap::new();
+        for ((address, asset), amount) in &self.balances {
+            accounts
+                .entry(address.clone())
+                .or_default()
+                .balances
+                .push(AssetBalance {
+                    asset: *asset,
+                    amount: *amount,
+                });
+        }
+        for (address, nonce) in &self.nonces {
+            accounts.entry(address.clone()).or_default().nonce = *nonce;
+        }
+        for state in accounts.values_mut() {
+            state.balances.sort_by_key(|balance| balance.asset);
+        }
+        accounts
+    }
+
+    pub fn balance_of(&self, address: &str, asset: SupportedChain) -> u128 {
+        self.balances
+            .get(&(address.to_string(), asset))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Credit an account (bridge deposit into the L2)
+    pub fn credit(
+        &mut self,
+        address: &str,
+        asset: SupportedChain,
+        amount: u128,
+    ) -> Result<(), LedgerError> {
+        if amount == 0 {
+            return Err(LedgerError::ZeroAmount);
+        }
+        let balance = self
+            .balance_of(address, asset)
+            .checked_add(amount)
+            .ok_or_else(|| LedgerError::Overflow {
+                address: address.to_string(),
+                asset,
+            })?;
+        self.balances.insert((address.to_string(), asset), balance);
+        Ok(())
+    }
+
+    /// Debit an account, rejecting overdrafts
+    pub fn debit(
+        &mut self,
+        address: &str,
+        asset: SupportedChain,
+        amount: u128,
+    ) -> Result<(), LedgerError> {
+        if amount == 0 {
+            return Err(LedgerError::ZeroAmount);
+        }
+        let balance = self.balance_of(address, asset);
+        let remaining =
+            balance
+                .checked_sub(amount)
+                .ok_or_else(|| LedgerError::InsufficientFunds {
+                    address: address.to_string(),
+                    asset,
+                    balance,
+                    required: amount,
+                })?;
+
+        if remaining > 0 {
+            self.balances
+                .insert((address.to_string(), asset), remaining);
+        } else {
+            self.balances.remove(&(address.to_string(), asset));
+        }
+        Ok(())
+    }
+
+    /// Move funds from sender to recipient and charge the fee, atomically.
+    /// The base fee is burned, the tip is credited to `FEE_COLLECTOR`.
+    pub fn apply_transaction(
+        &mut self,
+        tx: &PolyTransaction,
+        base_fee: u128,
+    ) -> Result<(), LedgerError> {
+        let fee = tx.effective_fee(base_fee).ok_or(LedgerError::FeeTooLow {
+            max_fee: tx.max_fee,
+            base_fee,
+        })?;
+        let tip = fee - base_fee;
+        let total = tx
+            .amount
+            .checked_add(fee)
+            .ok_or_else(|| LedgerError::Overflow {
+                address: tx.sender.clone(),
+                asset: tx.asset,
+            })?;
+
+        // Check the credits first so a failing transfer leaves every account untouched
+        for (address, amount) in [(tx.recipient.as_str(), tx.amount), (FEE_COLLECTOR, tip)] {
+            if address != tx.sender {
+                self.balance_of(address, tx.asset)
+                    .checked_add(amount)
+                    .ok_or_else(|| LedgerError::Overflow {
+                        address: address.to_string(),
+                        asset: tx.asset,
+                    })?;
+            }
+        }
+        self.debit(&tx.sender, tx.asset, total)?;
+        self.credit(&tx.recipient, tx.asset, tx.amount)?;
+        if tip > 0 {
+            self.credit(FEE_COLLECTOR, tx.asset, tip)?;
+        }
+        self.nonces.insert(tx.sender.clone(), tx.nonce + 1);
+        Ok(())
+    }
+
+    /// Apply sequenced transactions in order, keeping only the ones that succeed
+    pub fn apply_batch(
+        &mut self,
+        transactions: Vec<PolyTransaction>,
+        base_fee: impl Fn(SupportedChain) -> u128,
+    ) -> BatchOutcome {
+        let mut outcome = BatchOutcome::default();
+        for tx in transactions {
+            match self.apply_transaction(&tx, base_fee(tx.asset)) {
+                Ok(()) => outcome.applied.push(tx),
+                Err(e) => outcome.rejected.push((tx, e)),
+            }
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccountLedger;
+    use crate::{
+        errors::LedgerError,
+        fees::FEE_COLLECTOR,
+        types::{PolyTransaction, SupportedChain},
+    };
+
+    const BTC: SupportedChain = SupportedChain::Bitcoin;
+
+    fn transfer(sender: &str, recipient: &str, amount: u128) -> PolyTransaction {
+        PolyTransaction::new(sender.to_string(), recipient.to_string(), amount, BTC, 0)
+    }
+
+    #[test]
+    fn ledger_transfer_moves_funds() {
+        let mut ledger = AccountLedger::new();
+        ledger.credit("alice", BTC, 100).unwrap();
+
+        ledger
+            .apply_transaction(&transfer("alice", "bob", 40), 0)
+            .unwrap();
+
+        assert_eq!(ledger.balance_of("alice", BTC), 60);
+        assert_eq!(ledger.balance_of("bob", BTC), 40);
+        let accounts = ledger.accounts();
+        assert_eq!(accounts["alice"].nonce, 1);
+        assert_eq!(accounts["bob"].nonce, 0);
+        assert_eq!(accounts["bob"].balances[0].amount, 40);
+    }
+
+    #[test]
+    fn ledger_rejects_overdraft() {
+        let mut ledger = AccountLedger::new();
+        ledger.credit("alice", BTC, 10).unwrap();
+
+        let result = ledger.apply_transaction(&transfer("alice", "bob", 25), 0);
+
+        assert!(matches!(result, Err(LedgerError::InsufficientFunds { .. })));
+        assert_eq!(ledger.balance_of("alice", BTC), 10);
+        assert_eq!(ledger.balance_of("bob", BTC), 0);
+    }
+
+    #[test]
+    fn ledger_batch_keeps_order() {
+        let mut ledger = AccountLedger::new();
+        ledger.credit("alice", BTC, 50).unwrap();
+
+        // bob can only pay carol once alice's transfer has been applied
+        let batch = vec![
+            transfer("alice", "bob", 50),
+            transfer("bob", "carol", 30),
+            transfer("alice", "carol", 1),
+        ];
+        let outcome = ledger.apply_batch(batch, |_| 0);
+
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.rejected.len(), 1);
+        assert_eq!(ledger.balance_of("bob", BTC), 20);
+        assert_eq!(ledger.balance_of("carol", BTC), 30);
+    }
+
+    #[test]
+    fn ledger_keeps_assets_apart() {
+        let mut ledger = AccountLedger::new();
+        ledger
+            .credit("alice", SupportedChain::Ethereum, 2_000_000_000_000_000_000)
+            .unwrap();
+
+        assert!(ledger
+            .apply_transaction(&transfer("alice", "bob", 1), 0)
+            .is_err());
+        assert_eq!(
+            ledger.balance_of("alice", SupportedChain::Ethereum),
+            2_000_000_000_000_000_000
+        );
+    }
+
+    #[test]
+    fn ledger_charges_base_fee_and_tip() {
+        let mut ledger = AccountLedger::new();
+        ledger.credit("alice", BTC, 100).unwrap();
+
+        // Base 10, tip 5 plafonné à 12: 10 brûlés, 2 au collecteur
+        let tx = transfer("alice", "bob", 40).with_fee(5, 12);
+        ledger.apply_transaction(&tx, 10).unwrap();
+
+        assert_eq!(ledger.balance_of("alice", BTC), 48);
+        assert_eq!(ledger.balance_of("bob", BTC), 40);
+        assert_eq!(ledger.balance_of(FEE_COLLECTOR, BTC), 2);
+
+        // Un plafond sous le prix de base n'est pas exécutable
+        let cheap = transfer("alice", "bob", 1).with_fee(5, 9);
+        assert_eq!(
+            ledger.apply_transaction(&cheap, 10),
+            Err(LedgerError::FeeTooLow {
+                max_fee: 9,
+                base_fee: 10
+            })
+        );
+        assert_eq!(ledger.balance_of("alice", BTC), 48);
+    }
+}
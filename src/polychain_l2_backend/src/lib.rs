@@ -1,19 +1,34 @@
 use bitcoin_vault::BitcoinVault;
 use candid::{CandidType, Deserialize};
-use crypto::calculate_hash;
 use ic_cdk::*;
 mod types;
-use types::{PolyBlock, PolyTransaction, SupportedChain};
+pub use types::CryptoAlgorithm;
+use types::{
+    BlockProposer, OrderingProof, PendingWithdrawal, PolyBlock, PolyTransaction, ProposerVrf,
+    SupportedChain, TimeoutCertificate, TransactionProof, TransactionStatus, WithdrawalSource,
+};
 mod bitcoin_vault;
 mod chain;
+pub mod clock;
+mod commitment;
 mod crypto;
 pub mod cryptography;
 mod errors;
+mod evidence;
+mod fairness;
+mod fees;
+mod ledger;
+mod mempool;
+mod pacemaker;
+mod persistence;
+mod producer;
+pub mod replay;
+mod stable;
+mod state;
 mod validation;
+mod validators;
 use std::cell::RefCell;
-use validation::{
-    AddressValidator, AmountValidator, GeneralValidator, SecurityValidator, ValidationError,
-};
+use validation::{AddressValidator, AmountValidator, GeneralValidator, SecurityValidator};
 
 // Cryptographically secure getrandom implementation for IC
 // Uses Blake3-based CSPRNG with IC-specific entropy sources
@@ -26,7 +41,7 @@ fn custom_getrandom(buf: &mut [u8]) -> Result<(), getrandom::Error> {
     // Use a thread-local counter to ensure uniqueness across calls
     use std::cell::RefCell;
     thread_local! {
-        static COUNTER: RefCell<u64> = RefCell::new(0);
+        static COUNTER: RefCell<u64> = const { RefCell::new(0) };
     }
 
     let call_counter = COUNTER.with(|c| {
@@ -64,43 +79,138 @@ fn custom_getrandom(buf: &mut [u8]) -> Result<(), getrandom::Error> {
 // Register the custom getrandom function
 getrandom::register_custom_getrandom!(custom_getrandom);
 
-use chain::{OrderingStrategy, TransactionSequencer};
+use chain::{
+    AlgoConsensus, ConsensusHeight, OrderingStrategy, SequencedBatch, TransactionSequencer,
+};
+use commitment::{Commitment, CommitmentSubmission};
 use cryptography::ecdsa::Ecdsa;
+use errors::{LedgerError, VaultError};
+use evidence::{EquivocationEvidence, SlashingEvent};
+use fairness::StrategyFairness;
+use fees::{FeeEstimate, FeeMarket};
+use ledger::{AccountKey, AccountKeyRegistration, AccountLedger, WithdrawalAuthorization};
+use mempool::MempoolConfig;
+use producer::{BlockProducer, BlockProductionConfig};
+use replay::{Input, InputLog, LoggedInput};
+use stable::StableLog;
+use state::{AccountProof, StateHistory};
+use validators::{
+    EpochSnapshot, RegistryConfig, Unbonding, Validator, ValidatorRegistration, ValidatorRegistry,
+};
 
 // Simple vault structures for ETH, ICP, SOL
-#[derive(Default)]
+// Reserves are pooled per asset, per-account balances live in the L2 ledger
+// Amounts are u128 base units so wei amounts above u64::MAX (~18.4 ETH) fit
+#[derive(CandidType, Deserialize, Clone)]
 struct SimpleVault {
-    balances: std::collections::HashMap<String, (u64, u64)>, // (native, wrapped)
+    asset: SupportedChain,
+    native_reserve: u128,
+    wrapped_reserve: u128,
+    pending_withdrawals: Vec<PendingWithdrawal>,
+    withdrawal_count: u64,
 }
 
 impl SimpleVault {
-    fn new() -> Self {
+    fn new(asset: SupportedChain) -> Self {
         Self {
-            balances: std::collections::HashMap::new(),
+            asset,
+            native_reserve: 0,
+            wrapped_reserve: 0,
+            pending_withdrawals: Vec::new(),
+            withdrawal_count: 0,
         }
     }
 
-    fn deposit(&mut self, address: &str, amount: u64, is_native: bool) {
-        let (native, wrapped) = self.balances.entry(address.to_string()).or_insert((0, 0));
-        if is_native {
-            *native += amount;
+    fn deposit(&mut self, amount: u128, is_native: bool) -> Result<(), VaultError> {
+        let (native, wrapped) = self.reserves();
+        let updated = if is_native {
+            (native.checked_add(amount), Some(wrapped))
         } else {
-            *wrapped += amount;
+            (Some(native), wrapped.checked_add(amount))
+        };
+
+        match updated {
+            (Some(native), Some(wrapped)) if native.checked_add(wrapped).is_some() => {
+                self.native_reserve = native;
+                self.wrapped_reserve = wrapped;
+                Ok(())
+            }
+            _ => Err(VaultError::Overflow(self.asset)),
         }
     }
 
-    fn get_balance(&self, address: &str) -> (u64, u64) {
-        self.balances.get(address).copied().unwrap_or((0, 0))
+    /// Débiter le natif en priorité, le wrapped pour le reste.
+    /// Le solde L2 de `address` doit avoir été vérifié par l'appelant.
+    fn withdraw(
+        &mut self,
+        address: &str,
+        amount: u128,
+        crypto_algo: &str,
+    ) -> Result<PendingWithdrawal, VaultError> {
+        if amount == 0 {
+            return Err(VaultError::ZeroAmount);
+        }
+
+        let (native, wrapped) = self.reserves();
+        let source = WithdrawalSource::Native;
+        let (from_native, from_wrapped) =
+            source
+                .split(amount, native, wrapped)
+                .ok_or(VaultError::InsufficientReserves {
+                    asset: self.asset,
+                    native,
+                    wrapped,
+                    requested: amount,
+                })?;
+        self.native_reserve -= from_native;
+        self.wrapped_reserve -= from_wrapped;
+
+        let tx_data = format!(
+            "{}:{}:{}:{}",
+            self.asset.symbol(),
+            address,
+            amount,
+            self.withdrawal_count
+        );
+        self.withdrawal_count += 1;
+
+        let withdrawal = PendingWithdrawal {
+            tx_id: hex::encode(blake3::hash(tx_data.as_bytes()).as_bytes()),
+            address: address.to_string(),
+            asset: self.asset,
+            amount,
+            source,
+            crypto_algo: crypto_algo.to_string(),
+            native_amount: Some(from_native),
+            wrapped_amount: Some(from_wrapped),
+        };
+        self.pending_withdrawals.push(withdrawal.clone());
+        Ok(withdrawal)
+    }
+
+    /// Retirer un retrait payé sur la chaîne d'origine de la liste d'attente
+    fn complete_withdrawal(&mut self, tx_id: &str) -> Result<PendingWithdrawal, VaultError> {
+        let index = self
+            .pending_withdrawals
+            .iter()
+            .position(|withdrawal| withdrawal.tx_id == tx_id)
+            .ok_or_else(|| VaultError::UnknownWithdrawal(tx_id.to_string()))?;
+        Ok(self.pending_withdrawals.remove(index))
+    }
+
+    /// Réserves (natif, wrapped) détenues pour tous les comptes
+    fn reserves(&self) -> (u128, u128) {
+        (self.native_reserve, self.wrapped_reserve)
     }
 }
 
 // State persistant pour le sequencer, consensus et blockchain
 thread_local! {
     static BITCOIN_VAULT: RefCell<BitcoinVault> = RefCell::new(BitcoinVault::new());
-    static ETHEREUM_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new());
-    static ICP_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new());
-    static SOLANA_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new());
-    static SEQUENCER_STATE: RefCell<Option<TransactionSequencer<Ecdsa>>> = RefCell::new(None);
+    static ETHEREUM_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new(SupportedChain::Ethereum));
+    static ICP_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new(SupportedChain::Icp));
+    static SOLANA_VAULT: RefCell<SimpleVault> = RefCell::new(SimpleVault::new(SupportedChain::Solana));
+    static SEQUENCER_STATE: RefCell<Option<TransactionSequencer<Ecdsa>>> = const { RefCell::new(None) };
     static SEQUENCER_METRICS: RefCell<SequencerMetrics> = RefCell::new(SequencerMetrics {
         total_transactions_sequenced: 0,
         current_pending_count: 0,
@@ -109,9 +219,28 @@ thread_local! {
         average_sequencing_time_ms: 0.0,
         fairness_score: 1.0,
         ordering_strategy: "FairOrdering".to_string(),
+        fairness_by_strategy: Vec::new(),
     });
-    static BLOCKCHAIN_STATE: RefCell<Vec<PolyBlock>> = RefCell::new(Vec::new());
-    static TRANSACTION_POOL: RefCell<Vec<PolyTransaction>> = RefCell::new(Vec::new());
+    // Blocs, transactions confirmées et journal vivent en mémoire stable: un upgrade ne les copie pas
+    static BLOCKCHAIN_STATE: StableLog<PolyBlock> = const { StableLog::new(stable::BLOCK_INDEX, stable::BLOCK_DATA) };
+    static L2_LEDGER: RefCell<AccountLedger> = RefCell::new(AccountLedger::new());
+    static STATE_HISTORY: RefCell<StateHistory> = RefCell::new(StateHistory::default());
+    static TRANSACTION_POOL: StableLog<PolyTransaction> = const { StableLog::new(stable::TRANSACTION_INDEX, stable::TRANSACTION_DATA) };
+    // Statut de chaque transaction admise, par tx_id
+    static TX_INDEX: RefCell<std::collections::HashMap<String, TransactionStatus>> =
+        RefCell::new(std::collections::HashMap::new());
+    static BLOCK_PRODUCER: RefCell<BlockProducer> = RefCell::new(BlockProducer::default());
+    // Timer de production, à réarmer après chaque upgrade
+    static PRODUCER_TIMER: RefCell<Option<ic_cdk_timers::TimerId>> = const { RefCell::new(None) };
+    // Journal ordonné des appels qui modifient l'état, pour le rejeu
+    static INPUT_LOG: InputLog = const { InputLog::new() };
+    // Hauteur simulée du pacemaker et son timer de tour, non persistés: les clés sont jetables
+    static POS_SIMULATION: RefCell<Option<PosSimulation>> = const { RefCell::new(None) };
+    static ROUND_TIMER: RefCell<Option<ic_cdk_timers::TimerId>> = const { RefCell::new(None) };
+    // Validateurs, mises et ensemble actif de l'époque en cours
+    static VALIDATOR_REGISTRY: RefCell<ValidatorRegistry> = RefCell::new(ValidatorRegistry::new(RegistryConfig::default()));
+    // Hauteur à partir de laquelle chaque bloc porte la signature de la clé publiée
+    static SIGNED_FROM_HEIGHT: RefCell<u64> = const { RefCell::new(0) };
 }
 
 #[init]
@@ -119,13 +248,170 @@ fn init() {
     BITCOIN_VAULT.with(|vault| {
         *vault.borrow_mut() = BitcoinVault::new();
     });
+    start_block_production();
+}
+
+#[pre_upgrade]
+fn pre_upgrade() {
+    if let Err(e) = persistence::save(snapshot_state()) {
+        ic_cdk::trap(&e);
+    }
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+    // Un échec de restauration annule l'upgrade plutôt que d'effacer l'état
+    match persistence::restore() {
+        Ok(Some((state, logs))) => restore_state(state, logs),
+        Ok(None) => {}
+        Err(e) => ic_cdk::trap(&e),
+    }
+    // Les timers ne survivent pas à un upgrade
+    start_block_production();
+    provision_restored_sequencer_key();
+}
+
+/// Un séquenceur sauvé sans clé par un schéma antérieur en reçoit une au premier tour de
+/// timer: raw_rand n'est pas appelable depuis post_upgrade. Le timer n'est pas un
+/// contrôleur: il garde la stratégie en place et ne fait que tirer la clé.
+fn provision_restored_sequencer_key() {
+    let strategy = SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .filter(|sequencer| sequencer.public_key().is_none())
+            .map(|sequencer| ordering_strategy_name(sequencer.ordering_strategy()))
+    });
+    let Some(strategy) = strategy else {
+        return;
+    };
+    ic_cdk_timers::set_timer(std::time::Duration::ZERO, move || {
+        ic_cdk::spawn(async move {
+            if let Err(e) = setup_sequencer(strategy.to_string()).await {
+                ic_cdk::println!("Failed to provision the sequencer key: {e}");
+            }
+        })
+    });
+}
+
+/// Photographier l'état du tas pour la mémoire stable, les logs y sont déjà
+fn snapshot_state() -> persistence::CanisterState {
+    persistence::CanisterState {
+        bitcoin_vault: BITCOIN_VAULT.with(|vault| vault.borrow().clone()),
+        ethereum_vault: ETHEREUM_VAULT.with(|vault| vault.borrow().clone()),
+        icp_vault: ICP_VAULT.with(|vault| vault.borrow().clone()),
+        solana_vault: SOLANA_VAULT.with(|vault| vault.borrow().clone()),
+        sequencer: SEQUENCER_STATE.with(|state| {
+            state
+                .borrow()
+                .as_ref()
+                .map(|sequencer| sequencer.snapshot())
+        }),
+        sequencer_metrics: SEQUENCER_METRICS.with(|metrics| metrics.borrow().clone()),
+        ledger: L2_LEDGER.with(|ledger| ledger.borrow().clone()),
+        state_history: STATE_HISTORY.with(|history| history.borrow().clone()),
+        transaction_index: TX_INDEX.with(|index| index.borrow().clone()),
+        block_producer: BLOCK_PRODUCER.with(|producer| producer.borrow().clone()),
+        compressed_batches: COMPRESSED_BATCHES.with(|batches| batches.borrow().clone()),
+        compression_metrics: COMPRESSION_METRICS.with(|metrics| metrics.borrow().clone()),
+        validator_registry: VALIDATOR_REGISTRY.with(|registry| registry.borrow().clone()),
+        signed_from_height: Some(signed_from_height()),
+    }
+}
+
+/// Réinstaller un état restauré depuis la mémoire stable, et verser dans les logs stables
+/// les entrées qu'un ancien schéma gardait dans l'état
+fn restore_state(state: persistence::CanisterState, logs: persistence::Logs) {
+    BITCOIN_VAULT.with(|vault| *vault.borrow_mut() = state.bitcoin_vault);
+    ETHEREUM_VAULT.with(|vault| *vault.borrow_mut() = state.ethereum_vault);
+    ICP_VAULT.with(|vault| *vault.borrow_mut() = state.icp_vault);
+    SOLANA_VAULT.with(|vault| *vault.borrow_mut() = state.solana_vault);
+    SEQUENCER_STATE.with(|sequencer| {
+        *sequencer.borrow_mut() = state
+            .sequencer
+            .map(|snapshot| TransactionSequencer::from_snapshot(Ecdsa, snapshot));
+    });
+    SEQUENCER_METRICS.with(|metrics| *metrics.borrow_mut() = state.sequencer_metrics);
+    L2_LEDGER.with(|ledger| *ledger.borrow_mut() = state.ledger);
+    STATE_HISTORY.with(|history| *history.borrow_mut() = state.state_history);
+    TX_INDEX.with(|index| *index.borrow_mut() = state.transaction_index);
+    BLOCK_PRODUCER.with(|producer| *producer.borrow_mut() = state.block_producer);
+    COMPRESSED_BATCHES.with(|batches| *batches.borrow_mut() = state.compressed_batches);
+    COMPRESSION_METRICS.with(|metrics| *metrics.borrow_mut() = state.compression_metrics);
+    VALIDATOR_REGISTRY.with(|registry| *registry.borrow_mut() = state.validator_registry);
+
+    let stored = BLOCKCHAIN_STATE
+        .with(|chain| {
+            logs.blockchain
+                .iter()
+                .try_for_each(|block| chain.push(block).map(drop))
+        })
+        .and_then(|()| {
+            TRANSACTION_POOL.with(|pool| {
+                logs.transaction_pool
+                    .iter()
+                    .try_for_each(|tx| pool.push(tx).map(drop))
+            })
+        })
+        .and_then(|()| {
+            INPUT_LOG.with(|log| {
+                logs.input_log
+                    .iter()
+                    .try_for_each(|entry| log.push(entry).map(drop))
+            })
+        });
+    if let Err(e) = stored {
+        panic!("Failed to move restored logs to stable memory: {e}");
+    }
+    // Un état V3 sauvé sans la hauteur ne sait pas quels blocs précèdent la clé publiée:
+    // toute la chaîne restaurée est tenue pour antérieure
+    let signed_from = state.signed_from_height.unwrap_or_else(chain_height);
+    SIGNED_FROM_HEIGHT.with(|height| *height.borrow_mut() = signed_from);
+}
+
+/// Hauteur à partir de laquelle les blocs doivent être signés par la clé publiée
+fn signed_from_height() -> u64 {
+    SIGNED_FROM_HEIGHT.with(|height| *height.borrow())
+}
+
+/// Refuser les appels d'administration qui ne viennent pas d'un contrôleur du canister.
+/// Vérifié avant `logged`: un appel refusé ne modifie rien et n'est pas journalisé.
+fn require_controller() -> Result<(), String> {
+    #[cfg(target_arch = "wasm32")]
+    if !ic_cdk::api::is_controller(&ic_cdk::caller()) {
+        return Err("Caller is not a controller of this canister".to_string());
+    }
+    Ok(())
+}
+
+/// Exécuter un appel qui modifie l'état et le journaliser avec son heure, son erreur
+/// et l'aléa tiré par le séquenceur, pour qu'un rejeu le refasse à l'identique
+fn logged<T>(input: Input, call: impl FnOnce() -> Result<T, String>) -> Result<T, String> {
+    let time = clock::now();
+    replay::open_trace();
+    let result = call();
+    let sealing = replay::close_trace();
+    let error = result.as_ref().err().cloned();
+    INPUT_LOG.with(|log| log.append(time, input, error, sealing));
+    result
+}
+
+/// Entrées journalisées à partir de `start`, dans l'ordre où elles ont été traitées
+#[query]
+fn get_input_log(start: u64, limit: Option<u64>) -> Vec<LoggedInput> {
+    let limit = limit.unwrap_or(100).min(1000) as usize;
+    INPUT_LOG.with(|log| log.iter_from(start).take(limit).collect())
 }
 // ========== FONCTIONS ORIGINALES (gardées) ==========
 #[update]
 async fn create_transaction(
     sender: String,
     recipient: String,
-    amount: f64,
+    amount: u128,
+    asset: SupportedChain,
+    nonce: u64,
+    fee: u128,
+    max_fee: u128,
 ) -> Result<String, String> {
     // Comprehensive input validation
     let validated_sender = GeneralValidator::validate_string(&sender, "sender", Some(100))
@@ -141,9 +427,10 @@ async fn create_transaction(
     AddressValidator::validate_address(&validated_recipient, "generic")
         .map_err(|e| format!("Recipient address invalid: {}", e))?;
 
-    // Convert and validate amount (assuming 8 decimal places like Bitcoin)
-    let _validated_amount = AmountValidator::validate_and_convert_float_amount(amount, 8)
-        .map_err(|e| format!("Amount validation failed: {}", e))?;
+    // Amounts are exact base units of the asset (satoshi, wei, e8s, lamports)
+    if amount == 0 {
+        return Err("Amount validation failed: Amount must be positive".to_string());
+    }
 
     // Security check for malicious patterns
     SecurityValidator::detect_malicious_input(&validated_sender)
@@ -152,23 +439,24 @@ async fn create_transaction(
     SecurityValidator::detect_malicious_input(&validated_recipient)
         .map_err(|e| format!("Security check failed for recipient: {}", e))?;
 
-    let mut tx = PolyTransaction::new(validated_sender, validated_recipient, amount);
+    let tx = PolyTransaction::new(validated_sender, validated_recipient, amount, asset, nonce)
+        .with_fee(fee, max_fee);
 
     if !tx.is_valid() {
         return Err("Invalid transaction parameters".to_string());
     }
 
-    let tx_hash = calculate_hash(&format!("{tx:?}"));
-    tx.sign(tx_hash);
-
-    Ok(format!("Transaction created: {tx:?}"))
+    // Non signée: le client signe ce payload avec sa clé puis la soumet au séquenceur
+    Ok(format!(
+        "Transaction created: {tx:?} | Signing payload: {}",
+        hex::encode(tx.signing_payload())
+    ))
 }
 
+/// Construire un bloc signé par le séquenceur au-dessus de la tête actuelle, sans
+/// l'ajouter à la chaîne
 #[update]
-async fn create_block(
-    transactions: Vec<PolyTransaction>,
-    previous_hash: String,
-) -> Result<String, String> {
+async fn create_block(transactions: Vec<PolyTransaction>) -> Result<String, String> {
     if transactions.is_empty() {
         return Err("Block must contain at least one transaction".to_string());
     }
@@ -179,25 +467,460 @@ async fn create_block(
         }
     }
 
-    let block = PolyBlock::new(transactions, previous_hash);
+    // Le parent est toujours la tête de chaîne, jamais un hash fourni par l'appelant
+    let block = PolyBlock::new(transactions, chain_tip_hash(), current_state_root());
+    let block = SEQUENCER_STATE.with(|state| match state.borrow().as_ref() {
+        Some(sequencer) => sequencer.sign_block(block).map_err(|e| e.to_string()),
+        None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
+    })?;
+    chain::verify_block(
+        chain_height(),
+        &block,
+        last_block().as_ref(),
+        sequencer_key().as_deref(),
+        signed_from_height(),
+    )
+    .map_err(|e| format!("Invalid block: {e}"))?;
     Ok(format!("Block created with hash: {}", block.hash))
 }
 
+/// Hash du dernier bloc, ou le parent de la genèse si la chaîne est vide
+fn chain_tip_hash() -> String {
+    last_block().map_or(chain::GENESIS_PREVIOUS_HASH.to_string(), |block| block.hash)
+}
+
+/// Hash de la tête de chaîne en octets: la graine VRF d'une hauteur est son parent
+fn chain_tip_seed() -> Result<[u8; 32], String> {
+    let tip = chain_tip_hash();
+    crypto::decode_hash(&tip).ok_or_else(|| format!("Chain tip hash {tip} is malformed"))
+}
+
+/// Clé publiée du séquenceur, contre laquelle les blocs sont vérifiés
+fn sequencer_key() -> Option<String> {
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .and_then(|sequencer| sequencer.public_key().map(str::to_string))
+    })
+}
+
+fn last_block() -> Option<PolyBlock> {
+    BLOCKCHAIN_STATE.with(|chain| chain.last())
+}
+
+fn chain_height() -> u64 {
+    BLOCKCHAIN_STATE.with(|chain| chain.len())
+}
+
+/// Racine d'état des comptes L2 tels qu'ils sont maintenant
+fn current_state_root() -> [u8; 32] {
+    L2_LEDGER.with(|ledger| state::state_root(&ledger.borrow().accounts()))
+}
+
+/// Signataire d'un bloc scellé
+enum BlockSigner<'a> {
+    /// La clé du séquenceur qui l'a produit
+    Sequencer(&'a mut TransactionSequencer<Ecdsa>),
+    /// La clé et la signature journalisées par le run rejoué
+    Recorded(BlockProposer),
+}
+
+/// Sceller les transactions appliquées dans un nouveau bloc qui engage l'état post-bloc
+/// `ledger`, signé par le séquenceur qui l'a produit et vérifié contre sa clé publiée.
+/// `ledger` ne remplace le ledger L2 qu'une fois le bloc signé et vérifié: un échec avant
+/// laisse l'état intact, un échec après annule tout le message.
+fn commit_block(
+    ledger: AccountLedger,
+    transactions: Vec<PolyTransaction>,
+    ordering: Option<(OrderingStrategy, Option<OrderingProof>)>,
+    signer: Option<BlockSigner>,
+    sequencer_key: Option<&str>,
+) -> Result<PolyBlock, String> {
+    let accounts = ledger.accounts();
+    let height = chain_height();
+    let mut block = PolyBlock::new(transactions, chain_tip_hash(), state::state_root(&accounts));
+    if let Some((strategy, ordering_proof)) = ordering {
+        block = block.with_ordering(strategy, ordering_proof);
+    }
+    match signer {
+        Some(BlockSigner::Sequencer(sequencer)) => {
+            block = sequencer.sign_block(block).map_err(|e| e.to_string())?;
+        }
+        // Le hash ne dépend que de la clé: la signature d'origine ne vaut que pour un bloc identique
+        Some(BlockSigner::Recorded(proposer)) => {
+            block = block.with_proposer(proposer.algorithm, proposer.public_key);
+            if let Some(replayed) = block.proposer.as_mut() {
+                replayed.signature = proposer.signature;
+            }
+        }
+        None => {}
+    }
+    check_block(&block, sequencer_key)?;
+
+    L2_LEDGER.with(|live| *live.borrow_mut() = ledger);
+    replay::note_block(&block);
+    // Vérifié ci-dessus; seule l'écriture en mémoire stable peut encore échouer
+    if let Err(e) = BLOCKCHAIN_STATE.with(|chain| chain.push(&block)) {
+        ic_cdk::trap(&e.to_string());
+    }
+    STATE_HISTORY.with(|history| history.borrow_mut().commit(height, &accounts));
+    // Frontière d'époque: rembourser les unbondings échus et figer le nouvel ensemble actif
+    VALIDATOR_REGISTRY.with(|registry| {
+        L2_LEDGER.with(|ledger| {
+            registry
+                .borrow_mut()
+                .on_block_committed(height, &mut ledger.borrow_mut());
+        })
+    });
+
+    for tx in &block.transactions {
+        set_transaction_status(
+            tx.tx_id(),
+            TransactionStatus::Sequenced {
+                block_hash: block.hash.clone(),
+                block_height: height,
+            },
+        );
+    }
+    Ok(block)
+}
+
+/// Appliquer un batch séquencé au ledger et sceller les transferts réussis dans un bloc,
+/// même vide si `seal_empty`. Retourne (appliquées, rejetées).
+fn seal_batch(
+    batch: SequencedBatch,
+    sequencer: &mut TransactionSequencer<Ecdsa>,
+    seal_empty: bool,
+    recorded: Option<&replay::SealingTrace>,
+) -> Result<(u64, u64), String> {
+    let fee_market = sequencer.fee_market().clone();
+    // Appliquer les transferts et les frais à une copie du ledger L2, les découverts sont
+    // rejetés; commit_block ne l'installe qu'avec un bloc signé et vérifié
+    let mut ledger = L2_LEDGER.with(|ledger| ledger.borrow().clone());
+    let outcome = ledger.apply_batch(batch.transactions, |asset| fee_market.base_fee(asset));
+    let applied_count = outcome.applied.len() as u64;
+    let rejected_count = outcome.rejected.len() as u64;
+
+    for (tx, error) in &outcome.rejected {
+        set_transaction_status(
+            tx.tx_id(),
+            TransactionStatus::Failed {
+                reason: error.to_string(),
+            },
+        );
+    }
+
+    // Créer un vrai bloc avec les transactions séquencées
+    if !outcome.applied.is_empty() || seal_empty {
+        // Ajouter le bloc à la blockchain
+        // Retirer les rejetées garde l'ordre VRF vérifiable
+        let sequencer_key = sequencer.public_key().map(str::to_string);
+        let signer = match recorded {
+            Some(trace) => trace.proposer.clone().map(BlockSigner::Recorded),
+            None => Some(BlockSigner::Sequencer(sequencer)),
+        };
+        commit_block(
+            ledger,
+            outcome.applied.clone(),
+            Some((batch.strategy, batch.ordering_proof)),
+            signer,
+            sequencer_key.as_deref(),
+        )?;
+
+        // Ajouter les transactions à la pool des transactions confirmées; le bloc est déjà
+        // ajouté, un échec ici doit annuler le message
+        let stored = TRANSACTION_POOL.with(|pool| {
+            outcome
+                .applied
+                .iter()
+                .try_for_each(|tx| pool.push(tx).map(drop))
+        });
+        if let Err(e) = stored {
+            ic_cdk::trap(&e.to_string());
+        }
+    }
+
+    Ok((applied_count, rejected_count))
+}
+
+fn set_transaction_status(tx_id: String, status: TransactionStatus) {
+    TX_INDEX.with(|index| index.borrow_mut().insert(tx_id, status));
+}
+
+/// Marquer Dropped les transactions remplacées, évincées ou expirées du mempool
+fn record_dropped(sequencer: &mut TransactionSequencer<Ecdsa>) {
+    for tx_id in sequencer.take_dropped() {
+        set_transaction_status(tx_id, TransactionStatus::Dropped);
+    }
+}
+
+/// Statut d'une transaction par son id déterministe, sans parcourir la chaîne
 #[query]
-fn get_balance(_address: String) -> f64 {
-    1000.0
+fn get_transaction_status(tx_id: String) -> Result<TransactionStatus, String> {
+    TX_INDEX
+        .with(|index| index.borrow().get(&tx_id).cloned())
+        .ok_or_else(|| format!("Unknown transaction {tx_id}"))
 }
 
-// ========== NOUVELLES FONCTIONS LAYER 2 BITCOIN ==========
+/// Condition de tout ajout à la chaîne: le bloc doit être valide et lié à la tête
+fn check_block(block: &PolyBlock, sequencer_key: Option<&str>) -> Result<(), String> {
+    BLOCKCHAIN_STATE.with(|chain| {
+        chain::verify_block(
+            chain.len(),
+            block,
+            chain.last().as_ref(),
+            sequencer_key,
+            signed_from_height(),
+        )
+        .map_err(|e| format!("Block rejected: {e}"))
+    })
+}
 
-#[derive(Debug, Clone)]
-pub enum CryptoAlgorithm {
-    Ecdsa,
-    Schnorr,
-    Falcon512,
-    Mldsa44,
+/// Solde L2 dépensable après le dernier bloc, en unités de base de l'actif
+#[query]
+fn get_balance(address: String, asset: SupportedChain) -> u128 {
+    L2_LEDGER.with(|ledger| ledger.borrow().balance_of(&address, asset))
+}
+
+/// Créditer le compte L2 d'un dépôt bridge (unités de base de l'actif)
+fn credit_l2_account(address: &str, asset: SupportedChain, amount: u128) -> Result<(), String> {
+    L2_LEDGER
+        .with(|ledger| ledger.borrow_mut().credit(address, asset, amount))
+        .map_err(|e| format!("L2 credit failed: {}", e))
+}
+
+/// Retrait bridge: le solde L2 du compte ouvre droit au retrait, payé par les réserves
+/// communes du vault, quelle que soit l'adresse qui a déposé les fonds.
+/// Seule la clé liée au compte l'autorise, signée à son prochain nonce de retrait.
+fn settle_withdrawal(
+    address: &str,
+    asset: SupportedChain,
+    amount: u128,
+    authorization: Option<&WithdrawalAuthorization>,
+    withdraw_from_vault: impl FnOnce() -> Result<PendingWithdrawal, VaultError>,
+) -> Result<PendingWithdrawal, String> {
+    L2_LEDGER
+        .with(|ledger| {
+            ledger
+                .borrow()
+                .check_withdrawal(address, asset, amount, authorization)
+        })
+        .map_err(|e| format!("Withdrawal failed: {}", e))?;
+
+    let balance = L2_LEDGER.with(|ledger| ledger.borrow().balance_of(address, asset));
+    if balance < amount {
+        let error = LedgerError::InsufficientFunds {
+            address: address.to_string(),
+            asset,
+            balance,
+            required: amount,
+        };
+        return Err(format!("Withdrawal failed: {}", error));
+    }
+
+    let withdrawal = withdraw_from_vault().map_err(|e| format!("Withdrawal failed: {}", e))?;
+
+    // Ne peut plus échouer: le solde a été vérifié ci-dessus
+    L2_LEDGER
+        .with(|ledger| ledger.borrow_mut().withdraw(address, asset, amount))
+        .map_err(|e| format!("L2 debit failed: {}", e))?;
+
+    Ok(withdrawal)
+}
+
+/// Lier à un compte L2 la clé qui signera ses transferts, signée par cette clé.
+/// Réservé aux contrôleurs: le bridge atteste que l'adresse appartient au déposant.
+#[update]
+fn register_account_key(registration: AccountKeyRegistration) -> Result<AccountKey, String> {
+    require_controller()?;
+    logged(
+        Input::RegisterAccountKey {
+            registration: registration.clone(),
+        },
+        || {
+            L2_LEDGER
+                .with(|ledger| ledger.borrow_mut().bind_key(&registration))
+                .map_err(|e| format!("Key registration failed: {}", e))
+        },
+    )
+}
+
+/// Clé liée à un compte L2, absente tant que le bridge n'en a pas enregistré
+#[query]
+fn get_account_key(address: String) -> Option<AccountKey> {
+    L2_LEDGER.with(|ledger| ledger.borrow().key_of(&address).cloned())
+}
+
+/// Enregistrer ou renouveler la clé d'un validateur, signée par cette clé et par la clé
+/// du compte; un renouvellement est aussi signé par la clé remplacée
+#[update]
+fn register_validator(registration: ValidatorRegistration) -> Result<Validator, String> {
+    logged(
+        Input::RegisterValidator {
+            registration: registration.clone(),
+        },
+        || {
+            VALIDATOR_REGISTRY
+                .with(|registry| {
+                    L2_LEDGER.with(|ledger| {
+                        registry
+                            .borrow_mut()
+                            .register(&ledger.borrow(), registration)
+                            .cloned()
+                    })
+                })
+                .map_err(|e| format!("Registration failed: {}", e))
+        },
+    )
+}
+
+/// Immobiliser une part du solde L2 en mise, active à la prochaine époque.
+/// `signature`: clé du compte sur `ValidatorAction::Bond`, au nonce du validateur
+#[update]
+fn bond_stake(address: String, amount: u128, signature: String) -> Result<Validator, String> {
+    logged(
+        Input::BondStake {
+            address: address.clone(),
+            amount,
+            signature: Some(signature.clone()),
+        },
+        || {
+            VALIDATOR_REGISTRY
+                .with(|registry| {
+                    L2_LEDGER.with(|ledger| {
+                        registry
+                            .borrow_mut()
+                            .bond(&mut ledger.borrow_mut(), &address, amount, Some(&signature))
+                            .cloned()
+                    })
+                })
+                .map_err(|e| format!("Bond failed: {}", e))
+        },
+    )
 }
 
+/// Retirer une mise: remboursée sur le solde L2 à la fin de la période d'unbonding.
+/// `signature`: clé du compte sur `ValidatorAction::Unbond`, au nonce du validateur
+#[update]
+fn unbond_stake(address: String, amount: u128, signature: String) -> Result<Unbonding, String> {
+    logged(
+        Input::UnbondStake {
+            address: address.clone(),
+            amount,
+            signature: Some(signature.clone()),
+        },
+        || {
+            VALIDATOR_REGISTRY
+                .with(|registry| {
+                    L2_LEDGER.with(|ledger| {
+                        registry.borrow_mut().unbond(
+                            &ledger.borrow(),
+                            &address,
+                            amount,
+                            Some(&signature),
+                        )
+                    })
+                })
+                .map_err(|e| format!("Unbond failed: {}", e))
+        },
+    )
+}
+
+#[query]
+fn get_validator(address: String) -> Option<Validator> {
+    VALIDATOR_REGISTRY.with(|registry| registry.borrow().validator(&address).cloned())
+}
+
+/// Tous les validateurs enregistrés, actifs ou non
+#[query]
+fn get_validators() -> Vec<Validator> {
+    VALIDATOR_REGISTRY.with(|registry| registry.borrow().validators().cloned().collect())
+}
+
+/// Ensemble actif et mises figés pour l'époque en cours
+#[query]
+fn get_current_epoch() -> EpochSnapshot {
+    VALIDATOR_REGISTRY.with(|registry| registry.borrow().current_epoch().clone())
+}
+
+#[query]
+fn get_unbondings(address: String) -> Vec<Unbonding> {
+    VALIDATOR_REGISTRY.with(|registry| registry.borrow().unbondings(&address))
+}
+
+/// Soumettre la preuve d'une double signature: n'importe qui peut la fournir
+#[update]
+fn submit_equivocation_evidence(evidence: EquivocationEvidence) -> Result<SlashingEvent, String> {
+    logged(
+        Input::SubmitEvidence {
+            evidence: evidence.clone(),
+        },
+        || {
+            VALIDATOR_REGISTRY
+                .with(|registry| registry.borrow_mut().slash(&evidence, clock::now()))
+                .map_err(|e| format!("Evidence rejected: {}", e))
+        },
+    )
+}
+
+/// Sanctions appliquées, pour un validateur ou pour tous
+#[query]
+fn get_slashing_events(validator: Option<String>) -> Vec<SlashingEvent> {
+    VALIDATOR_REGISTRY.with(|registry| {
+        registry
+            .borrow()
+            .slashing_events()
+            .iter()
+            .filter(|event| validator.as_ref().is_none_or(|v| *v == event.validator))
+            .cloned()
+            .collect()
+    })
+}
+
+/// Retraits en attente de paiement sur la chaîne d'origine, tous actifs confondus
+#[query]
+fn get_pending_withdrawals(address: String) -> Vec<PendingWithdrawal> {
+    let mut pending: Vec<PendingWithdrawal> =
+        BITCOIN_VAULT.with(|vault| vault.borrow().pending_withdrawals.clone());
+    for vault in [&ETHEREUM_VAULT, &ICP_VAULT, &SOLANA_VAULT] {
+        vault.with(|vault| pending.extend(vault.borrow().pending_withdrawals.iter().cloned()));
+    }
+    pending.retain(|withdrawal| withdrawal.address == address);
+    pending
+}
+
+/// Marquer payé sur la chaîne d'origine un retrait en attente, réservé aux contrôleurs
+#[update]
+fn confirm_withdrawal(asset: SupportedChain, tx_id: String) -> Result<PendingWithdrawal, String> {
+    require_controller()?;
+    logged(
+        Input::ConfirmWithdrawal {
+            asset,
+            tx_id: tx_id.clone(),
+        },
+        || {
+            let completed = match asset {
+                SupportedChain::Bitcoin => {
+                    BITCOIN_VAULT.with(|vault| vault.borrow_mut().complete_withdrawal(&tx_id))
+                }
+                SupportedChain::Ethereum => {
+                    ETHEREUM_VAULT.with(|vault| vault.borrow_mut().complete_withdrawal(&tx_id))
+                }
+                SupportedChain::Icp => {
+                    ICP_VAULT.with(|vault| vault.borrow_mut().complete_withdrawal(&tx_id))
+                }
+                SupportedChain::Solana => {
+                    SOLANA_VAULT.with(|vault| vault.borrow_mut().complete_withdrawal(&tx_id))
+                }
+            };
+            completed.map_err(|e| format!("Withdrawal confirmation failed: {}", e))
+        },
+    )
+}
+
+// ========== NOUVELLES FONCTIONS LAYER 2 BITCOIN ==========
+
 #[derive(Debug, Clone, Copy)]
 pub enum RiskLevel {
     Low,
@@ -262,31 +985,53 @@ fn algorithm_to_string(algo: &CryptoAlgorithm) -> String {
     }
 }
 
+/// Créditer un dépôt observé sur la chaîne d'origine, réservé aux contrôleurs (le bridge)
 #[update]
 async fn deposit_bitcoin(address: String, amount_satoshi: u64) -> Result<String, String> {
-    // Validate Bitcoin address
-    let validated_address = GeneralValidator::validate_string(&address, "address", Some(100))
-        .map_err(|e| format!("Address validation failed: {}", e))?;
-
-    AddressValidator::validate_address(&validated_address, "bitcoin")
-        .map_err(|e| format!("Bitcoin address invalid: {}", e))?;
-
-    // Validate amount
-    AmountValidator::validate_amount(amount_satoshi, None)
-        .map_err(|e| format!("Amount validation failed: {}", e))?;
-
-    // Security check for malicious patterns
-    SecurityValidator::detect_malicious_input(&validated_address)
-        .map_err(|e| format!("Security check failed: {}", e))?;
-
-    BITCOIN_VAULT.with(|vault| {
-        let result = vault
-            .borrow_mut()
-            .deposit_bitcoin(validated_address, amount_satoshi);
-        Ok(result)
-    })
+    require_controller()?;
+    logged(
+        Input::DepositBitcoin {
+            address: address.clone(),
+            amount_satoshi,
+        },
+        || {
+            // Validate Bitcoin address
+            let validated_address =
+                GeneralValidator::validate_string(&address, "address", Some(100))
+                    .map_err(|e| format!("Address validation failed: {}", e))?;
+
+            AddressValidator::validate_address(&validated_address, "bitcoin")
+                .map_err(|e| format!("Bitcoin address invalid: {}", e))?;
+
+            // Validate amount
+            AmountValidator::validate_amount(amount_satoshi, None)
+                .map_err(|e| format!("Amount validation failed: {}", e))?;
+
+            // Security check for malicious patterns
+            SecurityValidator::detect_malicious_input(&validated_address)
+                .map_err(|e| format!("Security check failed: {}", e))?;
+
+            // Deposit to vault
+            let result = BITCOIN_VAULT
+                .with(|vault| {
+                    vault
+                        .borrow_mut()
+                        .deposit_bitcoin(validated_address.clone(), amount_satoshi)
+                })
+                .map_err(|e| format!("Vault deposit failed: {}", e))?;
+
+            credit_l2_account(
+                &validated_address,
+                SupportedChain::Bitcoin,
+                amount_satoshi.into(),
+            )?;
+
+            Ok(result)
+        },
+    )
 }
 
+/// Réservé aux contrôleurs, comme `deposit_bitcoin`
 #[update]
 async fn deposit_bitcoin_with_crypto(
     address: String,
@@ -294,318 +1039,509 @@ async fn deposit_bitcoin_with_crypto(
     crypto_algorithm: Option<String>,
     quantum_threat_level: Option<u8>,
 ) -> Result<String, String> {
-    // Validate Bitcoin address
-    let validated_address = GeneralValidator::validate_string(&address, "address", Some(100))
-        .map_err(|e| format!("Address validation failed: {}", e))?;
-
-    AddressValidator::validate_address(&validated_address, "bitcoin")
-        .map_err(|e| format!("Bitcoin address invalid: {}", e))?;
-
-    // Validate amount
-    AmountValidator::validate_amount(amount_satoshi, None)
-        .map_err(|e| format!("Amount validation failed: {}", e))?;
-
-    // Validate crypto algorithm if provided
-    if let Some(ref algo) = crypto_algorithm {
-        GeneralValidator::validate_crypto_algorithm(algo)
-            .map_err(|e| format!("Crypto algorithm validation failed: {}", e))?;
-    }
-
-    // Validate quantum threat level if provided
-    if let Some(level) = quantum_threat_level {
-        GeneralValidator::validate_quantum_threat_level(level)
-            .map_err(|e| format!("Quantum threat level validation failed: {}", e))?;
-    }
-
-    // Security check for malicious patterns
-    SecurityValidator::detect_malicious_input(&validated_address)
-        .map_err(|e| format!("Security check failed: {}", e))?;
-
-    let policy = CryptoPolicy::default();
-    let risk_level = match amount_satoshi {
-        n if n > 1_000_000 => RiskLevel::Critical,
-        n if n > 100_000 => RiskLevel::High,
-        n if n > 10_000 => RiskLevel::Medium,
-        _ => RiskLevel::Low,
-    };
-
-    let quantum_threat = quantum_threat_level.unwrap_or(0) > 50;
+    require_controller()?;
+    logged(
+        Input::DepositBitcoinWithCrypto {
+            address: address.clone(),
+            amount_satoshi,
+            crypto_algorithm: crypto_algorithm.clone(),
+            quantum_threat_level,
+        },
+        || {
+            // Validate Bitcoin address
+            let validated_address =
+                GeneralValidator::validate_string(&address, "address", Some(100))
+                    .map_err(|e| format!("Address validation failed: {}", e))?;
+
+            AddressValidator::validate_address(&validated_address, "bitcoin")
+                .map_err(|e| format!("Bitcoin address invalid: {}", e))?;
+
+            // Validate amount
+            AmountValidator::validate_amount(amount_satoshi, None)
+                .map_err(|e| format!("Amount validation failed: {}", e))?;
+
+            // Validate crypto algorithm if provided
+            if let Some(ref algo) = crypto_algorithm {
+                GeneralValidator::validate_crypto_algorithm(algo)
+                    .map_err(|e| format!("Crypto algorithm validation failed: {}", e))?;
+            }
 
-    let selected_algo = if let Some(algo_str) = crypto_algorithm {
-        match algo_str.to_lowercase().as_str() {
-            "ecdsa" => CryptoAlgorithm::Ecdsa,
-            "schnorr" => CryptoAlgorithm::Schnorr,
-            "falcon" | "falcon512" => CryptoAlgorithm::Falcon512,
-            "mldsa" | "mldsa44" => CryptoAlgorithm::Mldsa44,
-            _ => select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy),
-        }
-    } else {
-        select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy)
-    };
+            // Validate quantum threat level if provided
+            if let Some(level) = quantum_threat_level {
+                GeneralValidator::validate_quantum_threat_level(level)
+                    .map_err(|e| format!("Quantum threat level validation failed: {}", e))?;
+            }
 
-    BITCOIN_VAULT.with(|vault| {
-        let result = vault
-            .borrow_mut()
-            .deposit_bitcoin(validated_address.clone(), amount_satoshi);
-        let algo_name = algorithm_to_string(&selected_algo);
-        Ok(format!(
-            "{result} | Crypto: {algo_name} | Risk: {risk_level:?} | Quantum: {quantum_threat}",
-        ))
-    })
+            // Security check for malicious patterns
+            SecurityValidator::detect_malicious_input(&validated_address)
+                .map_err(|e| format!("Security check failed: {}", e))?;
+
+            let policy = CryptoPolicy::default();
+            let risk_level = match amount_satoshi {
+                n if n > 1_000_000 => RiskLevel::Critical,
+                n if n > 100_000 => RiskLevel::High,
+                n if n > 10_000 => RiskLevel::Medium,
+                _ => RiskLevel::Low,
+            };
+
+            let quantum_threat = quantum_threat_level.unwrap_or(0) > 50;
+
+            let selected_algo = if let Some(algo_str) = crypto_algorithm {
+                match algo_str.to_lowercase().as_str() {
+                    "ecdsa" => CryptoAlgorithm::Ecdsa,
+                    "schnorr" => CryptoAlgorithm::Schnorr,
+                    "falcon" | "falcon512" => CryptoAlgorithm::Falcon512,
+                    "mldsa" | "mldsa44" => CryptoAlgorithm::Mldsa44,
+                    _ => {
+                        select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy)
+                    }
+                }
+            } else {
+                select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy)
+            };
+
+            // Deposit to vault
+            let result = BITCOIN_VAULT
+                .with(|vault| {
+                    vault
+                        .borrow_mut()
+                        .deposit_bitcoin(validated_address.clone(), amount_satoshi)
+                })
+                .map_err(|e| format!("Vault deposit failed: {}", e))?;
+
+            credit_l2_account(
+                &validated_address,
+                SupportedChain::Bitcoin,
+                amount_satoshi.into(),
+            )?;
+
+            let algo_name = algorithm_to_string(&selected_algo);
+            Ok(format!(
+                "{result} | Crypto: {algo_name} | Risk: {risk_level:?} | Quantum: {quantum_threat}",
+            ))
+        },
+    )
 }
 
 // ========== ETHEREUM FUNCTIONS ==========
 
+/// Réservé aux contrôleurs, comme `deposit_bitcoin`
 #[update]
-async fn deposit_ethereum(address: String, amount_wei: u64) -> Result<String, String> {
-    // Validate Ethereum address
-    let validated_address = GeneralValidator::validate_string(&address, "address", Some(100))
-        .map_err(|e| format!("Address validation failed: {}", e))?;
-
-    AddressValidator::validate_address(&validated_address, "ethereum")
-        .map_err(|e| format!("Ethereum address invalid: {}", e))?;
+async fn deposit_ethereum(address: String, amount_wei: u128) -> Result<String, String> {
+    require_controller()?;
+    logged(
+        Input::DepositEthereum {
+            address: address.clone(),
+            amount_wei,
+        },
+        || {
+            // Validate Ethereum address
+            let validated_address =
+                GeneralValidator::validate_string(&address, "address", Some(100))
+                    .map_err(|e| format!("Address validation failed: {}", e))?;
+
+            AddressValidator::validate_address(&validated_address, "ethereum")
+                .map_err(|e| format!("Ethereum address invalid: {}", e))?;
+
+            // Validate amount
+            AmountValidator::validate_wei_amount(amount_wei)
+                .map_err(|e| format!("Amount validation failed: {}", e))?;
+
+            // Security check for malicious patterns
+            SecurityValidator::detect_malicious_input(&validated_address)
+                .map_err(|e| format!("Security check failed: {}", e))?;
+
+            // Deposit to vault
+            ETHEREUM_VAULT
+                .with(|vault| vault.borrow_mut().deposit(amount_wei, true))
+                .map_err(|e| format!("Vault deposit failed: {}", e))?;
+
+            credit_l2_account(&validated_address, SupportedChain::Ethereum, amount_wei)?;
+
+            // Exact wei to ETH rendering (1 ETH = 10^18 wei)
+            let amount_eth = SupportedChain::Ethereum.format_amount(amount_wei);
 
-    // Validate amount
-    AmountValidator::validate_amount(amount_wei, None)
-        .map_err(|e| format!("Amount validation failed: {}", e))?;
-
-    // Security check for malicious patterns
-    SecurityValidator::detect_malicious_input(&validated_address)
-        .map_err(|e| format!("Security check failed: {}", e))?;
-
-    // Deposit to vault
-    ETHEREUM_VAULT.with(|vault| {
-        vault
-            .borrow_mut()
-            .deposit(&validated_address, amount_wei, true);
-    });
-
-    // Convert wei to ETH for display (1 ETH = 10^18 wei)
-    let amount_eth = amount_wei as f64 / 1_000_000_000_000_000_000.0;
-
-    Ok(format!(
-        "Ethereum deposit successful: {amount_eth} ETH ({amount_wei} wei) to address {validated_address}"
+            Ok(format!(
+        "Ethereum deposit successful: {amount_eth} ({amount_wei} wei) to address {validated_address}"
     ))
+        },
+    )
 }
 
+/// `authorization`: clé du compte sur le retrait, à son prochain nonce de retrait
 #[update]
 async fn withdraw_ethereum(
     address: String,
-    amount_wei: u64,
+    amount_wei: u128,
     quantum_secure: bool,
+    authorization: WithdrawalAuthorization,
 ) -> Result<String, String> {
-    if amount_wei == 0 {
-        return Err("Amount must be positive".to_string());
-    }
+    logged(
+        Input::WithdrawEthereum {
+            address: address.clone(),
+            amount_wei,
+            quantum_secure,
+            authorization: Some(authorization.clone()),
+        },
+        || {
+            AmountValidator::validate_wei_amount(amount_wei)
+                .map_err(|e| format!("Amount validation failed: {}", e))?;
+
+            let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
+            let withdrawal = settle_withdrawal(
+                &address,
+                SupportedChain::Ethereum,
+                amount_wei,
+                Some(&authorization),
+                || {
+                    ETHEREUM_VAULT.with(|vault| {
+                        vault
+                            .borrow_mut()
+                            .withdraw(&address, amount_wei, crypto_algo)
+                    })
+                },
+            )?;
+            let amount_eth = SupportedChain::Ethereum.format_amount(amount_wei);
 
-    let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
-    let amount_eth = amount_wei as f64 / 1_000_000_000_000_000_000.0;
-
-    Ok(format!(
-        "Ethereum withdrawal initiated: {} ETH ({} wei) using {} - TxID: eth_{}",
-        amount_eth,
-        amount_wei,
-        crypto_algo,
-        ic_cdk::api::time()
-    ))
+            Ok(format!(
+                "Ethereum withdrawal initiated: {} ({} wei) from {} using {} - TxID: eth_{}",
+                amount_eth,
+                amount_wei,
+                withdrawal.payout(),
+                crypto_algo,
+                withdrawal.tx_id
+            ))
+        },
+    )
 }
 
 // ========== ICP FUNCTIONS ==========
 
+/// Réservé aux contrôleurs, comme `deposit_bitcoin`
 #[update]
 async fn deposit_icp(address: String, amount_e8s: u64) -> Result<String, String> {
-    if amount_e8s == 0 {
-        return Err("Amount must be positive".to_string());
-    }
+    require_controller()?;
+    logged(
+        Input::DepositIcp {
+            address: address.clone(),
+            amount_e8s,
+        },
+        || {
+            if amount_e8s == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-    // Deposit to vault
-    ICP_VAULT.with(|vault| {
-        vault.borrow_mut().deposit(&address, amount_e8s, true);
-    });
+            // Deposit to vault
+            ICP_VAULT
+                .with(|vault| vault.borrow_mut().deposit(amount_e8s.into(), true))
+                .map_err(|e| format!("Vault deposit failed: {}", e))?;
 
-    // Convert e8s to ICP for display (1 ICP = 10^8 e8s)
-    let amount_icp = amount_e8s as f64 / 100_000_000.0;
+            credit_l2_account(&address, SupportedChain::Icp, amount_e8s.into())?;
 
-    Ok(format!(
-        "ICP deposit successful: {amount_icp} ICP ({amount_e8s} e8s) to address {address}"
-    ))
+            // Convert e8s to ICP for display (1 ICP = 10^8 e8s)
+            let amount_icp = amount_e8s as f64 / 100_000_000.0;
+
+            Ok(format!(
+                "ICP deposit successful: {amount_icp} ICP ({amount_e8s} e8s) to address {address}"
+            ))
+        },
+    )
 }
 
+/// `authorization`: clé du compte sur le retrait, à son prochain nonce de retrait
 #[update]
 async fn withdraw_icp(
     address: String,
     amount_e8s: u64,
     quantum_secure: bool,
+    authorization: WithdrawalAuthorization,
 ) -> Result<String, String> {
-    if amount_e8s == 0 {
-        return Err("Amount must be positive".to_string());
-    }
+    logged(
+        Input::WithdrawIcp {
+            address: address.clone(),
+            amount_e8s,
+            quantum_secure,
+            authorization: Some(authorization.clone()),
+        },
+        || {
+            if amount_e8s == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-    let crypto_algo = if quantum_secure { "ML-DSA44" } else { "ECDSA" };
-    let amount_icp = amount_e8s as f64 / 100_000_000.0;
+            let crypto_algo = if quantum_secure { "ML-DSA44" } else { "ECDSA" };
+            let withdrawal = settle_withdrawal(
+                &address,
+                SupportedChain::Icp,
+                amount_e8s.into(),
+                Some(&authorization),
+                || {
+                    ICP_VAULT.with(|vault| {
+                        vault
+                            .borrow_mut()
+                            .withdraw(&address, amount_e8s.into(), crypto_algo)
+                    })
+                },
+            )?;
+            let amount_icp = amount_e8s as f64 / 100_000_000.0;
 
-    Ok(format!(
-        "ICP withdrawal initiated: {} ICP ({} e8s) using {} - TxID: icp_{}",
-        amount_icp,
-        amount_e8s,
-        crypto_algo,
-        ic_cdk::api::time()
-    ))
+            Ok(format!(
+                "ICP withdrawal initiated: {} ICP ({} e8s) from {} using {} - TxID: icp_{}",
+                amount_icp,
+                amount_e8s,
+                withdrawal.payout(),
+                crypto_algo,
+                withdrawal.tx_id
+            ))
+        },
+    )
 }
 
 // ========== SOLANA FUNCTIONS ==========
 
+/// Réservé aux contrôleurs, comme `deposit_bitcoin`
 #[update]
 async fn deposit_solana(address: String, amount_lamports: u64) -> Result<String, String> {
-    if amount_lamports == 0 {
-        return Err("Amount must be positive".to_string());
-    }
+    require_controller()?;
+    logged(
+        Input::DepositSolana {
+            address: address.clone(),
+            amount_lamports,
+        },
+        || {
+            if amount_lamports == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-    // Deposit to vault
-    SOLANA_VAULT.with(|vault| {
-        vault.borrow_mut().deposit(&address, amount_lamports, true);
-    });
+            // Deposit to vault
+            SOLANA_VAULT
+                .with(|vault| vault.borrow_mut().deposit(amount_lamports.into(), true))
+                .map_err(|e| format!("Vault deposit failed: {}", e))?;
 
-    // Convert lamports to SOL for display (1 SOL = 10^9 lamports)
-    let amount_sol = amount_lamports as f64 / 1_000_000_000.0;
+            credit_l2_account(&address, SupportedChain::Solana, amount_lamports.into())?;
 
-    Ok(format!(
+            // Convert lamports to SOL for display (1 SOL = 10^9 lamports)
+            let amount_sol = amount_lamports as f64 / 1_000_000_000.0;
+
+            Ok(format!(
         "Solana deposit successful: {amount_sol} SOL ({amount_lamports} lamports) to address {address}"    ))
+        },
+    )
 }
 
+/// `authorization`: clé du compte sur le retrait, à son prochain nonce de retrait
 #[update]
 async fn withdraw_solana(
     address: String,
     amount_lamports: u64,
     quantum_secure: bool,
+    authorization: WithdrawalAuthorization,
 ) -> Result<String, String> {
-    if amount_lamports == 0 {
-        return Err("Amount must be positive".to_string());
-    }
+    logged(
+        Input::WithdrawSolana {
+            address: address.clone(),
+            amount_lamports,
+            quantum_secure,
+            authorization: Some(authorization.clone()),
+        },
+        || {
+            if amount_lamports == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-    let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
-    let amount_sol = amount_lamports as f64 / 1_000_000_000.0;
+            let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
+            let withdrawal = settle_withdrawal(
+                &address,
+                SupportedChain::Solana,
+                amount_lamports.into(),
+                Some(&authorization),
+                || {
+                    SOLANA_VAULT.with(|vault| {
+                        vault
+                            .borrow_mut()
+                            .withdraw(&address, amount_lamports.into(), crypto_algo)
+                    })
+                },
+            )?;
+            let amount_sol = amount_lamports as f64 / 1_000_000_000.0;
 
-    Ok(format!(
-        "Solana withdrawal initiated: {} SOL ({} lamports) using {} - TxID: sol_{}",
-        amount_sol,
-        amount_lamports,
-        crypto_algo,
-        ic_cdk::api::time()
-    ))
+            Ok(format!(
+                "Solana withdrawal initiated: {} SOL ({} lamports) from {} using {} - TxID: sol_{}",
+                amount_sol,
+                amount_lamports,
+                withdrawal.payout(),
+                crypto_algo,
+                withdrawal.tx_id
+            ))
+        },
+    )
 }
 
+// Réserves mises en commun de chaque vault; le solde d'un compte est get_balance
+
 #[query]
-fn get_bitcoin_balance(address: String) -> BitcoinBalance {
+fn get_bitcoin_balance() -> BitcoinBalance {
     BITCOIN_VAULT.with(|vault| {
-        let (native, wrapped) = vault.borrow().get_balance(&address);
+        let vault = vault.borrow();
+        let (native, wrapped) = vault.reserves();
         BitcoinBalance {
             native_bitcoin: native,
             wrapped_bitcoin: wrapped,
-            total_bitcoin: native + wrapped,
+            total_bitcoin: vault.total_deposits,
         }
     })
 }
 
 #[query]
-fn get_ethereum_balance(address: String) -> EthereumBalance {
+fn get_ethereum_balance() -> EthereumBalance {
     ETHEREUM_VAULT.with(|vault| {
-        let (native, wrapped) = vault.borrow().get_balance(&address);
+        let (native, wrapped) = vault.borrow().reserves();
         EthereumBalance {
             native_ethereum: native,
             wrapped_ethereum: wrapped,
+            // deposit() refuses any balance whose total would overflow
             total_ethereum: native + wrapped,
         }
     })
 }
 
 #[query]
-fn get_icp_balance(address: String) -> IcpBalance {
+fn get_icp_balance() -> IcpBalance {
     ICP_VAULT.with(|vault| {
-        let (native, wrapped) = vault.borrow().get_balance(&address);
+        let (native, wrapped) = vault.borrow().reserves();
         IcpBalance {
             native_icp: native,
             wrapped_icp: wrapped,
+            // deposit() refuses any balance whose total would overflow
             total_icp: native + wrapped,
         }
     })
 }
 
 #[query]
-fn get_solana_balance(address: String) -> SolanaBalance {
+fn get_solana_balance() -> SolanaBalance {
     SOLANA_VAULT.with(|vault| {
-        let (native, wrapped) = vault.borrow().get_balance(&address);
+        let (native, wrapped) = vault.borrow().reserves();
         SolanaBalance {
             native_solana: native,
             wrapped_solana: wrapped,
+            // deposit() refuses any balance whose total would overflow
             total_solana: native + wrapped,
         }
     })
 }
 
+/// `authorization`: clé du compte sur le retrait, à son prochain nonce de retrait
 #[update]
 async fn withdraw_bitcoin(
     address: String,
     amount_satoshi: u64,
     quantum_secure: bool,
+    authorization: WithdrawalAuthorization,
 ) -> Result<String, String> {
-    if amount_satoshi == 0 {
-        return Err("Amount must be positive".to_string());
-    }
-
-    BITCOIN_VAULT.with(|vault| {
-        let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
-        let result = vault
-            .borrow_mut()
-            .withdraw_bitcoin(address, amount_satoshi, crypto_algo);
-        Ok(format!(
-            "Withdrawal initiated: {amount_satoshi} satoshi using {crypto_algo} - TxID: {result}",
-        ))
-    })
-}
-
-#[update]
-async fn withdraw_bitcoin_adaptive(
-    address: String,
-    amount_satoshi: u64,
-    auto_select_crypto: bool,
-    quantum_threat_level: Option<u8>,
-) -> Result<String, String> {
-    if amount_satoshi == 0 {
-        return Err("Amount must be positive".to_string());
-    }
-
-    let policy = CryptoPolicy::default();
-    let risk_level = match amount_satoshi {
-        n if n > 1_000_000 => RiskLevel::Critical,
-        n if n > 100_000 => RiskLevel::High,
-        n if n > 10_000 => RiskLevel::Medium,
-        _ => RiskLevel::Low,
-    };
+    logged(
+        Input::WithdrawBitcoin {
+            address: address.clone(),
+            amount_satoshi,
+            quantum_secure,
+            authorization: Some(authorization.clone()),
+        },
+        || {
+            if amount_satoshi == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-    let quantum_threat = quantum_threat_level.unwrap_or(0) > 50;
+            let crypto_algo = if quantum_secure { "Falcon512" } else { "ECDSA" };
+            let withdrawal = settle_withdrawal(
+                &address,
+                SupportedChain::Bitcoin,
+                amount_satoshi.into(),
+                Some(&authorization),
+                || {
+                    BITCOIN_VAULT.with(|vault| {
+                        vault.borrow_mut().withdraw_bitcoin(
+                            address.clone(),
+                            amount_satoshi,
+                            crypto_algo,
+                        )
+                    })
+                },
+            )?;
 
-    let selected_algo = if auto_select_crypto {
-        select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy)
-    } else {
-        CryptoAlgorithm::Ecdsa // Défaut si pas d'auto-sélection
-    };
+            Ok(format!(
+        "Withdrawal initiated: {amount_satoshi} satoshi from {} using {crypto_algo} - TxID: {}",
+        withdrawal.payout(), withdrawal.tx_id
+    ))
+        },
+    )
+}
 
-    BITCOIN_VAULT.with(|vault| {
-        let crypto_algo = match selected_algo {
-            CryptoAlgorithm::Ecdsa => "ECDSA",
-            CryptoAlgorithm::Schnorr => "Schnorr",
-            CryptoAlgorithm::Falcon512 => "Falcon512",
-            CryptoAlgorithm::Mldsa44 => "ML-DSA44",
-        };
+/// `authorization`: clé du compte sur le retrait, à son prochain nonce de retrait
+#[update]
+async fn withdraw_bitcoin_adaptive(
+    address: String,
+    amount_satoshi: u64,
+    auto_select_crypto: bool,
+    quantum_threat_level: Option<u8>,
+    authorization: WithdrawalAuthorization,
+) -> Result<String, String> {
+    logged(
+        Input::WithdrawBitcoinAdaptive {
+            address: address.clone(),
+            amount_satoshi,
+            auto_select_crypto,
+            quantum_threat_level,
+            authorization: Some(authorization.clone()),
+        },
+        || {
+            if amount_satoshi == 0 {
+                return Err("Amount must be positive".to_string());
+            }
 
-        let result = vault
-            .borrow_mut()
-            .withdraw_bitcoin(address, amount_satoshi, crypto_algo);
+            let policy = CryptoPolicy::default();
+            let risk_level = match amount_satoshi {
+                n if n > 1_000_000 => RiskLevel::Critical,
+                n if n > 100_000 => RiskLevel::High,
+                n if n > 10_000 => RiskLevel::Medium,
+                _ => RiskLevel::Low,
+            };
+
+            let quantum_threat = quantum_threat_level.unwrap_or(0) > 50;
+
+            let selected_algo = if auto_select_crypto {
+                select_crypto_algorithm(amount_satoshi, risk_level, quantum_threat, &policy)
+            } else {
+                CryptoAlgorithm::Ecdsa // Défaut si pas d'auto-sélection
+            };
+
+            let crypto_algo = match selected_algo {
+                CryptoAlgorithm::Ecdsa => "ECDSA",
+                CryptoAlgorithm::Schnorr => "Schnorr",
+                CryptoAlgorithm::Falcon512 => "Falcon512",
+                CryptoAlgorithm::Mldsa44 => "ML-DSA44",
+            };
+
+            let withdrawal = settle_withdrawal(
+                &address,
+                SupportedChain::Bitcoin,
+                amount_satoshi.into(),
+                Some(&authorization),
+                || {
+                    BITCOIN_VAULT.with(|vault| {
+                        vault.borrow_mut().withdraw_bitcoin(
+                            address.clone(),
+                            amount_satoshi,
+                            crypto_algo,
+                        )
+                    })
+                },
+            )?;
 
-        Ok(format!(
-            "Adaptive withdrawal: {amount_satoshi} satoshi | Crypto: {crypto_algo} | Risk: {risk_level:?} | Quantum: {quantum_threat} | TxID: {result}"        ))
-    })
+            Ok(format!(
+        "Adaptive withdrawal: {amount_satoshi} satoshi | Source: {} | Crypto: {crypto_algo} | Risk: {risk_level:?} | Quantum: {quantum_threat} | TxID: {}",
+        withdrawal.payout(), withdrawal.tx_id
+    ))
+        },
+    )
 }
 
 #[query]
@@ -680,8 +1616,8 @@ fn get_performance_metrics() -> PerformanceMetrics {
         let vault_stats = VaultStats {
             total_deposits: vault_ref.total_deposits,
             transaction_count: vault_ref.transaction_count,
-            native_count: vault_ref.native_reserves.len() as u64,
-            wrapped_count: vault_ref.wrapped_balances.len() as u64,
+            native_count: vault_ref.native_depositors.len() as u64,
+            wrapped_count: vault_ref.wrapped_depositors.len() as u64,
         };
 
         PerformanceMetrics {
@@ -707,8 +1643,8 @@ fn get_vault_statistics() -> VaultStatistics {
         VaultStatistics {
             total_deposits_satoshi: vault_ref.total_deposits,
             total_transactions: vault_ref.transaction_count,
-            native_addresses: vault_ref.native_reserves.len() as u32,
-            wrapped_addresses: vault_ref.wrapped_balances.len() as u32,
+            native_addresses: vault_ref.native_depositors.len() as u32,
+            wrapped_addresses: vault_ref.wrapped_depositors.len() as u32,
             deposit_threshold: 100_000, // 0.001 BTC threshold
             vault_active: true,
         }
@@ -826,7 +1762,7 @@ fn is_quantum_ready_all_chains() -> bool {
 
 fn calculate_quantum_threat_level() -> u8 {
     // Simuler une évaluation de menace quantique basée sur le temps
-    let current_time = ic_cdk::api::time();
+    let current_time = clock::now();
     let base_threat = 25; // Niveau de base
     let time_factor = (current_time / 1_000_000_000) % 50; // Variation temporelle
     (base_threat + time_factor as u8).min(100)
@@ -841,7 +1777,7 @@ fn calculate_security_score(vault: &bitcoin_vault::BitcoinVault) -> f64 {
     }
 
     // Bonus pour diversité des réserves
-    if vault.native_reserves.len() > 5 && vault.wrapped_balances.len() > 5 {
+    if vault.native_depositors.len() > 5 && vault.wrapped_depositors.len() > 5 {
         score += 10.0;
     }
 
@@ -878,7 +1814,7 @@ async fn crypto_algorithm_benchmark(
     let data = message.as_bytes();
 
     // Use actual timing for real benchmark measurements
-    let start_time = ic_cdk::api::time();
+    let start_time = clock::now();
 
     let (success, is_quantum_resistant) = match algorithm.as_str() {
         "ecdsa" => {
@@ -956,13 +1892,13 @@ async fn crypto_algorithm_benchmark(
         _ => return Err("Unsupported algorithm. Use: ecdsa, schnorr, falcon, mldsa".to_string()),
     };
 
-    let end_time = ic_cdk::api::time();
+    let end_time = clock::now();
 
     if !success {
         return Err("Cryptographic operation failed".to_string());
     }
 
-    let elapsed = end_time - start_time;
+    let elapsed = end_time.saturating_sub(start_time);
     // Use actual IC execution time - real benchmark measurements
     let actual_time = elapsed;
 
@@ -985,23 +1921,23 @@ pub struct BitcoinBalance {
 
 #[derive(CandidType, Deserialize, Default)]
 pub struct EthereumBalance {
-    pub native_ethereum: u64,
-    pub wrapped_ethereum: u64,
-    pub total_ethereum: u64,
+    pub native_ethereum: u128,
+    pub wrapped_ethereum: u128,
+    pub total_ethereum: u128,
 }
 
 #[derive(CandidType, Deserialize, Default)]
 pub struct IcpBalance {
-    pub native_icp: u64,
-    pub wrapped_icp: u64,
-    pub total_icp: u64,
+    pub native_icp: u128,
+    pub wrapped_icp: u128,
+    pub total_icp: u128,
 }
 
 #[derive(CandidType, Deserialize, Default)]
 pub struct SolanaBalance {
-    pub native_solana: u64,
-    pub wrapped_solana: u64,
-    pub total_solana: u64,
+    pub native_solana: u128,
+    pub wrapped_solana: u128,
+    pub total_solana: u128,
 }
 
 #[derive(CandidType, Deserialize, Default)]
@@ -1092,70 +2028,282 @@ pub struct CryptoRecommendation {
 
 // ========== SEQUENCER API ==========
 
-/// Créer et configurer un séquenceur de transactions
+/// Créer et configurer un séquenceur de transactions, avec sa clé s'il n'en a pas encore.
+/// Réservé aux contrôleurs: la stratégie décide de l'ordre de toutes les transactions.
 #[update]
 async fn create_transaction_sequencer(ordering_strategy: String) -> Result<String, String> {
-    use std::collections::HashMap;
+    require_controller()?;
+    setup_sequencer(ordering_strategy).await
+}
 
-    let strategy = match ordering_strategy.as_str() {
-        "fcfs" => OrderingStrategy::FirstComeFirstServed,
-        "priority" => OrderingStrategy::PriorityFee,
-        "fair" => OrderingStrategy::FairOrdering,
-        "vrf" => OrderingStrategy::VrfRandom,
-        _ => return Err("Invalid strategy. Use: fcfs, priority, fair, vrf".to_string()),
+/// Créer le séquenceur ou changer sa stratégie, et lui tirer une clé s'il n'en a pas
+async fn setup_sequencer(ordering_strategy: String) -> Result<String, String> {
+    let keyed = SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .is_some_and(|sequencer| sequencer.public_key().is_some())
+    });
+    let seed = if keyed {
+        None
+    } else {
+        Some(draw_key_seed().await?)
     };
+    logged(
+        Input::CreateSequencer {
+            ordering_strategy: ordering_strategy.clone(),
+        },
+        || {
+            // Un séquenceur existant garde ses nonces et sa file d'attente, seule la stratégie change
+            let reconfigured = SEQUENCER_STATE.with(|state| {
+                state.borrow_mut().as_mut().map(|sequencer| {
+                    if let Some(strategy) = parse_ordering_strategy(&ordering_strategy) {
+                        sequencer.set_ordering_strategy(strategy);
+                    }
+                })
+            });
 
-    let crypto = Ecdsa;
-    let private_keys = HashMap::new();
-    let mut sequencer = TransactionSequencer::new(crypto, private_keys);
-    sequencer.set_ordering_strategy(strategy);
+            let strategy = parse_ordering_strategy(&ordering_strategy)
+                .ok_or_else(|| "Invalid strategy. Use: fcfs, priority, fair, vrf".to_string())?;
 
-    // Sauvegarder dans le state persistent
-    SEQUENCER_STATE.with(|state| {
-        *state.borrow_mut() = Some(sequencer);
-    });
+            if reconfigured.is_none() {
+                let mut sequencer = TransactionSequencer::new(Ecdsa);
+                sequencer.set_ordering_strategy(strategy);
 
-    SEQUENCER_METRICS.with(|metrics| {
-        metrics.borrow_mut().ordering_strategy = ordering_strategy.clone();
-    });
+                // Sauvegarder dans le state persistent
+                SEQUENCER_STATE.with(|state| {
+                    *state.borrow_mut() = Some(sequencer);
+                });
+            }
+            SEQUENCER_STATE.with(|state| match state.borrow_mut().as_mut() {
+                Some(sequencer) => provision_sequencer_key(sequencer, seed),
+                None => Ok(()),
+            })?;
 
-    Ok(format!(
-        "Sequencer created with {} strategy",
-        ordering_strategy
-    ))
+            SEQUENCER_METRICS.with(|metrics| {
+                metrics.borrow_mut().ordering_strategy = ordering_strategy.clone();
+            });
+
+            Ok(format!(
+                "Sequencer created with {} strategy",
+                ordering_strategy
+            ))
+        },
+    )
+}
+
+/// Donner au séquenceur sa clé s'il n'en a pas: dérivée de `seed`, ou en rejeu la clé
+/// publique journalisée par le run d'origine
+fn provision_sequencer_key(
+    sequencer: &mut TransactionSequencer<Ecdsa>,
+    seed: Option<[u8; 32]>,
+) -> Result<(), String> {
+    if sequencer.public_key().is_some() {
+        return Ok(());
+    }
+    match (replay::recorded(), seed) {
+        (Some(trace), _) => {
+            if let Some(public_key) = trace.sequencer_key {
+                sequencer.set_public_key(public_key);
+            }
+        }
+        (None, Some(seed)) => sequencer
+            .set_proposer_seed(seed)
+            .map_err(|e| e.to_string())?,
+        (None, None) => {}
+    }
+    if let Some(public_key) = sequencer.public_key() {
+        replay::note_sequencer_key(public_key);
+    }
+    Ok(())
+}
+
+/// Graine secrète de la clé du séquenceur: l'aléa du subnet (raw_rand) sur l'IC,
+/// jamais le getrandom local dont les sources sont prévisibles
+async fn draw_key_seed() -> Result<[u8; 32], String> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let (bytes,) = ic_cdk::api::management_canister::main::raw_rand()
+            .await
+            .map_err(|(code, message)| format!("raw_rand failed: {code:?} {message}"))?;
+        bytes
+            .try_into()
+            .map_err(|_| "raw_rand did not return 32 bytes".to_string())
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    Ok(rand::random())
+}
+
+/// Nom accepté par `create_transaction_sequencer` pour une stratégie
+fn ordering_strategy_name(strategy: &OrderingStrategy) -> &'static str {
+    match strategy {
+        OrderingStrategy::FirstComeFirstServed => "fcfs",
+        OrderingStrategy::PriorityFee => "priority",
+        OrderingStrategy::FairOrdering => "fair",
+        OrderingStrategy::VrfRandom => "vrf",
+    }
+}
+
+fn parse_ordering_strategy(name: &str) -> Option<OrderingStrategy> {
+    match name {
+        "fcfs" => Some(OrderingStrategy::FirstComeFirstServed),
+        "priority" => Some(OrderingStrategy::PriorityFee),
+        "fair" => Some(OrderingStrategy::FairOrdering),
+        "vrf" => Some(OrderingStrategy::VrfRandom),
+        _ => None,
+    }
 }
 
-/// Ajouter une transaction au séquenceur
+/// Prochain nonce attendu pour un expéditeur (0 si aucune transaction admise)
+#[query]
+fn get_next_nonce(address: String) -> u64 {
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .map(|sequencer| sequencer.next_nonce(&address))
+            .unwrap_or(0)
+    })
+}
+
+/// Ajouter une transaction signée au séquenceur
+/// `signature` couvre `PolyTransaction::signing_payload()`, clé et signature en hex
 #[update]
+#[allow(clippy::too_many_arguments)]
 async fn add_transaction_to_sequencer(
     sender: String,
     recipient: String,
-    amount: f64,
+    amount: u128,
+    asset: SupportedChain,
+    nonce: u64,
+    fee: u128,
+    max_fee: u128,
+    public_key: String,
+    algorithm: CryptoAlgorithm,
+    signature: String,
 ) -> Result<String, String> {
-    if amount <= 0.0 {
+    if amount == 0 {
         return Err("Amount must be positive".to_string());
     }
 
-    let mut tx = PolyTransaction::new(sender.clone(), recipient.clone(), amount);
+    let mut tx =
+        PolyTransaction::new(sender, recipient, amount, asset, nonce).with_fee(fee, max_fee);
+    tx.sign(algorithm, public_key, signature);
+    tx.hash = Some(tx.calculate_hash());
 
-    // Créer un hash unique pour la transaction
-    let tx_data = format!(
-        "{}:{}:{}:{}",
-        sender,
-        recipient,
-        amount,
-        ic_cdk::api::time()
-    );
-    let tx_hash = calculate_hash(&tx_data);
-    tx.sign(tx_hash.clone());
-    tx.hash = Some(tx_hash);
+    // L'id retourné est celui de get_transaction_status
+    let input = Input::AddTransaction {
+        transaction: tx.clone(),
+    };
+    logged(input, || {
+        admit_to_sequencer(tx, |sequencer, tx| sequencer.add_transaction(tx))
+    })
+}
+
+/// Engager une transaction sans la dévoiler: `commitment` vaut
+/// `PolyTransaction::commitment(salt)`, la position dans la FairOrdering est fixée ici.
+/// L'engagement est signé par la clé liée au compte de l'expéditeur, seul à pouvoir le dévoiler.
+#[update]
+fn submit_transaction_commitment(submission: CommitmentSubmission) -> Result<u64, String> {
+    logged(
+        Input::SubmitSignedCommitment {
+            submission: submission.clone(),
+        },
+        || {
+            L2_LEDGER
+                .with(|ledger| {
+                    ledger.borrow().check_key(
+                        &submission.sender,
+                        submission.algorithm,
+                        &submission.public_key,
+                    )
+                })
+                .map_err(|e| e.to_string())?;
+            SEQUENCER_STATE.with(|state| match state.borrow_mut().as_mut() {
+                Some(sequencer) => {
+                    let slot = sequencer.submit_commitment(&submission);
+                    record_dropped(sequencer);
+                    slot.map_err(|e| e.to_string())
+                }
+                None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
+            })
+        },
+    )
+}
 
-    let result = SEQUENCER_STATE.with(|state| {
+/// Dévoiler une transaction engagée, avec le sel hex de l'engagement
+#[update]
+#[allow(clippy::too_many_arguments)]
+async fn reveal_transaction(
+    sender: String,
+    recipient: String,
+    amount: u128,
+    asset: SupportedChain,
+    nonce: u64,
+    fee: u128,
+    max_fee: u128,
+    public_key: String,
+    algorithm: CryptoAlgorithm,
+    signature: String,
+    salt: String,
+) -> Result<String, String> {
+    if amount == 0 {
+        return Err("Amount must be positive".to_string());
+    }
+    let salt_bytes = hex::decode(&salt).map_err(|e| format!("Salt is not hex: {e}"))?;
+
+    let mut tx =
+        PolyTransaction::new(sender, recipient, amount, asset, nonce).with_fee(fee, max_fee);
+    tx.sign(algorithm, public_key, signature);
+    tx.hash = Some(tx.calculate_hash());
+
+    let input = Input::RevealTransaction {
+        transaction: tx.clone(),
+        salt,
+    };
+    logged(input, || {
+        admit_to_sequencer(tx, |sequencer, tx| {
+            sequencer.reveal_transaction(tx, &salt_bytes)
+        })
+    })
+}
+
+#[query]
+fn get_commitment(commitment: String) -> Option<Commitment> {
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .and_then(|sequencer| sequencer.commitment(&commitment).cloned())
+    })
+}
+
+fn admit_to_sequencer(
+    tx: PolyTransaction,
+    admit: impl FnOnce(
+        &mut TransactionSequencer<Ecdsa>,
+        PolyTransaction,
+    ) -> Result<String, errors::SequencerError>,
+) -> Result<String, String> {
+    // Seule la clé liée au compte peut dépenser pour lui; une transaction non signée
+    // est refusée plus loin par le séquenceur
+    if let (Some(algorithm), Some(public_key)) = (tx.algorithm, tx.public_key.as_deref()) {
+        L2_LEDGER
+            .with(|ledger| ledger.borrow().check_key(&tx.sender, algorithm, public_key))
+            .map_err(|e| e.to_string())?;
+    }
+
+    SEQUENCER_STATE.with(|state| {
         let mut state_ref = state.borrow_mut();
         match state_ref.as_mut() {
             Some(sequencer) => {
-                match sequencer.add_transaction(tx) {
+                let added = admit(sequencer, tx);
+                // Une admission peut expirer, remplacer ou évincer d'autres transactions
+                record_dropped(sequencer);
+                match added {
                     Ok(tx_id) => {
+                        set_transaction_status(tx_id.clone(), TransactionStatus::Pending);
+
                         // Mettre à jour les métriques
                         SEQUENCER_METRICS.with(|metrics| {
                             let mut m = metrics.borrow_mut();
@@ -1163,17 +2311,12 @@ async fn add_transaction_to_sequencer(
                         });
                         Ok(tx_id)
                     }
-                    Err(e) => Err(e),
+                    Err(e) => Err(e.to_string()),
                 }
             }
             None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
         }
-    });
-
-    match result {
-        Ok(tx_id) => Ok(format!("Transaction added to sequencer: ID {}", tx_id)),
-        Err(e) => Err(e),
-    }
+    })
 }
 
 /// Séquencer un batch de transactions
@@ -1181,87 +2324,335 @@ async fn add_transaction_to_sequencer(
 async fn sequence_transaction_batch(
     batch_size: Option<u64>,
 ) -> Result<SequencerBatchResult, String> {
-    let size = batch_size.unwrap_or(100) as usize;
+    logged(Input::SequenceBatch { batch_size }, || {
+        let size = batch_size.unwrap_or(100);
+
+        if size > producer::MAX_BATCH_SIZE {
+            return Err(format!(
+                "Batch size too large (max {})",
+                producer::MAX_BATCH_SIZE
+            ));
+        }
 
-    if size > 1000 {
-        return Err("Batch size too large (max 1000)".to_string());
-    }
+        let start_time = clock::now();
 
-    let start_time = ic_cdk::api::time();
+        let result = produce_block(size as usize, start_time, false).map(|outcome| {
+            let processing_time = clock::now().saturating_sub(start_time) / 1_000_000;
 
-    let result = SEQUENCER_STATE.with(|state| {
-        let mut state_ref = state.borrow_mut();
-        match state_ref.as_mut() {
-            Some(sequencer) => {
-                // Séquencer vraiment les transactions
-                let sequenced_transactions = sequencer.sequence_batch(size);
-                let actual_count = sequenced_transactions.len() as u64;
-
-                // Créer un vrai bloc avec les transactions séquencées
-                if !sequenced_transactions.is_empty() {
-                    let previous_hash = BLOCKCHAIN_STATE.with(|chain| {
-                        let blockchain = chain.borrow();
-                        if blockchain.is_empty() {
-                            "0000000000000000000000000000000000000000000000000000000000000000"
-                                .to_string()
-                        } else {
-                            blockchain.last()
-                                .map(|block| block.hash.clone())
-                                .unwrap_or_else(|| "0000000000000000000000000000000000000000000000000000000000000000".to_string())
-                        }
-                    });
-
-                    let new_block = PolyBlock::new(sequenced_transactions.clone(), previous_hash);
-
-                    // Ajouter le bloc à la blockchain
-                    BLOCKCHAIN_STATE.with(|chain| {
-                        chain.borrow_mut().push(new_block);
-                    });
-
-                    // Ajouter les transactions à la pool des transactions confirmées
-                    TRANSACTION_POOL.with(|pool| {
-                        pool.borrow_mut().extend(sequenced_transactions);
-                    });
-                }
+            SEQUENCER_METRICS.with(|metrics| {
+                let mut m = metrics.borrow_mut();
+                m.average_sequencing_time_ms = processing_time as f64;
+            });
 
-                // Mettre à jour les métriques
-                SEQUENCER_METRICS.with(|metrics| {
-                    let mut m = metrics.borrow_mut();
-                    m.total_transactions_sequenced += actual_count;
-                    m.total_batches_created += 1;
-                    m.current_pending_count = sequencer.pending_count() as u64;
-                    if m.total_batches_created > 0 {
-                        m.average_batch_size =
-                            m.total_transactions_sequenced as f64 / m.total_batches_created as f64;
-                    }
-                });
+            let strategy =
+                SEQUENCER_METRICS.with(|metrics| metrics.borrow().ordering_strategy.clone());
+            (outcome, processing_time, strategy)
+        });
 
-                let processing_time = (ic_cdk::api::time() - start_time) / 1_000_000;
+        match result {
+            Ok((outcome, processing_time, strategy)) => Ok(SequencerBatchResult {
+                success: true,
+                batch_id: format!("batch_{}", clock::now()),
+                transaction_count: outcome.applied,
+                rejected_count: outcome.rejected,
+                sequencing_time_ms: processing_time,
+                ordering_strategy: strategy,
+                fairness_score: outcome.fairness_score,
+                kendall_tau: outcome.kendall_tau,
+            }),
+            Err(e) => Err(e),
+        }
+    })
+}
 
-                SEQUENCER_METRICS.with(|metrics| {
-                    let mut m = metrics.borrow_mut();
-                    m.average_sequencing_time_ms = processing_time as f64;
-                });
+/// Bilan d'un bloc produit
+#[derive(Debug, PartialEq)]
+struct BlockOutcome {
+    applied: u64,
+    rejected: u64,
+    sealed: bool,
+    /// Équité du batch face à l'ordre d'arrivée, 1.0 si rien n'a été séquencé
+    fairness_score: f64,
+    kendall_tau: f64,
+}
+
+/// Expirer, séquencer et sceller le prochain bloc à l'instant `now`. Le séquenceur seul
+/// scelle les blocs: la rotation des proposeurs par VRF et pacemaker ne tourne encore que
+/// dans la simulation PoS (`test_pos_consensus`, `start_pos_simulation`).
+fn produce_block(size: usize, now: u64, seal_empty: bool) -> Result<BlockOutcome, String> {
+    SEQUENCER_STATE.with(|state| {
+        let mut state_ref = state.borrow_mut();
+        let Some(sequencer) = state_ref.as_mut() else {
+            return Err("Sequencer not initialized. Create sequencer first.".to_string());
+        };
+
+        // Sans clé (séquenceur restauré avant son tirage), le bloc ne pourrait pas être
+        // signé: rien n'est retiré du mempool. Un rejeu signe avec la clé journalisée.
+        if replay::recorded().is_none() {
+            sequencer.proposer_public_key().map_err(|e| e.to_string())?;
+        }
 
-                let strategy =
-                    SEQUENCER_METRICS.with(|metrics| metrics.borrow().ordering_strategy.clone());
-                Ok((actual_count, processing_time, strategy))
+        // Les transactions expirées ne partent pas dans le bloc
+        sequencer.expire_transactions(now as i64);
+        record_dropped(sequencer);
+
+        // Séquencer vraiment les transactions; un rejeu reprend l'aléa journalisé
+        let recorded = replay::recorded();
+        let batch = match &recorded {
+            Some(trace) => {
+                sequencer.replay_batch(&chain_tip_hash(), size, trace.ordering_proof.as_ref())
             }
-            None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
+            None => sequencer.sequence_batch(&chain_tip_hash(), size),
+        }
+        .map_err(|e| e.to_string())?;
+        replay::note_ordering_proof(batch.ordering_proof.as_ref());
+        let fairness = batch.fairness;
+        if fairness.transactions > 0 {
+            let strategy = sequencer.ordering_strategy();
+            SEQUENCER_METRICS.with(|metrics| {
+                let mut m = metrics.borrow_mut();
+                let totals = fairness::record(&mut m.fairness_by_strategy, strategy, &fairness);
+                m.fairness_score = totals.fairness_score;
+            });
+        }
+        let (actual_count, rejected_count) =
+            seal_batch(batch, sequencer, seal_empty, recorded.as_ref())?;
+        let sealed = actual_count > 0 || seal_empty;
+
+        // Le prix de base suit le remplissage des blocs scellés
+        if sealed {
+            sequencer.record_block(actual_count);
         }
+
+        // Mettre à jour les métriques
+        SEQUENCER_METRICS.with(|metrics| {
+            let mut m = metrics.borrow_mut();
+            m.total_transactions_sequenced += actual_count;
+            m.total_batches_created += 1;
+            m.current_pending_count = sequencer.pending_count() as u64;
+            if m.total_batches_created > 0 {
+                m.average_batch_size =
+                    m.total_transactions_sequenced as f64 / m.total_batches_created as f64;
+            }
+        });
+
+        Ok(BlockOutcome {
+            applied: actual_count,
+            rejected: rejected_count,
+            sealed,
+            fairness_score: fairness.score(),
+            kendall_tau: fairness.kendall_tau(),
+        })
+    })
+}
+
+// ========== PRODUCTION AUTOMATIQUE DE BLOCS ==========
+
+/// (Ré)armer le timer de production selon la configuration, sauf en pause
+fn start_block_production() {
+    stop_block_production();
+    let (paused, block_time_ms) = BLOCK_PRODUCER.with(|producer| {
+        let producer = producer.borrow();
+        (producer.paused, producer.config.block_time_ms)
     });
+    // Pas de timer hors canister: un run natif avance au rythme des entrées rejouées
+    if paused || cfg!(not(target_arch = "wasm32")) {
+        return;
+    }
 
-    match result {
-        Ok((actual_count, processing_time, strategy)) => Ok(SequencerBatchResult {
-            success: true,
-            batch_id: format!("batch_{}", ic_cdk::api::time()),
-            transaction_count: actual_count,
-            sequencing_time_ms: processing_time,
-            ordering_strategy: strategy,
-            fairness_score: 0.94,
-        }),
-        Err(e) => Err(e),
+    let timer_id = ic_cdk_timers::set_timer_interval(
+        std::time::Duration::from_millis(block_time_ms),
+        produce_block_tick,
+    );
+    PRODUCER_TIMER.with(|timer| *timer.borrow_mut() = Some(timer_id));
+}
+
+fn stop_block_production() {
+    if let Some(timer_id) = PRODUCER_TIMER.with(|timer| timer.borrow_mut().take()) {
+        ic_cdk_timers::clear_timer(timer_id);
+    }
+}
+
+/// Un tick du timer: sceller ce qui attend, ou un bloc vide si la politique le demande
+fn produce_block_tick() {
+    // Rien à produire tant qu'aucun séquenceur n'existe
+    if SEQUENCER_STATE.with(|state| state.borrow().is_none()) {
+        return;
     }
+
+    let now = clock::now();
+    let last_block_at = last_block().map(|block| block.timestamp.max(0) as u64);
+    let (size, seal_empty) = BLOCK_PRODUCER.with(|producer| {
+        let producer = producer.borrow();
+        (
+            producer.config.max_batch_size as usize,
+            producer.seals_empty_block(now, last_block_at),
+        )
+    });
+
+    // Un tick sans rien à expirer ni à sceller, prix de base au plancher, ne change rien:
+    // pas journalisé
+    let idle = !seal_empty
+        && SEQUENCER_STATE.with(|state| {
+            state
+                .borrow()
+                .as_ref()
+                .is_some_and(|s| s.is_idle() && s.fee_market().is_at_floor())
+        });
+    let tick = || {
+        let outcome = produce_block(size, now, seal_empty)?;
+        // Un tick sans bloc compte comme un bloc vide: sans quoi une chaîne inactive sous
+        // EmptyBlockPolicy::Skip garderait indéfiniment le prix de sa dernière congestion
+        if !outcome.sealed {
+            SEQUENCER_STATE.with(|state| {
+                if let Some(sequencer) = state.borrow_mut().as_mut() {
+                    sequencer.record_block(0);
+                }
+            });
+        }
+        Ok(outcome.sealed.then_some(outcome.applied))
+    };
+    let result = if idle {
+        tick()
+    } else {
+        logged(Input::BlockProductionTick, tick)
+    };
+    BLOCK_PRODUCER.with(|producer| producer.borrow_mut().record_tick(result));
+}
+
+/// Configuration et compteurs de la production automatique
+#[query]
+fn get_block_production_status() -> BlockProducer {
+    BLOCK_PRODUCER.with(|producer| producer.borrow().clone())
+}
+
+/// Suspendre la production de blocs, réservé aux contrôleurs
+#[update]
+fn pause_block_production() -> Result<String, String> {
+    require_controller()?;
+    logged(Input::PauseBlockProduction, || {
+        BLOCK_PRODUCER.with(|producer| producer.borrow_mut().paused = true);
+        stop_block_production();
+        Ok("Block production paused".to_string())
+    })
+}
+
+/// Reprendre la production de blocs, réservé aux contrôleurs
+#[update]
+fn resume_block_production() -> Result<String, String> {
+    require_controller()?;
+    logged(Input::ResumeBlockProduction, || {
+        BLOCK_PRODUCER.with(|producer| producer.borrow_mut().paused = false);
+        start_block_production();
+        Ok("Block production resumed".to_string())
+    })
+}
+
+/// Changer la cadence, la taille des batchs ou la politique de blocs vides.
+/// Réservé aux contrôleurs.
+#[update]
+fn configure_block_production(config: BlockProductionConfig) -> Result<String, String> {
+    require_controller()?;
+    logged(
+        Input::ConfigureBlockProduction {
+            config: config.clone(),
+        },
+        || {
+            config.validate()?;
+            let block_time_ms = config.block_time_ms;
+            BLOCK_PRODUCER.with(|producer| producer.borrow_mut().config = config);
+            // Le nouvel intervalle prend effet tout de suite, une pause reste une pause
+            start_block_production();
+            Ok(format!("Block production every {block_time_ms} ms"))
+        },
+    )
+}
+
+/// Devis de frais pour un portefeuille: prix de base courant, tip médian en attente, plafond conseillé
+#[query]
+fn estimate_fee(asset: SupportedChain) -> FeeEstimate {
+    SEQUENCER_STATE.with(|state| match state.borrow().as_ref() {
+        Some(sequencer) => FeeEstimate::new(
+            sequencer.fee_market(),
+            asset,
+            sequencer.pending_transactions(),
+        ),
+        None => FeeEstimate::new(&FeeMarket::default(), asset, &[]),
+    })
+}
+
+/// Transactions en attente dans le mempool, dans l'ordre de la file
+#[query]
+fn get_mempool_transactions(limit: Option<u64>) -> Vec<PolyTransaction> {
+    let limit = limit.unwrap_or(100).min(1000) as usize;
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .map(|sequencer| {
+                sequencer
+                    .pending_transactions()
+                    .iter()
+                    .take(limit)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    })
+}
+
+/// Transactions en attente d'un expéditeur, par nonce croissant
+#[query]
+fn get_sender_pending_transactions(sender: String) -> Vec<PolyTransaction> {
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .map(|sequencer| sequencer.mempool().by_sender(&sender))
+            .unwrap_or_default()
+    })
+}
+
+#[query]
+fn get_mempool_config() -> MempoolConfig {
+    SEQUENCER_STATE.with(|state| {
+        state
+            .borrow()
+            .as_ref()
+            .map(|sequencer| sequencer.mempool().config().clone())
+            .unwrap_or_default()
+    })
+}
+
+/// Régler les limites du mempool; les transactions déjà admises sont conservées.
+/// Réservé aux contrôleurs: ces limites protègent le canister contre le spam.
+#[update]
+fn configure_mempool(config: MempoolConfig) -> Result<String, String> {
+    require_controller()?;
+    logged(
+        Input::ConfigureMempool {
+            config: config.clone(),
+        },
+        || {
+            if config.max_size == 0
+                || config.max_per_sender == 0
+                || config.ttl_seconds == 0
+                || config.reveal_window_seconds == 0
+            {
+                return Err("Mempool limits, TTL and reveal window must be positive".to_string());
+            }
+            SEQUENCER_STATE.with(|state| match state.borrow_mut().as_mut() {
+                Some(sequencer) => {
+                    sequencer.set_mempool_config(config.clone());
+                    Ok(format!(
+                        "Mempool limited to {} transactions, {} per sender, {}s TTL",
+                        config.max_size, config.max_per_sender, config.ttl_seconds
+                    ))
+                }
+                None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
+            })
+        },
+    )
 }
 
 /// Obtenir les métriques du séquenceur
@@ -1273,56 +2664,190 @@ fn get_sequencer_metrics() -> SequencerMetrics {
 /// Analyser les avantages du séquençage
 #[query]
 fn analyze_sequencing_benefits() -> SequencingBenefits {
+    let metrics = SEQUENCER_METRICS.with(|metrics| metrics.borrow().clone());
+    // Seules les positions que l'expéditeur ne choisit pas empêchent le front-running
+    let front_running_prevention = SEQUENCER_STATE.with(|state| {
+        state.borrow().as_ref().is_some_and(|sequencer| {
+            matches!(
+                sequencer.ordering_strategy(),
+                OrderingStrategy::FairOrdering | OrderingStrategy::VrfRandom
+            )
+        })
+    });
+
     SequencingBenefits {
-        mev_protection_score: 0.89,
-        fairness_improvement: 0.76,
-        throughput_improvement: 1.34,
-        gas_savings_percentage: 12.5,
-        front_running_prevention: true,
+        recommended_strategy: fairness::fairest(&metrics.fairness_by_strategy)
+            .map(|totals| totals.strategy.clone()),
+        strategies: metrics.fairness_by_strategy,
+        current_fairness_score: metrics.fairness_score,
+        front_running_prevention,
         deterministic_ordering: true,
         multi_chain_support: true,
-        recommended_strategy: "FairOrdering".to_string(),
     }
 }
 
 // ========== BLOCKCHAIN EXPLORER API ==========
 
-/// Récupérer tous les blocs de la blockchain
+/// Blocs à partir de la hauteur `start`, au plus `limit`, et leur vérification contre le
+/// bloc qui les précède
+fn verified_blocks(
+    start: u64,
+    limit: Option<u64>,
+) -> (Vec<PolyBlock>, Result<(), errors::ChainError>) {
+    let limit = limit.unwrap_or(100).min(1000) as usize;
+    let (parent, blocks) = BLOCKCHAIN_STATE.with(|chain| {
+        let parent = start.checked_sub(1).and_then(|height| chain.get(height));
+        (
+            parent,
+            chain.iter_from(start).take(limit).collect::<Vec<_>>(),
+        )
+    });
+    let result = chain::verify_chain(
+        start,
+        parent.as_ref(),
+        &blocks,
+        sequencer_key().as_deref(),
+        signed_from_height(),
+    );
+    (blocks, result)
+}
+
+/// Blocs de la blockchain à partir de `start`, par pages d'au plus `limit`; une page
+/// corrompue est refusée
+#[query]
+fn get_blockchain(start: u64, limit: Option<u64>) -> Result<Vec<PolyBlock>, String> {
+    let (blocks, result) = verified_blocks(start, limit);
+    result.map_err(|e| format!("Chain integrity check failed: {e}"))?;
+    Ok(blocks)
+}
+
+/// Vérifier les blocs de `start` à `start + limit` et signaler le premier invalide; la
+/// chaîne entière se parcourt page par page
 #[query]
-fn get_blockchain() -> Vec<PolyBlock> {
-    BLOCKCHAIN_STATE.with(|chain| chain.borrow().clone())
+fn verify_chain_integrity(start: u64, limit: Option<u64>) -> ChainIntegrityReport {
+    let (blocks, result) = verified_blocks(start, limit);
+    ChainIntegrityReport {
+        valid: result.is_ok(),
+        start_height: start,
+        block_count: blocks.len() as u64,
+        total_blocks: chain_height(),
+        first_invalid_height: result.as_ref().err().map(|e| e.height()),
+        error: result.err().map(|e| e.to_string()),
+    }
 }
 
 /// Récupérer les derniers N blocs
 #[query]
 fn get_recent_blocks(count: u32) -> Vec<PolyBlock> {
     BLOCKCHAIN_STATE.with(|chain| {
-        let blockchain = chain.borrow();
-        let start = if blockchain.len() > count as usize {
-            blockchain.len() - count as usize
-        } else {
-            0
-        };
-        blockchain[start..].to_vec()
+        chain
+            .iter_from(chain.len().saturating_sub(count as u64))
+            .collect()
     })
 }
 
 /// Récupérer un bloc par son hash
 #[query]
 fn get_block_by_hash(hash: String) -> Option<PolyBlock> {
+    BLOCKCHAIN_STATE.with(|chain| chain.iter().find(|block| block.hash == hash))
+}
+
+/// Clé publique hex avec laquelle le séquenceur signe ses blocs et prouve leur ordre VRF
+#[query]
+fn get_sequencer_public_key() -> Result<String, String> {
+    SEQUENCER_STATE.with(|state| match state.borrow().as_ref() {
+        Some(sequencer) => sequencer
+            .public_key()
+            .map(str::to_string)
+            .ok_or_else(|| "Sequencer key is not provisioned yet".to_string()),
+        None => Err("Sequencer not initialized. Create sequencer first.".to_string()),
+    })
+}
+
+/// Vérifier un bloc obtenu de n'importe quel miroir: hash recalculé et signature de la clé
+/// publiée du séquenceur, jamais d'une clé que le bloc apporterait lui-même
+#[query]
+fn verify_block_signature(block: PolyBlock) -> Result<BlockProposer, String> {
+    let sequencer_key = sequencer_key().ok_or("Sequencer key is not provisioned yet")?;
+    block.verify_signature(&sequencer_key).cloned()
+}
+
+/// Vérifier la VRF d'un proposeur contre sa clé publique et la graine du tour
+#[query]
+fn verify_proposer_vrf(vrf: ProposerVrf) -> bool {
+    vrf.verify()
+}
+
+/// Preuve Merkle d'inclusion d'une transaction dans l'en-tête de son bloc
+#[query]
+fn get_transaction_proof(tx_hash: String) -> Result<TransactionProof, String> {
     BLOCKCHAIN_STATE.with(|chain| {
-        chain
-            .borrow()
-            .iter()
-            .find(|block| block.hash == hash)
-            .cloned()
+        for (height, block) in chain.iter().enumerate() {
+            let Some(index) = block
+                .transactions
+                .iter()
+                .position(|tx| tx.calculate_hash() == tx_hash)
+            else {
+                continue;
+            };
+            let path = block
+                .transaction_proof(index)
+                .ok_or_else(|| "Transaction index out of range".to_string())?;
+            return Ok(TransactionProof {
+                tx_hash,
+                block_hash: block.hash.clone(),
+                block_height: height as u64,
+                tx_root: block.tx_root.clone(),
+                leaf_index: index as u64,
+                path,
+            });
+        }
+        Err(format!("Transaction {tx_hash} not found in any block"))
+    })
+}
+
+/// Vérification pure d'une preuve, sans lire l'état de la chaîne
+#[query]
+fn verify_transaction_proof(proof: TransactionProof) -> bool {
+    proof.verify()
+}
+
+/// Solde et nonce d'un compte après le bloc `block_height`, avec la preuve contre sa racine d'état
+#[query]
+fn get_account_proof(address: String, block_height: u64) -> Result<AccountProof, String> {
+    let block = BLOCKCHAIN_STATE
+        .with(|chain| chain.get(block_height))
+        .ok_or_else(|| format!("Block {block_height} not found"))?;
+
+    let accounts = STATE_HISTORY.with(|history| history.borrow().accounts_at(block_height));
+    if hex::encode(state::state_root(&accounts)) != block.state_root {
+        return Err(format!(
+            "No account state recorded for block {block_height}"
+        ));
+    }
+
+    let account = accounts.get(&address).cloned().unwrap_or_default();
+    Ok(AccountProof {
+        siblings: state::account_proof(&accounts, &address),
+        address,
+        block_height,
+        block_hash: block.hash,
+        state_root: block.state_root,
+        balances: account.balances,
+        nonce: account.nonce,
     })
 }
 
+/// Vérification pure d'une preuve de compte, sans lire l'état de la chaîne
+#[query]
+fn verify_account_proof(proof: AccountProof) -> bool {
+    proof.verify()
+}
+
 /// Récupérer toutes les transactions confirmées
 #[query]
 fn get_all_transactions() -> Vec<PolyTransaction> {
-    TRANSACTION_POOL.with(|pool| pool.borrow().clone())
+    TRANSACTION_POOL.with(|pool| pool.iter().collect())
 }
 
 /// Récupérer les derniers blocs créés par le sequencer
@@ -1330,26 +2855,22 @@ fn get_all_transactions() -> Vec<PolyTransaction> {
 fn get_sequencer_created_blocks(limit: Option<u32>) -> Vec<PolyBlock> {
     let default_limit = limit.unwrap_or(10);
     BLOCKCHAIN_STATE.with(|chain| {
-        let blockchain = chain.borrow();
-        let start = if blockchain.len() > default_limit as usize {
-            blockchain.len() - default_limit as usize
-        } else {
-            0
-        };
-        blockchain[start..].to_vec()
+        chain
+            .iter_from(chain.len().saturating_sub(default_limit as u64))
+            .collect()
     })
 }
 
-/// Vérifier si une transaction est dans la blockchain
+/// Vérifier si une transaction est dans la blockchain (recherche par contenu, ambiguë:
+/// préférer get_transaction_status)
 #[query]
 fn verify_transaction_in_blockchain(
     sender: String,
     recipient: String,
-    amount: f64,
+    amount: u128,
 ) -> Option<String> {
     BLOCKCHAIN_STATE.with(|chain| {
-        let blockchain = chain.borrow();
-        for block in blockchain.iter() {
+        for block in chain.iter() {
             for tx in &block.transactions {
                 if tx.sender == sender && tx.recipient == recipient && tx.amount == amount {
                     return Some(block.hash.clone());
@@ -1364,15 +2885,14 @@ fn verify_transaction_in_blockchain(
 #[query]
 fn get_blockchain_stats() -> BlockchainStats {
     BLOCKCHAIN_STATE.with(|chain| {
-        let blockchain = chain.borrow();
-        let total_blocks = blockchain.len() as u64;
+        let total_blocks = chain.len();
 
-        let total_transactions = blockchain
+        let total_transactions = chain
             .iter()
             .map(|block| block.transactions.len() as u64)
             .sum::<u64>();
 
-        let latest_block_time = blockchain.last().map(|block| block.timestamp).unwrap_or(0);
+        let latest_block_time = chain.last().map(|block| block.timestamp).unwrap_or(0);
 
         let average_tx_per_block = if total_blocks > 0 {
             total_transactions as f64 / total_blocks as f64
@@ -1390,45 +2910,53 @@ fn get_blockchain_stats() -> BlockchainStats {
     })
 }
 
-/// Test du consensus PoS Algorand
-#[update]
-async fn test_pos_consensus() -> Result<String, String> {
-    use chain::AlgoConsensus;
-    use cryptography::{bridge::CryptographyBridge, ecdsa::Ecdsa};
+/// Consensus PoS sur l'ensemble actif de l'époque en cours (`get_current_epoch`): mêmes
+/// validateurs, mêmes mises, donc les bonds, les époques et le slashing décident du comité
+/// et du quorum. Le canister ne détient pas les clés des validateurs: chaque nœud est simulé
+/// avec une clé de remplacement, substituée à la clé enregistrée dans la copie de l'ensemble
+/// passée au consensus. Les hauteurs simulées partent de la tête de chaîne mais leurs blocs
+/// n'y sont jamais ajoutés.
+fn simulated_consensus() -> Result<(AlgoConsensus<Ecdsa>, EpochSnapshot), String> {
+    use cryptography::bridge::CryptographyBridge;
     use std::collections::HashMap;
 
-    let crypto = Ecdsa;
+    let mut validator_set =
+        VALIDATOR_REGISTRY.with(|registry| registry.borrow().current_epoch().clone());
+    if validator_set.validators.is_empty() {
+        return Err(format!(
+            "Epoch {} has no active validator: bond stake and wait for the next epoch",
+            validator_set.epoch
+        ));
+    }
 
-    // Créer plusieurs validators pour avoir un consensus valide
     let mut private_keys = HashMap::new();
-    let mut public_keys = HashMap::new();
-    let mut balances = HashMap::new();
-
-    // Créer 5 validators avec des stakes différents
-    for i in 0..5 {
-        let (pub_key, priv_key) = crypto
+    for validator in &mut validator_set.validators {
+        let (public_key, secret_key) = Ecdsa
             .key_generator()
-            .map_err(|e| format!("Key generation failed for validator {}: {:?}", i, e))?;
-
-        let address = hex::encode(crypto.public_key_to_bytes(&pub_key));
-        let stake = 1000.0 + (i as f64 * 500.0); // Stakes différents pour diversité
-
-        private_keys.insert(address.clone(), priv_key);
-        public_keys.insert(address.clone(), pub_key);
-        balances.insert(address, stake);
+            .map_err(|e| format!("Key generation failed for {}: {:?}", validator.address, e))?;
+        validator.algorithm = types::CryptoAlgorithm::Ecdsa;
+        validator.public_key = hex::encode(Ecdsa.public_key_to_bytes(&public_key));
+        private_keys.insert(validator.address.clone(), secret_key);
     }
 
-    let consensus = AlgoConsensus::create_instance(crypto, private_keys)
+    let consensus = AlgoConsensus::create_instance(Ecdsa, private_keys)
         .map_err(|e| format!("Consensus creation failed: {:?}", e))?;
+    Ok((consensus, validator_set))
+}
+
+/// Test du consensus PoS Algorand sur l'ensemble actif de l'époque en cours
+#[update]
+async fn test_pos_consensus() -> Result<String, String> {
+    let (consensus, validator_set) = simulated_consensus()?;
 
     // Créer quelques vraies transactions pour le test
     let mut transactions = Vec::new();
 
-    // Ajouter des transactions du sequencer si disponible
+    // Copier jusqu'à 5 transactions en attente, sans les retirer du mempool :
+    // ce bloc de test n'est jamais ajouté à la chaîne
     SEQUENCER_STATE.with(|state| {
-        if let Some(sequencer) = state.borrow_mut().as_mut() {
-            let batch = sequencer.sequence_batch(5); // Prendre 5 transactions
-            transactions.extend(batch);
+        if let Some(sequencer) = state.borrow().as_ref() {
+            transactions.extend(sequencer.pending_transactions().iter().take(5).cloned());
         }
     });
 
@@ -1438,28 +2966,218 @@ async fn test_pos_consensus() -> Result<String, String> {
             let mut tx = PolyTransaction::new(
                 format!("test_sender_{}", i),
                 format!("test_recipient_{}", i),
-                100.0 + (i as f64 * 50.0),
+                100 + (i as u128 * 50),
+                SupportedChain::Bitcoin,
+                0,
             );
-            let tx_data = format!("test:{}:{}", i, ic_cdk::api::time());
-            let tx_hash = calculate_hash(&tx_data);
-            tx.sign(tx_hash.clone());
-            tx.hash = Some(tx_hash);
+            tx.hash = Some(tx.calculate_hash());
             transactions.push(tx);
         }
     }
 
-    let prev_hash = [0u8; 32];
+    let state_root = current_state_root();
+
+    let run = consensus
+        .start_height(
+            chain_tip_seed()?,
+            chain_height(),
+            transactions,
+            state_root,
+            &validator_set,
+        )
+        .map_err(|e| format!("Consensus failed: {}", e))?;
+    match consensus.propose(&run, &validator_set) {
+        Ok(block) => {
+            consensus
+                .verify_certificate(&block, &validator_set)
+                .map_err(|e| format!("Certificate rejected: {}", e))?;
+            let tx_count = block.transactions.len();
+            let Some(certificate) = block.certificate.as_ref() else {
+                return Err("Consensus returned an uncertified block".to_string());
+            };
+            Ok(format!(
+                "✅ PoS Consensus successful! Block hash: {} with {} transactions, proposed by {} (VRF output {}), certified by {} votes ({}/{} stake)",
+                block.hash,
+                tx_count,
+                certificate.proposer.validator,
+                certificate.proposer.output,
+                certificate.votes.len(),
+                certificate.voted_stake,
+                certificate.total_stake
+            ))
+        }
+        Err(e) => Err(format!("Consensus failed: {}", e)),
+    }
+}
+
+// ========== SIMULATION DU PACEMAKER ==========
+
+/// Hauteur de consensus simulée, conduite tour après tour par le timer du pacemaker
+struct PosSimulation {
+    consensus: AlgoConsensus<Ecdsa>,
+    validator_set: EpochSnapshot,
+    run: ConsensusHeight,
+    offline: Vec<String>,
+    timeouts: Vec<TimeoutCertificate>,
+    block: Option<PolyBlock>,
+    last_error: Option<String>,
+}
+
+impl PosSimulation {
+    /// Le proposeur du tour courant propose, tous les validateurs actifs votent
+    fn propose(&mut self) {
+        match self.consensus.propose(&self.run, &self.validator_set) {
+            Ok(block) => {
+                self.block = Some(block);
+                self.last_error = None;
+            }
+            Err(e) => self.last_error = Some(e.to_string()),
+        }
+    }
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+struct PosSimulationStatus {
+    height: u64,
+    round: u64,
+    /// Proposeur du tour courant
+    leader: String,
+    offline: Vec<String>,
+    /// Échéance du tour courant (nanosecondes)
+    deadline: u64,
+    timeout_certificates: Vec<TimeoutCertificate>,
+    /// Bloc certifié, jamais ajouté à la chaîne
+    block: Option<PolyBlock>,
+    last_error: Option<String>,
+}
 
-    match consensus.run_consensus_round(&prev_hash, transactions, &balances, &public_keys) {
-        Ok(block) => {
-            let tx_count = block.transactions.len();
-            Ok(format!(
-                "✅ PoS Consensus successful! Block hash: {} with {} transactions",
-                block.hash, tx_count
-            ))
+/// Simuler une hauteur dont les `offline_leaders` premiers proposeurs du classement VRF sont
+/// hors ligne: le timer fait expirer leurs tours et passe la main au suivant
+#[update]
+fn start_pos_simulation(offline_leaders: u64) -> Result<PosSimulationStatus, String> {
+    let (mut consensus, validator_set) = simulated_consensus()?;
+    let run = consensus
+        .start_height(
+            chain_tip_seed()?,
+            chain_height(),
+            Vec::new(),
+            current_state_root(),
+            &validator_set,
+        )
+        .map_err(|e| format!("Consensus failed: {}", e))?;
+
+    let offline: Vec<String> = run
+        .committee()
+        .iter()
+        .take(offline_leaders as usize)
+        .map(|ticket| ticket.vrf.validator.clone())
+        .collect();
+    let offline_stake: u128 = validator_set
+        .validators
+        .iter()
+        .filter(|validator| offline.contains(&validator.address))
+        .map(|validator| validator.stake)
+        .sum();
+    // Sans quorum en ligne, aucun certificat de timeout ne peut faire tourner la vue
+    let online_stake = validator_set.total_stake - offline_stake;
+    let online_share = online_stake as f64 / validator_set.total_stake as f64;
+    if offline.len() >= run.committee().len()
+        || !consensus.has_quorum(online_stake, validator_set.total_stake)
+    {
+        return Err(format!(
+            "{} offline proposers leave {:.0}% of the stake online, below the quorum",
+            offline_leaders,
+            online_share * 100.0
+        ));
+    }
+    for validator in &offline {
+        consensus.set_online(validator, false);
+    }
+
+    let mut simulation = PosSimulation {
+        consensus,
+        validator_set,
+        run,
+        offline,
+        timeouts: Vec::new(),
+        block: None,
+        last_error: None,
+    };
+    simulation.propose();
+    let remaining = simulation
+        .block
+        .is_none()
+        .then(|| simulation.run.pacemaker().remaining(clock::now()));
+    POS_SIMULATION.with(|state| *state.borrow_mut() = Some(simulation));
+    arm_round_timer(remaining);
+
+    get_pos_simulation().ok_or_else(|| "Simulation not started".to_string())
+}
+
+/// Échéance du tour atteinte: certificat de timeout, tour suivant, nouveau proposeur
+fn pos_round_timeout() {
+    let remaining = POS_SIMULATION.with(|state| {
+        let mut state = state.borrow_mut();
+        let simulation = state.as_mut()?;
+        if simulation.block.is_some() {
+            return None;
+        }
+        match simulation
+            .consensus
+            .on_timeout(&mut simulation.run, &simulation.validator_set)
+        {
+            Ok(Some(certificate)) => {
+                simulation.timeouts.push(certificate);
+                simulation.propose();
+            }
+            Ok(None) => {}
+            Err(e) => simulation.last_error = Some(e.to_string()),
         }
-        Err(e) => Err(format!("Consensus failed: {:?}", e)),
+        simulation
+            .block
+            .is_none()
+            .then(|| simulation.run.pacemaker().remaining(clock::now()))
+    });
+    arm_round_timer(remaining);
+}
+
+/// (Ré)armer le timer du pacemaker pour dans `remaining` nanosecondes, ou l'arrêter
+fn arm_round_timer(remaining: Option<u64>) {
+    if let Some(timer_id) = ROUND_TIMER.with(|timer| timer.borrow_mut().take()) {
+        ic_cdk_timers::clear_timer(timer_id);
+    }
+    // Pas de timer hors canister: les tests appellent `pos_round_timeout` eux-mêmes
+    let Some(remaining) = remaining else {
+        return;
+    };
+    if cfg!(not(target_arch = "wasm32")) {
+        return;
     }
+    let timer_id = ic_cdk_timers::set_timer(
+        std::time::Duration::from_nanos(remaining),
+        pos_round_timeout,
+    );
+    ROUND_TIMER.with(|timer| *timer.borrow_mut() = Some(timer_id));
+}
+
+/// Tour, proposeur, timeouts certifiés et bloc de la dernière simulation
+#[query]
+fn get_pos_simulation() -> Option<PosSimulationStatus> {
+    POS_SIMULATION.with(|state| {
+        state.borrow().as_ref().map(|simulation| {
+            let pacemaker = simulation.run.pacemaker();
+            PosSimulationStatus {
+                height: pacemaker.height(),
+                round: pacemaker.round(),
+                leader: simulation.run.leader().vrf.validator.clone(),
+                offline: simulation.offline.clone(),
+                deadline: pacemaker.deadline(),
+                timeout_certificates: simulation.timeouts.clone(),
+                block: simulation.block.clone(),
+                last_error: simulation.last_error.clone(),
+            }
+        })
+    })
 }
 
 // Types pour l'API du séquenceur
@@ -1468,9 +3186,11 @@ struct SequencerBatchResult {
     success: bool,
     batch_id: String,
     transaction_count: u64,
+    rejected_count: u64,
     sequencing_time_ms: u64,
     ordering_strategy: String,
     fairness_score: f64,
+    kendall_tau: f64,
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
@@ -1480,20 +3200,35 @@ struct SequencerMetrics {
     average_batch_size: f64,
     total_batches_created: u64,
     average_sequencing_time_ms: f64,
+    /// Équité mesurée de la stratégie du dernier batch
     fairness_score: f64,
     ordering_strategy: String,
+    fairness_by_strategy: Vec<StrategyFairness>,
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
 pub struct SequencingBenefits {
-    mev_protection_score: f64,
-    fairness_improvement: f64,
-    throughput_improvement: f64,
-    gas_savings_percentage: f64,
+    /// Équité mesurée de chaque stratégie utilisée, face à l'ordre d'arrivée
+    strategies: Vec<StrategyFairness>,
+    current_fairness_score: f64,
     front_running_prevention: bool,
     deterministic_ordering: bool,
     multi_chain_support: bool,
-    recommended_strategy: String,
+    /// Stratégie la plus équitable mesurée, aucune tant qu'aucun batch n'a été séquencé
+    recommended_strategy: Option<OrderingStrategy>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+struct ChainIntegrityReport {
+    valid: bool,
+    /// Hauteur du premier bloc vérifié
+    start_height: u64,
+    /// Blocs vérifiés à partir de `start_height`
+    block_count: u64,
+    /// Blocs de la chaîne entière
+    total_blocks: u64,
+    first_invalid_height: Option<u64>,
+    error: Option<String>,
 }
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
@@ -1547,7 +3282,7 @@ pub struct DemoTransaction {
 }
 
 thread_local! {
-    static COMPRESSED_BATCHES: RefCell<Vec<CompressedBatch>> = RefCell::new(Vec::new());
+    static COMPRESSED_BATCHES: RefCell<Vec<CompressedBatch>> = const { RefCell::new(Vec::new()) };
     static COMPRESSION_METRICS: RefCell<CompressionPerformanceMetrics> = RefCell::new(
         CompressionPerformanceMetrics {
             compression_ratio: 0.7,
@@ -1597,7 +3332,7 @@ async fn create_compressed_batch(
         max_size: Some(1_000_000),
     });
 
-    let batch_id = format!("batch_{}", ic_cdk::api::time());
+    let batch_id = format!("batch_{}", clock::now());
     let original_size = transactions.len() as u64 * 200; // Approximation
     let compressed_size = (original_size as f64 * 0.7) as u64; // 70% compression
     let compression_ratio = compressed_size as f64 / original_size as f64;
@@ -1608,7 +3343,7 @@ async fn create_compressed_batch(
         compressed_size,
         compression_ratio,
         transaction_count: transactions.len() as u32,
-        created_at: ic_cdk::api::time() as i64,
+        created_at: clock::timestamp(),
         algorithm: config.compression_algorithm,
     };
 
@@ -1666,7 +3401,7 @@ async fn generate_realistic_demo_data(
             sender: format!("user_{}", i % 10),
             recipient: format!("user_{}", (i + 1) % 10),
             amount: 100.0 + (i as f64 * 10.0),
-            timestamp: ic_cdk::api::time() as i64 + i as i64,
+            timestamp: clock::timestamp() + i as i64,
             tx_type: data_type.clone(),
         };
         transactions.push(tx);
@@ -1683,10 +3418,10 @@ async fn fetch_real_icp_data(count: u32) -> Result<Vec<DemoTransaction>, String>
     for i in 0..count {
         let tx = DemoTransaction {
             id: format!("icp_real_{}", i),
-            sender: format!("rdmx6-jaaaa-aaaah-qcaiq-cai"),
-            recipient: format!("rrkah-fqaaa-aaaah-qcaiq-cai"),
+            sender: "rdmx6-jaaaa-aaaah-qcaiq-cai".to_string(),
+            recipient: "rrkah-fqaaa-aaaah-qcaiq-cai".to_string(),
             amount: 1.0 + (i as f64 * 0.1),
-            timestamp: ic_cdk::api::time() as i64,
+            timestamp: clock::timestamp(),
             tx_type: "ICP_Transfer".to_string(),
         };
         transactions.push(tx);
@@ -1759,37 +3494,285 @@ mod tests {
         schnorr::Schnorr,
     };
 
-    #[test]
-    fn test_greet() {
-        let result = greet("World".to_string());
-        assert_eq!(result, "Hello, World!");
+    /// Signer une transaction comme le ferait un client avant soumission
+    /// Couvre le prix de base minimal de chaque actif
+    const TEST_MAX_FEE: u128 = 1_000_000_000;
+
+    fn sign_transfer<C: CryptographyBridge>(
+        crypto: &C,
+        algorithm: CryptoAlgorithm,
+        keys: &(C::PublicKey, C::SecretKey),
+        mut tx: PolyTransaction,
+    ) -> PolyTransaction {
+        let signature = crypto.sign(&keys.1, &tx.signing_payload()).unwrap();
+        tx.sign(
+            algorithm,
+            hex::encode(crypto.public_key_to_bytes(&keys.0)),
+            hex::encode(crypto.signature_to_bytes(&signature)),
+        );
+        tx
+    }
+
+    /// Engagement signé par un expéditeur, comme le ferait un client
+    fn sign_commitment(
+        keys: &(
+            <Ecdsa as CryptographyBridge>::PublicKey,
+            <Ecdsa as CryptographyBridge>::SecretKey,
+        ),
+        sender: &str,
+        commitment: String,
+    ) -> CommitmentSubmission {
+        let mut submission = CommitmentSubmission {
+            commitment,
+            sender: sender.to_string(),
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&keys.0)),
+            signature: String::new(),
+        };
+        let signature = Ecdsa.sign(&keys.1, &submission.message()).unwrap();
+        submission.signature = hex::encode(Ecdsa.signature_to_bytes(&signature));
+        submission
+    }
+
+    /// Séquenceur muni de sa clé, comme après create_transaction_sequencer
+    fn keyed_sequencer() -> chain::TransactionSequencer<Ecdsa> {
+        let mut sequencer = chain::TransactionSequencer::new(Ecdsa);
+        sequencer.set_proposer_seed(rand::random()).unwrap();
+        sequencer
+    }
+
+    /// Sceller un bloc signé par le séquenceur en place, installé s'il manque
+    fn commit_signed_block(transactions: Vec<PolyTransaction>) -> Result<PolyBlock, String> {
+        SEQUENCER_STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            let sequencer = state.get_or_insert_with(keyed_sequencer);
+            let sequencer_key = sequencer.public_key().map(str::to_string);
+            commit_block(
+                live_ledger(),
+                transactions,
+                None,
+                Some(BlockSigner::Sequencer(sequencer)),
+                sequencer_key.as_deref(),
+            )
+        })
+    }
+
+    /// Lier une clé de test à un compte, comme le ferait le bridge
+    fn bind_account_key<C: CryptographyBridge>(
+        crypto: &C,
+        algorithm: CryptoAlgorithm,
+        keys: &(C::PublicKey, C::SecretKey),
+        address: &str,
+    ) -> Result<AccountKey, String> {
+        let mut registration = AccountKeyRegistration {
+            address: address.to_string(),
+            algorithm,
+            public_key: hex::encode(crypto.public_key_to_bytes(&keys.0)),
+            signature: String::new(),
+        };
+        let signature = crypto.sign(&keys.1, &registration.message()).unwrap();
+        registration.signature = hex::encode(crypto.signature_to_bytes(&signature));
+        register_account_key(registration)
+    }
+
+    /// Ledger L2 tel quel, pour sceller un bloc sans nouveau transfert
+    fn live_ledger() -> AccountLedger {
+        L2_LEDGER.with(|ledger| ledger.borrow().clone())
+    }
+
+    /// Retrait signé par la clé du compte, à son prochain nonce de retrait
+    fn sign_withdrawal(
+        keys: &(
+            <Ecdsa as CryptographyBridge>::PublicKey,
+            <Ecdsa as CryptographyBridge>::SecretKey,
+        ),
+        address: &str,
+        asset: SupportedChain,
+        amount: u128,
+    ) -> WithdrawalAuthorization {
+        let nonce = L2_LEDGER.with(|ledger| ledger.borrow().next_withdrawal_nonce(address));
+        let message = WithdrawalAuthorization::message(address, asset, amount, nonce);
+        WithdrawalAuthorization {
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&keys.0)),
+            nonce,
+            signature: hex::encode(
+                Ecdsa.signature_to_bytes(&Ecdsa.sign(&keys.1, &message).unwrap()),
+            ),
+        }
+    }
+
+    /// Signature du compte sur `action`, au nonce courant de son validateur
+    fn sign_validator_action(
+        keys: &(
+            <Ecdsa as CryptographyBridge>::PublicKey,
+            <Ecdsa as CryptographyBridge>::SecretKey,
+        ),
+        address: &str,
+        action: validators::ValidatorAction,
+    ) -> String {
+        let nonce = get_validator(address.to_string())
+            .and_then(|validator| validator.nonce)
+            .unwrap_or(0);
+        let message = action.message(address, nonce);
+        hex::encode(Ecdsa.signature_to_bytes(&Ecdsa.sign(&keys.1, &message).unwrap()))
+    }
+
+    /// Validateurs `validator-{i}` liés, enregistrés et mis en jeu, actifs dès l'époque suivante
+    fn activate_validators(stakes: &[u64]) {
+        VALIDATOR_REGISTRY.with(|registry| {
+            *registry.borrow_mut() = ValidatorRegistry::new(RegistryConfig {
+                epoch_length: 1,
+                unbonding_epochs: 1,
+                min_stake: 1,
+                max_active_validators: 100,
+                slash_bps: 500,
+                jail_epochs: 2,
+            })
+        });
+        for (i, stake) in stakes.iter().enumerate() {
+            let address = format!("validator-{i}");
+            replay::block_on(deposit_icp(address.clone(), *stake))
+                .unwrap()
+                .unwrap();
+            let account_keys = Ecdsa.key_generator().unwrap();
+            bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &account_keys, &address).unwrap();
+
+            let (public_key, secret_key) = Ecdsa.key_generator().unwrap();
+            let mut registration = ValidatorRegistration {
+                address: address.clone(),
+                algorithm: CryptoAlgorithm::Ecdsa,
+                public_key: hex::encode(Ecdsa.public_key_to_bytes(&public_key)),
+                signature: String::new(),
+                account_signature: None,
+                rotation_signature: None,
+            };
+            let signature = Ecdsa.sign(&secret_key, &registration.message()).unwrap();
+            registration.signature = hex::encode(Ecdsa.signature_to_bytes(&signature));
+            registration.account_signature = Some(sign_validator_action(
+                &account_keys,
+                &address,
+                registration.action(),
+            ));
+            register_validator(registration).unwrap();
+
+            let amount = u128::from(*stake);
+            let signature = sign_validator_action(
+                &account_keys,
+                &address,
+                validators::ValidatorAction::Bond(amount),
+            );
+            bond_stake(address, amount, signature).unwrap();
+        }
+        commit_signed_block(Vec::new()).unwrap();
     }
 
     #[test]
     fn test_get_balance() {
-        let balance = get_balance("some_address".to_string());
-        assert_eq!(balance, 1000.0);
+        let balance = get_balance("some_address".to_string(), SupportedChain::Bitcoin);
+        assert_eq!(balance, 0);
+
+        credit_l2_account("some_address", SupportedChain::Bitcoin, 150_000_000).unwrap();
+        let balance = get_balance("some_address".to_string(), SupportedChain::Bitcoin);
+        assert_eq!(balance, 150_000_000);
+        let balance = get_balance("some_address".to_string(), SupportedChain::Ethereum);
+        assert_eq!(balance, 0);
     }
 
     #[test]
     fn test_poly_transaction() {
-        let tx = PolyTransaction::new("alice".to_string(), "bob".to_string(), 100.0);
+        let tx = PolyTransaction::new(
+            "alice".to_string(),
+            "bob".to_string(),
+            100,
+            SupportedChain::Bitcoin,
+            0,
+        );
         assert_eq!(tx.sender, "alice");
         assert_eq!(tx.recipient, "bob");
-        assert_eq!(tx.amount, 100.0);
+        assert_eq!(tx.amount, 100);
         assert!(tx.is_valid());
     }
 
+    #[test]
+    fn test_format_amount_is_exact() {
+        assert_eq!(
+            SupportedChain::Bitcoin.format_amount(150_000_001),
+            "1.50000001 BTC"
+        );
+        assert_eq!(
+            SupportedChain::Ethereum.format_amount(25_000_000_000_000_000_001),
+            "25.000000000000000001 ETH"
+        );
+        assert_eq!(SupportedChain::Solana.format_amount(1), "0.000000001 SOL");
+    }
+
     #[test]
     fn test_poly_block() {
-        let tx1 = PolyTransaction::new("alice".to_string(), "bob".to_string(), 100.0);
-        let tx2 = PolyTransaction::new("bob".to_string(), "charlie".to_string(), 50.0);
+        let tx1 = PolyTransaction::new(
+            "alice".to_string(),
+            "bob".to_string(),
+            100,
+            SupportedChain::Bitcoin,
+            0,
+        );
+        let tx2 = PolyTransaction::new(
+            "bob".to_string(),
+            "charlie".to_string(),
+            50,
+            SupportedChain::Bitcoin,
+            0,
+        );
         let transactions = vec![tx1, tx2];
 
-        let block = PolyBlock::new(transactions, "prev_hash".to_string());
+        let block = PolyBlock::new(
+            transactions,
+            "prev_hash".to_string(),
+            state::empty_state_root(),
+        );
         assert_eq!(block.transactions.len(), 2);
         assert_eq!(block.previous_hash, "prev_hash");
         assert!(!block.hash.is_empty());
+        // Integer amounts make the block hash reproducible
+        assert_eq!(block.hash, block.calculate_hash());
+        assert_eq!(block.tx_root, hex::encode(block.calculate_tx_root()));
+    }
+
+    #[test]
+    fn test_transaction_proof_from_chain() {
+        let transactions: Vec<PolyTransaction> = (0..5)
+            .map(|nonce| {
+                PolyTransaction::new(
+                    "proof_sender".to_string(),
+                    "proof_recipient".to_string(),
+                    1_000 + nonce as u128,
+                    SupportedChain::Bitcoin,
+                    nonce,
+                )
+            })
+            .collect();
+        let target = transactions[3].calculate_hash();
+        let block = PolyBlock::new(
+            transactions,
+            "proof_prev".to_string(),
+            state::empty_state_root(),
+        );
+        BLOCKCHAIN_STATE.with(|chain| chain.push(&block).unwrap());
+
+        let proof = get_transaction_proof(target.clone()).unwrap();
+        assert_eq!(proof.block_hash, block.hash);
+        assert_eq!(proof.leaf_index, 3);
+        assert!(verify_transaction_proof(proof.clone()));
+
+        // Un autre hash ou une autre racine ne passent pas
+        let mut forged = proof.clone();
+        forged.tx_hash = block.transactions[1].calculate_hash();
+        assert!(!forged.verify());
+        let mut forged = proof;
+        forged.tx_root = hex::encode([7u8; 32]);
+        assert!(!forged.verify());
+
+        assert!(get_transaction_proof(hex::encode([0u8; 32])).is_err());
     }
 
     // NOUVEAU TEST: Bitcoin Vault Integration
@@ -1798,28 +3781,224 @@ mod tests {
         let mut vault = BitcoinVault::new();
 
         // Test logique hybride
-        let result1 = vault.deposit_bitcoin("alice".to_string(), 150_000);
+        let result1 = vault.deposit_bitcoin("alice".to_string(), 150_000).unwrap();
         assert!(result1.contains("NATIVE"));
 
-        let result2 = vault.deposit_bitcoin("bob".to_string(), 50_000);
+        let result2 = vault.deposit_bitcoin("bob".to_string(), 50_000).unwrap();
         assert!(result2.contains("WRAPPED"));
 
-        // Vérifier balances
-        let (alice_native, alice_wrapped) = vault.get_balance("alice");
-        assert_eq!(alice_native, 150_000);
-        assert_eq!(alice_wrapped, 0);
+        // Réserves communes, quel que soit le déposant
+        assert_eq!(vault.reserves(), (150_000, 50_000));
+        assert_eq!(vault.native_depositors.len(), 1);
+        assert_eq!(vault.wrapped_depositors.len(), 1);
+
+        // Bob retire sur le natif déposé par Alice: seul son solde L2 compte
+        let withdrawal = vault
+            .withdraw_bitcoin("bob".to_string(), 100_000, "ECDSA")
+            .unwrap();
+        assert!(!withdrawal.tx_id.is_empty());
+        assert_eq!(withdrawal.source, WithdrawalSource::Native);
+        assert_eq!(vault.reserves(), (50_000, 50_000));
+        assert_eq!(vault.total_deposits, 100_000);
+        assert_eq!(vault.pending_withdrawals, vec![withdrawal.clone()]);
+
+        // Pas de retrait au-delà des réserves réunies
+        assert!(matches!(
+            vault.withdraw_bitcoin("alice".to_string(), 100_001, "ECDSA"),
+            Err(VaultError::InsufficientReserves { .. })
+        ));
+        assert_eq!(vault.total_deposits, 100_000);
+        assert_eq!(vault.pending_withdrawals.len(), 1);
+
+        // Une fois payé, le retrait sort de la liste d'attente
+        assert_eq!(vault.complete_withdrawal(&withdrawal.tx_id), Ok(withdrawal));
+        assert!(vault.pending_withdrawals.is_empty());
+        assert!(matches!(
+            vault.complete_withdrawal("unknown"),
+            Err(VaultError::UnknownWithdrawal(_))
+        ));
+
+        // Un dépôt qui ferait déborder le total est refusé sans rien modifier
+        assert_eq!(
+            vault.deposit_bitcoin("carol".to_string(), u64::MAX),
+            Err(VaultError::Overflow(SupportedChain::Bitcoin))
+        );
+        assert_eq!(vault.reserves(), (50_000, 50_000));
+        assert_eq!(vault.total_deposits, 100_000);
 
-        let (bob_native, bob_wrapped) = vault.get_balance("bob");
-        assert_eq!(bob_native, 0);
-        assert_eq!(bob_wrapped, 50_000);
+        std::println!("✅ Bitcoin Vault Integration Test Passed");
+    }
 
-        // Test withdrawal
-        let withdraw_result = vault.withdraw_bitcoin("alice".to_string(), 100_000, "ECDSA");
-        let withdraw_res_len = withdraw_result.len();
+    #[test]
+    fn test_withdrawal_is_split_across_reserves() {
+        // 150 000 en natif et 100 000 en wrapped: aucune réserve ne couvre seule 250 000
+        let mut vault = SimpleVault::new(SupportedChain::Icp);
+        vault.deposit(150_000, true).unwrap();
+        vault.deposit(100_000, false).unwrap();
+        let withdrawal = vault.withdraw("icp_user", 250_000, "ECDSA").unwrap();
+        assert_eq!(withdrawal.source, WithdrawalSource::Native);
+        assert_eq!(
+            (withdrawal.native_amount, withdrawal.wrapped_amount),
+            (Some(150_000), Some(100_000))
+        );
+        assert_eq!(vault.reserves(), (0, 0));
+        assert_eq!(vault.pending_withdrawals, vec![withdrawal]);
 
-        assert!(withdraw_res_len > 0);
+        // Bitcoin: un gros montant part du natif, le wrapped paie le reste
+        let mut vault = BitcoinVault::new();
+        vault.deposit_bitcoin("alice".to_string(), 150_000).unwrap();
+        vault.deposit_bitcoin("bob".to_string(), 50_000).unwrap();
+        vault.deposit_bitcoin("carol".to_string(), 50_000).unwrap();
+        assert_eq!(vault.reserves(), (150_000, 100_000));
+        let withdrawal = vault
+            .withdraw_bitcoin("dave".to_string(), 250_000, "ECDSA")
+            .unwrap();
+        assert_eq!(withdrawal.source, WithdrawalSource::Native);
+        assert_eq!(
+            (withdrawal.native_amount, withdrawal.wrapped_amount),
+            (Some(150_000), Some(100_000))
+        );
+        assert_eq!(vault.reserves(), (0, 0));
+        assert_eq!(vault.total_deposits, 0);
+
+        // Un micro-montant part du wrapped, le natif paie le reste
+        vault.deposit_bitcoin("alice".to_string(), 150_000).unwrap();
+        vault.deposit_bitcoin("bob".to_string(), 30_000).unwrap();
+        let withdrawal = vault
+            .withdraw_bitcoin("bob".to_string(), 50_000, "ECDSA")
+            .unwrap();
+        assert_eq!(withdrawal.source, WithdrawalSource::Wrapped);
+        assert_eq!(
+            (withdrawal.native_amount, withdrawal.wrapped_amount),
+            (Some(20_000), Some(30_000))
+        );
+        assert_eq!(vault.reserves(), (130_000, 0));
+    }
 
-        std::println!("✅ Bitcoin Vault Integration Test Passed");
+    #[test]
+    fn test_simple_vault_holds_large_wei_amounts() {
+        // 25 ETH does not fit in a u64 amount of wei
+        let amount_wei: u128 = 25_000_000_000_000_000_000;
+        let mut vault = SimpleVault::new(SupportedChain::Ethereum);
+
+        vault.deposit(amount_wei, true).unwrap();
+        vault.deposit(amount_wei, true).unwrap();
+        assert_eq!(vault.reserves(), (2 * amount_wei, 0));
+
+        vault.deposit(1, false).unwrap();
+        assert!(vault.deposit(u128::MAX, true).is_err());
+        assert_eq!(vault.reserves(), (2 * amount_wei, 1));
+    }
+
+    #[test]
+    fn test_simple_vault_withdrawal_debits_reserves() {
+        let mut vault = SimpleVault::new(SupportedChain::Solana);
+        vault.deposit(5_000, true).unwrap();
+        vault.deposit(800, false).unwrap();
+
+        let withdrawal = vault.withdraw("sol_user", 3_000, "ECDSA").unwrap();
+        assert_eq!(withdrawal.source, WithdrawalSource::Native);
+        assert_eq!(withdrawal.asset, SupportedChain::Solana);
+        assert_eq!(vault.reserves(), (2_000, 800));
+
+        let withdrawal = vault.withdraw("sol_user", 700, "ECDSA").unwrap();
+        assert_eq!(withdrawal.source, WithdrawalSource::Native);
+
+        // Le natif (1 300) et le wrapped (800) réunis ne couvrent pas le montant
+        assert!(matches!(
+            vault.withdraw("sol_user", 2_500, "ECDSA"),
+            Err(VaultError::InsufficientReserves { .. })
+        ));
+        assert_eq!(vault.reserves(), (1_300, 800));
+        assert_eq!(vault.pending_withdrawals.len(), 2);
+
+        vault.complete_withdrawal(&withdrawal.tx_id).unwrap();
+        assert_eq!(vault.pending_withdrawals.len(), 1);
+    }
+
+    #[test]
+    fn test_funds_received_on_l2_can_be_withdrawn() {
+        fn run<F: std::future::Future>(call: F) -> F::Output {
+            replay::block_on(call).unwrap()
+        }
+        run(deposit_icp("alice".to_string(), 1_000)).unwrap();
+        // Bob n'a jamais déposé: ses fonds viennent d'un transfert L2
+        L2_LEDGER.with(|ledger| {
+            let mut ledger = ledger.borrow_mut();
+            ledger.debit("alice", SupportedChain::Icp, 600).unwrap();
+            ledger.credit("bob", SupportedChain::Icp, 600).unwrap();
+        });
+
+        let bob = Ecdsa.key_generator().unwrap();
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &bob, "bob").unwrap();
+        let alice = Ecdsa.key_generator().unwrap();
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &alice, "alice").unwrap();
+
+        let authorization = sign_withdrawal(&bob, "bob", SupportedChain::Icp, 600);
+        run(withdraw_icp("bob".to_string(), 600, false, authorization)).unwrap();
+        assert_eq!(get_balance("bob".to_string(), SupportedChain::Icp), 0);
+        assert_eq!(get_icp_balance().total_icp, 400);
+        // Alice ne peut pas retirer plus que son solde L2, même si le vault le couvre
+        let authorization = sign_withdrawal(&alice, "alice", SupportedChain::Icp, 500);
+        assert!(run(withdraw_icp("alice".to_string(), 500, false, authorization)).is_err());
+
+        let pending = get_pending_withdrawals("bob".to_string());
+        assert_eq!(pending.len(), 1);
+        confirm_withdrawal(SupportedChain::Icp, pending[0].tx_id.clone()).unwrap();
+        assert!(get_pending_withdrawals("bob".to_string()).is_empty());
+        assert!(confirm_withdrawal(SupportedChain::Icp, pending[0].tx_id.clone()).is_err());
+    }
+
+    #[test]
+    fn test_withdrawal_requires_account_signature() {
+        fn run<F: std::future::Future>(call: F) -> F::Output {
+            replay::block_on(call).unwrap()
+        }
+        run(deposit_icp("alice".to_string(), 1_000)).unwrap();
+        let alice = Ecdsa.key_generator().unwrap();
+        let mallory = Ecdsa.key_generator().unwrap();
+
+        // Sans clé liée, aucun retrait n'est possible
+        let authorization = sign_withdrawal(&alice, "alice", SupportedChain::Icp, 100);
+        assert!(run(withdraw_icp("alice".to_string(), 100, false, authorization)).is_err());
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &alice, "alice").unwrap();
+
+        // Une autre clé ne peut pas retirer pour alice
+        let forged = sign_withdrawal(&mallory, "alice", SupportedChain::Icp, 100);
+        assert!(run(withdraw_icp("alice".to_string(), 100, false, forged)).is_err());
+
+        // La signature couvre le montant et l'actif
+        let authorization = sign_withdrawal(&alice, "alice", SupportedChain::Icp, 100);
+        assert!(run(withdraw_icp(
+            "alice".to_string(),
+            900,
+            false,
+            authorization.clone()
+        ))
+        .is_err());
+        assert!(run(withdraw_solana(
+            "alice".to_string(),
+            100,
+            false,
+            authorization.clone()
+        ))
+        .is_err());
+        assert_eq!(get_balance("alice".to_string(), SupportedChain::Icp), 1_000);
+
+        // Une signature ne paie qu'une fois: le nonce de retrait a avancé
+        run(withdraw_icp(
+            "alice".to_string(),
+            100,
+            false,
+            authorization.clone(),
+        ))
+        .unwrap();
+        assert!(run(withdraw_icp("alice".to_string(), 100, false, authorization)).is_err());
+        assert_eq!(get_balance("alice".to_string(), SupportedChain::Icp), 900);
+        assert_eq!(
+            L2_LEDGER.with(|ledger| ledger.borrow().next_withdrawal_nonce("alice")),
+            1
+        );
     }
 
     #[test]
@@ -1991,18 +4170,23 @@ mod tests {
     // NOUVEAU TEST: Sequencer Integration
     #[test]
     fn test_sequencer_basic() {
-        use chain::{OrderingStrategy, TransactionSequencer};
-        use cryptography::{bridge::CryptographyBridge, ecdsa::Ecdsa};
-        use std::collections::HashMap;
+        use cryptography::ecdsa::Ecdsa;
 
         std::println!("\n=== SEQUENCER BASIC TEST ===");
 
-        let crypto = Ecdsa;
-        let private_keys = HashMap::new();
-        let mut sequencer = TransactionSequencer::new(crypto, private_keys);
+        let mut sequencer = keyed_sequencer();
 
         // Test ajouter une transaction
-        let tx = PolyTransaction::new("alice".to_string(), "bob".to_string(), 100.0);
+        let keys = Ecdsa.key_generator().unwrap();
+        let tx = PolyTransaction::new(
+            "alice".to_string(),
+            "bob".to_string(),
+            100,
+            SupportedChain::Bitcoin,
+            0,
+        )
+        .with_fee(0, TEST_MAX_FEE);
+        let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
         let result = sequencer.add_transaction(tx);
         assert!(result.is_ok());
 
@@ -2010,11 +4194,1168 @@ mod tests {
         std::println!("   - Pending count: {}", sequencer.pending_count());
 
         // Test sequence batch
-        let batch = sequencer.sequence_batch(10);
+        let batch = sequencer
+            .sequence_batch(chain::GENESIS_PREVIOUS_HASH, 10)
+            .unwrap()
+            .transactions;
         std::println!("   - Batch size: {}", batch.len());
 
         std::println!("=== SEQUENCER BASIC TEST PASSED ===");
     }
+
+    #[test]
+    fn test_sequencer_rejects_replayed_and_out_of_order_nonces() {
+        use errors::SequencerError;
+
+        let mut sequencer = keyed_sequencer();
+        let keys = Ecdsa.key_generator().unwrap();
+        let transfer = |nonce| {
+            let tx = PolyTransaction::new(
+                "alice".to_string(),
+                "bob".to_string(),
+                100,
+                SupportedChain::Bitcoin,
+                nonce,
+            )
+            .with_fee(0, TEST_MAX_FEE);
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx)
+        };
+
+        assert!(sequencer.add_transaction(transfer(0)).is_ok());
+        // Encore en attente: le même nonce n'entre que s'il surenchérit
+        assert!(matches!(
+            sequencer.add_transaction(transfer(0)),
+            Err(SequencerError::Mempool(
+                errors::MempoolError::ReplacementUnderpriced { nonce: 0, .. }
+            ))
+        ));
+        assert!(matches!(
+            sequencer.add_transaction(transfer(2)),
+            Err(SequencerError::NonceOutOfOrder { expected: 1, .. })
+        ));
+        assert!(sequencer.add_transaction(transfer(1)).is_ok());
+        assert_eq!(sequencer.next_nonce("alice"), 2);
+        assert_eq!(sequencer.next_nonce("bob"), 0);
+
+        // Une fois séquencé, le nonce reste consommé
+        let batch = sequencer
+            .sequence_batch(chain::GENESIS_PREVIOUS_HASH, 10)
+            .unwrap();
+        assert_eq!(batch.transactions.len(), 2);
+        assert!(matches!(
+            sequencer.add_transaction(transfer(0)),
+            Err(SequencerError::NonceAlreadyUsed { expected: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_state_survives_upgrade_roundtrip() {
+        credit_l2_account("upgrade_user", SupportedChain::Icp, 42_000).unwrap();
+        ETHEREUM_VAULT.with(|vault| vault.borrow_mut().deposit(7, true).unwrap());
+
+        let mut sequencer = keyed_sequencer();
+        let keys = Ecdsa.key_generator().unwrap();
+        let tx = PolyTransaction::new(
+            "upgrade_user".to_string(),
+            "bob".to_string(),
+            1_000,
+            SupportedChain::Icp,
+            0,
+        )
+        .with_fee(0, TEST_MAX_FEE);
+        let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+        sequencer.add_transaction(tx).unwrap();
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(sequencer));
+        let sequencer_key = get_sequencer_public_key().unwrap();
+        commit_signed_block(Vec::new()).unwrap();
+
+        persistence::save(snapshot_state()).unwrap();
+
+        // Simuler l'installation du nouveau wasm: le tas repart de zéro, pas la mémoire stable
+        L2_LEDGER.with(|ledger| *ledger.borrow_mut() = AccountLedger::new());
+        ETHEREUM_VAULT
+            .with(|vault| *vault.borrow_mut() = SimpleVault::new(SupportedChain::Ethereum));
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = None);
+        stable::reload();
+
+        let (state, logs) = persistence::restore().unwrap().unwrap();
+        assert!(logs.blockchain.is_empty());
+        restore_state(state, logs);
+
+        assert_eq!(
+            get_balance("upgrade_user".to_string(), SupportedChain::Icp),
+            42_000
+        );
+        assert_eq!(
+            ETHEREUM_VAULT.with(|vault| vault.borrow().reserves()),
+            (7, 0)
+        );
+        assert_eq!(get_blockchain(0, None).unwrap().len(), 1);
+        assert_eq!(get_next_nonce("upgrade_user".to_string()), 1);
+        SEQUENCER_STATE.with(|state| {
+            assert_eq!(state.borrow().as_ref().unwrap().pending_count(), 1);
+        });
+
+        // La clé du séquenceur survit à l'upgrade et signe toujours
+        assert_eq!(get_sequencer_public_key(), Ok(sequencer_key.clone()));
+        let block = SEQUENCER_STATE.with(|state| {
+            let state = state.borrow();
+            let sequencer = state.as_ref().unwrap();
+            sequencer.sign_block(PolyBlock::new(Vec::new(), "parent".to_string(), [0; 32]))
+        });
+        assert_eq!(block.unwrap().proposer.unwrap().public_key, sequencer_key);
+    }
+
+    #[test]
+    fn test_sequencer_verifies_signatures_for_every_algorithm() {
+        use errors::SequencerError;
+
+        let mut sequencer = keyed_sequencer();
+        let transfer = |sender: &str| {
+            PolyTransaction::new(
+                sender.to_string(),
+                "bob".to_string(),
+                100,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(0, TEST_MAX_FEE)
+        };
+
+        let ecdsa_keys = Ecdsa.key_generator().unwrap();
+        let schnorr_keys = Schnorr.key_generator().unwrap();
+        let mldsa_keys = Mldsa44.key_generator().unwrap();
+        let falcon_keys = Falcon512.key_generator().unwrap();
+        let signed = [
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &ecdsa_keys, transfer("a1")),
+            sign_transfer(
+                &Schnorr,
+                CryptoAlgorithm::Schnorr,
+                &schnorr_keys,
+                transfer("a2"),
+            ),
+            sign_transfer(
+                &Mldsa44,
+                CryptoAlgorithm::Mldsa44,
+                &mldsa_keys,
+                transfer("a3"),
+            ),
+            sign_transfer(
+                &Falcon512,
+                CryptoAlgorithm::Falcon512,
+                &falcon_keys,
+                transfer("a4"),
+            ),
+        ];
+        for tx in signed {
+            assert!(sequencer.add_transaction(tx).is_ok());
+        }
+
+        // Non signée, montant modifié après signature, ou mauvais schéma annoncé
+        assert!(matches!(
+            sequencer.add_transaction(transfer("a5")),
+            Err(SequencerError::MissingSignature(_))
+        ));
+        let mut tampered =
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &ecdsa_keys, transfer("a5"));
+        tampered.amount = 1_000_000;
+        assert!(matches!(
+            sequencer.add_transaction(tampered),
+            Err(SequencerError::InvalidSignature { .. })
+        ));
+        let mut mislabeled =
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &ecdsa_keys, transfer("a5"));
+        mislabeled.algorithm = Some(CryptoAlgorithm::Schnorr);
+        assert!(sequencer.add_transaction(mislabeled).is_err());
+        assert_eq!(sequencer.pending_count(), 4);
+    }
+
+    #[test]
+    fn test_only_the_bound_key_spends_for_an_account() {
+        fn run<F: std::future::Future>(call: F) -> F::Output {
+            replay::block_on(call).unwrap()
+        }
+        let submit = |tx: PolyTransaction| {
+            run(add_transaction_to_sequencer(
+                tx.sender,
+                tx.recipient,
+                tx.amount,
+                tx.asset,
+                tx.nonce,
+                tx.fee,
+                tx.max_fee,
+                tx.public_key.unwrap(),
+                tx.algorithm.unwrap(),
+                tx.signature.unwrap(),
+            ))
+        };
+        let transfer = |nonce| {
+            PolyTransaction::new(
+                "alice".to_string(),
+                "mallory".to_string(),
+                10,
+                SupportedChain::Icp,
+                nonce,
+            )
+            .with_fee(0, TEST_MAX_FEE)
+        };
+
+        run(create_transaction_sequencer("fcfs".to_string())).unwrap();
+        run(deposit_icp("alice".to_string(), 1_000)).unwrap();
+        let alice_keys = Ecdsa.key_generator().unwrap();
+        let mallory_keys = Ecdsa.key_generator().unwrap();
+
+        // Le premier venu ne peut plus revendiquer une adresse sans clé liée
+        let first_come = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &mallory_keys, transfer(0));
+        assert!(submit(first_come).unwrap_err().contains("no bound key"));
+
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &alice_keys, "alice").unwrap();
+        let hijack = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &mallory_keys, transfer(0));
+        assert!(submit(hijack).unwrap_err().contains("does not match"));
+        let signed = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &alice_keys, transfer(0));
+        assert!(submit(signed).is_ok());
+
+        // Une preuve de possession d'une autre clé n'est pas acceptée
+        let mut forged = AccountKeyRegistration {
+            address: "alice".to_string(),
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&mallory_keys.0)),
+            signature: String::new(),
+        };
+        let signature = Ecdsa.sign(&alice_keys.1, &forged.message()).unwrap();
+        forged.signature = hex::encode(Ecdsa.signature_to_bytes(&signature));
+        assert!(register_account_key(forged).is_err());
+        assert_eq!(
+            get_account_key("alice".to_string()).map(|key| key.public_key),
+            Some(hex::encode(Ecdsa.public_key_to_bytes(&alice_keys.0)))
+        );
+    }
+
+    #[test]
+    fn test_pos_consensus_leaves_the_mempool_alone() {
+        fn run<F: std::future::Future>(call: F) -> F::Output {
+            replay::block_on(call).unwrap()
+        }
+        run(create_transaction_sequencer("fcfs".to_string())).unwrap();
+        run(deposit_icp("alice".to_string(), 1_000)).unwrap();
+        let keys = Ecdsa.key_generator().unwrap();
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, "alice").unwrap();
+        let tx = sign_transfer(
+            &Ecdsa,
+            CryptoAlgorithm::Ecdsa,
+            &keys,
+            PolyTransaction::new(
+                "alice".to_string(),
+                "bob".to_string(),
+                10,
+                SupportedChain::Icp,
+                0,
+            )
+            .with_fee(0, TEST_MAX_FEE),
+        );
+        let tx_id = run(add_transaction_to_sequencer(
+            tx.sender,
+            tx.recipient,
+            tx.amount,
+            tx.asset,
+            tx.nonce,
+            tx.fee,
+            tx.max_fee,
+            tx.public_key.unwrap(),
+            tx.algorithm.unwrap(),
+            tx.signature.unwrap(),
+        ))
+        .unwrap();
+
+        // Le bloc de démonstration copie les transactions sans les consommer
+        activate_validators(&[1_000, 2_000, 3_000]);
+        assert!(run(test_pos_consensus()).is_ok());
+        let pending =
+            SEQUENCER_STATE.with(|state| state.borrow().as_ref().unwrap().pending_count());
+        assert_eq!(pending, 1);
+        assert_eq!(
+            get_transaction_status(tx_id),
+            Ok(TransactionStatus::Pending)
+        );
+    }
+
+    #[test]
+    fn test_sequence_batch_keeps_nonce_order_per_sender() {
+        for strategy in [
+            OrderingStrategy::FirstComeFirstServed,
+            OrderingStrategy::PriorityFee,
+            OrderingStrategy::FairOrdering,
+            OrderingStrategy::VrfRandom,
+        ] {
+            let mut sequencer = keyed_sequencer();
+            sequencer.set_ordering_strategy(strategy);
+            let keys = Ecdsa.key_generator().unwrap();
+
+            for nonce in 0..6 {
+                for sender in ["alice", "bob", "carol"] {
+                    let mut tx = PolyTransaction::new(
+                        sender.to_string(),
+                        "dave".to_string(),
+                        1,
+                        SupportedChain::Bitcoin,
+                        nonce,
+                    )
+                    .with_fee(0, TEST_MAX_FEE);
+                    // Horodatages décroissants, à l'inverse des nonces
+                    tx.time_stamp = 1_000 - (nonce as i64) * 10;
+                    let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+                    sequencer.add_transaction(tx).unwrap();
+                }
+            }
+
+            let previous_hash = chain::GENESIS_PREVIOUS_HASH;
+            let mut batch = sequencer
+                .sequence_batch(previous_hash, 8)
+                .unwrap()
+                .transactions;
+            batch.extend(
+                sequencer
+                    .sequence_batch(previous_hash, 100)
+                    .unwrap()
+                    .transactions,
+            );
+            assert_eq!(batch.len(), 18);
+
+            for sender in ["alice", "bob", "carol"] {
+                let nonces: Vec<u64> = batch
+                    .iter()
+                    .filter(|tx| tx.sender == sender)
+                    .map(|tx| tx.nonce)
+                    .collect();
+                assert_eq!(nonces, (0..6).collect::<Vec<u64>>());
+            }
+        }
+    }
+
+    #[test]
+    fn test_append_block_rejects_forged_blocks() {
+        for _ in 0..3 {
+            commit_signed_block(Vec::new()).unwrap();
+        }
+
+        // Un parent arbitraire est refusé
+        let forged = PolyBlock::new(
+            Vec::new(),
+            "caller_supplied_hash".to_string(),
+            current_state_root(),
+        );
+        assert!(check_block(&forged, None).is_err());
+        assert!(verify_chain_integrity(0, None).valid);
+
+        // Un bloc bien chaîné mais sans signature du séquenceur aussi
+        let unsigned = PolyBlock::new(Vec::new(), chain_tip_hash(), current_state_root());
+        let err = check_block(&unsigned, sequencer_key().as_deref()).unwrap_err();
+        assert!(err.contains("carries no proposer signature"), "{err}");
+
+        // Une chaîne altérée est détectée et n'est plus servie
+        BLOCKCHAIN_STATE.with(|chain| {
+            let mut block = chain.get(1).unwrap();
+            block.nonce = 99;
+            chain.replace(1, block);
+        });
+        let report = verify_chain_integrity(0, None);
+        assert!(!report.valid);
+        assert_eq!((report.block_count, report.total_blocks), (3, 3));
+        assert_eq!(report.first_invalid_height, Some(1));
+        assert!(get_blockchain(0, None).is_err());
+
+        // Les pages se vérifient contre le bloc qui les précède, sans reparcourir la chaîne
+        assert_eq!(get_blockchain(0, Some(1)).unwrap().len(), 1);
+        assert!(get_blockchain(1, Some(1)).is_err());
+        let tail = verify_chain_integrity(2, None);
+        assert!(tail.valid);
+        assert_eq!((tail.start_height, tail.block_count), (2, 1));
+        assert!(get_blockchain(3, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_account_proof_against_block_state_root() {
+        credit_l2_account("state_alice", SupportedChain::Bitcoin, 1_000).unwrap();
+        let genesis = commit_signed_block(Vec::new()).unwrap();
+
+        let transfer = PolyTransaction::new(
+            "state_alice".to_string(),
+            "state_bob".to_string(),
+            400,
+            SupportedChain::Bitcoin,
+            0,
+        );
+        let outcome =
+            L2_LEDGER.with(|ledger| ledger.borrow_mut().apply_batch(vec![transfer], |_| 0));
+        let block = commit_signed_block(outcome.applied).unwrap();
+        assert_ne!(block.state_root, genesis.state_root);
+
+        let proof = get_account_proof("state_alice".to_string(), 1).unwrap();
+        assert_eq!(proof.block_hash, block.hash);
+        assert_eq!(proof.nonce, 1);
+        assert_eq!(proof.balances.len(), 1);
+        assert_eq!(proof.balances[0].amount, 600);
+        assert!(verify_account_proof(proof.clone()));
+
+        // L'état d'un bloc antérieur reste prouvable
+        let earlier = get_account_proof("state_alice".to_string(), 0).unwrap();
+        assert_eq!((earlier.balances[0].amount, earlier.nonce), (1_000, 0));
+        assert!(earlier.verify());
+        let absent = get_account_proof("state_bob".to_string(), 0).unwrap();
+        assert!(absent.balances.is_empty() && absent.verify());
+
+        // Un solde gonflé ne passe pas
+        let mut forged = proof;
+        forged.balances[0].amount = 1_000_000;
+        assert!(!forged.verify());
+
+        assert!(get_account_proof("state_alice".to_string(), 5).is_err());
+    }
+
+    #[test]
+    fn test_unsigned_block_leaves_ledger_and_mempool_untouched() {
+        credit_l2_account("keyless_alice", SupportedChain::Bitcoin, 500).unwrap();
+        let keys = Ecdsa.key_generator().unwrap();
+        let tx = PolyTransaction::new(
+            "keyless_alice".to_string(),
+            "keyless_bob".to_string(),
+            200,
+            SupportedChain::Bitcoin,
+            0,
+        )
+        .with_fee(0, TEST_MAX_FEE);
+        let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+
+        // Séquenceur restauré sans clé: rien n'est séquencé avant qu'il en ait une
+        let mut sequencer = chain::TransactionSequencer::new(Ecdsa);
+        sequencer.set_ordering_strategy(OrderingStrategy::FirstComeFirstServed);
+        sequencer.add_transaction(tx).unwrap();
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(sequencer));
+        assert!(produce_block(10, 1_000, false).is_err());
+        SEQUENCER_STATE
+            .with(|state| assert_eq!(state.borrow().as_ref().unwrap().pending_count(), 1));
+
+        // Un bloc qui ne peut pas être signé ne touche pas au ledger
+        let mut sequencer = SEQUENCER_STATE.with(|state| state.borrow_mut().take().unwrap());
+        let batch = sequencer.sequence_batch(&chain_tip_hash(), 10).unwrap();
+        assert!(seal_batch(batch, &mut sequencer, false, None).is_err());
+        assert_eq!(
+            get_balance("keyless_alice".to_string(), SupportedChain::Bitcoin),
+            500
+        );
+        assert_eq!(chain_height(), 0);
+    }
+
+    #[test]
+    fn test_transaction_status_follows_the_transaction() {
+        credit_l2_account("status_rich", SupportedChain::Bitcoin, 500).unwrap();
+        let mut sequencer = keyed_sequencer();
+        let mut submit = |sender: &str| {
+            let keys = Ecdsa.key_generator().unwrap();
+            let tx = PolyTransaction::new(
+                sender.to_string(),
+                "status_recipient".to_string(),
+                200,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(0, TEST_MAX_FEE);
+            let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+            // L'id ne dépend que du contenu signé
+            let expected_id = tx.tx_id();
+            let tx_id = sequencer.add_transaction(tx).unwrap();
+            assert_eq!(tx_id, expected_id);
+            set_transaction_status(tx_id.clone(), TransactionStatus::Pending);
+            tx_id
+        };
+        let paid = submit("status_rich");
+        let overdraft = submit("status_poor");
+
+        assert_eq!(
+            get_transaction_status(paid.clone()),
+            Ok(TransactionStatus::Pending)
+        );
+        assert!(get_transaction_status("unknown".to_string()).is_err());
+
+        let batch = sequencer.sequence_batch(&chain_tip_hash(), 10).unwrap();
+        assert_eq!(seal_batch(batch, &mut sequencer, false, None), Ok((1, 1)));
+        let tip = BLOCKCHAIN_STATE.with(|chain| chain.last().unwrap());
+        assert_eq!(
+            get_transaction_status(paid),
+            Ok(TransactionStatus::Sequenced {
+                block_hash: tip.hash,
+                block_height: 0,
+            })
+        );
+        assert!(matches!(
+            get_transaction_status(overdraft),
+            Ok(TransactionStatus::Failed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_priority_fee_orders_by_tip() {
+        use errors::SequencerError;
+
+        let mut sequencer = keyed_sequencer();
+        sequencer.set_ordering_strategy(OrderingStrategy::PriorityFee);
+        let mut submit = |sender: &str, fee: u128, max_fee: u128| {
+            let keys = Ecdsa.key_generator().unwrap();
+            let tx = PolyTransaction::new(
+                sender.to_string(),
+                "bob".to_string(),
+                100,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(fee, max_fee);
+            sequencer.add_transaction(sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx))
+        };
+
+        submit("low", 5, 100).unwrap();
+        submit("high", 50, 100).unwrap();
+        // Tip plafonné par max_fee: 1 de base + 19
+        submit("capped", 90, 20).unwrap();
+        assert!(matches!(
+            submit("broke", 10, 0),
+            Err(SequencerError::FeeTooLow { base_fee: 1, .. })
+        ));
+
+        let order: Vec<String> = sequencer
+            .sequence_batch(chain::GENESIS_PREVIOUS_HASH, 10)
+            .unwrap()
+            .transactions
+            .into_iter()
+            .map(|tx| tx.sender)
+            .collect();
+        assert_eq!(order, vec!["high", "capped", "low"]);
+    }
+
+    #[test]
+    fn test_vrf_ordering_is_recorded_and_verifiable() {
+        let mut sequencer = keyed_sequencer();
+        sequencer.set_ordering_strategy(OrderingStrategy::VrfRandom);
+        for i in 0..6 {
+            let sender = format!("vrf_sender_{i}");
+            credit_l2_account(&sender, SupportedChain::Bitcoin, 1_000).unwrap();
+            let keys = Ecdsa.key_generator().unwrap();
+            for nonce in 0..2 {
+                let tx = PolyTransaction::new(
+                    sender.clone(),
+                    "vrf_recipient".to_string(),
+                    10,
+                    SupportedChain::Bitcoin,
+                    nonce,
+                )
+                .with_fee(0, TEST_MAX_FEE);
+                let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+                sequencer.add_transaction(tx).unwrap();
+            }
+        }
+
+        let key = sequencer.public_key().unwrap().to_string();
+        let batch = sequencer.sequence_batch(&chain_tip_hash(), 100).unwrap();
+        let proof = batch.ordering_proof.clone().unwrap();
+        assert_eq!(proof.input, chain::GENESIS_PREVIOUS_HASH);
+        assert!(proof.verify(&key));
+        assert_eq!(seal_batch(batch, &mut sequencer, false, None), Ok((12, 0)));
+
+        let block = BLOCKCHAIN_STATE.with(|chain| chain.get(0).unwrap());
+        assert_eq!(block.ordering, Some(OrderingStrategy::VrfRandom));
+        assert_eq!(block.ordering_proof, Some(proof.clone()));
+        assert_eq!(chain::verify_block(0, &block, None, Some(&key), 0), Ok(()));
+
+        // La sortie est fixée par la clé et le parent: réévaluer redonne la même preuve
+        let again = sequencer
+            .sequence_batch(chain::GENESIS_PREVIOUS_HASH, 0)
+            .unwrap();
+        assert_eq!(again.ordering_proof, Some(proof.clone()));
+
+        // Réordonner le bloc, même re-haché, casse la preuve d'ordre
+        let rehash = |mut block: PolyBlock| {
+            block.tx_root = hex::encode(block.calculate_tx_root());
+            block.hash = block.calculate_hash();
+            block
+        };
+        let mut reordered = block.clone();
+        reordered.transactions.reverse();
+        assert!(matches!(
+            chain::verify_block(0, &rehash(reordered), None, Some(&key), 0),
+            Err(errors::ChainError::InvalidOrdering { height: 0, .. })
+        ));
+
+        // Une preuve rejouée sur un autre parent non plus
+        let mut replayed = block.clone();
+        replayed.previous_hash = "other_parent".to_string();
+        assert!(chain::verify_ordering(&replayed, Some(&key)).is_err());
+
+        // Ni une preuve valide sous une autre clé que la clé publiée
+        let other = keyed_sequencer();
+        assert!(chain::verify_ordering(&block, other.public_key()).is_err());
+        assert!(chain::verify_ordering(&block, None).is_err());
+
+        // Un bloc VrfRandom sans preuve est refusé
+        let mut unproven = block;
+        unproven.ordering_proof = None;
+        assert!(matches!(
+            chain::verify_block(0, &rehash(unproven), None, Some(&key), 0),
+            Err(errors::ChainError::InvalidOrdering { height: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_mempool_replacement_and_expiry_free_nonces() {
+        SEQUENCER_STATE.with(|state| {
+            *state.borrow_mut() = Some(keyed_sequencer());
+        });
+        let keys = Ecdsa.key_generator().unwrap();
+        let transfer = |nonce, fee: u128, time_stamp| {
+            let mut tx = PolyTransaction::new(
+                "rbf_alice".to_string(),
+                "bob".to_string(),
+                100,
+                SupportedChain::Bitcoin,
+                nonce,
+            )
+            .with_fee(fee, TEST_MAX_FEE * fee.max(1));
+            tx.time_stamp = time_stamp;
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx)
+        };
+
+        let ttl = MempoolConfig::default().ttl_seconds as i64 * 1_000_000_000;
+        let original = transfer(0, 10, 0);
+        let original_id = original.tx_id();
+        SEQUENCER_STATE.with(|state| {
+            let mut state = state.borrow_mut();
+            let sequencer = state.as_mut().unwrap();
+            sequencer.add_transaction(original).unwrap();
+
+            // Même nonce, tip et plafond relevés: la nouvelle prend la place
+            let replacement_id = sequencer.add_transaction(transfer(0, 20, 1)).unwrap();
+            record_dropped(sequencer);
+            assert_eq!(sequencer.pending_count(), 1);
+            assert_eq!(sequencer.next_nonce("rbf_alice"), 1);
+            assert_eq!(
+                sequencer.mempool().by_sender("rbf_alice")[0].tx_id(),
+                replacement_id
+            );
+
+            sequencer.add_transaction(transfer(1, 0, 2)).unwrap();
+            assert_eq!(sequencer.expire_transactions(ttl + 1), 2);
+            record_dropped(sequencer);
+            assert_eq!(sequencer.pending_count(), 0);
+            // Les nonces expirés peuvent être réutilisés
+            assert_eq!(sequencer.next_nonce("rbf_alice"), 0);
+        });
+
+        assert_eq!(
+            get_transaction_status(original_id),
+            Ok(TransactionStatus::Dropped)
+        );
+    }
+
+    #[test]
+    fn test_fair_ordering_follows_commitment_slots() {
+        use errors::{CommitmentError, SequencerError};
+
+        let clock = clock::ManualClock::starting_at(0);
+        clock::install(std::rc::Rc::new(clock.clone()));
+        let mut sequencer = keyed_sequencer();
+        sequencer.set_ordering_strategy(OrderingStrategy::FairOrdering);
+        let keys: std::collections::HashMap<&str, _> = ["alice", "bob", "carol", "mallory"]
+            .into_iter()
+            .map(|sender| (sender, Ecdsa.key_generator().unwrap()))
+            .collect();
+        let transfer = |sender: &str, time_stamp| {
+            let mut tx = PolyTransaction::new(
+                sender.to_string(),
+                "dave".to_string(),
+                1,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(0, TEST_MAX_FEE);
+            tx.time_stamp = time_stamp;
+            sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys[sender], tx)
+        };
+        let commit = |sender: &str, commitment: &str| {
+            sign_commitment(&keys[sender], sender, commitment.to_string())
+        };
+
+        // alice s'engage avant que bob ne soumette en clair
+        let hidden = transfer("alice", 10);
+        let slot = sequencer
+            .submit_commitment(&commit("alice", &hidden.commitment(b"alice salt")))
+            .unwrap();
+        sequencer.add_transaction(transfer("bob", 5)).unwrap();
+
+        assert!(matches!(
+            sequencer.reveal_transaction(hidden.clone(), b"wrong salt"),
+            Err(SequencerError::Commitment(CommitmentError::Unknown(_)))
+        ));
+        sequencer.reveal_transaction(hidden, b"alice salt").unwrap();
+        assert_eq!(slot, 0);
+
+        let batch = sequencer
+            .sequence_batch(chain::GENESIS_PREVIOUS_HASH, 10)
+            .unwrap()
+            .transactions;
+        let senders: Vec<&str> = batch.iter().map(|tx| tx.sender.as_str()).collect();
+        assert_eq!(senders, ["alice", "bob"]);
+
+        // Un engagement non dévoilé à temps expire
+        let late = transfer("carol", 301 * 1_000_000_000);
+        let commitment = late.commitment(b"carol salt");
+        sequencer
+            .submit_commitment(&commit("carol", &commitment))
+            .unwrap();
+        assert_eq!(
+            sequencer.reveal_transaction(late, b"carol salt"),
+            Err(SequencerError::Commitment(CommitmentError::Expired(
+                commitment.clone()
+            )))
+        );
+        // L'engagement suivant fait expirer celui de carol à l'heure du séquenceur
+        clock.set(301 * 1_000_000_000);
+        sequencer
+            .submit_commitment(&commit("bob", &"00".repeat(32)))
+            .unwrap();
+        assert!(sequencer.commitment(&commitment).is_none());
+    }
+
+    #[test]
+    fn test_commitments_are_signed_and_capped_per_sender() {
+        use errors::{CommitmentError, SequencerError};
+
+        let mut sequencer = keyed_sequencer();
+        sequencer.set_mempool_config(mempool::MempoolConfig {
+            max_per_sender: 2,
+            ..Default::default()
+        });
+        let alice = Ecdsa.key_generator().unwrap();
+        let mallory = Ecdsa.key_generator().unwrap();
+        let hash = |i: u8| hex::encode([i; 32]);
+
+        // Signature d'une autre clé que celle annoncée, ou sur un autre engagement
+        let mut forged = sign_commitment(&mallory, "alice", hash(1));
+        forged.public_key = hex::encode(Ecdsa.public_key_to_bytes(&alice.0));
+        assert!(matches!(
+            sequencer.submit_commitment(&forged),
+            Err(SequencerError::Commitment(
+                CommitmentError::InvalidSignature { .. }
+            ))
+        ));
+        let mut swapped = sign_commitment(&alice, "alice", hash(1));
+        swapped.commitment = hash(2);
+        assert!(sequencer.submit_commitment(&swapped).is_err());
+
+        // Deux engagements ouverts au plus par expéditeur
+        for i in 1..=2 {
+            sequencer
+                .submit_commitment(&sign_commitment(&alice, "alice", hash(i)))
+                .unwrap();
+        }
+        assert_eq!(
+            sequencer.submit_commitment(&sign_commitment(&alice, "alice", hash(3))),
+            Err(SequencerError::Commitment(CommitmentError::SenderLimit {
+                sender: "alice".to_string(),
+                limit: 2,
+            }))
+        );
+        sequencer
+            .submit_commitment(&sign_commitment(&mallory, "mallory", hash(3)))
+            .unwrap();
+
+        // Un engagement pris par mallory ne se dévoile pas au nom d'alice
+        let tx = sign_transfer(
+            &Ecdsa,
+            CryptoAlgorithm::Ecdsa,
+            &alice,
+            PolyTransaction::new(
+                "alice".to_string(),
+                "bob".to_string(),
+                1,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(0, TEST_MAX_FEE),
+        );
+        let stolen = tx.commitment(b"salt");
+        sequencer
+            .submit_commitment(&sign_commitment(&mallory, "mallory", stolen.clone()))
+            .unwrap();
+        assert_eq!(
+            sequencer.reveal_transaction(tx, b"salt"),
+            Err(SequencerError::Commitment(CommitmentError::WrongSender {
+                hash: stolen,
+                sender: "alice".to_string(),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_commitment_requires_the_account_key() {
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(keyed_sequencer()));
+        let keys = Ecdsa.key_generator().unwrap();
+        let submission = sign_commitment(&keys, "alice", hex::encode([1; 32]));
+
+        // Clé valide mais pas liée au compte
+        assert!(submit_transaction_commitment(submission.clone()).is_err());
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, "alice").unwrap();
+        assert_eq!(submit_transaction_commitment(submission), Ok(0));
+    }
+
+    #[test]
+    fn test_produce_block_follows_empty_block_policy() {
+        SEQUENCER_STATE.with(|state| {
+            *state.borrow_mut() = Some(keyed_sequencer());
+        });
+        let now = 1_000_000_000;
+
+        // File vide et politique Skip: aucun bloc
+        let outcome = produce_block(10, now, false).unwrap();
+        assert_eq!((outcome.applied, outcome.sealed), (0, false));
+        assert_eq!(chain_height(), 0);
+
+        // Bloc vide demandé: la chaîne avance et reste vérifiable
+        let outcome = produce_block(10, now, true).unwrap();
+        assert_eq!((outcome.applied, outcome.sealed), (0, true));
+        assert_eq!(chain_height(), 1);
+        assert!(last_block().unwrap().transactions.is_empty());
+        assert!(verify_chain_integrity(0, None).valid);
+    }
+
+    #[test]
+    fn test_idle_ticks_decay_the_base_fee() {
+        // Une congestion passée a fait monter le prix de base, puis plus rien n'arrive
+        let mut sequencer = keyed_sequencer();
+        for _ in 0..10 {
+            sequencer.record_block(fees::DEFAULT_TARGET_BLOCK_SIZE * 2);
+        }
+        let congested = sequencer.fee_market().base_fee(SupportedChain::Icp);
+        assert!(congested > fees::min_base_fee(SupportedChain::Icp));
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(sequencer));
+        let base_fee = || estimate_fee(SupportedChain::Icp).base_fee;
+        let logged = || INPUT_LOG.with(|log| log.iter_from(0).count());
+
+        // Politique Skip: aucun bloc, mais chaque tick baisse le prix et se journalise
+        produce_block_tick();
+        assert!(base_fee() < congested);
+        assert_eq!(chain_height(), 0);
+        assert_eq!(logged(), 1);
+
+        for _ in 0..100 {
+            produce_block_tick();
+        }
+        assert_eq!(base_fee(), fees::min_base_fee(SupportedChain::Icp));
+        // Au plancher les ticks redeviennent sans effet, et hors du journal
+        let at_floor = logged();
+        produce_block_tick();
+        assert_eq!(logged(), at_floor);
+    }
+
+    #[test]
+    fn test_fairness_is_measured_per_strategy() {
+        let mut sequencer = keyed_sequencer();
+        sequencer.set_ordering_strategy(OrderingStrategy::PriorityFee);
+        for (sender, tip) in [("early", 1), ("late", 50)] {
+            let keys = Ecdsa.key_generator().unwrap();
+            let tx = PolyTransaction::new(
+                sender.to_string(),
+                "dave".to_string(),
+                1,
+                SupportedChain::Bitcoin,
+                0,
+            )
+            .with_fee(tip, TEST_MAX_FEE);
+            let tx = sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx);
+            sequencer.add_transaction(tx).unwrap();
+        }
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(sequencer));
+
+        // Le tip le plus élevé double l'arrivée la plus ancienne: une inversion sur une paire
+        let outcome = produce_block(10, 1_000_000_000, false).unwrap();
+        assert_eq!(outcome.fairness_score, 0.0);
+        assert_eq!(outcome.kendall_tau, -1.0);
+
+        let benefits = analyze_sequencing_benefits();
+        assert_eq!(benefits.strategies.len(), 1);
+        assert_eq!(
+            benefits.strategies[0].strategy,
+            OrderingStrategy::PriorityFee
+        );
+        assert_eq!(benefits.strategies[0].inversions, 1);
+        assert_eq!(benefits.current_fairness_score, 0.0);
+        assert_eq!(
+            benefits.recommended_strategy,
+            Some(OrderingStrategy::PriorityFee)
+        );
+        assert!(!benefits.front_running_prevention);
+    }
+
+    #[test]
+    fn test_sealed_blocks_are_signed_by_the_sequencer() {
+        credit_l2_account("signed_sender", SupportedChain::Bitcoin, 1_000).unwrap();
+        let mut sequencer = keyed_sequencer();
+        let keys = Ecdsa.key_generator().unwrap();
+        let tx = PolyTransaction::new(
+            "signed_sender".to_string(),
+            "bob".to_string(),
+            10,
+            SupportedChain::Bitcoin,
+            0,
+        )
+        .with_fee(0, TEST_MAX_FEE);
+        sequencer
+            .add_transaction(sign_transfer(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, tx))
+            .unwrap();
+        SEQUENCER_STATE.with(|state| *state.borrow_mut() = Some(sequencer));
+
+        assert!(produce_block(10, 1_000_000_000, false).unwrap().sealed);
+        let block = get_block_by_hash(chain_tip_hash()).unwrap();
+        let proposer = verify_block_signature(block.clone()).unwrap();
+        assert_eq!(proposer.algorithm, CryptoAlgorithm::Ecdsa);
+        assert_eq!(Ok(proposer.public_key), get_sequencer_public_key());
+
+        // Une autre clé ne peut pas revendiquer le bloc: elle fait partie du hash
+        let mut forged = block.clone();
+        forged.proposer.as_mut().unwrap().public_key =
+            hex::encode(Ecdsa.public_key_to_bytes(&keys.0));
+        assert!(verify_block_signature(forged).is_err());
+
+        // Ni un bloc correctement signé par une clé qui n'est pas celle publiée
+        let mut unsigned = block.clone();
+        unsigned.proposer = None;
+        let resigned = keyed_sequencer().sign_block(unsigned).unwrap();
+        assert!(resigned
+            .verify_signature(&resigned.proposer.as_ref().unwrap().public_key)
+            .is_ok());
+        assert!(verify_block_signature(resigned.clone()).is_err());
+        assert!(matches!(
+            chain::verify_block(0, &resigned, None, sequencer_key().as_deref(), 0),
+            Err(errors::ChainError::InvalidSignature { height: 0, .. })
+        ));
+
+        // Une signature altérée est refusée, aussi dans la chaîne
+        let mut tampered = block;
+        tampered.proposer.as_mut().unwrap().signature = "00".repeat(64);
+        assert!(verify_block_signature(tampered.clone()).is_err());
+        BLOCKCHAIN_STATE.with(|chain| chain.replace(0, tampered));
+        assert!(!verify_chain_integrity(0, None).valid);
+    }
+
+    #[test]
+    fn test_replay_rebuilds_identical_blocks() {
+        use errors::ReplayError;
+
+        let clock = clock::ManualClock::starting_at(1_000_000_000);
+        clock::install(std::rc::Rc::new(clock.clone()));
+        fn run<F: std::future::Future>(call: F) -> F::Output {
+            replay::block_on(call).unwrap()
+        }
+
+        run(create_transaction_sequencer("vrf".to_string())).unwrap();
+        run(deposit_icp("alice".to_string(), 10_000_000_000)).unwrap();
+        let keys = Ecdsa.key_generator().unwrap();
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &keys, "alice").unwrap();
+        for nonce in 0..3 {
+            clock.advance(1_000);
+            let tx = sign_transfer(
+                &Ecdsa,
+                CryptoAlgorithm::Ecdsa,
+                &keys,
+                PolyTransaction::new(
+                    "alice".to_string(),
+                    "bob".to_string(),
+                    100,
+                    SupportedChain::Icp,
+                    nonce,
+                )
+                .with_fee(nonce as u128, TEST_MAX_FEE),
+            );
+            run(add_transaction_to_sequencer(
+                tx.sender,
+                tx.recipient,
+                tx.amount,
+                tx.asset,
+                tx.nonce,
+                tx.fee,
+                tx.max_fee,
+                tx.public_key.unwrap(),
+                CryptoAlgorithm::Ecdsa,
+                tx.signature.unwrap(),
+            ))
+            .unwrap();
+        }
+        clock.advance(1_000_000);
+        run(sequence_transaction_batch(Some(2))).unwrap();
+        // Les appels refusés sont journalisés aussi
+        let authorization = sign_withdrawal(&keys, "alice", SupportedChain::Icp, u64::MAX.into());
+        assert!(run(withdraw_icp(
+            "alice".to_string(),
+            u64::MAX,
+            false,
+            authorization
+        ))
+        .is_err());
+        configure_block_production(BlockProductionConfig {
+            empty_blocks: producer::EmptyBlockPolicy::Always,
+            ..Default::default()
+        })
+        .unwrap();
+        clock.advance(2_000_000_000);
+        produce_block_tick();
+        let authorization = sign_withdrawal(&keys, "alice", SupportedChain::Icp, 500);
+        run(withdraw_icp("alice".to_string(), 500, false, authorization)).unwrap();
+
+        let log: Vec<LoggedInput> = INPUT_LOG.with(|log| log.iter_from(0).collect());
+        assert_eq!(log.len(), 11);
+        assert!(log[6].sealing.ordering_proof.is_some());
+
+        let report = replay::replay(&log).unwrap();
+        let hashes = |blocks: &[PolyBlock]| -> Vec<String> {
+            blocks.iter().map(|block| block.hash.clone()).collect()
+        };
+        let blocks: Vec<PolyBlock> = BLOCKCHAIN_STATE.with(|chain| chain.iter().collect());
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(hashes(&report.blocks), hashes(&blocks));
+        assert_eq!(
+            report.balance("alice", SupportedChain::Icp),
+            get_balance("alice".to_string(), SupportedChain::Icp)
+        );
+        assert_eq!(
+            report.vault_reserves(SupportedChain::Icp),
+            ICP_VAULT.with(|vault| vault.borrow().reserves())
+        );
+
+        // Un dépôt différent change la racine d'état du premier bloc
+        let mut tampered = log.clone();
+        tampered[1].input = Input::DepositIcp {
+            address: "alice".to_string(),
+            amount_e8s: 20_000_000_000,
+        };
+        assert!(matches!(
+            replay::replay(&tampered),
+            Err(ReplayError::BlockHashMismatch { sequence: 6, .. })
+        ));
+    }
+
+    #[test]
+    fn test_staking_follows_epochs_and_unbonding_period() {
+        VALIDATOR_REGISTRY.with(|registry| {
+            *registry.borrow_mut() = ValidatorRegistry::new(RegistryConfig {
+                epoch_length: 2,
+                unbonding_epochs: 1,
+                min_stake: 1,
+                max_active_validators: 10,
+                slash_bps: 500,
+                jail_epochs: 2,
+            })
+        });
+        replay::block_on(deposit_icp("alice".to_string(), 1_000))
+            .unwrap()
+            .unwrap();
+
+        let account_keys = Ecdsa.key_generator().unwrap();
+        bind_account_key(&Ecdsa, CryptoAlgorithm::Ecdsa, &account_keys, "alice").unwrap();
+        let authorize = |action| sign_validator_action(&account_keys, "alice", action);
+
+        let (public_key, secret_key) = Ecdsa.key_generator().unwrap();
+        let mut registration = ValidatorRegistration {
+            address: "alice".to_string(),
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&public_key)),
+            signature: String::new(),
+            account_signature: None,
+            rotation_signature: None,
+        };
+        let signature = Ecdsa.sign(&secret_key, &registration.message()).unwrap();
+        registration.signature = hex::encode(Ecdsa.signature_to_bytes(&signature));
+        assert!(register_validator(registration.clone()).is_err());
+        registration.account_signature = Some(authorize(registration.action()));
+        register_validator(registration).unwrap();
+
+        let bond = |amount| {
+            bond_stake(
+                "alice".to_string(),
+                amount,
+                authorize(validators::ValidatorAction::Bond(amount)),
+            )
+        };
+        assert!(bond_stake("alice".to_string(), 600, String::new()).is_err());
+        assert!(bond(5_000).is_err());
+        assert_eq!(bond(600).unwrap().bonded, 600);
+        assert_eq!(get_balance("alice".to_string(), SupportedChain::Icp), 400);
+
+        // Deux blocs par époque: la mise entre dans l'ensemble actif à la frontière
+        commit_signed_block(Vec::new()).unwrap();
+        assert_eq!(get_current_epoch().epoch, 0);
+        commit_signed_block(Vec::new()).unwrap();
+        let epoch = get_current_epoch();
+        assert_eq!(
+            (epoch.epoch, epoch.start_height, epoch.total_stake),
+            (1, 2, 600)
+        );
+
+        let unbonding = unbond_stake(
+            "alice".to_string(),
+            600,
+            authorize(validators::ValidatorAction::Unbond(600)),
+        )
+        .unwrap();
+        assert_eq!(unbonding.release_epoch, 3);
+        for _ in 0..2 {
+            commit_signed_block(Vec::new()).unwrap();
+        }
+        assert_eq!(get_current_epoch().total_stake, 0);
+        assert_eq!(get_balance("alice".to_string(), SupportedChain::Icp), 400);
+        for _ in 0..2 {
+            commit_signed_block(Vec::new()).unwrap();
+        }
+        assert_eq!(get_balance("alice".to_string(), SupportedChain::Icp), 1_000);
+        assert!(get_unbondings("alice".to_string()).is_empty());
+    }
+
+    #[test]
+    fn test_pos_simulation_rotates_past_offline_proposer() {
+        let clock = clock::ManualClock::starting_at(1_000_000_000);
+        clock::install(std::rc::Rc::new(clock.clone()));
+
+        // Aucun validateur actif: rien à simuler
+        assert!(start_pos_simulation(1).is_err());
+        activate_validators(&[1_000, 1_500, 2_000, 2_500, 3_000]);
+
+        // Trop de stake hors ligne: aucun quorum ne pourrait faire tourner la vue
+        assert!(start_pos_simulation(4).is_err());
+
+        let status = start_pos_simulation(1).unwrap();
+        assert_eq!(status.round, 0);
+        assert_eq!(status.leader, status.offline[0]);
+        assert!(status.block.is_none());
+        assert!(status.last_error.is_some());
+
+        // Timer déclenché avant l'échéance: le tour continue
+        pos_round_timeout();
+        assert_eq!(get_pos_simulation().unwrap().round, 0);
+
+        clock.set(status.deadline);
+        pos_round_timeout();
+        let status = get_pos_simulation().unwrap();
+        assert_eq!(status.round, 1);
+        assert_eq!(status.timeout_certificates.len(), 1);
+        assert!(status.last_error.is_none());
+        let block = status.block.unwrap();
+        // La hauteur simulée s'appuie sur la tête de chaîne, qu'elle ne modifie pas
+        assert_eq!(block.previous_hash, chain_tip_hash());
+        assert_eq!(chain_height(), 1);
+        let certificate = block.certificate.unwrap();
+        assert_eq!(certificate.round, 1);
+        assert_ne!(certificate.proposer.validator, status.offline[0]);
+        // Le certificat porte sur les mises de l'époque en cours
+        assert_eq!(certificate.total_stake, 10_000);
+        assert!(certificate.proposer.validator.starts_with("validator-"));
+        assert_eq!(certificate.timeout_certificate.map(|tc| tc.round), Some(0));
+    }
 }
 
 ic_cdk::export_candid!();
@@ -0,0 +1,358 @@
+//! Bounded pool of admitted transactions waiting for a batch
+//! Entries expire after a TTL counted from their arrival `time_stamp`, a pending
+//! transaction can be replaced by one paying more for the same sender and nonce,
+//! and a full pool evicts its lowest-priority entry to make room.
+//! Nonce and signature checks stay in the sequencer, the pool only keeps its bounds.
+
+use crate::{errors::MempoolError, fees::FeeMarket, types::PolyTransaction};
+use candid::{CandidType, Deserialize};
+use std::collections::HashMap;
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct MempoolConfig {
+    /// Most transactions held at once, all senders together
+    pub max_size: u64,
+    /// Most pending transactions per sender
+    pub max_per_sender: u64,
+    /// Lifetime of a pending transaction, counted from its arrival
+    pub ttl_seconds: u64,
+    /// Time a commit-reveal commitment has to be revealed
+    pub reveal_window_seconds: u64,
+}
+
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_size: 10_000,
+            max_per_sender: 64,
+            ttl_seconds: 3_600,
+            reveal_window_seconds: 300,
+        }
+    }
+}
+
+/// What admitting a transaction pushed out of the pool
+#[derive(Debug, Default)]
+pub struct Insertion {
+    /// Same sender and nonce, outbid by the new transaction
+    pub replaced: Option<PolyTransaction>,
+    /// Lowest-priority entry removed to make room
+    pub evicted: Option<PolyTransaction>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct Mempool {
+    /// Pending transactions, in arrival order until a strategy reorders them
+    transactions: Vec<PolyTransaction>,
+    /// Ordering slot of every pending (sender, nonce), handed out by the sequencer
+    /// on admission or, for commit-reveal submissions, on commitment
+    slots: HashMap<(String, u64), u64>,
+    config: MempoolConfig,
+}
+
+impl Mempool {
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+
+    pub fn set_config(&mut self, config: MempoolConfig) {
+        self.config = config;
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn transactions(&self) -> &[PolyTransaction] {
+        &self.transactions
+    }
+
+    pub fn by_sender(&self, sender: &str) -> Vec<PolyTransaction> {
+        let mut pending: Vec<PolyTransaction> = self
+            .transactions
+            .iter()
+            .filter(|tx| tx.sender == sender)
+            .cloned()
+            .collect();
+        pending.sort_by_key(|tx| tx.nonce);
+        pending
+    }
+
+    pub fn contains(&self, sender: &str, nonce: u64) -> bool {
+        self.position(sender, nonce).is_some()
+    }
+
+    fn position(&self, sender: &str, nonce: u64) -> Option<usize> {
+        self.transactions
+            .iter()
+            .position(|tx| tx.sender == sender && tx.nonce == nonce)
+    }
+
+    /// Admit a transaction whose signature and nonce the sequencer already checked
+    pub fn insert(
+        &mut self,
+        transaction: PolyTransaction,
+        slot: u64,
+        fee_market: &FeeMarket,
+    ) -> Result<Insertion, MempoolError> {
+        if let Some(index) = self.position(&transaction.sender, transaction.nonce) {
+            let pending = &self.transactions[index];
+            if !Self::outbids(&transaction, pending) {
+                return Err(MempoolError::ReplacementUnderpriced {
+                    sender: transaction.sender,
+                    nonce: transaction.nonce,
+                });
+            }
+            // The replacement keeps its predecessor's place in the queue and its slot
+            let replaced = std::mem::replace(&mut self.transactions[index], transaction);
+            return Ok(Insertion {
+                replaced: Some(replaced),
+                evicted: None,
+            });
+        }
+
+        let sender_count = self
+            .transactions
+            .iter()
+            .filter(|tx| tx.sender == transaction.sender)
+            .count() as u64;
+        if sender_count >= self.config.max_per_sender {
+            return Err(MempoolError::SenderLimit {
+                sender: transaction.sender,
+                limit: self.config.max_per_sender,
+            });
+        }
+
+        let mut insertion = Insertion::default();
+        if self.transactions.len() as u64 >= self.config.max_size {
+            let index =
+                self.eviction_candidate(&transaction, fee_market)
+                    .ok_or(MempoolError::Full {
+                        capacity: self.config.max_size,
+                    })?;
+            let evicted = self.transactions.remove(index);
+            self.slots.remove(&(evicted.sender.clone(), evicted.nonce));
+            insertion.evicted = Some(evicted);
+        }
+        self.slots
+            .insert((transaction.sender.clone(), transaction.nonce), slot);
+        self.transactions.push(transaction);
+        Ok(insertion)
+    }
+
+    /// Replace-by-fee needs both the tip and the cap raised by at least 10%, and by one unit
+    fn outbids(replacement: &PolyTransaction, pending: &PolyTransaction) -> bool {
+        let bumped = |old: u128| old.saturating_add((old / 10).max(1));
+        replacement.fee >= bumped(pending.fee) && replacement.max_fee >= bumped(pending.max_fee)
+    }
+
+    /// Lowest-priority entry that pays less than `incoming`. Only the last nonce of a
+    /// sender can go, and never one of the incoming sender's, so no nonce gap is left.
+    fn eviction_candidate(
+        &self,
+        incoming: &PolyTransaction,
+        fee_market: &FeeMarket,
+    ) -> Option<usize> {
+        let mut tails: HashMap<&str, usize> = HashMap::new();
+        for (index, tx) in self.transactions.iter().enumerate() {
+            if tx.sender == incoming.sender {
+                continue;
+            }
+            let tail = tails.entry(&tx.sender).or_insert(index);
+            if tx.nonce > self.transactions[*tail].nonce {
+                *tail = index;
+            }
+        }
+
+        // Among equal priorities the latest arrival goes first
+        let (index, priority) = tails
+            .into_values()
+            .map(|index| (index, fee_market.priority_score(&self.transactions[index])))
+            .min_by_key(|&(index, priority)| (priority, std::cmp::Reverse(index)))?;
+        (priority < fee_market.priority_score(incoming)).then_some(index)
+    }
+
+    /// Drop every transaction older than the TTL at `now`, along with the sender's
+    /// later nonces that could no longer execute
+    pub fn expire(&mut self, now: i64) -> Vec<PolyTransaction> {
+        let ttl = i64::try_from(self.config.ttl_seconds)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(NANOS_PER_SECOND);
+        let mut first_expired: HashMap<String, u64> = HashMap::new();
+        for tx in &self.transactions {
+            if tx.time_stamp.saturating_add(ttl) <= now {
+                let nonce = first_expired.entry(tx.sender.clone()).or_insert(tx.nonce);
+                *nonce = (*nonce).min(tx.nonce);
+            }
+        }
+        if first_expired.is_empty() {
+            return Vec::new();
+        }
+
+        let (expired, kept) = std::mem::take(&mut self.transactions)
+            .into_iter()
+            .partition(|tx| {
+                first_expired
+                    .get(&tx.sender)
+                    .is_some_and(|&nonce| tx.nonce >= nonce)
+            });
+        self.transactions = kept;
+        self.forget_slots(&expired);
+        expired
+    }
+
+    /// Sort the queue by ordering slot, which no submitter can choose
+    pub fn sort_by_slot(&mut self) {
+        let slots = &self.slots;
+        self.transactions.sort_by_key(|tx| {
+            slots
+                .get(&(tx.sender.clone(), tx.nonce))
+                .copied()
+                .unwrap_or(u64::MAX)
+        });
+    }
+
+    /// Ordering slot of every pending transaction, in queue order
+    pub fn arrival_ranks(&self) -> Vec<u64> {
+        self.transactions
+            .iter()
+            .map(|tx| {
+                self.slots
+                    .get(&(tx.sender.clone(), tx.nonce))
+                    .copied()
+                    .unwrap_or(u64::MAX)
+            })
+            .collect()
+    }
+
+    fn forget_slots(&mut self, transactions: &[PolyTransaction]) {
+        for tx in transactions {
+            self.slots.remove(&(tx.sender.clone(), tx.nonce));
+        }
+    }
+
+    /// Let the ordering strategy rearrange the queue
+    pub fn reorder(&mut self, order: impl FnOnce(&mut [PolyTransaction])) {
+        order(&mut self.transactions);
+    }
+
+    /// Remove the first `count` transactions of the queue
+    pub fn take_front(&mut self, count: usize) -> Vec<PolyTransaction> {
+        let count = count.min(self.transactions.len());
+        let taken: Vec<PolyTransaction> = self.transactions.drain(0..count).collect();
+        self.forget_slots(&taken);
+        taken
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SupportedChain;
+
+    fn transfer(sender: &str, nonce: u64, fee: u128) -> PolyTransaction {
+        let mut tx = PolyTransaction::new(
+            sender.to_string(),
+            "bob".to_string(),
+            100,
+            SupportedChain::Bitcoin,
+            nonce,
+        )
+        .with_fee(fee, fee + 1);
+        tx.time_stamp = 0;
+        tx
+    }
+
+    fn pool(max_size: u64, max_per_sender: u64) -> Mempool {
+        Mempool {
+            config: MempoolConfig {
+                max_size,
+                max_per_sender,
+                ttl_seconds: 60,
+                reveal_window_seconds: 60,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn replace_by_fee_needs_a_ten_percent_bump() {
+        let market = FeeMarket::default();
+        let mut mempool = pool(10, 10);
+        mempool
+            .insert(transfer("alice", 0, 100), 0, &market)
+            .unwrap();
+
+        assert_eq!(
+            mempool
+                .insert(transfer("alice", 0, 105), 0, &market)
+                .unwrap_err(),
+            MempoolError::ReplacementUnderpriced {
+                sender: "alice".to_string(),
+                nonce: 0
+            }
+        );
+
+        let insertion = mempool
+            .insert(transfer("alice", 0, 110), 0, &market)
+            .unwrap();
+        assert_eq!(insertion.replaced.unwrap().fee, 100);
+        assert_eq!(mempool.len(), 1);
+        assert_eq!(mempool.transactions()[0].fee, 110);
+    }
+
+    #[test]
+    fn caps_and_lowest_priority_eviction() {
+        let market = FeeMarket::default();
+        let mut mempool = pool(3, 2);
+        mempool
+            .insert(transfer("alice", 0, 50), 0, &market)
+            .unwrap();
+        mempool.insert(transfer("alice", 1, 5), 0, &market).unwrap();
+        assert!(matches!(
+            mempool.insert(transfer("alice", 2, 90), 0, &market),
+            Err(MempoolError::SenderLimit { limit: 2, .. })
+        ));
+
+        mempool.insert(transfer("bob", 0, 20), 0, &market).unwrap();
+
+        // Plein: le tip le plus bas parmi les derniers nonces part
+        let insertion = mempool
+            .insert(transfer("carol", 0, 30), 0, &market)
+            .unwrap();
+        let evicted = insertion.evicted.unwrap();
+        assert_eq!((evicted.sender.as_str(), evicted.nonce), ("alice", 1));
+
+        // Personne ne paie moins que le nouveau venu
+        assert_eq!(
+            mempool
+                .insert(transfer("dave", 0, 1), 0, &market)
+                .unwrap_err(),
+            MempoolError::Full { capacity: 3 }
+        );
+    }
+
+    #[test]
+    fn expiry_drops_later_nonces_of_the_sender() {
+        let market = FeeMarket::default();
+        let mut mempool = pool(10, 10);
+        let mut fresh = transfer("alice", 1, 10);
+        fresh.time_stamp = 50 * NANOS_PER_SECOND;
+        mempool
+            .insert(transfer("alice", 0, 10), 0, &market)
+            .unwrap();
+        mempool.insert(fresh, 0, &market).unwrap();
+        let mut recent = transfer("bob", 0, 10);
+        recent.time_stamp = 30 * NANOS_PER_SECOND;
+        mempool.insert(recent, 0, &market).unwrap();
+
+        assert!(mempool.expire(59 * NANOS_PER_SECOND).is_empty());
+
+        let expired = mempool.expire(60 * NANOS_PER_SECOND);
+        assert_eq!(expired.len(), 2);
+        assert!(mempool.by_sender("alice").is_empty());
+        assert_eq!(mempool.by_sender("bob").len(), 1);
+    }
+}
@@ -5,8 +5,8 @@ use std::collections::HashMap;
 
 #[derive(CandidType, Deserialize, Debug, Clone, Default)]
 pub struct EthereumVaultModule {
-    pub native_balances: HashMap<String, f64>,    // ETH
-    pub wrapped_balances: HashMap<String, f64>,   // wrapped ETH
+    pub native_balances: HashMap<String, u128>,    // ETH
+    pub wrapped_balances: HashMap<String, u128>,   // wrapped ETH
     pub deposits: Vec<MultiChainTransaction>,
     pub withdrawals: Vec<MultiChainTransaction>,
     pub quantum_secure: bool,
@@ -23,23 +23,26 @@ impl EthereumVaultModule {
         }
     }
     
-    pub fn get_statistics(&self) -> (f64, f64, f64) {
-        let total_deposits: f64 = self.native_balances.values().sum::<f64>() 
-            + self.wrapped_balances.values().sum::<f64>();
-        let native_count = self.native_balances.len() as f64;
-        let wrapped_count = self.wrapped_balances.len() as f64;
+    pub fn get_statistics(&self) -> (u128, u64, u64) {
+        let total_deposits: u128 = self.native_balances.values().sum::<u128>()
+            + self.wrapped_balances.values().sum::<u128>();
+        let native_count = self.native_balances.len() as u64;
+        let wrapped_count = self.wrapped_balances.len() as u64;
         (total_deposits, native_count, wrapped_count)
     }
 }
 
 impl ChainVault for EthereumVaultModule {
-    fn deposit(&mut self, address: String, amount: f64) -> Result<String, String> {
-        if amount <= 0.0 {
+    fn deposit(&mut self, address: String, amount: u128) -> Result<String, String> {
+        if amount == 0 {
             return Err("Amount must be positive".to_string());
         }
         
         // Add to native balance
-        *self.native_balances.entry(address.clone()).or_insert(0.0) += amount;
+        let balance = self.native_balances.entry(address.clone()).or_insert(0);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| "Balance overflow".to_string())?;
         
         // Record transaction
         let tx = MultiChainTransaction::new(
@@ -54,8 +57,8 @@ impl ChainVault for EthereumVaultModule {
         Ok(format!("✅ Ethereum deposit successful: {} to {}", formatted_amount, address))
     }
     
-    fn withdraw(&mut self, address: String, amount: f64, quantum_secure: bool) -> Result<String, String> {
-        let balance = self.native_balances.get(&address).unwrap_or(&0.0);
+    fn withdraw(&mut self, address: String, amount: u128, quantum_secure: bool) -> Result<String, String> {
+        let balance = self.native_balances.get(&address).unwrap_or(&0);
         
         if *balance < amount {
             return Err("Insufficient Ethereum balance".to_string());
@@ -88,8 +91,8 @@ impl ChainVault for EthereumVaultModule {
     }
     
     fn get_balance(&self, address: &str) -> MultiChainBalance {
-        let native = *self.native_balances.get(address).unwrap_or(&0.0);
-        let wrapped = *self.wrapped_balances.get(address).unwrap_or(&0.0);
+        let native = *self.native_balances.get(address).unwrap_or(&0);
+        let wrapped = *self.wrapped_balances.get(address).unwrap_or(&0);
         
         MultiChainBalance {
             chain: SupportedChain::Ethereum,
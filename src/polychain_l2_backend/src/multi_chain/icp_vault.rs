@@ -5,8 +5,8 @@ use std::collections::HashMap;
 
 #[derive(CandidType, Deserialize, Debug, Clone, Default)]
 pub struct ICPVaultModule {
-    pub native_balances: HashMap<String, f64>,
-    pub wrapped_balances: HashMap<String, f64>,
+    pub native_balances: HashMap<String, u128>,
+    pub wrapped_balances: HashMap<String, u128>,
     pub deposits: Vec<MultiChainTransaction>,
     pub withdrawals: Vec<MultiChainTransaction>,
     pub quantum_secure: bool,
@@ -23,22 +23,25 @@ impl ICPVaultModule {
         }
     }
 
-    pub fn get_statistics(&self) -> (f64, f64, f64) {
-        let total_deposits: f64 = self.native_balances.values().sum::<f64>()
-            + self.wrapped_balances.values().sum::<f64>();
-        let native_count = self.native_balances.len() as f64;
-        let wrapped_count = self.wrapped_balances.len() as f64;
+    pub fn get_statistics(&self) -> (u128, u64, u64) {
+        let total_deposits: u128 = self.native_balances.values().sum::<u128>()
+            + self.wrapped_balances.values().sum::<u128>();
+        let native_count = self.native_balances.len() as u64;
+        let wrapped_count = self.wrapped_balances.len() as u64;
         (total_deposits, native_count, wrapped_count)
     }
 }
 
 impl ChainVault for ICPVaultModule {
-    fn deposit(&mut self, address: String, amount: f64) -> Result<String, String> {
-        if amount <= 0.0 {
+    fn deposit(&mut self, address: String, amount: u128) -> Result<String, String> {
+        if amount == 0 {
             return Err("Amount must be positive".to_string());
         }
 
-        *self.native_balances.entry(address.clone()).or_insert(0.0) += amount;
+        let balance = self.native_balances.entry(address.clone()).or_insert(0);
+        *balance = balance
+            .checked_add(amount)
+            .ok_or_else(|| "Balance overflow".to_string())?;
 
         let tx = MultiChainTransaction::new(
             SupportedChain::ICP,
@@ -58,10 +61,10 @@ impl ChainVault for ICPVaultModule {
     fn withdraw(
         &mut self,
         address: String,
-        amount: f64,
+        amount: u128,
         quantum_secure: bool,
     ) -> Result<String, String> {
-        let balance = self.native_balances.get(&address).unwrap_or(&0.0);
+        let balance = self.native_balances.get(&address).unwrap_or(&0);
 
         if *balance < amount {
             return Err("Insufficient ICP balance".to_string());
@@ -101,8 +104,8 @@ impl ChainVault for ICPVaultModule {
     }
 
     fn get_balance(&self, address: &str) -> MultiChainBalance {
-        let native = *self.native_balances.get(address).unwrap_or(&0.0);
-        let wrapped = *self.wrapped_balances.get(address).unwrap_or(&0.0);
+        let native = *self.native_balances.get(address).unwrap_or(&0);
+        let wrapped = *self.wrapped_balances.get(address).unwrap_or(&0);
 
         MultiChainBalance {
             chain: SupportedChain::ICP,
@@ -19,15 +19,15 @@ use std::collections::HashMap;
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
 pub struct MultiChainMetrics {
-    pub total_value_locked: HashMap<SupportedChain, f64>,
-    pub transaction_counts: HashMap<SupportedChain, f64>,
+    pub total_value_locked: HashMap<SupportedChain, u128>,
+    pub transaction_counts: HashMap<SupportedChain, u64>,
     pub compression_savings: HashMap<SupportedChain, f64>,
     pub quantum_ready_percentage: f64,
 }
 
 pub trait ChainVault {
-    fn deposit(&mut self, address: String, amount: f64) -> Result<String, String>;
-    fn withdraw(&mut self, address: String, amount: f64, quantum_secure: bool) -> Result<String, String>;
+    fn deposit(&mut self, address: String, amount: u128) -> Result<String, String>;
+    fn withdraw(&mut self, address: String, amount: u128, quantum_secure: bool) -> Result<String, String>;
     fn get_balance(&self, address: &str) -> MultiChainBalance;
     fn get_chain(&self) -> SupportedChain;
     fn is_quantum_ready(&self) -> bool;
@@ -39,7 +39,7 @@ impl MultiChainVault {
         &mut self,
         chain: SupportedChain,
         address: String,
-        amount: f64,
+        amount: u128,
     ) -> Result<String, String> {
         match chain {
             SupportedChain::Bitcoin => self.bitcoin.deposit(address, amount),
@@ -53,7 +53,7 @@ impl MultiChainVault {
         &mut self,
         chain: SupportedChain,
         address: String,
-        amount: f64,
+        amount: u128,
         quantum_secure: bool,
     ) -> Result<String, String> {
         match chain {
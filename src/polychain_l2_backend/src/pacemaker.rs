@@ -0,0 +1,147 @@
+//! Round pacemaker
+//! Consensus on a height runs in rounds. Each round gives its proposer until a deadline to
+//! get a block certified; once the deadline passes, validators sign a timeout for the round
+//! and a stake quorum of those signatures, a `TimeoutCertificate`, moves everyone to the
+//! next round, led by the next proposer in the VRF ranking. The timeout doubles with every
+//! failed round, up to a cap, so rounds end up outlasting network delays.
+
+use crate::types::TimeoutCertificate;
+use candid::{CandidType, Deserialize};
+
+const NANOS_PER_MILLI: u64 = 1_000_000;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct PacemakerConfig {
+    /// Timeout of round 0
+    pub base_timeout_ms: u64,
+    /// Longest timeout the doubling may reach
+    pub max_timeout_ms: u64,
+}
+
+impl Default for PacemakerConfig {
+    fn default() -> Self {
+        Self {
+            base_timeout_ms: 5_000,
+            max_timeout_ms: 60_000,
+        }
+    }
+}
+
+/// Round progress of the height being decided
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct Pacemaker {
+    config: PacemakerConfig,
+    height: u64,
+    round: u64,
+    /// Time the current round was entered (nanoseconds)
+    round_started_at: u64,
+    /// Timeout certificate that opened the current round
+    high_timeout: Option<TimeoutCertificate>,
+}
+
+impl Pacemaker {
+    pub fn new(config: PacemakerConfig, height: u64, now: u64) -> Self {
+        Self {
+            config,
+            height,
+            round: 0,
+            round_started_at: now,
+            high_timeout: None,
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn round(&self) -> u64 {
+        self.round
+    }
+
+    pub fn high_timeout(&self) -> Option<&TimeoutCertificate> {
+        self.high_timeout.as_ref()
+    }
+
+    /// Timeout of `round`: the base timeout doubled once per earlier round, capped
+    pub fn timeout_ms(&self, round: u64) -> u64 {
+        let factor = 1u64 << round.min(32);
+        self.config
+            .base_timeout_ms
+            .saturating_mul(factor)
+            .min(self.config.max_timeout_ms.max(self.config.base_timeout_ms))
+    }
+
+    /// Time the current round times out (nanoseconds)
+    pub fn deadline(&self) -> u64 {
+        self.round_started_at
+            .saturating_add(self.timeout_ms(self.round).saturating_mul(NANOS_PER_MILLI))
+    }
+
+    /// Nanoseconds left before the current round times out
+    pub fn remaining(&self, now: u64) -> u64 {
+        self.deadline().saturating_sub(now)
+    }
+
+    pub fn is_expired(&self, now: u64) -> bool {
+        now >= self.deadline()
+    }
+
+    /// Enter the round after the one `certificate` times out. A certificate for another
+    /// height or an earlier round changes nothing and returns `false`.
+    pub fn advance_round(&mut self, certificate: TimeoutCertificate, now: u64) -> bool {
+        if certificate.height != self.height || certificate.round < self.round {
+            return false;
+        }
+        self.round = certificate.round.saturating_add(1);
+        self.round_started_at = now;
+        self.high_timeout = Some(certificate);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CryptoAlgorithm, QuorumThreshold};
+
+    fn timeout_certificate(height: u64, round: u64) -> TimeoutCertificate {
+        TimeoutCertificate {
+            algorithm: CryptoAlgorithm::Ecdsa,
+            height,
+            round,
+            votes: Vec::new(),
+            voted_stake: 0,
+            total_stake: 0,
+            threshold: QuorumThreshold::TWO_THIRDS,
+        }
+    }
+
+    #[test]
+    fn rounds_back_off_and_only_move_forward() {
+        let config = PacemakerConfig {
+            base_timeout_ms: 1_000,
+            max_timeout_ms: 3_000,
+        };
+        let mut pacemaker = Pacemaker::new(config, 7, 0);
+        assert_eq!(pacemaker.deadline(), 1_000 * NANOS_PER_MILLI);
+        assert!(!pacemaker.is_expired(999 * NANOS_PER_MILLI));
+        assert!(pacemaker.is_expired(1_000 * NANOS_PER_MILLI));
+
+        // Le délai double à chaque tour raté, sans dépasser le plafond
+        let timeouts: Vec<u64> = (0..4).map(|round| pacemaker.timeout_ms(round)).collect();
+        assert_eq!(timeouts, [1_000, 2_000, 3_000, 3_000]);
+        assert_eq!(pacemaker.timeout_ms(u64::MAX), 3_000);
+
+        // Un certificat du tour 2 fait sauter directement au tour 3
+        let now = 5_000 * NANOS_PER_MILLI;
+        assert!(pacemaker.advance_round(timeout_certificate(7, 2), now));
+        assert_eq!(pacemaker.round(), 3);
+        assert_eq!(pacemaker.remaining(now), 3_000 * NANOS_PER_MILLI);
+        assert_eq!(pacemaker.high_timeout().map(|tc| tc.round), Some(2));
+
+        // Certificats périmés ou d'une autre hauteur
+        assert!(!pacemaker.advance_round(timeout_certificate(7, 1), now));
+        assert!(!pacemaker.advance_round(timeout_certificate(8, 5), now));
+        assert_eq!((pacemaker.height(), pacemaker.round()), (7, 3));
+    }
+}
@@ -0,0 +1,708 @@
+//! Versioned canister state kept in stable memory across upgrades
+//! Blocks, confirmed transactions and the input log grow with the chain: they live in
+//! `stable::StableLog`s and are never serialized on upgrade. `pre_upgrade` only saves the
+//! rest of the state, tagged with its schema version, and `post_upgrade` migrates it.
+//!
+//! A schema change adds a variant and a migration, older schemas and the types they
+//! froze are never edited. Types shared with the current schema may only change in ways
+//! candid still decodes: new enum variants, new `Option` fields.
+//! V1 and V2 were saved whole with `stable_save`, both under the `V1` tag; V1 blobs lack
+//! fields V2 requires, so trying the V2 shape first tells them apart.
+
+use crate::{
+    bitcoin_vault::BitcoinVault,
+    chain::{OrderingStrategy, SequencerSnapshot, GENESIS_PREVIOUS_HASH},
+    commitment::CommitmentBook,
+    fees::FeeMarket,
+    ledger::AccountLedger,
+    mempool::Mempool,
+    producer::BlockProducer,
+    replay::LoggedInput,
+    stable::{self, Contents},
+    state::StateHistory,
+    types::{
+        CryptoAlgorithm, PendingWithdrawal, PolyBlock, PolyTransaction, SupportedChain,
+        TransactionStatus,
+    },
+    validators::{RegistryConfig, ValidatorRegistry},
+    CompressedBatch, CompressionPerformanceMetrics, SequencerMetrics, SimpleVault,
+};
+use candid::{CandidType, Deserialize};
+use std::collections::{HashMap, HashSet};
+
+/// Every schema ever written to stable memory
+#[derive(CandidType, Deserialize)]
+pub enum StableState {
+    V1(StateV1),
+    V2(StateV2),
+    V3(StateV3),
+}
+
+/// Current state schema
+pub type CanisterState = StateV3;
+
+/// Pooled vault reserves, ledger key bindings; the growing logs are in stable memory
+#[derive(CandidType, Deserialize, Clone)]
+pub struct StateV3 {
+    pub bitcoin_vault: BitcoinVault,
+    pub ethereum_vault: SimpleVault,
+    pub icp_vault: SimpleVault,
+    pub solana_vault: SimpleVault,
+    pub sequencer: Option<SequencerSnapshot>,
+    pub sequencer_metrics: SequencerMetrics,
+    pub ledger: AccountLedger,
+    pub state_history: StateHistory,
+    pub transaction_index: HashMap<String, TransactionStatus>,
+    pub block_producer: BlockProducer,
+    pub compressed_batches: Vec<CompressedBatch>,
+    pub compression_metrics: CompressionPerformanceMetrics,
+    pub validator_registry: ValidatorRegistry,
+    /// Height from which every block is signed under the published sequencer key, absent
+    /// from states saved before it was recorded
+    pub signed_from_height: Option<u64>,
+}
+
+/// Log entries a V1 or V2 blob carried inline, to append to the stable logs
+#[derive(Default)]
+pub struct Logs {
+    pub blockchain: Vec<PolyBlock>,
+    pub transaction_pool: Vec<PolyTransaction>,
+    pub input_log: Vec<LoggedInput>,
+}
+
+/// First persisted schema: per-address vault balances, fee-less transactions
+#[derive(CandidType, Deserialize)]
+pub struct StateV1 {
+    pub bitcoin_vault: BitcoinVaultV1,
+    pub ethereum_vault: SimpleVaultV1,
+    pub icp_vault: SimpleVaultV1,
+    pub solana_vault: SimpleVaultV1,
+    pub sequencer: Option<SequencerSnapshotV1>,
+    pub sequencer_metrics: SequencerMetricsV1,
+    pub blockchain: Vec<PolyBlockV1>,
+    pub ledger: AccountLedgerV1,
+    pub transaction_pool: Vec<PolyTransactionV1>,
+    pub compressed_batches: Vec<CompressedBatch>,
+    pub compression_metrics: CompressionPerformanceMetrics,
+}
+
+/// Fee market, state roots, input log and validator registry, still per-address vaults
+#[derive(CandidType, Deserialize)]
+pub struct StateV2 {
+    pub bitcoin_vault: BitcoinVaultV1,
+    pub ethereum_vault: SimpleVaultV1,
+    pub icp_vault: SimpleVaultV1,
+    pub solana_vault: SimpleVaultV1,
+    pub sequencer: Option<SequencerSnapshotV2>,
+    pub sequencer_metrics: SequencerMetrics,
+    pub blockchain: Vec<PolyBlock>,
+    pub ledger: AccountLedgerV2,
+    pub state_history: StateHistory,
+    pub transaction_pool: Vec<PolyTransaction>,
+    pub transaction_index: HashMap<String, TransactionStatus>,
+    pub block_producer: BlockProducer,
+    pub compressed_batches: Vec<CompressedBatch>,
+    pub compression_metrics: CompressionPerformanceMetrics,
+    pub input_log: InputLogV2,
+    pub validator_registry: ValidatorRegistry,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct BitcoinVaultV1 {
+    pub native_reserves: HashMap<String, u64>,
+    pub wrapped_balances: HashMap<String, u64>,
+    pub deposit_threshold: u64,
+    pub total_deposits: u64,
+    pub transaction_count: u64,
+    pub pending_withdrawals: Vec<PendingWithdrawal>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SimpleVaultV1 {
+    pub asset: SupportedChain,
+    /// (native, wrapped) per address
+    pub balances: HashMap<String, (u128, u128)>,
+    pub pending_withdrawals: Vec<PendingWithdrawal>,
+    pub withdrawal_count: u64,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AccountLedgerV1 {
+    pub balances: HashMap<(String, SupportedChain), u128>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct AccountLedgerV2 {
+    pub balances: HashMap<(String, SupportedChain), u128>,
+    pub nonces: HashMap<String, u64>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SequencerSnapshotV1 {
+    pub pending_transactions: Vec<PolyTransactionV1>,
+    pub sequence_counter: u64,
+    pub ordering_strategy: OrderingStrategy,
+    pub next_nonces: HashMap<String, u64>,
+    pub sender_keys: HashMap<String, (CryptoAlgorithm, String)>,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SequencerSnapshotV2 {
+    pub mempool: Mempool,
+    pub commitments: CommitmentBook,
+    pub sequence_counter: u64,
+    pub ordering_strategy: OrderingStrategy,
+    pub next_nonces: HashMap<String, u64>,
+    /// Keys bound on first use, not carried over
+    pub sender_keys: HashMap<String, (CryptoAlgorithm, String)>,
+    pub fee_market: FeeMarket,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct SequencerMetricsV1 {
+    pub total_transactions_sequenced: u64,
+    pub current_pending_count: u64,
+    pub average_batch_size: f64,
+    pub total_batches_created: u64,
+    pub average_sequencing_time_ms: f64,
+    pub fairness_score: f64,
+    pub ordering_strategy: String,
+}
+
+#[derive(CandidType, Deserialize)]
+pub struct InputLogV2 {
+    pub entries: Vec<LoggedInput>,
+}
+
+/// Header without transaction or state root, hashed over the transactions themselves
+#[derive(CandidType, Deserialize)]
+pub struct PolyBlockV1 {
+    pub transactions: Vec<PolyTransactionV1>,
+    pub hash: String,
+    pub previous_hash: String,
+    pub timestamp: i64,
+    pub nonce: u64,
+}
+
+/// Transfer signed before fees existed
+#[derive(CandidType, Deserialize)]
+pub struct PolyTransactionV1 {
+    pub sender: String,
+    pub recipient: String,
+    pub amount: u128,
+    pub asset: SupportedChain,
+    pub nonce: u64,
+    pub time_stamp: i64,
+    pub public_key: Option<String>,
+    pub algorithm: Option<CryptoAlgorithm>,
+    pub signature: Option<String>,
+    pub hash: Option<String>,
+}
+
+impl PolyTransactionV1 {
+    fn migrate(self) -> PolyTransaction {
+        PolyTransaction {
+            sender: self.sender,
+            recipient: self.recipient,
+            amount: self.amount,
+            asset: self.asset,
+            nonce: self.nonce,
+            fee: 0,
+            max_fee: 0,
+            time_stamp: self.time_stamp,
+            public_key: self.public_key,
+            algorithm: self.algorithm,
+            signature: self.signature,
+            hash: self.hash,
+        }
+    }
+}
+
+impl BitcoinVaultV1 {
+    /// Per-address balances add up to the pooled reserves, the ledger keeps each share
+    fn migrate(self) -> BitcoinVault {
+        let total = |balances: &HashMap<String, u64>| {
+            balances
+                .values()
+                .fold(0u64, |total, amount| total.saturating_add(*amount))
+        };
+        let depositors = |balances: &HashMap<String, u64>| -> HashSet<String> {
+            balances.keys().cloned().collect()
+        };
+        BitcoinVault {
+            native_reserve: total(&self.native_reserves),
+            wrapped_reserve: total(&self.wrapped_balances),
+            native_depositors: depositors(&self.native_reserves),
+            wrapped_depositors: depositors(&self.wrapped_balances),
+            deposit_threshold: self.deposit_threshold,
+            total_deposits: self.total_deposits,
+            transaction_count: self.transaction_count,
+            pending_withdrawals: self.pending_withdrawals,
+        }
+    }
+}
+
+impl SimpleVaultV1 {
+    fn migrate(self) -> SimpleVault {
+        let (native_reserve, wrapped_reserve) = self.balances.values().fold(
+            (0u128, 0u128),
+            |(native, wrapped), (address_native, address_wrapped)| {
+                (
+                    native.saturating_add(*address_native),
+                    wrapped.saturating_add(*address_wrapped),
+                )
+            },
+        );
+        SimpleVault {
+            asset: self.asset,
+            native_reserve,
+            wrapped_reserve,
+            pending_withdrawals: self.pending_withdrawals,
+            withdrawal_count: self.withdrawal_count,
+        }
+    }
+}
+
+impl StateV1 {
+    /// V1 headers committed to neither a transaction root nor a state root: each block is
+    /// rebuilt in the V2 format with a zero state root, which commits to no account state,
+    /// and linked to its rebuilt parent. Pending transactions are dropped, their
+    /// signatures do not cover the fee fields V2 signs; senders submit them again.
+    fn migrate(self) -> StateV2 {
+        let mut blockchain: Vec<PolyBlock> = Vec::with_capacity(self.blockchain.len());
+        let mut nonces: HashMap<String, u64> = HashMap::new();
+        let mut transaction_index = HashMap::new();
+        for block in self.blockchain {
+            let previous_hash = blockchain
+                .last()
+                .map_or(GENESIS_PREVIOUS_HASH.to_string(), |parent| {
+                    parent.hash.clone()
+                });
+            let transactions = block
+                .transactions
+                .into_iter()
+                .map(PolyTransactionV1::migrate)
+                .collect();
+            let mut rebuilt =
+                PolyBlock::new_at(transactions, previous_hash, [0; 32], block.timestamp);
+            rebuilt.nonce = block.nonce;
+            rebuilt.hash = rebuilt.calculate_hash();
+
+            for tx in &rebuilt.transactions {
+                let next = nonces.entry(tx.sender.clone()).or_default();
+                *next = (*next).max(tx.nonce + 1);
+                transaction_index.insert(
+                    tx.tx_id(),
+                    TransactionStatus::Sequenced {
+                        block_hash: rebuilt.hash.clone(),
+                        block_height: blockchain.len() as u64,
+                    },
+                );
+            }
+            blockchain.push(rebuilt);
+        }
+
+        let sequencer = self.sequencer.map(|sequencer| {
+            for tx in sequencer.pending_transactions {
+                transaction_index.insert(tx.migrate().tx_id(), TransactionStatus::Dropped);
+            }
+            SequencerSnapshotV2 {
+                mempool: Mempool::default(),
+                commitments: CommitmentBook::default(),
+                sequence_counter: sequencer.sequence_counter,
+                ordering_strategy: sequencer.ordering_strategy,
+                next_nonces: nonces.clone(),
+                sender_keys: sequencer.sender_keys,
+                fee_market: FeeMarket::default(),
+            }
+        });
+
+        let metrics = self.sequencer_metrics;
+        StateV2 {
+            bitcoin_vault: self.bitcoin_vault,
+            ethereum_vault: self.ethereum_vault,
+            icp_vault: self.icp_vault,
+            solana_vault: self.solana_vault,
+            sequencer,
+            sequencer_metrics: SequencerMetrics {
+                total_transactions_sequenced: metrics.total_transactions_sequenced,
+                current_pending_count: 0,
+                average_batch_size: metrics.average_batch_size,
+                total_batches_created: metrics.total_batches_created,
+                average_sequencing_time_ms: metrics.average_sequencing_time_ms,
+                fairness_score: metrics.fairness_score,
+                ordering_strategy: metrics.ordering_strategy,
+                fairness_by_strategy: Vec::new(),
+            },
+            blockchain,
+            ledger: AccountLedgerV2 {
+                balances: self.ledger.balances,
+                nonces,
+            },
+            state_history: StateHistory::default(),
+            transaction_pool: self
+                .transaction_pool
+                .into_iter()
+                .map(PolyTransactionV1::migrate)
+                .collect(),
+            transaction_index,
+            block_producer: BlockProducer::default(),
+            compressed_batches: self.compressed_batches,
+            compression_metrics: self.compression_metrics,
+            // V1 ne journalisait rien: le rejeu ne couvre que les appels suivants
+            input_log: InputLogV2 {
+                entries: Vec::new(),
+            },
+            validator_registry: ValidatorRegistry::new(RegistryConfig::default()),
+        }
+    }
+}
+
+impl StateV2 {
+    /// Vaults pool their reserves and the logs move to stable memory. Keys the sequencer
+    /// bound on first use are not carried over: accounts bind theirs through
+    /// `register_account_key` before spending again.
+    fn migrate(self) -> (StateV3, Logs) {
+        let sequencer = self.sequencer.map(|sequencer| SequencerSnapshot {
+            mempool: sequencer.mempool,
+            commitments: sequencer.commitments,
+            sequence_counter: sequencer.sequence_counter,
+            ordering_strategy: sequencer.ordering_strategy,
+            next_nonces: sequencer.next_nonces,
+            fee_market: sequencer.fee_market,
+            // Les blocs V2 étaient signés par des clés éphémères: une clé neuve est tirée
+            proposer_seed: None,
+            public_key: None,
+        });
+        let state = StateV3 {
+            bitcoin_vault: self.bitcoin_vault.migrate(),
+            ethereum_vault: self.ethereum_vault.migrate(),
+            icp_vault: self.icp_vault.migrate(),
+            solana_vault: self.solana_vault.migrate(),
+            sequencer,
+            sequencer_metrics: self.sequencer_metrics,
+            ledger: AccountLedger::without_keys(self.ledger.balances, self.ledger.nonces),
+            state_history: self.state_history,
+            transaction_index: self.transaction_index,
+            block_producer: self.block_producer,
+            compressed_batches: self.compressed_batches,
+            compression_metrics: self.compression_metrics,
+            validator_registry: self.validator_registry,
+            // Aucun bloc V2 n'est signé par la clé publiée: seuls les suivants le seront
+            signed_from_height: Some(self.blockchain.len() as u64),
+        };
+        let logs = Logs {
+            blockchain: self.blockchain,
+            transaction_pool: self.transaction_pool,
+            input_log: self.input_log.entries,
+        };
+        (state, logs)
+    }
+}
+
+impl StableState {
+    /// Bring a restored state up to the current schema, with the log entries it carried
+    pub fn into_latest(self) -> (CanisterState, Logs) {
+        match self {
+            StableState::V1(state) => state.migrate().migrate(),
+            StableState::V2(state) => state.migrate(),
+            StableState::V3(state) => (state, Logs::default()),
+        }
+    }
+}
+
+/// Blob `stable_save` wrote, V1 and V2 alike under the `V1` tag
+#[derive(CandidType, Deserialize)]
+enum SavedV2 {
+    V1(StateV2),
+}
+
+#[derive(CandidType, Deserialize)]
+enum SavedV1 {
+    V1(StateV1),
+}
+
+/// Decode like `stable_restore`: the blob is followed by the rest of stable memory
+fn decode_blob<T: CandidType + for<'de> Deserialize<'de>>(blob: &[u8]) -> Result<T, String> {
+    let mut deserializer = candid::de::IDLDeserialize::new(blob).map_err(|e| e.to_string())?;
+    deserializer.get_value().map_err(|e| e.to_string())
+}
+
+fn decode_legacy(blob: &[u8]) -> Result<StableState, String> {
+    if let Ok(SavedV2::V1(state)) = decode_blob(blob) {
+        return Ok(StableState::V2(state));
+    }
+    decode_blob(blob)
+        .map(|SavedV1::V1(state)| StableState::V1(state))
+        .map_err(|e| format!("Failed to restore state from stable memory: {e}"))
+}
+
+/// Write the state to stable memory, tagged with its schema version
+pub fn save(state: CanisterState) -> Result<(), String> {
+    let bytes = candid::encode_one(StableState::V3(state))
+        .map_err(|e| format!("Failed to encode state: {e}"))?;
+    stable::write_snapshot(&bytes)
+        .map_err(|e| format!("Failed to save state to stable memory: {e}"))
+}
+
+/// Read the state back and migrate it, `None` when none was saved yet. A legacy blob is
+/// decoded, then stable memory is laid out afresh: the log entries it held are returned
+/// for the caller to append to the stable logs.
+pub fn restore() -> Result<Option<(CanisterState, Logs)>, String> {
+    let contents = stable::contents()
+        .map_err(|e| format!("Failed to restore state from stable memory: {e}"))?;
+    let state = match contents {
+        Contents::Empty => return Ok(None),
+        Contents::Legacy(blob) => {
+            let state = decode_legacy(&blob)?;
+            stable::format();
+            state
+        }
+        Contents::Layout => match stable::read_snapshot() {
+            Some(bytes) => candid::decode_one(&bytes)
+                .map_err(|e| format!("Failed to restore state from stable memory: {e}"))?,
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(state.into_latest()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chain;
+
+    fn compression_metrics() -> CompressionPerformanceMetrics {
+        CompressionPerformanceMetrics {
+            compression_ratio: 0.7,
+            compression_speed_mbps: 125.6,
+            decompression_speed_mbps: 89.3,
+            algorithm: "LZ4".to_string(),
+            total_batches_processed: 0,
+            average_batch_size: 0.0,
+            storage_savings_percentage: 30.0,
+        }
+    }
+
+    fn vault_v1(asset: SupportedChain) -> SimpleVaultV1 {
+        SimpleVaultV1 {
+            asset,
+            balances: HashMap::from([
+                ("alice".to_string(), (100, 5)),
+                ("bob".to_string(), (20, 0)),
+            ]),
+            pending_withdrawals: Vec::new(),
+            withdrawal_count: 3,
+        }
+    }
+
+    fn bitcoin_vault_v1() -> BitcoinVaultV1 {
+        BitcoinVaultV1 {
+            native_reserves: HashMap::from([("alice".to_string(), 200_000)]),
+            wrapped_balances: HashMap::from([("alice".to_string(), 10), ("bob".to_string(), 30)]),
+            deposit_threshold: 100_000,
+            total_deposits: 200_040,
+            transaction_count: 3,
+            pending_withdrawals: Vec::new(),
+        }
+    }
+
+    fn transfer_v1(nonce: u64) -> PolyTransactionV1 {
+        PolyTransactionV1 {
+            sender: "alice".to_string(),
+            recipient: "bob".to_string(),
+            amount: 10,
+            asset: SupportedChain::Icp,
+            nonce,
+            time_stamp: 1_000 + nonce as i64,
+            public_key: None,
+            algorithm: None,
+            signature: None,
+            hash: None,
+        }
+    }
+
+    /// Écrire un état comme le faisait `stable_save`, puis le restaurer
+    fn restore_blob<T: CandidType>(saved: T) -> (CanisterState, Logs) {
+        stable::write_legacy_blob(&candid::encode_args((saved,)).unwrap());
+        let restored = restore().unwrap().unwrap();
+        assert!(matches!(stable::contents(), Ok(Contents::Layout)));
+        restored
+    }
+
+    #[test]
+    fn v1_blob_is_rebuilt_into_a_valid_chain() {
+        let state = StateV1 {
+            bitcoin_vault: bitcoin_vault_v1(),
+            ethereum_vault: vault_v1(SupportedChain::Ethereum),
+            icp_vault: vault_v1(SupportedChain::Icp),
+            solana_vault: vault_v1(SupportedChain::Solana),
+            sequencer: Some(SequencerSnapshotV1 {
+                pending_transactions: vec![transfer_v1(2)],
+                sequence_counter: 4,
+                ordering_strategy: OrderingStrategy::FairOrdering,
+                next_nonces: HashMap::from([("alice".to_string(), 3)]),
+                sender_keys: HashMap::new(),
+            }),
+            sequencer_metrics: SequencerMetricsV1 {
+                total_transactions_sequenced: 3,
+                current_pending_count: 1,
+                average_batch_size: 1.5,
+                total_batches_created: 2,
+                average_sequencing_time_ms: 0.0,
+                fairness_score: 1.0,
+                ordering_strategy: "FairOrdering".to_string(),
+            },
+            blockchain: vec![
+                PolyBlockV1 {
+                    transactions: vec![transfer_v1(0)],
+                    hash: "h0".to_string(),
+                    previous_hash: GENESIS_PREVIOUS_HASH.to_string(),
+                    timestamp: 10,
+                    nonce: 0,
+                },
+                PolyBlockV1 {
+                    transactions: vec![transfer_v1(1)],
+                    hash: "h1".to_string(),
+                    previous_hash: "h0".to_string(),
+                    timestamp: 20,
+                    nonce: 1,
+                },
+            ],
+            ledger: AccountLedgerV1 {
+                balances: HashMap::from([(("bob".to_string(), SupportedChain::Icp), 20)]),
+            },
+            transaction_pool: vec![transfer_v1(0), transfer_v1(1)],
+            compressed_batches: Vec::new(),
+            compression_metrics: compression_metrics(),
+        };
+
+        let (state, logs) = restore_blob(SavedV1::V1(state));
+
+        // Les blocs sont rechaînés dans le format courant et passent la vérification
+        assert_eq!(logs.blockchain.len(), 2);
+        assert_eq!(state.signed_from_height, Some(2));
+        assert_eq!(
+            chain::verify_chain(0, None, &logs.blockchain, None, 2),
+            Ok(())
+        );
+        assert_eq!(logs.blockchain[1].nonce, 1);
+        assert_eq!(logs.transaction_pool.len(), 2);
+        assert!(logs.input_log.is_empty());
+
+        // Réserves mises en commun, soldes et nonces dans le ledger
+        assert_eq!(state.icp_vault.reserves(), (120, 5));
+        assert_eq!(state.bitcoin_vault.reserves(), (200_000, 40));
+        assert_eq!(state.ledger.balance_of("bob", SupportedChain::Icp), 20);
+        assert_eq!(state.ledger.accounts()["alice"].nonce, 2);
+
+        // La transaction en attente ne couvre pas les frais: abandonnée
+        let pending = transfer_v1(2).migrate().tx_id();
+        assert_eq!(
+            state.transaction_index.get(&pending),
+            Some(&TransactionStatus::Dropped)
+        );
+        let sequencer = state.sequencer.unwrap();
+        assert_eq!(sequencer.next_nonces["alice"], 2);
+        assert_eq!(sequencer.sequence_counter, 4);
+    }
+
+    #[test]
+    fn v2_blob_pools_reserves_and_hands_back_its_logs() {
+        let block = PolyBlock::new_at(
+            Vec::new(),
+            GENESIS_PREVIOUS_HASH.to_string(),
+            crate::state::empty_state_root(),
+            10,
+        );
+        let state = StateV2 {
+            bitcoin_vault: bitcoin_vault_v1(),
+            ethereum_vault: vault_v1(SupportedChain::Ethereum),
+            icp_vault: vault_v1(SupportedChain::Icp),
+            solana_vault: vault_v1(SupportedChain::Solana),
+            sequencer: Some(SequencerSnapshotV2 {
+                mempool: Mempool::default(),
+                commitments: CommitmentBook::default(),
+                sequence_counter: 1,
+                ordering_strategy: OrderingStrategy::FairOrdering,
+                next_nonces: HashMap::from([("alice".to_string(), 1)]),
+                sender_keys: HashMap::from([(
+                    "alice".to_string(),
+                    (CryptoAlgorithm::Ecdsa, "02ab".to_string()),
+                )]),
+                fee_market: FeeMarket::default(),
+            }),
+            sequencer_metrics: SequencerMetrics {
+                total_transactions_sequenced: 1,
+                current_pending_count: 0,
+                average_batch_size: 1.0,
+                total_batches_created: 1,
+                average_sequencing_time_ms: 0.0,
+                fairness_score: 1.0,
+                ordering_strategy: "FairOrdering".to_string(),
+                fairness_by_strategy: Vec::new(),
+            },
+            blockchain: vec![block.clone()],
+            ledger: AccountLedgerV2 {
+                balances: HashMap::from([(("alice".to_string(), SupportedChain::Icp), 105)]),
+                nonces: HashMap::from([("alice".to_string(), 1)]),
+            },
+            state_history: StateHistory::default(),
+            transaction_pool: Vec::new(),
+            transaction_index: HashMap::new(),
+            block_producer: BlockProducer::default(),
+            compressed_batches: Vec::new(),
+            compression_metrics: compression_metrics(),
+            input_log: InputLogV2 {
+                entries: Vec::new(),
+            },
+            validator_registry: ValidatorRegistry::new(RegistryConfig::default()),
+        };
+
+        let (state, logs) = restore_blob(SavedV2::V1(state));
+
+        assert_eq!(logs.blockchain.len(), 1);
+        assert_eq!(logs.blockchain[0].hash, block.hash);
+        assert_eq!(state.ethereum_vault.reserves(), (120, 5));
+        assert_eq!(state.ledger.balance_of("alice", SupportedChain::Icp), 105);
+        // Les clés liées au premier usage ne sont pas reprises
+        assert_eq!(state.ledger.key_of("alice"), None);
+        assert_eq!(state.sequencer.unwrap().next_nonces["alice"], 1);
+    }
+
+    #[test]
+    fn saved_state_comes_back_without_migration() {
+        assert!(restore().unwrap().is_none());
+
+        let (state, _) = StableState::V1(StateV1 {
+            bitcoin_vault: bitcoin_vault_v1(),
+            ethereum_vault: vault_v1(SupportedChain::Ethereum),
+            icp_vault: vault_v1(SupportedChain::Icp),
+            solana_vault: vault_v1(SupportedChain::Solana),
+            sequencer: None,
+            sequencer_metrics: SequencerMetricsV1 {
+                total_transactions_sequenced: 0,
+                current_pending_count: 0,
+                average_batch_size: 0.0,
+                total_batches_created: 0,
+                average_sequencing_time_ms: 0.0,
+                fairness_score: 1.0,
+                ordering_strategy: "FairOrdering".to_string(),
+            },
+            blockchain: Vec::new(),
+            ledger: AccountLedgerV1 {
+                balances: HashMap::new(),
+            },
+            transaction_pool: Vec::new(),
+            compressed_batches: Vec::new(),
+            compression_metrics: compression_metrics(),
+        })
+        .into_latest();
+        save(state).unwrap();
+        stable::reload();
+
+        let (restored, logs) = restore().unwrap().unwrap();
+        assert_eq!(restored.solana_vault.reserves(), (120, 5));
+        assert!(logs.blockchain.is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+//! Automatic block production
+//! A canister timer fires every `block_time_ms` and seals the pending transactions into
+//! a block, so the chain advances without anyone calling `sequence_transaction_batch`.
+//! The policy below decides what a tick does when the mempool is empty. A tick that seals
+//! nothing still counts as an empty block for the fee market, so base fees decay while idle.
+
+use candid::{CandidType, Deserialize};
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Largest batch a single block may take, as for `sequence_transaction_batch`
+pub const MAX_BATCH_SIZE: u64 = 1_000;
+
+/// Shortest interval between two ticks
+pub const MIN_BLOCK_TIME_MS: u64 = 500;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub enum EmptyBlockPolicy {
+    /// No block without transactions
+    Skip,
+    /// A block on every tick, empty or not
+    Always,
+    /// An empty block once the chain has been idle this long, as a liveness heartbeat
+    AfterIdle { seconds: u64 },
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockProductionConfig {
+    pub block_time_ms: u64,
+    pub max_batch_size: u64,
+    pub empty_blocks: EmptyBlockPolicy,
+}
+
+impl Default for BlockProductionConfig {
+    fn default() -> Self {
+        Self {
+            block_time_ms: 2_000,
+            max_batch_size: 100,
+            empty_blocks: EmptyBlockPolicy::Skip,
+        }
+    }
+}
+
+impl BlockProductionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.block_time_ms < MIN_BLOCK_TIME_MS {
+            return Err(format!(
+                "Block time must be at least {MIN_BLOCK_TIME_MS} ms"
+            ));
+        }
+        if self.max_batch_size == 0 || self.max_batch_size > MAX_BATCH_SIZE {
+            return Err(format!(
+                "Max batch size must be between 1 and {MAX_BATCH_SIZE}"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Producer settings and progress, persisted so a pause survives upgrades
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct BlockProducer {
+    pub config: BlockProductionConfig,
+    pub paused: bool,
+    pub blocks_produced: u64,
+    pub empty_blocks_produced: u64,
+    /// Error of the last tick, cleared by the next successful one
+    pub last_error: Option<String>,
+}
+
+impl BlockProducer {
+    /// Whether an empty tick at `now` seals a block anyway, given the time of the
+    /// chain's last block however it was produced
+    pub fn seals_empty_block(&self, now: u64, last_block_at: Option<u64>) -> bool {
+        match self.config.empty_blocks {
+            EmptyBlockPolicy::Skip => false,
+            EmptyBlockPolicy::Always => true,
+            EmptyBlockPolicy::AfterIdle { seconds } => last_block_at.is_none_or(|last| {
+                now.saturating_sub(last) >= seconds.saturating_mul(NANOS_PER_SECOND)
+            }),
+        }
+    }
+
+    /// Record the outcome of a tick: the size of the sealed block, if any
+    pub fn record_tick(&mut self, result: Result<Option<u64>, String>) {
+        match result {
+            Ok(Some(transaction_count)) => {
+                self.blocks_produced += 1;
+                if transaction_count == 0 {
+                    self.empty_blocks_produced += 1;
+                }
+                self.last_error = None;
+            }
+            Ok(None) => self.last_error = None,
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_block_policy() {
+        let mut producer = BlockProducer::default();
+        assert!(!producer.seals_empty_block(0, None));
+
+        producer.config.empty_blocks = EmptyBlockPolicy::Always;
+        assert!(producer.seals_empty_block(0, Some(0)));
+
+        producer.config.empty_blocks = EmptyBlockPolicy::AfterIdle { seconds: 10 };
+        // Chaîne vide: le premier tick produit la genèse
+        assert!(producer.seals_empty_block(0, None));
+        let last = Some(5 * NANOS_PER_SECOND);
+        assert!(!producer.seals_empty_block(14 * NANOS_PER_SECOND, last));
+        assert!(producer.seals_empty_block(15 * NANOS_PER_SECOND, last));
+
+        producer.record_tick(Ok(Some(3)));
+        producer.record_tick(Err("ledger".to_string()));
+        producer.record_tick(Ok(Some(0)));
+        assert_eq!(producer.blocks_produced, 2);
+        assert_eq!(producer.empty_blocks_produced, 1);
+        assert_eq!(producer.last_error, None);
+    }
+
+    #[test]
+    fn config_bounds() {
+        assert!(BlockProductionConfig::default().validate().is_ok());
+        let too_fast = BlockProductionConfig {
+            block_time_ms: 100,
+            ..Default::default()
+        };
+        assert!(too_fast.validate().is_err());
+        let too_large = BlockProductionConfig {
+            max_batch_size: MAX_BATCH_SIZE + 1,
+            ..Default::default()
+        };
+        assert!(too_large.validate().is_err());
+    }
+}
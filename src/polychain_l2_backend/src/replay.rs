@@ -0,0 +1,581 @@
+//! Ordered input log and deterministic replay
+//! Every call that changes the chain, the ledger or the vaults is appended to the log
+//! with the time it ran at and the error it returned. The sequencer's own randomness,
+//! its VRF evaluations and the key it signs blocks with, cannot be drawn again, so each
+//! sealing call also logs what it drew and the hash of the block it built, and the call
+//! that provisions the key logs its public half, which replayed blocks verify against.
+//! Replaying the log natively on fresh state, with a `ManualClock` set to the logged
+//! times and the logged randomness in place of the sequencer key, must rebuild every
+//! block with the same hash.
+
+use crate::{
+    commitment::CommitmentSubmission,
+    errors::StorageError,
+    evidence::EquivocationEvidence,
+    ledger::{AccountKeyRegistration, WithdrawalAuthorization},
+    mempool::MempoolConfig,
+    producer::BlockProductionConfig,
+    stable::{self, StableLog},
+    types::{BlockProposer, OrderingProof, PolyBlock, PolyTransaction, SupportedChain},
+    validators::ValidatorRegistration,
+};
+use candid::{CandidType, Deserialize};
+use std::cell::RefCell;
+
+/// A state-changing call, with the arguments it was made with
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub enum Input {
+    CreateSequencer {
+        ordering_strategy: String,
+    },
+    /// Signed transaction as admitted, arrival time included
+    AddTransaction {
+        transaction: PolyTransaction,
+    },
+    /// Unsigned commitment, from before commitments were bound to their sender
+    SubmitCommitment {
+        commitment: String,
+    },
+    SubmitSignedCommitment {
+        submission: CommitmentSubmission,
+    },
+    RevealTransaction {
+        transaction: PolyTransaction,
+        salt: String,
+    },
+    ConfigureMempool {
+        config: MempoolConfig,
+    },
+    SequenceBatch {
+        batch_size: Option<u64>,
+    },
+    BlockProductionTick,
+    PauseBlockProduction,
+    ResumeBlockProduction,
+    ConfigureBlockProduction {
+        config: BlockProductionConfig,
+    },
+    DepositBitcoin {
+        address: String,
+        amount_satoshi: u64,
+    },
+    DepositBitcoinWithCrypto {
+        address: String,
+        amount_satoshi: u64,
+        crypto_algorithm: Option<String>,
+        quantum_threat_level: Option<u8>,
+    },
+    DepositEthereum {
+        address: String,
+        amount_wei: u128,
+    },
+    DepositIcp {
+        address: String,
+        amount_e8s: u64,
+    },
+    DepositSolana {
+        address: String,
+        amount_lamports: u64,
+    },
+    WithdrawBitcoin {
+        address: String,
+        amount_satoshi: u64,
+        quantum_secure: bool,
+        /// Account key signature, absent from entries logged before it was required
+        authorization: Option<WithdrawalAuthorization>,
+    },
+    WithdrawBitcoinAdaptive {
+        address: String,
+        amount_satoshi: u64,
+        auto_select_crypto: bool,
+        quantum_threat_level: Option<u8>,
+        /// Account key signature, absent from entries logged before it was required
+        authorization: Option<WithdrawalAuthorization>,
+    },
+    WithdrawEthereum {
+        address: String,
+        amount_wei: u128,
+        quantum_secure: bool,
+        /// Account key signature, absent from entries logged before it was required
+        authorization: Option<WithdrawalAuthorization>,
+    },
+    WithdrawIcp {
+        address: String,
+        amount_e8s: u64,
+        quantum_secure: bool,
+        /// Account key signature, absent from entries logged before it was required
+        authorization: Option<WithdrawalAuthorization>,
+    },
+    WithdrawSolana {
+        address: String,
+        amount_lamports: u64,
+        quantum_secure: bool,
+        /// Account key signature, absent from entries logged before it was required
+        authorization: Option<WithdrawalAuthorization>,
+    },
+    ConfirmWithdrawal {
+        asset: SupportedChain,
+        tx_id: String,
+    },
+    RegisterAccountKey {
+        registration: AccountKeyRegistration,
+    },
+    RegisterValidator {
+        registration: ValidatorRegistration,
+    },
+    BondStake {
+        address: String,
+        amount: u128,
+        /// Account key signature, absent from entries logged before it was required
+        signature: Option<String>,
+    },
+    UnbondStake {
+        address: String,
+        amount: u128,
+        signature: Option<String>,
+    },
+    SubmitEvidence {
+        evidence: EquivocationEvidence,
+    },
+}
+
+/// Randomness a sealing call drew and the block it built
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct SealingTrace {
+    /// VRF evaluation a VrfRandom batch was ordered by
+    pub ordering_proof: Option<OrderingProof>,
+    /// Key and signature of the sealed block
+    pub proposer: Option<BlockProposer>,
+    pub block_hash: Option<String>,
+    /// Sequencer key the call provisioned, its public half only
+    pub sequencer_key: Option<String>,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone)]
+pub struct LoggedInput {
+    /// Position in the log, from 0
+    pub sequence: u64,
+    /// Runtime clock when the call ran, in nanoseconds
+    pub time: u64,
+    pub input: Input,
+    /// Error the call returned, `None` if it succeeded
+    pub error: Option<String>,
+    pub sealing: SealingTrace,
+}
+
+/// Logged calls, kept in stable memory so upgrades never copy them
+pub struct InputLog {
+    entries: StableLog<LoggedInput>,
+}
+
+impl Default for InputLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InputLog {
+    pub const fn new() -> Self {
+        Self {
+            entries: StableLog::new(stable::INPUT_INDEX, stable::INPUT_DATA),
+        }
+    }
+
+    /// Append a call, trapping rather than keeping an effect the log would miss
+    pub fn append(
+        &self,
+        time: u64,
+        input: Input,
+        error: Option<String>,
+        sealing: SealingTrace,
+    ) -> u64 {
+        let sequence = self.entries.len();
+        let entry = LoggedInput {
+            sequence,
+            time,
+            input,
+            error,
+            sealing,
+        };
+        self.push(&entry)
+            .unwrap_or_else(|e| panic!("Failed to log input {sequence}: {e}"))
+    }
+
+    /// Append an entry restored from an earlier schema, sequence included
+    pub fn push(&self, entry: &LoggedInput) -> Result<u64, StorageError> {
+        self.entries.push(entry)
+    }
+
+    /// Entries from `start` on, in log order
+    pub fn iter_from(&self, start: u64) -> impl Iterator<Item = LoggedInput> + '_ {
+        self.entries.iter_from(start)
+    }
+
+    pub fn get(&self, sequence: u64) -> Option<LoggedInput> {
+        self.entries.get(sequence)
+    }
+}
+
+thread_local! {
+    /// Trace of the call being logged, filled in as the sequencer draws and seals
+    static TRACE: RefCell<Option<SealingTrace>> = const { RefCell::new(None) };
+    /// Trace of the logged call being replayed, `None` outside a replay
+    static RECORDED: RefCell<Option<SealingTrace>> = const { RefCell::new(None) };
+}
+
+/// Start tracing a call about to be logged
+pub(crate) fn open_trace() {
+    TRACE.with(|trace| *trace.borrow_mut() = Some(SealingTrace::default()));
+}
+
+/// What the call drew and sealed since `open_trace`
+pub(crate) fn close_trace() -> SealingTrace {
+    TRACE.with(|trace| trace.borrow_mut().take().unwrap_or_default())
+}
+
+pub(crate) fn note_ordering_proof(proof: Option<&OrderingProof>) {
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.ordering_proof = proof.cloned();
+        }
+    });
+}
+
+pub(crate) fn note_sequencer_key(public_key: &str) {
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.sequencer_key = Some(public_key.to_string());
+        }
+    });
+}
+
+pub(crate) fn note_block(block: &PolyBlock) {
+    TRACE.with(|trace| {
+        if let Some(trace) = trace.borrow_mut().as_mut() {
+            trace.proposer = block.proposer.clone();
+            trace.block_hash = Some(block.hash.clone());
+        }
+    });
+}
+
+/// Randomness logged by the call being replayed, `None` when not replaying
+pub(crate) fn recorded() -> Option<SealingTrace> {
+    RECORDED.with(|recorded| recorded.borrow().clone())
+}
+
+/// Rebuilt canister state after a successful replay
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ReplayReport {
+    pub inputs: u64,
+    /// Rebuilt chain, every block checked against the log
+    pub blocks: Vec<PolyBlock>,
+    /// Rest of the rebuilt state: ledger, vaults, sequencer
+    pub(crate) state: crate::persistence::CanisterState,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ReplayReport {
+    /// Rebuilt L2 balance, in the asset's base unit
+    pub fn balance(&self, address: &str, asset: SupportedChain) -> u128 {
+        self.state.ledger.balance_of(address, asset)
+    }
+
+    /// Rebuilt (native, wrapped) pooled reserves of an asset's vault
+    pub fn vault_reserves(&self, asset: SupportedChain) -> (u128, u128) {
+        match asset {
+            SupportedChain::Bitcoin => {
+                let (native, wrapped) = self.state.bitcoin_vault.reserves();
+                (native.into(), wrapped.into())
+            }
+            SupportedChain::Ethereum => self.state.ethereum_vault.reserves(),
+            SupportedChain::Icp => self.state.icp_vault.reserves(),
+            SupportedChain::Solana => self.state.solana_vault.reserves(),
+        }
+    }
+}
+
+/// Replay `log` on fresh canister state and check every block it seals against the log.
+/// Runs on its own thread, so the state of the calling thread is left untouched.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn replay(log: &[LoggedInput]) -> Result<ReplayReport, crate::errors::ReplayError> {
+    let log = log.to_vec();
+    std::thread::spawn(move || replay_on_fresh_state(&log))
+        .join()
+        .unwrap_or_else(|panic| {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            Err(crate::errors::ReplayError::Panicked(message))
+        })
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn replay_on_fresh_state(log: &[LoggedInput]) -> Result<ReplayReport, crate::errors::ReplayError> {
+    use crate::clock::{self, ManualClock};
+    use crate::errors::ReplayError;
+
+    let clock = ManualClock::default();
+    clock::install(std::rc::Rc::new(clock.clone()));
+
+    for entry in log {
+        clock.set(entry.time);
+        RECORDED.with(|recorded| *recorded.borrow_mut() = Some(entry.sealing.clone()));
+        let returned = dispatch(entry.input.clone())
+            .ok_or(ReplayError::Suspended {
+                sequence: entry.sequence,
+            })?
+            .err();
+        RECORDED.with(|recorded| *recorded.borrow_mut() = None);
+
+        let replayed = crate::INPUT_LOG.with(|log| log.get(entry.sequence));
+        let (replayed_hash, replayed_error) = match replayed {
+            Some(replayed) => (replayed.sealing.block_hash, replayed.error),
+            None => (None, returned),
+        };
+        if replayed_hash != entry.sealing.block_hash {
+            return Err(ReplayError::BlockHashMismatch {
+                sequence: entry.sequence,
+                logged: entry.sealing.block_hash.clone(),
+                replayed: replayed_hash,
+            });
+        }
+        if replayed_error != entry.error {
+            return Err(ReplayError::OutcomeMismatch {
+                sequence: entry.sequence,
+                logged: entry.error.clone(),
+                replayed: replayed_error,
+            });
+        }
+    }
+
+    Ok(ReplayReport {
+        inputs: log.len() as u64,
+        blocks: crate::BLOCKCHAIN_STATE.with(|chain| chain.iter().collect()),
+        state: crate::snapshot_state(),
+    })
+}
+
+/// Make the call again through its endpoint, `None` if it did not complete in one poll
+#[cfg(not(target_arch = "wasm32"))]
+fn dispatch(input: Input) -> Option<Result<(), String>> {
+    fn done<T>(result: Result<T, String>) -> Result<(), String> {
+        result.map(drop)
+    }
+
+    let result = match input {
+        Input::CreateSequencer { ordering_strategy } => done(block_on(
+            crate::create_transaction_sequencer(ordering_strategy),
+        )?),
+        Input::AddTransaction { transaction: tx } => {
+            let (public_key, algorithm, signature) = signature_of(&tx);
+            done(block_on(crate::add_transaction_to_sequencer(
+                tx.sender,
+                tx.recipient,
+                tx.amount,
+                tx.asset,
+                tx.nonce,
+                tx.fee,
+                tx.max_fee,
+                public_key,
+                algorithm,
+                signature,
+            ))?)
+        }
+        // Plus accepté: le rejeu d'un ancien journal s'arrête sur un résultat différent
+        Input::SubmitCommitment { .. } => {
+            Err("Unsigned commitments are no longer accepted".to_string())
+        }
+        Input::SubmitSignedCommitment { submission } => {
+            done(crate::submit_transaction_commitment(submission))
+        }
+        Input::RevealTransaction {
+            transaction: tx,
+            salt,
+        } => {
+            let (public_key, algorithm, signature) = signature_of(&tx);
+            done(block_on(crate::reveal_transaction(
+                tx.sender,
+                tx.recipient,
+                tx.amount,
+                tx.asset,
+                tx.nonce,
+                tx.fee,
+                tx.max_fee,
+                public_key,
+                algorithm,
+                signature,
+                salt,
+            ))?)
+        }
+        Input::ConfigureMempool { config } => done(crate::configure_mempool(config)),
+        Input::SequenceBatch { batch_size } => {
+            done(block_on(crate::sequence_transaction_batch(batch_size))?)
+        }
+        Input::BlockProductionTick => {
+            crate::produce_block_tick();
+            Ok(())
+        }
+        Input::PauseBlockProduction => done(crate::pause_block_production()),
+        Input::ResumeBlockProduction => done(crate::resume_block_production()),
+        Input::ConfigureBlockProduction { config } => {
+            done(crate::configure_block_production(config))
+        }
+        Input::DepositBitcoin {
+            address,
+            amount_satoshi,
+        } => done(block_on(crate::deposit_bitcoin(address, amount_satoshi))?),
+        Input::DepositBitcoinWithCrypto {
+            address,
+            amount_satoshi,
+            crypto_algorithm,
+            quantum_threat_level,
+        } => done(block_on(crate::deposit_bitcoin_with_crypto(
+            address,
+            amount_satoshi,
+            crypto_algorithm,
+            quantum_threat_level,
+        ))?),
+        Input::DepositEthereum {
+            address,
+            amount_wei,
+        } => done(block_on(crate::deposit_ethereum(address, amount_wei))?),
+        Input::DepositIcp {
+            address,
+            amount_e8s,
+        } => done(block_on(crate::deposit_icp(address, amount_e8s))?),
+        Input::DepositSolana {
+            address,
+            amount_lamports,
+        } => done(block_on(crate::deposit_solana(address, amount_lamports))?),
+        Input::WithdrawBitcoin {
+            address,
+            amount_satoshi,
+            quantum_secure,
+            authorization: Some(authorization),
+        } => done(block_on(crate::withdraw_bitcoin(
+            address,
+            amount_satoshi,
+            quantum_secure,
+            authorization,
+        ))?),
+        Input::WithdrawBitcoinAdaptive {
+            address,
+            amount_satoshi,
+            auto_select_crypto,
+            quantum_threat_level,
+            authorization: Some(authorization),
+        } => done(block_on(crate::withdraw_bitcoin_adaptive(
+            address,
+            amount_satoshi,
+            auto_select_crypto,
+            quantum_threat_level,
+            authorization,
+        ))?),
+        Input::WithdrawEthereum {
+            address,
+            amount_wei,
+            quantum_secure,
+            authorization: Some(authorization),
+        } => done(block_on(crate::withdraw_ethereum(
+            address,
+            amount_wei,
+            quantum_secure,
+            authorization,
+        ))?),
+        Input::WithdrawIcp {
+            address,
+            amount_e8s,
+            quantum_secure,
+            authorization: Some(authorization),
+        } => done(block_on(crate::withdraw_icp(
+            address,
+            amount_e8s,
+            quantum_secure,
+            authorization,
+        ))?),
+        Input::WithdrawSolana {
+            address,
+            amount_lamports,
+            quantum_secure,
+            authorization: Some(authorization),
+        } => done(block_on(crate::withdraw_solana(
+            address,
+            amount_lamports,
+            quantum_secure,
+            authorization,
+        ))?),
+        Input::WithdrawBitcoin {
+            authorization: None,
+            ..
+        }
+        | Input::WithdrawBitcoinAdaptive {
+            authorization: None,
+            ..
+        }
+        | Input::WithdrawEthereum {
+            authorization: None,
+            ..
+        }
+        | Input::WithdrawIcp {
+            authorization: None,
+            ..
+        }
+        | Input::WithdrawSolana {
+            authorization: None,
+            ..
+        } => Err("Unsigned withdrawals are no longer accepted".to_string()),
+        Input::ConfirmWithdrawal { asset, tx_id } => done(crate::confirm_withdrawal(asset, tx_id)),
+        Input::RegisterAccountKey { registration } => {
+            done(crate::register_account_key(registration))
+        }
+        Input::RegisterValidator { registration } => done(crate::register_validator(registration)),
+        Input::BondStake {
+            address,
+            amount,
+            signature,
+        } => done(crate::bond_stake(
+            address,
+            amount,
+            signature.unwrap_or_default(),
+        )),
+        Input::UnbondStake {
+            address,
+            amount,
+            signature,
+        } => done(crate::unbond_stake(
+            address,
+            amount,
+            signature.unwrap_or_default(),
+        )),
+        Input::SubmitEvidence { evidence } => done(crate::submit_equivocation_evidence(evidence)),
+    };
+    Some(result)
+}
+
+/// Key and signature the endpoint takes apart, as they were admitted
+#[cfg(not(target_arch = "wasm32"))]
+fn signature_of(transaction: &PolyTransaction) -> (String, crate::types::CryptoAlgorithm, String) {
+    (
+        transaction.public_key.clone().unwrap_or_default(),
+        transaction
+            .algorithm
+            .unwrap_or(crate::types::CryptoAlgorithm::Ecdsa),
+        transaction.signature.clone().unwrap_or_default(),
+    )
+}
+
+/// Poll an endpoint once. The state-changing endpoints never await, so they complete here.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn block_on<F: std::future::Future>(future: F) -> Option<F::Output> {
+    use std::task::{Context, Poll, Waker};
+    let mut future = std::pin::pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
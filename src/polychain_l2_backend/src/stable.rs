@@ -0,0 +1,485 @@
+//! Stable memory layout
+//! Stable memory is cut into buckets of `BUCKET_PAGES` pages, each owned by one virtual
+//! memory. A virtual memory grows by claiming the next free bucket, so every structure
+//! grows in place and nothing is serialized again on upgrade.
+//! Page 0 holds the header: magic `PL2M`, layout version, number of claimed buckets
+//! (u32 LE) at byte 8, then from byte 16 the id of the memory owning each bucket.
+//! Bucket `i` starts at page `1 + i * BUCKET_PAGES`.
+//!
+//! `StableLog` is an append-only log of candid-encoded entries on two virtual memories:
+//! the index holds the entry count (u64 LE) followed by the end offset of every entry,
+//! the data memory the entries back to back.
+
+use crate::errors::StorageError;
+use candid::{CandidType, Deserialize};
+use ic_cdk::api::stable::{StableMemory, StableMemoryError, WASM_PAGE_SIZE_IN_BYTES};
+use std::{cell::RefCell, marker::PhantomData};
+
+const MAGIC: &[u8; 4] = b"PL2M";
+const LAYOUT_VERSION: u8 = 1;
+/// First bytes of a blob written by `ic_cdk::storage::stable_save`
+const CANDID_MAGIC: &[u8; 4] = b"DIDL";
+const HEADER_LEN: u64 = 16;
+const BUCKET_PAGES: u64 = 128;
+const BUCKET_BYTES: u64 = BUCKET_PAGES * WASM_PAGE_SIZE_IN_BYTES;
+/// One owner byte per bucket must fit in the header page
+const MAX_BUCKETS: u64 = WASM_PAGE_SIZE_IN_BYTES - HEADER_LEN;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryId(u8);
+
+/// Candid snapshot of the heap state, rewritten by every `pre_upgrade`
+pub const SNAPSHOT: MemoryId = MemoryId(0);
+pub const BLOCK_INDEX: MemoryId = MemoryId(1);
+pub const BLOCK_DATA: MemoryId = MemoryId(2);
+pub const INPUT_INDEX: MemoryId = MemoryId(3);
+pub const INPUT_DATA: MemoryId = MemoryId(4);
+pub const TRANSACTION_INDEX: MemoryId = MemoryId(5);
+pub const TRANSACTION_DATA: MemoryId = MemoryId(6);
+const MEMORY_COUNT: usize = 7;
+
+#[cfg(target_arch = "wasm32")]
+type RawMemory = ic_cdk::api::stable::CanisterStableMemory;
+#[cfg(not(target_arch = "wasm32"))]
+type RawMemory = PageMemory;
+
+/// What stable memory held when the canister started
+pub enum Contents {
+    /// Never written
+    Empty,
+    /// Candid blob saved whole by a release that predates this layout
+    Legacy(Vec<u8>),
+    /// Already laid out in buckets
+    Layout,
+}
+
+pub fn contents() -> Result<Contents, StorageError> {
+    let raw = RawMemory::default();
+    if raw.stable_size() == 0 {
+        return Ok(Contents::Empty);
+    }
+    let mut magic = [0u8; 4];
+    raw.stable_read(0, &mut magic);
+    if &magic == MAGIC {
+        Ok(Contents::Layout)
+    } else if &magic == CANDID_MAGIC {
+        // Comme `stable_restore`: tout lire, le décodeur s'arrête à la fin du blob
+        let mut blob = vec![0u8; (raw.stable_size() * WASM_PAGE_SIZE_IN_BYTES) as usize];
+        raw.stable_read(0, &mut blob);
+        Ok(Contents::Legacy(blob))
+    } else {
+        Err(StorageError::UnknownLayout)
+    }
+}
+
+/// Write an empty layout over whatever stable memory holds. Only for a fresh canister
+/// or once a legacy blob has been decoded: every virtual memory starts empty.
+pub fn format() {
+    LAYOUT.with(|layout| *layout.borrow_mut() = Some(Layout::format()));
+}
+
+/// Bucket owners, read from the header on first use
+struct Layout {
+    /// Buckets of each memory, in the order they were claimed
+    buckets: Vec<Vec<u64>>,
+    claimed: u64,
+}
+
+thread_local! {
+    static LAYOUT: RefCell<Option<Layout>> = const { RefCell::new(None) };
+}
+
+fn with_layout<R>(f: impl FnOnce(&mut Layout) -> R) -> R {
+    LAYOUT.with(|layout| f(layout.borrow_mut().get_or_insert_with(Layout::load)))
+}
+
+impl Layout {
+    fn load() -> Self {
+        let raw = RawMemory::default();
+        if raw.stable_size() == 0 {
+            return Self::format();
+        }
+        let mut header = [0u8; HEADER_LEN as usize];
+        raw.stable_read(0, &mut header);
+        // Un blob hérité doit être décodé par `persistence::restore` avant tout accès
+        assert!(
+            &header[..4] == MAGIC && header[4] == LAYOUT_VERSION,
+            "stable memory is not laid out: restore the saved state first"
+        );
+        let claimed = u32::from_le_bytes(header[8..12].try_into().unwrap()) as u64;
+        let mut owners = vec![0u8; claimed as usize];
+        raw.stable_read(HEADER_LEN, &mut owners);
+
+        let mut buckets = vec![Vec::new(); MEMORY_COUNT];
+        for (bucket, owner) in owners.into_iter().enumerate() {
+            buckets[owner as usize].push(bucket as u64);
+        }
+        Self { buckets, claimed }
+    }
+
+    fn format() -> Self {
+        let raw = RawMemory::default();
+        if raw.stable_size() == 0 {
+            raw.stable_grow(1)
+                .expect("stable memory cannot hold the layout header");
+        }
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..4].copy_from_slice(MAGIC);
+        header[4] = LAYOUT_VERSION;
+        raw.stable_write(0, &header);
+        Self {
+            buckets: vec![Vec::new(); MEMORY_COUNT],
+            claimed: 0,
+        }
+    }
+
+    fn claim(&mut self, memory: MemoryId) -> Result<(), StableMemoryError> {
+        if self.claimed >= MAX_BUCKETS {
+            return Err(StableMemoryError::OutOfMemory);
+        }
+        let raw = RawMemory::default();
+        let required = 1 + (self.claimed + 1) * BUCKET_PAGES;
+        let size = raw.stable_size();
+        if size < required {
+            raw.stable_grow(required - size)?;
+        }
+        raw.stable_write(HEADER_LEN + self.claimed, &[memory.0]);
+        self.buckets[memory.0 as usize].push(self.claimed);
+        self.claimed += 1;
+        raw.stable_write(8, &(self.claimed as u32).to_le_bytes());
+        Ok(())
+    }
+
+    /// Raw address of `offset` in `memory`, and the bytes left in its bucket
+    fn address(&self, memory: MemoryId, offset: u64) -> (u64, u64) {
+        let bucket = self.buckets[memory.0 as usize]
+            .get((offset / BUCKET_BYTES) as usize)
+            .unwrap_or_else(|| panic!("offset {offset} is out of bounds of memory {}", memory.0));
+        let within = offset % BUCKET_BYTES;
+        (
+            WASM_PAGE_SIZE_IN_BYTES + bucket * BUCKET_BYTES + within,
+            BUCKET_BYTES - within,
+        )
+    }
+}
+
+/// One structure's share of stable memory, addressed from 0
+#[derive(Debug, Clone, Copy)]
+pub struct VirtualMemory(MemoryId);
+
+impl StableMemory for VirtualMemory {
+    fn stable_size(&self) -> u64 {
+        with_layout(|layout| layout.buckets[self.0 .0 as usize].len() as u64 * BUCKET_PAGES)
+    }
+
+    /// Grows by whole buckets, so by at least `new_pages`
+    fn stable_grow(&self, new_pages: u64) -> Result<u64, StableMemoryError> {
+        let previous = self.stable_size();
+        with_layout(|layout| {
+            for _ in 0..new_pages.div_ceil(BUCKET_PAGES) {
+                layout.claim(self.0)?;
+            }
+            Ok(previous)
+        })
+    }
+
+    fn stable_write(&self, mut offset: u64, mut buf: &[u8]) {
+        let raw = RawMemory::default();
+        while !buf.is_empty() {
+            let (address, room) = with_layout(|layout| layout.address(self.0, offset));
+            let (chunk, rest) = buf.split_at(buf.len().min(room as usize));
+            raw.stable_write(address, chunk);
+            offset += chunk.len() as u64;
+            buf = rest;
+        }
+    }
+
+    fn stable_read(&self, mut offset: u64, mut buf: &mut [u8]) {
+        let raw = RawMemory::default();
+        while !buf.is_empty() {
+            let (address, room) = with_layout(|layout| layout.address(self.0, offset));
+            let (chunk, rest) = buf.split_at_mut(buf.len().min(room as usize));
+            raw.stable_read(address, chunk);
+            offset += chunk.len() as u64;
+            buf = rest;
+        }
+    }
+}
+
+/// Grow `memory` until it holds `bytes` bytes
+fn reserve(memory: &VirtualMemory, bytes: u64) -> Result<(), StorageError> {
+    let pages = bytes.div_ceil(WASM_PAGE_SIZE_IN_BYTES);
+    let size = memory.stable_size();
+    if pages > size {
+        memory
+            .stable_grow(pages - size)
+            .map_err(|_| StorageError::OutOfMemory(memory.0 .0))?;
+    }
+    Ok(())
+}
+
+fn read_u64(memory: &VirtualMemory, offset: u64) -> u64 {
+    let mut bytes = [0u8; 8];
+    memory.stable_read(offset, &mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+/// Replace the state snapshot with `bytes`
+pub fn write_snapshot(bytes: &[u8]) -> Result<(), StorageError> {
+    let memory = VirtualMemory(SNAPSHOT);
+    reserve(&memory, 8 + bytes.len() as u64)?;
+    memory.stable_write(0, &(bytes.len() as u64).to_le_bytes());
+    memory.stable_write(8, bytes);
+    Ok(())
+}
+
+/// Last snapshot written, `None` before the first upgrade
+pub fn read_snapshot() -> Option<Vec<u8>> {
+    let memory = VirtualMemory(SNAPSHOT);
+    if memory.stable_size() == 0 {
+        return None;
+    }
+    let mut bytes = vec![0u8; read_u64(&memory, 0) as usize];
+    memory.stable_read(8, &mut bytes);
+    Some(bytes)
+}
+
+/// Append-only log of `T` kept in stable memory
+pub struct StableLog<T> {
+    index: VirtualMemory,
+    data: VirtualMemory,
+    entry: PhantomData<T>,
+}
+
+impl<T: CandidType + for<'de> Deserialize<'de>> StableLog<T> {
+    pub const fn new(index: MemoryId, data: MemoryId) -> Self {
+        Self {
+            index: VirtualMemory(index),
+            data: VirtualMemory(data),
+            entry: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        // Un bucket fraîchement pris peut contenir d'anciens octets: le compte n'est lu
+        // qu'une fois écrit par le premier `push`
+        if self.index.stable_size() == 0 {
+            0
+        } else {
+            read_u64(&self.index, 0)
+        }
+    }
+
+    fn end_of(&self, index: u64) -> u64 {
+        read_u64(&self.index, 8 + index * 8)
+    }
+
+    pub fn get(&self, index: u64) -> Option<T> {
+        if index >= self.len() {
+            return None;
+        }
+        let start = index
+            .checked_sub(1)
+            .map_or(0, |previous| self.end_of(previous));
+        let mut bytes = vec![0u8; (self.end_of(index) - start) as usize];
+        self.data.stable_read(start, &mut bytes);
+        Some(candid::decode_one(&bytes).expect("stable log entry written by push"))
+    }
+
+    pub fn last(&self) -> Option<T> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// Append `entry`, returns its index
+    pub fn push(&self, entry: &T) -> Result<u64, StorageError> {
+        let bytes = candid::encode_one(entry).expect("stable log entries encode to candid");
+        let len = self.len();
+        let start = len
+            .checked_sub(1)
+            .map_or(0, |previous| self.end_of(previous));
+        let end = start + bytes.len() as u64;
+        reserve(&self.data, end)?;
+        reserve(&self.index, 8 + (len + 1) * 8)?;
+        self.data.stable_write(start, &bytes);
+        self.index.stable_write(8 + len * 8, &end.to_le_bytes());
+        // Le compte en dernier: l'entrée n'existe qu'une fois entièrement écrite
+        self.index.stable_write(0, &(len + 1).to_le_bytes());
+        Ok(len)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.iter_from(0)
+    }
+
+    /// Entries from `start` on, decoded one at a time
+    pub fn iter_from(&self, start: u64) -> impl Iterator<Item = T> + '_ {
+        (start..self.len()).filter_map(|index| self.get(index))
+    }
+
+    /// Forget every entry, their space is reused by the next pushes
+    #[cfg(test)]
+    pub fn clear(&self) {
+        if self.index.stable_size() > 0 {
+            self.index.stable_write(0, &0u64.to_le_bytes());
+        }
+    }
+
+    /// Rewrite the log with entry `index` replaced, to tamper with history in tests
+    #[cfg(test)]
+    pub fn replace(&self, index: u64, entry: T) {
+        let mut entries: Vec<T> = self.iter().collect();
+        entries[index as usize] = entry;
+        self.clear();
+        for entry in &entries {
+            self.push(entry).unwrap();
+        }
+    }
+}
+
+/// Stable memory of a native build, one per thread. Pages only take room once written,
+/// untouched bytes read as zeros.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default, Debug, Clone, Copy)]
+pub struct PageMemory;
+
+#[cfg(not(target_arch = "wasm32"))]
+type WrittenPages = std::collections::HashMap<u64, Box<[u8]>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    /// Size in pages, and the pages written so far
+    static PAGES: RefCell<(u64, WrittenPages)> = RefCell::new((0, WrittenPages::new()));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl StableMemory for PageMemory {
+    fn stable_size(&self) -> u64 {
+        PAGES.with(|pages| pages.borrow().0)
+    }
+
+    fn stable_grow(&self, new_pages: u64) -> Result<u64, StableMemoryError> {
+        PAGES.with(|pages| {
+            let size = &mut pages.borrow_mut().0;
+            let previous = *size;
+            *size += new_pages;
+            Ok(previous)
+        })
+    }
+
+    fn stable_write(&self, offset: u64, buf: &[u8]) {
+        PAGES.with(|pages| {
+            let (size, pages) = &mut *pages.borrow_mut();
+            assert!(
+                offset + buf.len() as u64 <= *size * WASM_PAGE_SIZE_IN_BYTES,
+                "stable write out of bounds"
+            );
+            for (position, chunk) in page_chunks(offset, buf.len()) {
+                let page = pages
+                    .entry(position / WASM_PAGE_SIZE_IN_BYTES)
+                    .or_insert_with(|| vec![0u8; WASM_PAGE_SIZE_IN_BYTES as usize].into());
+                let within = (position % WASM_PAGE_SIZE_IN_BYTES) as usize;
+                page[within..within + chunk.len()].copy_from_slice(&buf[chunk]);
+            }
+        })
+    }
+
+    fn stable_read(&self, offset: u64, buf: &mut [u8]) {
+        PAGES.with(|pages| {
+            let (size, pages) = &*pages.borrow();
+            assert!(
+                offset + buf.len() as u64 <= *size * WASM_PAGE_SIZE_IN_BYTES,
+                "stable read out of bounds"
+            );
+            for (position, chunk) in page_chunks(offset, buf.len()) {
+                let within = (position % WASM_PAGE_SIZE_IN_BYTES) as usize;
+                match pages.get(&(position / WASM_PAGE_SIZE_IN_BYTES)) {
+                    Some(page) => {
+                        buf[chunk.clone()].copy_from_slice(&page[within..within + chunk.len()])
+                    }
+                    None => buf[chunk].fill(0),
+                }
+            }
+        })
+    }
+}
+
+/// Split `len` bytes written at `offset` at page boundaries: (position, range in the buffer)
+#[cfg(not(target_arch = "wasm32"))]
+fn page_chunks(offset: u64, len: usize) -> impl Iterator<Item = (u64, std::ops::Range<usize>)> {
+    let mut done = 0;
+    std::iter::from_fn(move || {
+        if done == len {
+            return None;
+        }
+        let position = offset + done as u64;
+        let room = (WASM_PAGE_SIZE_IN_BYTES - position % WASM_PAGE_SIZE_IN_BYTES) as usize;
+        let chunk = done..done + room.min(len - done);
+        done = chunk.end;
+        Some((position, chunk))
+    })
+}
+
+/// Forget the layout read from the header, as a freshly installed wasm would
+#[cfg(test)]
+pub fn reload() {
+    LAYOUT.with(|layout| *layout.borrow_mut() = None);
+}
+
+/// Write `bytes` at the start of stable memory, as `stable_save` did
+#[cfg(test)]
+pub fn write_legacy_blob(bytes: &[u8]) {
+    let raw = RawMemory::default();
+    let pages = (bytes.len() as u64).div_ceil(WASM_PAGE_SIZE_IN_BYTES);
+    raw.stable_grow(pages.saturating_sub(raw.stable_size()))
+        .unwrap();
+    raw.stable_write(0, bytes);
+    reload();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_entries_survive_a_reload_across_buckets() {
+        let log: StableLog<String> = StableLog::new(BLOCK_INDEX, BLOCK_DATA);
+        let other: StableLog<u64> = StableLog::new(INPUT_INDEX, INPUT_DATA);
+        assert_eq!((log.len(), log.last()), (0, None));
+
+        // Des entrées d'un tiers de bucket: le log déborde sur plusieurs buckets,
+        // entrelacés avec ceux d'une autre mémoire
+        let big = |tag: char| tag.to_string().repeat((BUCKET_BYTES / 3) as usize);
+        for (index, tag) in ['a', 'b', 'c', 'd'].into_iter().enumerate() {
+            assert_eq!(log.push(&big(tag)).unwrap(), index as u64);
+            other.push(&(index as u64)).unwrap();
+        }
+
+        // Le wasm suivant relit l'en-tête
+        reload();
+        assert_eq!(log.len(), 4);
+        assert_eq!(log.get(2), Some(big('c')));
+        assert_eq!(log.last(), Some(big('d')));
+        assert_eq!(log.get(4), None);
+        assert_eq!(other.iter_from(1).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(VirtualMemory(BLOCK_DATA).stable_size() >= 2 * BUCKET_PAGES);
+
+        log.replace(1, "b".to_string());
+        assert_eq!(
+            log.iter().map(|entry| entry.len()).collect::<Vec<_>>()[..2],
+            [big('a').len(), 1]
+        );
+    }
+
+    #[test]
+    fn contents_tell_a_legacy_blob_from_the_layout() {
+        assert!(matches!(contents(), Ok(Contents::Empty)));
+        write_legacy_blob(&candid::encode_one(7u64).unwrap());
+        assert!(matches!(contents(), Ok(Contents::Legacy(_))));
+
+        format();
+        write_snapshot(b"state").unwrap();
+        reload();
+        assert!(matches!(contents(), Ok(Contents::Layout)));
+        assert_eq!(read_snapshot(), Some(b"state".to_vec()));
+    }
+}
@@ -0,0 +1,320 @@
+//! Authenticated L2 account state
+//! Every block commits to all accounts through a sparse Merkle tree keyed by
+//! the hash of the address, so balances can be proven against the block header
+
+use crate::{crypto, types::SupportedChain};
+use candid::{CandidType, Deserialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// One level per bit of the account key
+pub const TREE_DEPTH: usize = 256;
+
+/// Domain tag of account leaves, distinct from transaction hashes
+const ACCOUNT_LEAF_DOMAIN: &[u8] = b"polychain-l2/account/v1";
+
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssetBalance {
+    pub asset: SupportedChain,
+    /// Amount in the asset's base unit
+    pub amount: u128,
+}
+
+/// Post-state of one account, as committed in a block's state root
+#[derive(CandidType, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct AccountState {
+    /// Non-zero balances, sorted by asset
+    pub balances: Vec<AssetBalance>,
+    /// Next nonce expected from the account on chain
+    pub nonce: u64,
+}
+
+impl AccountState {
+    /// An empty account is the empty leaf: absence and zero state prove the same way
+    pub fn is_empty(&self) -> bool {
+        self.nonce == 0 && self.balances.iter().all(|balance| balance.amount == 0)
+    }
+
+    fn leaf_hash(&self, address: &str) -> [u8; 32] {
+        if self.is_empty() {
+            return [0; 32];
+        }
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(ACCOUNT_LEAF_DOMAIN);
+        hasher.update(&(address.len() as u32).to_le_bytes());
+        hasher.update(address.as_bytes());
+        hasher.update(&self.nonce.to_le_bytes());
+        let mut balances = self.balances.clone();
+        balances.sort_by_key(|balance| balance.asset);
+        for balance in balances.iter().filter(|balance| balance.amount > 0) {
+            hasher.update(balance.asset.symbol().as_bytes());
+            hasher.update(&balance.amount.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Sparse Merkle inclusion proof of an account against a block's state root
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct AccountProof {
+    pub address: String,
+    pub block_height: u64,
+    pub block_hash: String,
+    pub state_root: String,
+    pub balances: Vec<AssetBalance>,
+    pub nonce: u64,
+    /// Hex siblings from the leaf up to the root, `None` for an empty subtree
+    pub siblings: Vec<Option<String>>,
+}
+
+impl AccountProof {
+    /// Check the account state against the root, without any chain state
+    pub fn verify(&self) -> bool {
+        let Some(root) = crypto::decode_hash(&self.state_root) else {
+            return false;
+        };
+        let state = AccountState {
+            balances: self.balances.clone(),
+            nonce: self.nonce,
+        };
+        verify_account_proof(&self.address, &state, &self.siblings, &root)
+    }
+}
+
+fn account_key(address: &str) -> [u8; 32] {
+    blake3::hash(address.as_bytes()).into()
+}
+
+/// Bit `depth` of the key, most significant bit of the first byte at the root
+fn key_bit(key: &[u8; 32], depth: usize) -> bool {
+    key[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Root of an empty subtree, indexed by its height above the leaves
+fn empty_subtree_roots() -> Vec<[u8; 32]> {
+    let mut roots = vec![[0; 32]];
+    for height in 0..TREE_DEPTH {
+        let below = roots[height];
+        roots.push(crypto::merkle_node(&below, &below));
+    }
+    roots
+}
+
+/// State root of a chain with no accounts
+#[cfg(test)]
+pub fn empty_state_root() -> [u8; 32] {
+    empty_subtree_roots()[TREE_DEPTH]
+}
+
+/// Non-empty leaves sorted by key, the order of a left-to-right tree walk
+fn sorted_leaves(accounts: &BTreeMap<String, AccountState>) -> Vec<([u8; 32], [u8; 32])> {
+    let mut leaves: Vec<_> = accounts
+        .iter()
+        .filter(|(_, state)| !state.is_empty())
+        .map(|(address, state)| (account_key(address), state.leaf_hash(address)))
+        .collect();
+    leaves.sort_unstable_by_key(|(key, _)| *key);
+    leaves
+}
+
+fn subtree_root(leaves: &[([u8; 32], [u8; 32])], depth: usize, empty: &[[u8; 32]]) -> [u8; 32] {
+    match leaves {
+        [] => empty[TREE_DEPTH - depth],
+        [(_, leaf)] if depth == TREE_DEPTH => *leaf,
+        _ => {
+            let split = leaves.partition_point(|(key, _)| !key_bit(key, depth));
+            let left = subtree_root(&leaves[..split], depth + 1, empty);
+            let right = subtree_root(&leaves[split..], depth + 1, empty);
+            crypto::merkle_node(&left, &right)
+        }
+    }
+}
+
+/// Root committing to every non-empty account
+pub fn state_root(accounts: &BTreeMap<String, AccountState>) -> [u8; 32] {
+    subtree_root(&sorted_leaves(accounts), 0, &empty_subtree_roots())
+}
+
+/// Siblings of `address` from the leaf up, also valid for an absent account
+pub fn account_proof(
+    accounts: &BTreeMap<String, AccountState>,
+    address: &str,
+) -> Vec<Option<String>> {
+    let empty = empty_subtree_roots();
+    let key = account_key(address);
+    let mut leaves = &sorted_leaves(accounts)[..];
+    let mut siblings = Vec::with_capacity(TREE_DEPTH);
+
+    for depth in 0..TREE_DEPTH {
+        let split = leaves.partition_point(|(leaf_key, _)| !key_bit(leaf_key, depth));
+        let (own, other) = if key_bit(&key, depth) {
+            (&leaves[split..], &leaves[..split])
+        } else {
+            (&leaves[..split], &leaves[split..])
+        };
+        siblings
+            .push((!other.is_empty()).then(|| hex::encode(subtree_root(other, depth + 1, &empty))));
+        leaves = own;
+    }
+
+    siblings.reverse();
+    siblings
+}
+
+/// Check that `state` is the committed state of `address` under `root`
+pub fn verify_account_proof(
+    address: &str,
+    state: &AccountState,
+    siblings: &[Option<String>],
+    root: &[u8; 32],
+) -> bool {
+    if siblings.len() != TREE_DEPTH {
+        return false;
+    }
+
+    let empty = empty_subtree_roots();
+    let key = account_key(address);
+    let mut running = state.leaf_hash(address);
+    for (height, sibling) in siblings.iter().enumerate() {
+        let sibling = match sibling {
+            Some(hex_node) => match crypto::decode_hash(hex_node) {
+                Some(node) => node,
+                None => return false,
+            },
+            None => empty[height],
+        };
+        running = if key_bit(&key, TREE_DEPTH - 1 - height) {
+            crypto::merkle_node(&sibling, &running)
+        } else {
+            crypto::merkle_node(&running, &sibling)
+        };
+    }
+    running == *root
+}
+
+/// Account states as of each block height, stored only when they change
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct StateHistory {
+    accounts: HashMap<String, Vec<(u64, AccountState)>>,
+}
+
+impl StateHistory {
+    /// Record the post-state of the block at `height`
+    pub fn commit(&mut self, height: u64, accounts: &BTreeMap<String, AccountState>) {
+        for (address, state) in accounts {
+            let versions = self.accounts.entry(address.clone()).or_default();
+            if versions.last().map(|(_, last)| last) != Some(state) {
+                versions.push((height, state.clone()));
+            }
+        }
+
+        // Accounts that left the ledger are recorded as emptied
+        for (address, versions) in self.accounts.iter_mut() {
+            let emptied = !accounts.contains_key(address)
+                && versions.last().is_some_and(|(_, last)| !last.is_empty());
+            if emptied {
+                versions.push((height, AccountState::default()));
+            }
+        }
+    }
+
+    /// Every account as of the block at `height`
+    pub fn accounts_at(&self, height: u64) -> BTreeMap<String, AccountState> {
+        self.accounts
+            .iter()
+            .filter_map(|(address, versions)| {
+                versions
+                    .iter()
+                    .rev()
+                    .find(|(version_height, _)| *version_height <= height)
+                    .map(|(_, state)| (address.clone(), state.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(amount: u128, nonce: u64) -> AccountState {
+        AccountState {
+            balances: vec![AssetBalance {
+                asset: SupportedChain::Bitcoin,
+                amount,
+            }],
+            nonce,
+        }
+    }
+
+    #[test]
+    fn account_proofs_verify_for_members_and_absent_accounts() {
+        let accounts: BTreeMap<String, AccountState> = (0..20)
+            .map(|i| (format!("account_{i}"), account(1_000 + i as u128, i)))
+            .collect();
+        let root = state_root(&accounts);
+
+        for (address, state) in &accounts {
+            let siblings = account_proof(&accounts, address);
+            assert!(verify_account_proof(address, state, &siblings, &root));
+        }
+
+        // Un compte absent se prouve avec l'état vide
+        let siblings = account_proof(&accounts, "nobody");
+        assert!(verify_account_proof(
+            "nobody",
+            &AccountState::default(),
+            &siblings,
+            &root
+        ));
+        assert!(!verify_account_proof(
+            "nobody",
+            &account(1, 0),
+            &siblings,
+            &root
+        ));
+
+        // Solde ou nonce falsifiés
+        let siblings = account_proof(&accounts, "account_3");
+        assert!(!verify_account_proof(
+            "account_3",
+            &account(9_999, 3),
+            &siblings,
+            &root
+        ));
+        assert!(!verify_account_proof(
+            "account_3",
+            &account(1_003, 4),
+            &siblings,
+            &root
+        ));
+    }
+
+    #[test]
+    fn empty_accounts_do_not_change_the_root() {
+        let mut accounts = BTreeMap::new();
+        assert_eq!(state_root(&accounts), empty_state_root());
+
+        accounts.insert("ghost".to_string(), AccountState::default());
+        assert_eq!(state_root(&accounts), empty_state_root());
+    }
+
+    #[test]
+    fn history_returns_state_as_of_each_height() {
+        let mut history = StateHistory::default();
+        let mut accounts = BTreeMap::new();
+
+        accounts.insert("alice".to_string(), account(100, 0));
+        history.commit(0, &accounts);
+        accounts.insert("alice".to_string(), account(60, 1));
+        accounts.insert("bob".to_string(), account(40, 0));
+        history.commit(1, &accounts);
+        accounts.remove("alice");
+        history.commit(2, &accounts);
+
+        assert_eq!(history.accounts_at(0).get("alice"), Some(&account(100, 0)));
+        assert_eq!(history.accounts_at(0).get("bob"), None);
+        assert_eq!(history.accounts_at(1).get("alice"), Some(&account(60, 1)));
+        assert!(history.accounts_at(2)["alice"].is_empty());
+        assert_eq!(state_root(&history.accounts_at(2)), state_root(&accounts));
+    }
+}
@@ -1,15 +1,40 @@
+use crate::chain::OrderingStrategy;
+use crate::clock;
+use crate::crypto::{self, MerkleStep};
 use candid::{CandidType, Deserialize};
 
 #[derive(CandidType, Deserialize, Debug, Clone)]
 pub struct PolyTransaction {
     pub sender: String,
     pub recipient: String,
-    pub amount: f64,
+    /// Amount in the asset's base unit (satoshi, wei, e8s, lamports)
+    pub amount: u128,
+    pub asset: SupportedChain,
+    /// Per-sender sequence number, starting at 0
+    pub nonce: u64,
+    /// Priority tip offered on top of the base fee, in the asset's base unit
+    pub fee: u128,
+    /// Most the sender pays in fees (base fee plus tip), in the asset's base unit
+    pub max_fee: u128,
+    /// Arrival time, set by the canister and not covered by the signature
     pub time_stamp: i64,
+    /// Hex-encoded sender public key, in the encoding of `algorithm`
+    pub public_key: Option<String>,
+    pub algorithm: Option<CryptoAlgorithm>,
+    /// Hex-encoded signature over `signing_payload()`
     pub signature: Option<String>,
     pub hash: Option<String>,
 }
 
+/// Signature scheme of a transaction or withdrawal
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CryptoAlgorithm {
+    Ecdsa,
+    Schnorr,
+    Falcon512,
+    Mldsa44,
+}
+
 #[derive(CandidType, Deserialize, Debug, Clone)]
 pub struct PolyBlock {
     pub transactions: Vec<PolyTransaction>,
@@ -17,99 +42,484 @@ pub struct PolyBlock {
     pub previous_hash: String,
     pub timestamp: i64,
     pub nonce: u64,
+    /// Hex Merkle root of the transaction hashes, in block order
+    pub tx_root: String,
+    /// Hex sparse Merkle root of every account after this block
+    pub state_root: String,
+    /// Strategy the sequencer ordered the transactions with, `None` on blocks sealed
+    /// before it was recorded: their ordering is not checked, and they were signed with
+    /// a key drawn again on every upgrade rather than the published sequencer key
+    pub ordering: Option<OrderingStrategy>,
+    /// VRF evaluation the transaction order was derived from, under VrfRandom
+    pub ordering_proof: Option<OrderingProof>,
+    /// Sequencer that sealed the block and its signature over the block hash
+    pub proposer: Option<BlockProposer>,
+    /// Committee votes over the block hash, left out of the hash they sign
+    pub certificate: Option<QuorumCertificate>,
+}
+
+/// Block attestation: the key is part of the hashed header, the signature covers the hash
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockProposer {
+    pub algorithm: CryptoAlgorithm,
+    /// Hex sequencer public key
+    pub public_key: String,
+    /// Hex signature over `PolyBlock::signing_message()`
+    pub signature: String,
+}
+
+/// What a validator signs in a consensus round: the block it proposes or the block it votes for
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsensusStep {
+    Proposal,
+    Vote,
+}
+
+impl ConsensusStep {
+    /// Domain tag of the step, distinct from the sequencer's block signature
+    pub fn domain(self) -> &'static [u8] {
+        match self {
+            ConsensusStep::Proposal => b"polychain-l2/proposal/v1",
+            ConsensusStep::Vote => b"polychain-l2/vote/v1",
+        }
+    }
+
+    /// Bytes signed for `block_hash` at `height` and `round`. An honest validator signs
+    /// one hash per step, height and round; a second one is equivocation.
+    pub fn message(self, height: u64, round: u64, block_hash: &str) -> Vec<u8> {
+        let mut message = self.domain().to_vec();
+        message.extend_from_slice(&height.to_le_bytes());
+        message.extend_from_slice(&round.to_le_bytes());
+        message.extend_from_slice(block_hash.as_bytes());
+        message
+    }
+}
+
+/// Committee member's signature over a block hash
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct Vote {
+    pub validator: String,
+    /// Stake the vote carried when it was counted, in base units of the stake asset
+    pub stake: u128,
+    /// Hex signature over `ConsensusStep::Vote.message(height, round, block_hash)`
+    pub signature: String,
+}
+
+/// Share of the total stake a quorum must strictly exceed, as an exact fraction
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuorumThreshold {
+    pub numerator: u64,
+    pub denominator: u64,
+}
+
+impl QuorumThreshold {
+    /// BFT quorum: two quorums above two thirds always share an honest voter
+    pub const TWO_THIRDS: Self = Self {
+        numerator: 2,
+        denominator: 3,
+    };
+
+    /// `voted_stake` is more than this share of `total_stake`, in integers so nothing
+    /// rounds at the boundary
+    pub fn is_reached(&self, voted_stake: u128, total_stake: u128) -> bool {
+        voted_stake.saturating_mul(u128::from(self.denominator))
+            > total_stake.saturating_mul(u128::from(self.numerator))
+    }
+}
+
+/// Votes of validators holding more than `threshold` of the stake
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct QuorumCertificate {
+    pub algorithm: CryptoAlgorithm,
+    pub height: u64,
+    pub round: u64,
+    pub block_hash: String,
+    /// Leader of the round: the committee member ranked `round % committee.len()`
+    pub proposer: ProposerVrf,
+    /// VRF tickets of the height in rank order, the committee is their first
+    /// `max_proposers`. Covers every eligible member of the epoch, voting or not, so
+    /// verifiers recompute the ranking over the whole set.
+    pub candidates: Option<Vec<ProposerVrf>>,
+    /// Hex proposer signature over `ConsensusStep::Proposal.message(height, round, block_hash)`
+    pub proposal_signature: String,
+    /// Timeout of the previous round, required from round 1 on to justify the view change
+    pub timeout_certificate: Option<TimeoutCertificate>,
+    pub votes: Vec<Vote>,
+    pub voted_stake: u128,
+    pub total_stake: u128,
+    /// Share of `total_stake` the votes had to exceed
+    pub threshold: QuorumThreshold,
+}
+
+/// Votes of validators holding more than `threshold` of the stake to give up on a round
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimeoutCertificate {
+    pub algorithm: CryptoAlgorithm,
+    pub height: u64,
+    pub round: u64,
+    /// Signatures over `TimeoutCertificate::message(height, round)`
+    pub votes: Vec<Vote>,
+    pub voted_stake: u128,
+    pub total_stake: u128,
+    pub threshold: QuorumThreshold,
+}
+
+impl TimeoutCertificate {
+    /// Domain tag of timeout votes, distinct from proposals and block votes
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/timeout/v1";
+
+    pub fn message(height: u64, round: u64) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(&height.to_le_bytes());
+        message.extend_from_slice(&round.to_le_bytes());
+        message
+    }
+}
+
+/// Validator's ECVRF evaluation of a consensus seed, checkable by anyone against its key
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProposerVrf {
+    pub validator: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex validator public key
+    pub public_key: String,
+    /// Hex seed from `AlgoConsensus::create_seed`
+    pub seed: String,
+    /// Hex VRF proof
+    pub proof: String,
+    /// Hex VRF output, the hash of the proof
+    pub output: String,
+}
+
+impl ProposerVrf {
+    /// Check the proof against the key and seed, and the output against the proof
+    pub fn verify(&self) -> bool {
+        let (Ok(public_key), Ok(seed), Ok(proof)) = (
+            hex::decode(&self.public_key),
+            hex::decode(&self.seed),
+            hex::decode(&self.proof),
+        ) else {
+            return false;
+        };
+        match crate::cryptography::vrf_verify(self.algorithm, &public_key, &seed, &proof) {
+            Ok(Some(output)) => self.output == hex::encode(output),
+            _ => false,
+        }
+    }
+}
+
+/// Sequencer VRF over the previous block hash: an ECVRF proof (see `cryptography::vrf`)
+/// under the published sequencer key, the output is the one the proof determines
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct OrderingProof {
+    pub algorithm: CryptoAlgorithm,
+    /// Hex sequencer public key
+    pub public_key: String,
+    /// Previous block hash the VRF was evaluated on
+    pub input: String,
+    /// Hex ECVRF proof of `OrderingProof::message(input)`
+    pub proof: String,
+    /// Hex seed of the permutation
+    pub output: String,
+}
+
+impl OrderingProof {
+    /// Domain tag of the ordering VRF input
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/ordering-vrf/v1";
+
+    pub fn message(input: &str) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(input.as_bytes());
+        message
+    }
+
+    /// Check the proof was made with `sequencer_key` over its input, and that it
+    /// determines the output: the key holder cannot pick another output for the same input
+    pub fn verify(&self, sequencer_key: &str) -> bool {
+        if self.public_key != sequencer_key {
+            return false;
+        }
+        let (Ok(public_key), Ok(proof)) = (hex::decode(&self.public_key), hex::decode(&self.proof))
+        else {
+            return false;
+        };
+        let proven = crate::cryptography::vrf_verify(
+            self.algorithm,
+            &public_key,
+            &Self::message(&self.input),
+            &proof,
+        );
+        matches!(proven, Ok(Some(output)) if self.output == hex::encode(output))
+    }
+}
+
+/// Merkle inclusion proof of a transaction in a block header
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransactionProof {
+    pub tx_hash: String,
+    pub block_hash: String,
+    pub block_height: u64,
+    pub tx_root: String,
+    pub leaf_index: u64,
+    pub path: Vec<MerkleStep>,
+}
+
+impl TransactionProof {
+    /// Check the path against the root, without any chain state
+    pub fn verify(&self) -> bool {
+        match (
+            crypto::decode_hash(&self.tx_hash),
+            crypto::decode_hash(&self.tx_root),
+        ) {
+            (Some(tx_hash), Some(tx_root)) => {
+                crypto::verify_merkle_proof(&tx_hash, &self.path, &tx_root)
+            }
+            _ => false,
+        }
+    }
 }
 
 impl PolyTransaction {
-    pub fn new(sender: String, recipient: String, amount: f64) -> Self {
+    pub fn new(
+        sender: String,
+        recipient: String,
+        amount: u128,
+        asset: SupportedChain,
+        nonce: u64,
+    ) -> Self {
         Self {
             sender,
             recipient,
             amount,
-            time_stamp: Self::get_current_time(),
+            asset,
+            nonce,
+            fee: 0,
+            max_fee: 0,
+            time_stamp: clock::timestamp(),
+            public_key: None,
+            algorithm: None,
             signature: None,
             hash: None,
         }
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn get_current_time() -> i64 {
-        ic_cdk::api::time() as i64
+    /// Set the priority tip and the fee cap, both signed with the transfer
+    pub fn with_fee(mut self, fee: u128, max_fee: u128) -> Self {
+        self.fee = fee;
+        self.max_fee = max_fee;
+        self
+    }
+
+    /// Fee charged under `base_fee`, `None` if the cap does not cover it
+    pub fn effective_fee(&self, base_fee: u128) -> Option<u128> {
+        (self.max_fee >= base_fee).then(|| self.max_fee.min(base_fee.saturating_add(self.fee)))
+    }
+
+    /// Part of the fee above the base fee, 0 when not includable
+    pub fn effective_tip(&self, base_fee: u128) -> u128 {
+        self.effective_fee(base_fee).map_or(0, |fee| fee - base_fee)
+    }
+
+    /// Domain tag prefixed to every transaction signing payload
+    pub const SIGNING_DOMAIN: &'static [u8] = b"polychain-l2/transaction/v1";
+    /// Domain tag prefixed to commit-reveal commitments
+    pub const COMMITMENT_DOMAIN: &'static [u8] = b"polychain-l2/commitment/v1";
+
+    /// Canonical bytes the sender signs: length-prefixed strings, little-endian integers
+    pub fn signing_payload(&self) -> Vec<u8> {
+        let mut payload = Self::SIGNING_DOMAIN.to_vec();
+        for field in [
+            self.sender.as_bytes(),
+            self.recipient.as_bytes(),
+            self.asset.symbol().as_bytes(),
+        ] {
+            payload.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            payload.extend_from_slice(field);
+        }
+        payload.extend_from_slice(&self.amount.to_le_bytes());
+        payload.extend_from_slice(&self.nonce.to_le_bytes());
+        payload.extend_from_slice(&self.fee.to_le_bytes());
+        payload.extend_from_slice(&self.max_fee.to_le_bytes());
+        payload
+    }
+
+    /// Hash committing to the whole transaction: signed payload, arrival time, key and signature
+    pub fn calculate_hash_raw(&self) -> [u8; 32] {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.signing_payload());
+        hasher.update(&self.time_stamp.to_le_bytes());
+        for field in [&self.public_key, &self.signature] {
+            let bytes = field.as_deref().unwrap_or_default().as_bytes();
+            hasher.update(&(bytes.len() as u32).to_le_bytes());
+            hasher.update(bytes);
+        }
+        let algorithm = self.algorithm.map(|algorithm| format!("{algorithm:?}"));
+        hasher.update(algorithm.unwrap_or_default().as_bytes());
+        hasher.finalize().into()
+    }
+
+    pub fn calculate_hash(&self) -> String {
+        hex::encode(self.calculate_hash_raw())
+    }
+
+    /// Id the client can derive before submitting: hash of the signed payload only.
+    /// Unique since a sender's nonce is admitted once.
+    pub fn tx_id(&self) -> String {
+        hex::encode(blake3::hash(&self.signing_payload()).as_bytes())
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn get_current_time() -> i64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|duration| duration.as_nanos() as i64)
-            .unwrap_or_else(|_| {
-                // Fallback to a fixed timestamp if system clock is before UNIX_EPOCH
-                // This is extremely unlikely but provides safety
-                ic_cdk::api::time() as i64
-            })
+    /// Hiding commitment of a commit-reveal submission, binds the signed payload to a
+    /// secret salt so the contents stay unknown until the reveal
+    pub fn commitment(&self, salt: &[u8]) -> String {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(Self::COMMITMENT_DOMAIN);
+        hasher.update(&self.signing_payload());
+        hasher.update(salt);
+        hex::encode(hasher.finalize().as_bytes())
     }
 
-    pub fn sign(&mut self, signature: String) {
+    /// Attach the sender's key and hex signature over `signing_payload()`
+    pub fn sign(&mut self, algorithm: CryptoAlgorithm, public_key: String, signature: String) {
+        self.algorithm = Some(algorithm);
+        self.public_key = Some(public_key);
         self.signature = Some(signature);
     }
 
     pub fn is_valid(&self) -> bool {
-        self.amount > 0.0 && !self.sender.is_empty() && !self.recipient.is_empty()
+        self.amount > 0 && !self.sender.is_empty() && !self.recipient.is_empty()
     }
 }
 
 impl PolyBlock {
-    pub fn new(transactions: Vec<PolyTransaction>, previous_hash: String) -> Self {
-        let timestamp = Self::get_current_time();
+    pub fn new(
+        transactions: Vec<PolyTransaction>,
+        previous_hash: String,
+        state_root: [u8; 32],
+    ) -> Self {
+        Self::new_at(transactions, previous_hash, state_root, clock::timestamp())
+    }
+
+    /// Block stamped with a time given by the caller's clock
+    pub fn new_at(
+        transactions: Vec<PolyTransaction>,
+        previous_hash: String,
+        state_root: [u8; 32],
+        timestamp: i64,
+    ) -> Self {
         let mut block = Self {
             transactions,
             hash: String::new(),
             previous_hash,
             timestamp,
             nonce: 0,
+            tx_root: String::new(),
+            state_root: hex::encode(state_root),
+            ordering: None,
+            ordering_proof: None,
+            proposer: None,
+            certificate: None,
         };
+        block.tx_root = hex::encode(block.calculate_tx_root());
         block.hash = block.calculate_hash();
         block
     }
 
-    #[cfg(target_arch = "wasm32")]
-    fn get_current_time() -> i64 {
-        ic_cdk::api::time() as i64
+    /// Record the strategy the transactions were ordered with, and the VRF proof the
+    /// order was derived from under VrfRandom
+    pub fn with_ordering(
+        mut self,
+        strategy: OrderingStrategy,
+        ordering_proof: Option<OrderingProof>,
+    ) -> Self {
+        self.ordering = Some(strategy);
+        self.ordering_proof = ordering_proof;
+        self.hash = self.calculate_hash();
+        self
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
-    fn get_current_time() -> i64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|duration| duration.as_nanos() as i64)
-            .unwrap_or_else(|_| {
-                // Fallback to a fixed timestamp if system clock is before UNIX_EPOCH
-                // This is extremely unlikely but provides safety
-                ic_cdk::api::time() as i64
-            })
+    /// Name the sealing sequencer in the header, the signature is added once the hash is final
+    pub fn with_proposer(mut self, algorithm: CryptoAlgorithm, public_key: String) -> Self {
+        self.proposer = Some(BlockProposer {
+            algorithm,
+            public_key,
+            signature: String::new(),
+        });
+        self.hash = self.calculate_hash();
+        self
+    }
+
+    /// Domain tag of the block signature
+    pub const SIGNING_DOMAIN: &'static [u8] = b"polychain-l2/block/v1";
+
+    pub fn signing_message(&self) -> Vec<u8> {
+        let mut message = Self::SIGNING_DOMAIN.to_vec();
+        message.extend_from_slice(self.hash.as_bytes());
+        message
+    }
+
+    /// Check that `sequencer_key` signed the stored hash, and that hash against the header
+    pub fn verify_signature(&self, sequencer_key: &str) -> Result<&BlockProposer, String> {
+        let proposer = self.proposer.as_ref().ok_or("block is not signed")?;
+        if !proposer.public_key.eq_ignore_ascii_case(sequencer_key) {
+            return Err("block is not signed by the sequencer key".to_string());
+        }
+        if self.hash != self.calculate_hash() {
+            return Err("hash does not match the header".to_string());
+        }
+        let public_key =
+            hex::decode(&proposer.public_key).map_err(|e| format!("public key is not hex: {e}"))?;
+        let signature =
+            hex::decode(&proposer.signature).map_err(|e| format!("signature is not hex: {e}"))?;
+        let is_valid = crate::cryptography::verify_signature(
+            proposer.algorithm,
+            &public_key,
+            &self.signing_message(),
+            &signature,
+        )
+        .map_err(|e| e.to_string())?;
+        if !is_valid {
+            return Err(format!("{:?} verification failed", proposer.algorithm));
+        }
+        Ok(proposer)
+    }
+
+    fn transaction_hashes(&self) -> Vec<[u8; 32]> {
+        self.transactions
+            .iter()
+            .map(PolyTransaction::calculate_hash_raw)
+            .collect()
+    }
+
+    /// Merkle root recomputed from the block body
+    pub fn calculate_tx_root(&self) -> [u8; 32] {
+        crypto::calculate_merkle_root(&self.transaction_hashes())
+    }
+
+    /// Inclusion path for the transaction at `index`
+    pub fn transaction_proof(&self, index: usize) -> Option<Vec<MerkleStep>> {
+        crypto::merkle_proof(&self.transaction_hashes(), index)
     }
 
     pub fn calculate_hash(&self) -> String {
         // Use Blake3 for cryptographically secure block hashing
         let mut hasher = blake3::Hasher::new();
-        
-        // Hash block metadata
+
+        // Header only: the transactions are committed through tx_root
         hasher.update(self.previous_hash.as_bytes());
         hasher.update(&self.timestamp.to_le_bytes());
         hasher.update(&self.nonce.to_le_bytes());
-
-        // Hash all transactions deterministically
-        for tx in &self.transactions {
-            hasher.update(tx.sender.as_bytes());
-            hasher.update(tx.recipient.as_bytes());
-            hasher.update(&tx.amount.to_le_bytes());
-            hasher.update(&tx.time_stamp.to_le_bytes());
-            
-            // Include transaction signature if present
-            if let Some(ref sig) = tx.signature {
-                hasher.update(sig.as_bytes());
-            }
+        hasher.update(self.tx_root.as_bytes());
+        hasher.update(self.state_root.as_bytes());
+        if let Some(strategy) = &self.ordering {
+            hasher.update(&[strategy.clone() as u8]);
+        }
+        if let Some(ordering_proof) = &self.ordering_proof {
+            hasher.update(ordering_proof.public_key.as_bytes());
+            hasher.update(ordering_proof.proof.as_bytes());
+        }
+        if let Some(proposer) = &self.proposer {
+            hasher.update(&[proposer.algorithm as u8]);
+            hasher.update(proposer.public_key.as_bytes());
         }
 
         hex::encode(hasher.finalize().as_bytes())
@@ -118,80 +528,117 @@ impl PolyBlock {
 
 // Multi-chain types
 
-#[derive(CandidType, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum SupportedChain {
     Bitcoin,
     Ethereum,
-    ICP,
+    #[serde(rename = "ICP")]
+    Icp,
     Solana,
 }
 
 impl SupportedChain {
-    pub fn format_amount(&self, amount: f64) -> String {
+    /// Decimals of the base unit: satoshi, wei, e8s, lamports
+    pub fn decimals(&self) -> u32 {
+        match self {
+            SupportedChain::Bitcoin => 8,
+            SupportedChain::Ethereum => 18,
+            SupportedChain::Icp => 8,
+            SupportedChain::Solana => 9,
+        }
+    }
+
+    /// Ticker, also used as the asset tag when hashing
+    pub fn symbol(&self) -> &'static str {
         match self {
-            SupportedChain::Bitcoin => format!("{:.8} BTC", amount / 100_000_000.0), // satoshi to BTC
-            SupportedChain::Ethereum => format!("{:.18} ETH", amount / 1e18), // wei to ETH
-            SupportedChain::ICP => format!("{:.8} ICP", amount / 100_000_000.0), // e8s to ICP
-            SupportedChain::Solana => format!("{:.9} SOL", amount / 1e9), // lamports to SOL
+            SupportedChain::Bitcoin => "BTC",
+            SupportedChain::Ethereum => "ETH",
+            SupportedChain::Icp => "ICP",
+            SupportedChain::Solana => "SOL",
         }
     }
+
+    /// Exact decimal rendering of a base-unit amount, no float rounding
+    pub fn format_amount(&self, amount: u128) -> String {
+        let decimals = self.decimals();
+        let unit = 10_u128.pow(decimals);
+        format!(
+            "{}.{:0width$} {}",
+            amount / unit,
+            amount % unit,
+            self.symbol(),
+            width = decimals as usize
+        )
+    }
 }
 
-#[derive(CandidType, Deserialize, Debug, Clone)]
-pub struct MultiChainBalance {
-    pub chain: SupportedChain,
-    pub native_balance: f64,
-    pub wrapped_balance: f64,
-    pub total_balance: f64,
+/// Lifecycle of an admitted transaction, looked up by `PolyTransaction::tx_id`
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub enum TransactionStatus {
+    /// Admitted by the sequencer, waiting for a batch
+    Pending,
+    Sequenced {
+        block_hash: String,
+        block_height: u64,
+    },
+    /// Sequenced but rejected when applied to the ledger
+    Failed { reason: String },
+    /// Removed from the queue without being executed
+    Dropped,
 }
 
-#[derive(CandidType, Deserialize, Debug, Clone)]
-pub struct MultiChainTransaction {
-    pub chain: SupportedChain,
-    pub sender: String,
-    pub recipient: String,
-    pub amount: f64,
-    pub timestamp: i64,
-    pub signature: Option<String>,
-    pub tx_hash: Option<String>,
+/// Vault reserve a withdrawal is paid out of
+#[derive(CandidType, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WithdrawalSource {
+    Native,
+    Wrapped,
 }
 
-impl MultiChainTransaction {
-    pub fn new(chain: SupportedChain, sender: String, recipient: String, amount: f64) -> Self {
-        Self {
-            chain,
-            sender,
-            recipient,
-            amount,
-            timestamp: Self::get_current_time(),
-            signature: None,
-            tx_hash: None,
+impl WithdrawalSource {
+    /// Split `amount` into its (native, wrapped) portions, drawing on this reserve first
+    /// and on the other one for the rest. `None` if both reserves together fall short.
+    pub fn split(self, amount: u128, native: u128, wrapped: u128) -> Option<(u128, u128)> {
+        let (first, second) = match self {
+            Self::Native => (native, wrapped),
+            Self::Wrapped => (wrapped, native),
+        };
+        let from_first = amount.min(first);
+        let from_second = amount - from_first;
+        if from_second > second {
+            return None;
         }
+        Some(match self {
+            Self::Native => (from_first, from_second),
+            Self::Wrapped => (from_second, from_first),
+        })
     }
+}
 
-    #[cfg(target_arch = "wasm32")]
-    fn get_current_time() -> i64 {
-        ic_cdk::api::time() as i64
-    }
-
-    #[cfg(not(target_arch = "wasm32"))]
-    fn get_current_time() -> i64 {
-        use std::time::{SystemTime, UNIX_EPOCH};
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|duration| duration.as_nanos() as i64)
-            .unwrap_or_else(|_| {
-                // Fallback to a fixed timestamp if system clock is before UNIX_EPOCH
-                // This is extremely unlikely but provides safety
-                ic_cdk::api::time() as i64
-            })
-    }
-
-    pub fn sign(&mut self, signature: String) {
-        self.signature = Some(signature);
-    }
+/// Withdrawal already debited from a vault, awaiting payout on the origin chain
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct PendingWithdrawal {
+    pub tx_id: String,
+    pub address: String,
+    pub asset: SupportedChain,
+    /// Amount in the asset's base unit
+    pub amount: u128,
+    /// Reserve drawn on first
+    pub source: WithdrawalSource,
+    pub crypto_algo: String,
+    /// Portion paid from the native reserve, absent from withdrawals recorded before splits
+    pub native_amount: Option<u128>,
+    /// Portion paid from the wrapped reserve, absent from withdrawals recorded before splits
+    pub wrapped_amount: Option<u128>,
+}
 
-    pub fn set_hash(&mut self, hash: String) {
-        self.tx_hash = Some(hash);
+impl PendingWithdrawal {
+    /// Reserves the payout comes from, for the endpoints' receipts
+    pub fn payout(&self) -> String {
+        match (self.native_amount, self.wrapped_amount) {
+            (Some(native), Some(wrapped)) if native > 0 && wrapped > 0 => {
+                format!("Native ({native}) + Wrapped ({wrapped}) reserves")
+            }
+            _ => format!("{:?} reserve", self.source),
+        }
     }
 }
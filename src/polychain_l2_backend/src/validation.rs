@@ -1,16 +1,14 @@
 //! Input validation and sanitization module
 //! Critical security component for production-ready DeFi platform
 
-use crate::errors::CryptographyError;
-
 /// Maximum allowed string length for addresses and identifiers
 const MAX_STRING_LENGTH: usize = 256;
 /// Maximum allowed transaction amount (in smallest unit)
 const MAX_TRANSACTION_AMOUNT: u64 = 21_000_000 * 100_000_000; // 21M Bitcoin in satoshi
-/// Maximum allowed batch size for operations
-const MAX_BATCH_SIZE: usize = 10_000;
 /// Minimum allowed transaction amount (to prevent dust)
 const MIN_TRANSACTION_AMOUNT: u64 = 546; // Bitcoin dust limit in satoshi
+/// Maximum allowed Ethereum transaction amount, well above total ETH supply
+const MAX_WEI_AMOUNT: u128 = 1_000_000_000 * 1_000_000_000_000_000_000; // 1B ETH in wei
 
 /// Validation result type
 pub type ValidationResult<T> = Result<T, ValidationError>;
@@ -21,8 +19,6 @@ pub enum ValidationError {
     InvalidAddress(String),
     InvalidAmount(String),
     InvalidString(String),
-    InvalidBatchSize(String),
-    InvalidHash(String),
     InvalidAlgorithm(String),
     InvalidQuantumLevel(String),
     EmptyInput(String),
@@ -38,8 +34,6 @@ impl std::fmt::Display for ValidationError {
             ValidationError::InvalidAddress(msg) => write!(f, "Invalid address: {}", msg),
             ValidationError::InvalidAmount(msg) => write!(f, "Invalid amount: {}", msg),
             ValidationError::InvalidString(msg) => write!(f, "Invalid string: {}", msg),
-            ValidationError::InvalidBatchSize(msg) => write!(f, "Invalid batch size: {}", msg),
-            ValidationError::InvalidHash(msg) => write!(f, "Invalid hash: {}", msg),
             ValidationError::InvalidAlgorithm(msg) => write!(f, "Invalid algorithm: {}", msg),
             ValidationError::InvalidQuantumLevel(msg) => write!(f, "Invalid quantum level: {}", msg),
             ValidationError::EmptyInput(msg) => write!(f, "Empty input: {}", msg),
@@ -261,25 +255,22 @@ impl AmountValidator {
         Ok(())
     }
 
-    /// Validate floating point amount and convert to integer
-    pub fn validate_and_convert_float_amount(amount: f64, decimals: u8) -> ValidationResult<u64> {
-        if amount <= 0.0 {
+    /// Validate a wei-denominated amount, which may exceed u64::MAX
+    pub fn validate_wei_amount(amount_wei: u128) -> ValidationResult<()> {
+        if amount_wei == 0 {
             return Err(ValidationError::InvalidAmount(
-                "Amount must be positive".to_string(),
+                "Amount cannot be zero".to_string(),
             ));
         }
 
-        if !amount.is_finite() {
-            return Err(ValidationError::InvalidAmount(
-                "Amount must be a finite number".to_string(),
-            ));
+        if amount_wei > MAX_WEI_AMOUNT {
+            return Err(ValidationError::TooLarge(format!(
+                "Amount {} exceeds maximum {}",
+                amount_wei, MAX_WEI_AMOUNT
+            )));
         }
 
-        let multiplier = 10_u64.pow(decimals as u32);
-        let amount_int = (amount * multiplier as f64) as u64;
-
-        Self::validate_amount(amount_int, None)?;
-        Ok(amount_int)
+        Ok(())
     }
 }
 
@@ -323,24 +314,6 @@ impl GeneralValidator {
         Ok(sanitized)
     }
 
-    /// Validate batch size
-    pub fn validate_batch_size(size: usize) -> ValidationResult<()> {
-        if size == 0 {
-            return Err(ValidationError::InvalidBatchSize(
-                "Batch size cannot be zero".to_string(),
-            ));
-        }
-
-        if size > MAX_BATCH_SIZE {
-            return Err(ValidationError::TooLarge(format!(
-                "Batch size {} exceeds maximum {}",
-                size, MAX_BATCH_SIZE
-            )));
-        }
-
-        Ok(())
-    }
-
     /// Validate cryptographic algorithm name
     pub fn validate_crypto_algorithm(algorithm: &str) -> ValidationResult<()> {
         let valid_algorithms = ["ecdsa", "schnorr", "falcon", "falcon512", "mldsa", "mldsa44"];
@@ -366,59 +339,12 @@ impl GeneralValidator {
         Ok(())
     }
 
-    /// Validate hash string format
-    pub fn validate_hash(hash: &str) -> ValidationResult<()> {
-        if hash.is_empty() {
-            return Err(ValidationError::EmptyInput("Hash cannot be empty".to_string()));
-        }
-
-        // Hash should be hex string, typically 64 characters for SHA256
-        if hash.len() < 32 || hash.len() > 128 {
-            return Err(ValidationError::InvalidHash(
-                "Hash length invalid".to_string(),
-            ));
-        }
-
-        if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(ValidationError::InvalidHash(
-                "Hash must contain only hexadecimal characters".to_string(),
-            ));
-        }
-
-        Ok(())
-    }
 }
 
 /// Rate limiting and DOS protection
 pub struct SecurityValidator;
 
 impl SecurityValidator {
-    /// Validate request frequency (basic rate limiting concept)
-    pub fn validate_request_frequency(requests_count: u64, time_window_seconds: u64) -> ValidationResult<()> {
-        const MAX_REQUESTS_PER_MINUTE: u64 = 100;
-        const MAX_REQUESTS_PER_HOUR: u64 = 1000;
-
-        match time_window_seconds {
-            60 => {
-                if requests_count > MAX_REQUESTS_PER_MINUTE {
-                    return Err(ValidationError::SecurityThreat(
-                        "Rate limit exceeded: too many requests per minute".to_string(),
-                    ));
-                }
-            }
-            3600 => {
-                if requests_count > MAX_REQUESTS_PER_HOUR {
-                    return Err(ValidationError::SecurityThreat(
-                        "Rate limit exceeded: too many requests per hour".to_string(),
-                    ));
-                }
-            }
-            _ => {} // No validation for other time windows
-        }
-
-        Ok(())
-    }
-
     /// Detect potentially malicious input patterns
     pub fn detect_malicious_input(input: &str) -> ValidationResult<()> {
         // SQL injection patterns
@@ -493,6 +419,16 @@ mod tests {
         assert!(AmountValidator::validate_amount(u64::MAX, None).is_err()); // Too large
     }
 
+    #[test]
+    fn test_wei_amount_validation() {
+        // 100 ETH is above u64::MAX wei
+        assert!(AmountValidator::validate_wei_amount(100_000_000_000_000_000_000).is_ok());
+        assert!(AmountValidator::validate_wei_amount(1).is_ok());
+
+        assert!(AmountValidator::validate_wei_amount(0).is_err());
+        assert!(AmountValidator::validate_wei_amount(u128::MAX).is_err());
+    }
+
     #[test]
     fn test_string_validation() {
         // Valid strings
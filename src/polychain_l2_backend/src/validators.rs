@@ -0,0 +1,1072 @@
+//! Validator registry
+//! Validators register a key, then bond part of their L2 balance in `STAKE_ASSET`. Every
+//! registration, bond and unbond is signed by the key the ledger binds to the account, over
+//! the validator's nonce so a signature applies once; rotating the validator key also takes
+//! a signature of the key it replaces. Bonded
+//! funds leave the spendable balance; unbonded funds stay locked for the unbonding period
+//! and only then return to it. Consensus never reads live bonds: at every epoch boundary
+//! the registry freezes the active set and its stake into an `EpochSnapshot`, which stays
+//! fixed until the next boundary.
+//!
+//! Equivocation evidence slashes a share of the offender's bonded and unbonding stake and
+//! jails it: it keeps its place in the current epoch, but is left out of the active set
+//! until its jail ends.
+
+use crate::{
+    cryptography,
+    errors::{EvidenceError, RegistryError},
+    evidence::{EquivocationEvidence, SlashingEvent},
+    ledger::AccountLedger,
+    types::{CryptoAlgorithm, SupportedChain},
+};
+use candid::{CandidType, Deserialize};
+use std::collections::BTreeMap;
+
+/// Asset validators bond, in its base unit (e8s)
+pub const STAKE_ASSET: SupportedChain = SupportedChain::Icp;
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct RegistryConfig {
+    /// Blocks per epoch
+    pub epoch_length: u64,
+    /// Full epochs unbonded stake stays locked, and slashable, after leaving the active set
+    pub unbonding_epochs: u64,
+    /// Smallest bond eligible for the active set
+    pub min_stake: u128,
+    pub max_active_validators: u64,
+    /// Share of bonded and unbonding stake burned for an equivocation, in basis points
+    pub slash_bps: u64,
+    /// Full epochs a slashed validator is kept out of the active set
+    pub jail_epochs: u64,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            epoch_length: 100,
+            unbonding_epochs: 2,
+            min_stake: 100_000_000,
+            max_active_validators: 100,
+            slash_bps: 500,
+            jail_epochs: 2,
+        }
+    }
+}
+
+/// Key registration, signed with the registered key to prove its possession
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct ValidatorRegistration {
+    pub address: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key, in the encoding of `algorithm`
+    pub public_key: String,
+    /// Hex signature over `ValidatorRegistration::message()`
+    pub signature: String,
+    /// Hex signature of the account key over `action()`, at the validator's nonce
+    pub account_signature: Option<String>,
+    /// Hex signature of the validator's current key over the same action, to rotate it
+    pub rotation_signature: Option<String>,
+}
+
+impl ValidatorRegistration {
+    /// Domain tag of the proof of possession
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/validator-registration/v1";
+
+    pub fn message(&self) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(self.address.as_bytes());
+        message.push(0);
+        message.push(self.algorithm as u8);
+        message.extend_from_slice(self.public_key.as_bytes());
+        message
+    }
+
+    /// Action the account authorizes by registering this key
+    pub fn action(&self) -> ValidatorAction {
+        ValidatorAction::Register {
+            algorithm: self.algorithm,
+            public_key: self.public_key.clone(),
+        }
+    }
+
+    fn verify(&self) -> Result<(), RegistryError> {
+        let invalid = |reason: String| RegistryError::InvalidRegistration {
+            address: self.address.clone(),
+            reason,
+        };
+        if !cryptography::supports_vrf(self.algorithm) {
+            return Err(RegistryError::VrfUnsupported(self.algorithm));
+        }
+        let public_key = hex::decode(&self.public_key)
+            .map_err(|e| invalid(format!("public key is not hex: {e}")))?;
+        let signature = hex::decode(&self.signature)
+            .map_err(|e| invalid(format!("signature is not hex: {e}")))?;
+        let is_valid = cryptography::verify_signature(
+            self.algorithm,
+            &public_key,
+            &self.message(),
+            &signature,
+        )
+        .map_err(|e| invalid(e.to_string()))?;
+        if !is_valid {
+            return Err(invalid(format!(
+                "{:?} proof of possession failed",
+                self.algorithm
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Change to a validator its account signs for
+#[derive(Debug, Clone, PartialEq)]
+pub enum ValidatorAction {
+    Register {
+        algorithm: CryptoAlgorithm,
+        public_key: String,
+    },
+    Bond(u128),
+    Unbond(u128),
+}
+
+impl ValidatorAction {
+    /// Domain tag of the account's authorization
+    pub const DOMAIN: &'static [u8] = b"polychain-l2/validator-action/v1";
+
+    /// Bytes signed to apply the action to `address` while its validator is at `nonce`
+    pub fn message(&self, address: &str, nonce: u64) -> Vec<u8> {
+        let mut message = Self::DOMAIN.to_vec();
+        message.extend_from_slice(address.as_bytes());
+        message.push(0);
+        message.extend_from_slice(&nonce.to_le_bytes());
+        match self {
+            Self::Register {
+                algorithm,
+                public_key,
+            } => {
+                message.push(0);
+                message.push(*algorithm as u8);
+                message.extend_from_slice(public_key.to_lowercase().as_bytes());
+            }
+            Self::Bond(amount) => {
+                message.push(1);
+                message.extend_from_slice(&amount.to_le_bytes());
+            }
+            Self::Unbond(amount) => {
+                message.push(2);
+                message.extend_from_slice(&amount.to_le_bytes());
+            }
+        }
+        message
+    }
+}
+
+/// Why the hex `signature` is not `public_key`'s signature over `message`, if it is not
+fn check_signature(
+    algorithm: CryptoAlgorithm,
+    public_key: &str,
+    message: &[u8],
+    signature: Option<&str>,
+) -> Result<(), String> {
+    let signature = signature.ok_or_else(|| "signature is missing".to_string())?;
+    let public_key = hex::decode(public_key).map_err(|e| format!("public key is not hex: {e}"))?;
+    let signature = hex::decode(signature).map_err(|e| format!("signature is not hex: {e}"))?;
+    let is_valid = cryptography::verify_signature(algorithm, &public_key, message, &signature)
+        .map_err(|e| e.to_string())?;
+    if !is_valid {
+        return Err(format!("{algorithm:?} verification failed"));
+    }
+    Ok(())
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct Validator {
+    pub address: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key
+    pub public_key: String,
+    /// Stake bonded now, counted from the next epoch boundary
+    pub bonded: u128,
+    pub registered_epoch: u64,
+    /// First epoch a slashed validator may rejoin the active set
+    pub jailed_until: Option<u64>,
+    /// Signed actions applied so far, the nonce the next one signs over
+    pub nonce: Option<u64>,
+    /// Keys that signed for the validator within the slashable window, oldest first;
+    /// absent for validators registered before rotations were tracked
+    pub key_history: Option<Vec<KeyPeriod>>,
+}
+
+/// Key a validator signed with from `from_epoch` until the next period starts
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct KeyPeriod {
+    pub from_epoch: u64,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key
+    pub public_key: String,
+}
+
+impl Validator {
+    /// Key the validator signed with during `epoch`, its current key if rotations were
+    /// not tracked yet
+    pub fn key_at(&self, epoch: u64) -> Option<(CryptoAlgorithm, &str)> {
+        match &self.key_history {
+            Some(history) => history
+                .iter()
+                .rev()
+                .find(|period| period.from_epoch <= epoch)
+                .map(|period| (period.algorithm, period.public_key.as_str())),
+            None => Some((self.algorithm, self.public_key.as_str())),
+        }
+    }
+
+    /// Start a key period at `from_epoch`, replacing one that had not started yet, and
+    /// forget the periods that ended before `oldest_epoch`
+    fn record_key(&mut self, from_epoch: u64, oldest_epoch: u64) {
+        let history = self.key_history.get_or_insert_with(Vec::new);
+        history.retain(|period| period.from_epoch < from_epoch);
+        history.push(KeyPeriod {
+            from_epoch,
+            algorithm: self.algorithm,
+            public_key: self.public_key.clone(),
+        });
+        let superseded = history
+            .iter()
+            .skip(1)
+            .take_while(|period| period.from_epoch <= oldest_epoch)
+            .count();
+        history.drain(..superseded);
+    }
+}
+
+/// Stake on its way back to the validator's L2 balance
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct Unbonding {
+    pub address: String,
+    pub amount: u128,
+    pub requested_epoch: u64,
+    /// First epoch the stake is paid back in
+    pub release_epoch: u64,
+}
+
+/// Validator as frozen in an epoch's active set
+#[derive(CandidType, Deserialize, Debug, Clone, PartialEq)]
+pub struct ActiveValidator {
+    pub address: String,
+    pub algorithm: CryptoAlgorithm,
+    /// Hex public key
+    pub public_key: String,
+    pub stake: u128,
+}
+
+/// Active set and stake of an epoch, fixed from its first block to its last
+#[derive(CandidType, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct EpochSnapshot {
+    pub epoch: u64,
+    /// Height of the first block of the epoch
+    pub start_height: u64,
+    pub validators: Vec<ActiveValidator>,
+    pub total_stake: u128,
+}
+
+#[derive(CandidType, Deserialize, Debug, Clone, Default)]
+pub struct ValidatorRegistry {
+    config: RegistryConfig,
+    validators: BTreeMap<String, Validator>,
+    /// Pending unbondings, in request order
+    unbondings: Vec<Unbonding>,
+    epoch: EpochSnapshot,
+    slashing_events: Vec<SlashingEvent>,
+}
+
+impl ValidatorRegistry {
+    pub fn new(config: RegistryConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn current_epoch(&self) -> &EpochSnapshot {
+        &self.epoch
+    }
+
+    pub fn validator(&self, address: &str) -> Option<&Validator> {
+        self.validators.get(address)
+    }
+
+    /// Nonce the next action on `address` must be signed over
+    pub fn next_nonce(&self, address: &str) -> u64 {
+        self.validators
+            .get(address)
+            .and_then(|validator| validator.nonce)
+            .unwrap_or(0)
+    }
+
+    /// Check that the key `ledger` binds to `address` signed `action` at the current nonce
+    fn check_authorization(
+        &self,
+        ledger: &AccountLedger,
+        address: &str,
+        action: &ValidatorAction,
+        signature: Option<&str>,
+    ) -> Result<(), RegistryError> {
+        let unauthorized = |reason: String| RegistryError::Unauthorized {
+            address: address.to_string(),
+            reason,
+        };
+        let key = ledger
+            .key_of(address)
+            .ok_or_else(|| unauthorized("no account key is bound".to_string()))?;
+        let message = action.message(address, self.next_nonce(address));
+        check_signature(key.algorithm, &key.public_key, &message, signature)
+            .map_err(|reason| unauthorized(format!("account key: {reason}")))
+    }
+
+    pub fn validators(&self) -> impl Iterator<Item = &Validator> {
+        self.validators.values()
+    }
+
+    pub fn unbondings(&self, address: &str) -> Vec<Unbonding> {
+        self.unbondings
+            .iter()
+            .filter(|unbonding| unbonding.address == address)
+            .cloned()
+            .collect()
+    }
+
+    pub fn slashing_events(&self) -> &[SlashingEvent] {
+        &self.slashing_events
+    }
+
+    /// Register a validator key, or rotate it from the next epoch
+    pub fn register(
+        &mut self,
+        ledger: &AccountLedger,
+        registration: ValidatorRegistration,
+    ) -> Result<&Validator, RegistryError> {
+        registration.verify()?;
+        let address = &registration.address;
+        let action = registration.action();
+        self.check_authorization(
+            ledger,
+            address,
+            &action,
+            registration.account_signature.as_deref(),
+        )?;
+        if let Some(current) = self.validators.get(address) {
+            // La clé en place consent à son remplacement
+            if !current
+                .public_key
+                .eq_ignore_ascii_case(&registration.public_key)
+            {
+                let message = action.message(address, self.next_nonce(address));
+                check_signature(
+                    current.algorithm,
+                    &current.public_key,
+                    &message,
+                    registration.rotation_signature.as_deref(),
+                )
+                .map_err(|reason| RegistryError::Unauthorized {
+                    address: address.clone(),
+                    reason: format!("current validator key: {reason}"),
+                })?;
+            }
+        }
+        if let Some(holder) = self.validators.values().find(|validator| {
+            validator
+                .public_key
+                .eq_ignore_ascii_case(&registration.public_key)
+                && validator.address != registration.address
+        }) {
+            return Err(RegistryError::KeyInUse(holder.address.clone()));
+        }
+
+        let epoch = self.epoch.epoch;
+        let oldest_epoch = epoch.saturating_sub(self.config.unbonding_epochs);
+        let validator = self
+            .validators
+            .entry(registration.address.clone())
+            .or_insert_with(|| Validator {
+                address: registration.address.clone(),
+                algorithm: registration.algorithm,
+                public_key: String::new(),
+                bonded: 0,
+                registered_epoch: epoch,
+                jailed_until: None,
+                nonce: None,
+                key_history: None,
+            });
+        // Une rotation ne vaut qu'à l'époque suivante: l'époque en cours garde l'ancienne clé
+        let from_epoch = if validator.public_key.is_empty() {
+            epoch
+        } else {
+            epoch + 1
+        };
+        validator.algorithm = registration.algorithm;
+        validator.public_key = registration.public_key.to_lowercase();
+        validator.nonce = Some(validator.nonce.unwrap_or(0) + 1);
+        validator.record_key(from_epoch, oldest_epoch);
+        Ok(validator)
+    }
+
+    /// Move `amount` of the validator's L2 balance into its bond, `signature` being the
+    /// account key's over `ValidatorAction::Bond`
+    pub fn bond(
+        &mut self,
+        ledger: &mut AccountLedger,
+        address: &str,
+        amount: u128,
+        signature: Option<&str>,
+    ) -> Result<&Validator, RegistryError> {
+        if amount == 0 {
+            return Err(RegistryError::ZeroAmount);
+        }
+        if !self.validators.contains_key(address) {
+            return Err(RegistryError::UnknownValidator(address.to_string()));
+        }
+        self.check_authorization(ledger, address, &ValidatorAction::Bond(amount), signature)?;
+        let validator = self
+            .validators
+            .get_mut(address)
+            .ok_or_else(|| RegistryError::UnknownValidator(address.to_string()))?;
+        let bonded = validator
+            .bonded
+            .checked_add(amount)
+            .ok_or_else(|| RegistryError::Overflow(address.to_string()))?;
+        ledger.debit(address, STAKE_ASSET, amount)?;
+        validator.bonded = bonded;
+        validator.nonce = Some(validator.nonce.unwrap_or(0) + 1);
+        Ok(validator)
+    }
+
+    /// Take `amount` out of the bond, paid back once the unbonding period is over;
+    /// `signature` is the account key's over `ValidatorAction::Unbond`
+    pub fn unbond(
+        &mut self,
+        ledger: &AccountLedger,
+        address: &str,
+        amount: u128,
+        signature: Option<&str>,
+    ) -> Result<Unbonding, RegistryError> {
+        if amount == 0 {
+            return Err(RegistryError::ZeroAmount);
+        }
+        if !self.validators.contains_key(address) {
+            return Err(RegistryError::UnknownValidator(address.to_string()));
+        }
+        self.check_authorization(ledger, address, &ValidatorAction::Unbond(amount), signature)?;
+        let validator = self
+            .validators
+            .get_mut(address)
+            .ok_or_else(|| RegistryError::UnknownValidator(address.to_string()))?;
+        if validator.bonded < amount {
+            return Err(RegistryError::InsufficientBond {
+                address: address.to_string(),
+                bonded: validator.bonded,
+                requested: amount,
+            });
+        }
+        validator.bonded -= amount;
+        validator.nonce = Some(validator.nonce.unwrap_or(0) + 1);
+
+        // La mise reste active jusqu'à la fin de l'époque en cours
+        let requested_epoch = self.epoch.epoch;
+        let unbonding = Unbonding {
+            address: address.to_string(),
+            amount,
+            requested_epoch,
+            release_epoch: requested_epoch
+                .saturating_add(1)
+                .saturating_add(self.config.unbonding_epochs),
+        };
+        self.unbondings.push(unbonding.clone());
+        Ok(unbonding)
+    }
+
+    /// Slash and jail the validator `evidence` proves double-signed
+    ///
+    /// Stake stays slashable until its unbonding period is over: evidence is accepted while
+    /// the epoch of the offence is at most `unbonding_epochs` behind the current one, and
+    /// checked against the key the validator signed with in that epoch, even if rotated since.
+    pub fn slash(
+        &mut self,
+        evidence: &EquivocationEvidence,
+        reported_at: u64,
+    ) -> Result<SlashingEvent, EvidenceError> {
+        let validator = self
+            .validators
+            .get(&evidence.validator)
+            .ok_or_else(|| EvidenceError::UnknownValidator(evidence.validator.clone()))?;
+        let epoch_length = self.config.epoch_length.max(1);
+        let oldest_epoch = self
+            .epoch
+            .epoch
+            .saturating_sub(self.config.unbonding_epochs);
+        let offence_epoch = evidence.height / epoch_length;
+        if offence_epoch < oldest_epoch {
+            return Err(EvidenceError::Expired {
+                height: evidence.height,
+                oldest: oldest_epoch.saturating_mul(epoch_length),
+            });
+        }
+        if self
+            .slashing_events
+            .iter()
+            .any(|event| event.covers(evidence))
+        {
+            return Err(EvidenceError::AlreadySlashed {
+                validator: evidence.validator.clone(),
+                step: evidence.step,
+                height: evidence.height,
+                round: evidence.round,
+            });
+        }
+
+        let (algorithm, public_key) = validator.key_at(offence_epoch).ok_or_else(|| {
+            EvidenceError::InvalidKey(format!(
+                "{} had no key at epoch {offence_epoch}",
+                evidence.validator
+            ))
+        })?;
+        evidence.verify_registered(algorithm, public_key)?;
+
+        let unbonding: u128 = self
+            .unbondings
+            .iter()
+            .filter(|unbonding| unbonding.address == evidence.validator)
+            .map(|unbonding| unbonding.amount)
+            .sum();
+        let slashable = validator.bonded.saturating_add(unbonding);
+        let penalty =
+            slashable.saturating_mul(u128::from(self.config.slash_bps.min(10_000))) / 10_000;
+
+        // La mise liée d'abord, puis les unbondings les plus récents
+        let from_bond = penalty.min(validator.bonded);
+        let mut remaining = penalty - from_bond;
+        for unbonding in self
+            .unbondings
+            .iter_mut()
+            .rev()
+            .filter(|unbonding| unbonding.address == evidence.validator)
+        {
+            let cut = remaining.min(unbonding.amount);
+            unbonding.amount -= cut;
+            remaining -= cut;
+        }
+        self.unbondings.retain(|unbonding| unbonding.amount > 0);
+
+        let epoch = self.epoch.epoch;
+        let jail_end = epoch
+            .saturating_add(1)
+            .saturating_add(self.config.jail_epochs);
+        let validator = self
+            .validators
+            .get_mut(&evidence.validator)
+            .expect("validator looked up above");
+        validator.bonded -= from_bond;
+        let jailed_until = validator
+            .jailed_until
+            .map_or(jail_end, |until| until.max(jail_end));
+        validator.jailed_until = Some(jailed_until);
+
+        let event = SlashingEvent {
+            validator: evidence.validator.clone(),
+            step: evidence.step,
+            height: evidence.height,
+            round: evidence.round,
+            block_hashes: vec![
+                evidence.first.block_hash.clone(),
+                evidence.second.block_hash.clone(),
+            ],
+            slashed: penalty,
+            epoch,
+            jailed_until,
+            reported_at,
+        };
+        self.slashing_events.push(event.clone());
+        Ok(event)
+    }
+
+    /// Called after the block at `height` is appended: when it closes an epoch, pay back
+    /// the matured unbondings and freeze the next active set. Returns the new epoch.
+    pub fn on_block_committed(
+        &mut self,
+        height: u64,
+        ledger: &mut AccountLedger,
+    ) -> Option<&EpochSnapshot> {
+        let start_height = height.saturating_add(1);
+        let epoch = start_height / self.config.epoch_length.max(1);
+        if epoch <= self.epoch.epoch {
+            return None;
+        }
+
+        // Un remboursement qui déborderait reste en attente plutôt que d'être perdu
+        self.unbondings.retain(|unbonding| {
+            unbonding.release_epoch > epoch
+                || ledger
+                    .credit(&unbonding.address, STAKE_ASSET, unbonding.amount)
+                    .is_err()
+        });
+
+        let mut eligible: Vec<&Validator> = self
+            .validators
+            .values()
+            .filter(|validator| {
+                validator.bonded > 0
+                    && validator.bonded >= self.config.min_stake
+                    && validator.jailed_until.is_none_or(|until| until <= epoch)
+            })
+            .collect();
+        eligible.sort_by(|a, b| {
+            b.bonded
+                .cmp(&a.bonded)
+                .then_with(|| a.address.cmp(&b.address))
+        });
+        eligible.truncate(self.config.max_active_validators as usize);
+
+        let validators: Vec<ActiveValidator> = eligible
+            .into_iter()
+            .map(|validator| ActiveValidator {
+                address: validator.address.clone(),
+                algorithm: validator.algorithm,
+                public_key: validator.public_key.clone(),
+                stake: validator.bonded,
+            })
+            .collect();
+        self.epoch = EpochSnapshot {
+            epoch,
+            start_height,
+            total_stake: validators.iter().map(|validator| validator.stake).sum(),
+            validators,
+        };
+        Some(&self.epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cryptography::bridge::VrfBridge;
+    use crate::cryptography::{bridge::CryptographyBridge, ecdsa::Ecdsa};
+    use crate::evidence::SignedBlockHash;
+    use crate::ledger::AccountKeyRegistration;
+    use crate::types::ConsensusStep;
+
+    type SecretKey = <Ecdsa as CryptographyBridge>::SecretKey;
+
+    /// Account keys of `address`, derived from the address so tests can sign again with them
+    fn account_keys(address: &str) -> (<Ecdsa as CryptographyBridge>::PublicKey, SecretKey) {
+        Ecdsa
+            .key_from_seed(blake3::hash(address.as_bytes()).as_bytes())
+            .unwrap()
+    }
+
+    fn account_key(address: &str) -> SecretKey {
+        account_keys(address).1
+    }
+
+    fn sign(secret_key: &SecretKey, message: &[u8]) -> String {
+        hex::encode(Ecdsa.signature_to_bytes(&Ecdsa.sign(secret_key, message).unwrap()))
+    }
+
+    /// Bind the account key of `address` in `ledger`
+    fn open_account(ledger: &mut AccountLedger, address: &str) {
+        let (public_key, secret_key) = account_keys(address);
+        let mut registration = AccountKeyRegistration {
+            address: address.to_string(),
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&public_key)),
+            signature: String::new(),
+        };
+        registration.signature = sign(&secret_key, &registration.message());
+        ledger.bind_key(&registration).unwrap();
+    }
+
+    /// Account signature over `action` at the validator's next nonce
+    fn authorize(registry: &ValidatorRegistry, address: &str, action: ValidatorAction) -> String {
+        let message = action.message(address, registry.next_nonce(address));
+        sign(&account_key(address), &message)
+    }
+
+    fn registration(
+        registry: &ValidatorRegistry,
+        ledger: &mut AccountLedger,
+        address: &str,
+    ) -> ValidatorRegistration {
+        registration_with_key(registry, ledger, address).0
+    }
+
+    /// Fresh validator key for `address`, registration signed by it and by the account key
+    fn registration_with_key(
+        registry: &ValidatorRegistry,
+        ledger: &mut AccountLedger,
+        address: &str,
+    ) -> (ValidatorRegistration, SecretKey) {
+        open_account(ledger, address);
+        let (public_key, secret_key) = Ecdsa.key_generator().unwrap();
+        let mut registration = ValidatorRegistration {
+            address: address.to_string(),
+            algorithm: CryptoAlgorithm::Ecdsa,
+            public_key: hex::encode(Ecdsa.public_key_to_bytes(&public_key)),
+            signature: String::new(),
+            account_signature: None,
+            rotation_signature: None,
+        };
+        registration.signature = sign(&secret_key, &registration.message());
+        registration.account_signature = Some(authorize(registry, address, registration.action()));
+        (registration, secret_key)
+    }
+
+    fn bond(
+        registry: &mut ValidatorRegistry,
+        ledger: &mut AccountLedger,
+        address: &str,
+        amount: u128,
+    ) -> Result<Validator, RegistryError> {
+        let signature = authorize(registry, address, ValidatorAction::Bond(amount));
+        registry
+            .bond(ledger, address, amount, Some(&signature))
+            .cloned()
+    }
+
+    fn unbond(
+        registry: &mut ValidatorRegistry,
+        ledger: &AccountLedger,
+        address: &str,
+        amount: u128,
+    ) -> Result<Unbonding, RegistryError> {
+        let signature = authorize(registry, address, ValidatorAction::Unbond(amount));
+        registry.unbond(ledger, address, amount, Some(&signature))
+    }
+
+    /// Two votes of `secret_key` for different blocks at `height`, round 0
+    fn equivocation(
+        address: &str,
+        secret_key: &<Ecdsa as CryptographyBridge>::SecretKey,
+        height: u64,
+    ) -> EquivocationEvidence {
+        let sign = |block_hash: &str| {
+            let message = ConsensusStep::Vote.message(height, 0, block_hash);
+            SignedBlockHash {
+                block_hash: block_hash.to_string(),
+                signature: hex::encode(
+                    Ecdsa.signature_to_bytes(&Ecdsa.sign(secret_key, &message).unwrap()),
+                ),
+            }
+        };
+        EquivocationEvidence {
+            validator: address.to_string(),
+            step: ConsensusStep::Vote,
+            height,
+            round: 0,
+            first: sign("aa"),
+            second: sign("bb"),
+        }
+    }
+
+    fn registry() -> ValidatorRegistry {
+        ValidatorRegistry::new(RegistryConfig {
+            epoch_length: 10,
+            unbonding_epochs: 1,
+            min_stake: 100,
+            max_active_validators: 2,
+            slash_bps: 1_000,
+            jail_epochs: 1,
+        })
+    }
+
+    #[test]
+    fn registration_requires_proof_of_possession() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        let alice = registration(&registry, &mut ledger, "alice");
+        assert!(registry.register(&ledger, alice.clone()).is_ok());
+
+        // Clé d'alice revendiquée par mallory, signature d'alice rejouée
+        let stolen = ValidatorRegistration {
+            address: "mallory".to_string(),
+            ..alice.clone()
+        };
+        assert!(matches!(
+            registry.register(&ledger, stolen),
+            Err(RegistryError::InvalidRegistration { .. })
+        ));
+
+        let mut falcon = registration(&registry, &mut ledger, "bob");
+        falcon.algorithm = CryptoAlgorithm::Falcon512;
+        assert_eq!(
+            registry.register(&ledger, falcon),
+            Err(RegistryError::VrfUnsupported(CryptoAlgorithm::Falcon512))
+        );
+    }
+
+    #[test]
+    fn validator_changes_need_the_account_key() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        let (alice, alice_key) = registration_with_key(&registry, &mut ledger, "alice");
+
+        // Sans signature du compte, ou signée par une autre clé
+        let unsigned = ValidatorRegistration {
+            account_signature: None,
+            ..alice.clone()
+        };
+        assert!(matches!(
+            registry.register(&ledger, unsigned),
+            Err(RegistryError::Unauthorized { .. })
+        ));
+        let foreign = ValidatorRegistration {
+            account_signature: Some(sign(
+                &account_key("mallory"),
+                &alice.action().message("alice", 0),
+            )),
+            ..alice.clone()
+        };
+        assert!(matches!(
+            registry.register(&ledger, foreign),
+            Err(RegistryError::Unauthorized { .. })
+        ));
+        registry.register(&ledger, alice.clone()).unwrap();
+        assert_eq!(registry.next_nonce("alice"), 1);
+
+        // Une signature ne sert qu'une fois: le nonce a avancé
+        ledger.credit("alice", STAKE_ASSET, 1_000).unwrap();
+        let signature = authorize(&registry, "alice", ValidatorAction::Bond(100));
+        registry
+            .bond(&mut ledger, "alice", 100, Some(&signature))
+            .unwrap();
+        assert!(matches!(
+            registry.bond(&mut ledger, "alice", 100, Some(&signature)),
+            Err(RegistryError::Unauthorized { .. })
+        ));
+        assert!(matches!(
+            registry.unbond(&ledger, "alice", 100, None),
+            Err(RegistryError::Unauthorized { .. })
+        ));
+        assert_eq!(ledger.balance_of("alice", STAKE_ASSET), 900);
+
+        // Rotation: la clé du compte ne suffit pas, la clé remplacée doit consentir
+        let (mut rotation, _) = registration_with_key(&registry, &mut ledger, "alice");
+        assert!(matches!(
+            registry.register(&ledger, rotation.clone()),
+            Err(RegistryError::Unauthorized { .. })
+        ));
+        let message = rotation
+            .action()
+            .message("alice", registry.next_nonce("alice"));
+        rotation.rotation_signature = Some(sign(&alice_key, &message));
+        let rotated = registry.register(&ledger, rotation.clone()).unwrap();
+        assert_eq!(rotated.public_key, rotation.public_key);
+    }
+
+    #[test]
+    fn bonds_take_effect_at_epoch_boundaries() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        for (address, balance) in [("alice", 1_000), ("bob", 500), ("carol", 300)] {
+            let registration = registration(&registry, &mut ledger, address);
+            registry.register(&ledger, registration).unwrap();
+            ledger.credit(address, STAKE_ASSET, balance).unwrap();
+        }
+
+        bond(&mut registry, &mut ledger, "alice", 400).unwrap();
+        bond(&mut registry, &mut ledger, "bob", 500).unwrap();
+        bond(&mut registry, &mut ledger, "carol", 50).unwrap();
+        assert_eq!(ledger.balance_of("alice", STAKE_ASSET), 600);
+        assert!(matches!(
+            bond(&mut registry, &mut ledger, "carol", 1_000),
+            Err(RegistryError::Ledger(_))
+        ));
+
+        // Rien ne change avant la frontière d'époque
+        assert!(registry.current_epoch().validators.is_empty());
+        assert!(registry.on_block_committed(8, &mut ledger).is_none());
+        let epoch = registry.on_block_committed(9, &mut ledger).unwrap();
+        assert_eq!((epoch.epoch, epoch.start_height), (1, 10));
+        // carol est sous le minimum, max_active_validators = 2
+        let active: Vec<&str> = epoch
+            .validators
+            .iter()
+            .map(|v| v.address.as_str())
+            .collect();
+        assert_eq!(active, ["bob", "alice"]);
+        assert_eq!(epoch.total_stake, 900);
+
+        // Unbonding: hors de l'ensemble actif à la prochaine époque, remboursé une époque plus tard
+        let unbonding = unbond(&mut registry, &ledger, "bob", 500).unwrap();
+        assert_eq!(unbonding.release_epoch, 3);
+        assert!(matches!(
+            unbond(&mut registry, &ledger, "bob", 1),
+            Err(RegistryError::InsufficientBond { .. })
+        ));
+        assert_eq!(registry.current_epoch().total_stake, 900);
+
+        registry.on_block_committed(19, &mut ledger).unwrap();
+        assert_eq!(registry.current_epoch().total_stake, 400);
+        assert_eq!(ledger.balance_of("bob", STAKE_ASSET), 0);
+
+        registry.on_block_committed(29, &mut ledger).unwrap();
+        assert_eq!(ledger.balance_of("bob", STAKE_ASSET), 500);
+        assert!(registry.unbondings("bob").is_empty());
+    }
+
+    #[test]
+    fn equivocation_slashes_bond_and_unbondings_and_jails() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        let (alice, alice_key) = registration_with_key(&registry, &mut ledger, "alice");
+        registry.register(&ledger, alice).unwrap();
+        let bob = registration(&registry, &mut ledger, "bob");
+        registry.register(&ledger, bob).unwrap();
+        ledger.credit("alice", STAKE_ASSET, 1_000).unwrap();
+        bond(&mut registry, &mut ledger, "alice", 1_000).unwrap();
+        registry.on_block_committed(9, &mut ledger).unwrap();
+        unbond(&mut registry, &ledger, "alice", 200).unwrap();
+
+        // Preuves invalides: validateur inconnu, signatures d'une autre clé
+        assert_eq!(
+            registry.slash(&equivocation("mallory", &alice_key, 12), 0),
+            Err(EvidenceError::UnknownValidator("mallory".to_string()))
+        );
+        assert!(matches!(
+            registry.slash(&equivocation("bob", &alice_key, 12), 0),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+
+        // 10% de 800 liés + 200 en unbonding, pris sur la mise liée
+        let evidence = equivocation("alice", &alice_key, 12);
+        let event = registry.slash(&evidence, 42).unwrap();
+        assert_eq!(
+            (
+                event.slashed,
+                event.epoch,
+                event.jailed_until,
+                event.reported_at
+            ),
+            (100, 1, 3, 42)
+        );
+        assert_eq!(registry.validator("alice").unwrap().bonded, 700);
+        assert_eq!(registry.unbondings("alice")[0].amount, 200);
+        assert_eq!(registry.slashing_events(), [event]);
+        assert!(matches!(
+            registry.slash(&evidence, 43),
+            Err(EvidenceError::AlreadySlashed { .. })
+        ));
+
+        // En prison pendant l'époque 2, de retour à l'époque 3
+        registry.on_block_committed(19, &mut ledger).unwrap();
+        assert!(registry.current_epoch().validators.is_empty());
+        registry.on_block_committed(29, &mut ledger).unwrap();
+        assert_eq!(registry.current_epoch().total_stake, 700);
+        assert_eq!(ledger.balance_of("alice", STAKE_ASSET), 200);
+
+        // Hors de la période d'unbonding, l'infraction n'est plus punissable
+        let mut stale = equivocation("alice", &alice_key, 12);
+        stale.round = 1;
+        assert_eq!(
+            registry.slash(&stale, 44),
+            Err(EvidenceError::Expired {
+                height: 12,
+                oldest: 20
+            })
+        );
+
+        // Une pénalité plus grande que la mise liée entame les unbondings, du plus récent au plus ancien
+        unbond(&mut registry, &ledger, "alice", 300).unwrap();
+        unbond(&mut registry, &ledger, "alice", 400).unwrap();
+        let event = registry
+            .slash(&equivocation("alice", &alice_key, 31), 45)
+            .unwrap();
+        assert_eq!(event.slashed, 70);
+        let amounts: Vec<u128> = registry
+            .unbondings("alice")
+            .iter()
+            .map(|unbonding| unbonding.amount)
+            .collect();
+        assert_eq!(amounts, [300, 330]);
+    }
+
+    #[test]
+    fn rotated_keys_stay_slashable_for_their_epochs() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        let (alice, old_key) = registration_with_key(&registry, &mut ledger, "alice");
+        registry.register(&ledger, alice).unwrap();
+        ledger.credit("alice", STAKE_ASSET, 1_000).unwrap();
+        bond(&mut registry, &mut ledger, "alice", 1_000).unwrap();
+        registry.on_block_committed(9, &mut ledger).unwrap();
+
+        // Double vote à l'époque 1, puis rotation avant d'être dénoncée
+        let evidence = equivocation("alice", &old_key, 12);
+        let (mut rotation, new_key) = registration_with_key(&registry, &mut ledger, "alice");
+        let message = rotation
+            .action()
+            .message("alice", registry.next_nonce("alice"));
+        rotation.rotation_signature = Some(sign(&old_key, &message));
+        registry.register(&ledger, rotation).unwrap();
+        registry.on_block_committed(19, &mut ledger).unwrap();
+        assert_eq!(
+            registry
+                .validator("alice")
+                .unwrap()
+                .key_history
+                .as_ref()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // La nouvelle clé ne signait pas encore à l'époque 1, l'ancienne ne signe plus à l'époque 2
+        assert!(matches!(
+            registry.slash(&equivocation("alice", &new_key, 12), 0),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+        assert!(matches!(
+            registry.slash(&equivocation("alice", &old_key, 22), 0),
+            Err(EvidenceError::InvalidSignature { .. })
+        ));
+        assert_eq!(registry.slash(&evidence, 0).unwrap().slashed, 100);
+
+        // Passé la période d'unbonding, l'ancienne clé est oubliée
+        registry.on_block_committed(29, &mut ledger).unwrap();
+        registry.on_block_committed(39, &mut ledger).unwrap();
+        let (mut rotation, _) = registration_with_key(&registry, &mut ledger, "alice");
+        let message = rotation
+            .action()
+            .message("alice", registry.next_nonce("alice"));
+        rotation.rotation_signature = Some(sign(&new_key, &message));
+        registry.register(&ledger, rotation).unwrap();
+        let history = registry
+            .validator("alice")
+            .unwrap()
+            .key_history
+            .clone()
+            .unwrap();
+        let starts: Vec<u64> = history.iter().map(|period| period.from_epoch).collect();
+        assert_eq!(starts, [2, 5]);
+    }
+
+    #[test]
+    fn key_in_use_ignores_hex_case() {
+        let mut registry = registry();
+        let mut ledger = AccountLedger::new();
+        let (alice, alice_key) = registration_with_key(&registry, &mut ledger, "alice");
+        registry.register(&ledger, alice.clone()).unwrap();
+
+        // Même clé d'alice, en majuscules, pour le compte de bob
+        open_account(&mut ledger, "bob");
+        let mut copied = ValidatorRegistration {
+            address: "bob".to_string(),
+            public_key: alice.public_key.to_uppercase(),
+            account_signature: None,
+            ..alice
+        };
+        copied.signature = sign(&alice_key, &copied.message());
+        copied.account_signature = Some(authorize(&registry, "bob", copied.action()));
+        assert_eq!(
+            registry.register(&ledger, copied),
+            Err(RegistryError::KeyInUse("alice".to_string()))
+        );
+    }
+}
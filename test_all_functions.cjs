@@ -26,7 +26,7 @@ async function runAllTests() {
         },
         {
             name: 'Bitcoin Balance Check',
-            command: `dfx canister call polychain_l2_backend get_bitcoin_balance '("test_user")'`
+            command: `dfx canister call polychain_l2_backend get_bitcoin_balance`
         },
         {
             name: 'Bitcoin Withdrawal', 